@@ -0,0 +1,112 @@
+//! Micro benchmarks of a single exchanger in isolation from the Treiber stack
+//! and the elimination array, thus exchanger level optimizations can be
+//! evaluated on their own.
+//!
+//! ```sh
+//! cargo bench --features bench-internals --bench exchanger
+//! ```
+//!
+//! Each pair of threads, one pushing, one popping, exchanges on its own
+//! exchanger. The exchangers of all pairs are laid out next to each other,
+//! either padded to a cache line each or not, to surface false sharing between
+//! pairs. An iteration is one successful exchange per pair, thus the time per
+//! iteration is the paired exchange latency and the reported throughput the
+//! number of exchanges per second across all pairs.
+//!
+//! Not available with the `no-elimination` feature, which compiles out the
+//! exchanger.
+
+#[cfg(not(feature = "no-elimination"))]
+mod benches {
+    use criterion::{BenchmarkId, Criterion, Throughput};
+    use crossbeam::utils::CachePadded;
+    use elimination_backoff_stack::bench_internals::Exchanger;
+    use std::ops::Deref;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn exchange<E>(exchangers: Arc<Vec<E>>, threads: usize, iters: u64) -> Duration
+    where
+        E: Deref<Target = Exchanger<u64>> + Send + Sync + 'static,
+    {
+        let pairs = threads / 2;
+        let barrier = Arc::new(Barrier::new(threads + 1));
+
+        let mut handlers = vec![];
+        for pair in 0..pairs {
+            let (push_exchangers, push_barrier) = (exchangers.clone(), barrier.clone());
+            handlers.push(thread::spawn(move || {
+                push_barrier.wait();
+                for mut item in 0..iters {
+                    while let Err(i) = push_exchangers[pair].push(item) {
+                        item = i;
+                    }
+                }
+            }));
+
+            let (pop_exchangers, pop_barrier) = (exchangers.clone(), barrier.clone());
+            handlers.push(thread::spawn(move || {
+                pop_barrier.wait();
+                for _ in 0..iters {
+                    while pop_exchangers[pair].pop().is_none() {}
+                }
+            }));
+        }
+
+        barrier.wait();
+        let start = Instant::now();
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+        start.elapsed()
+    }
+
+    /// Unpadded exchanger, dereferencing like [`CachePadded`] does.
+    struct Plain(Exchanger<u64>);
+
+    impl Deref for Plain {
+        type Target = Exchanger<u64>;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    fn bench_paired_exchange(c: &mut Criterion) {
+        let mut group = c.benchmark_group("exchanger/paired");
+        group.sample_size(10);
+
+        for threads in [2, 4, 8] {
+            let pairs = threads / 2;
+            group.throughput(Throughput::Elements(pairs as u64));
+
+            group.bench_with_input(BenchmarkId::new("unpadded", threads), &threads, |b, t| {
+                b.iter_custom(|iters| {
+                    let exchangers = (0..pairs).map(|_| Plain(Exchanger::new())).collect();
+                    exchange(Arc::new(exchangers), *t, iters)
+                })
+            });
+            group.bench_with_input(BenchmarkId::new("padded", threads), &threads, |b, t| {
+                b.iter_custom(|iters| {
+                    let exchangers = (0..pairs)
+                        .map(|_| CachePadded::new(Exchanger::new()))
+                        .collect();
+                    exchange(Arc::new(exchangers), *t, iters)
+                })
+            });
+        }
+
+        group.finish();
+    }
+
+    criterion::criterion_group!(benches, bench_paired_exchange);
+}
+
+#[cfg(not(feature = "no-elimination"))]
+criterion::criterion_main!(benches::benches);
+
+#[cfg(feature = "no-elimination")]
+fn main() {
+    eprintln!("exchanger benchmarks are not available with the `no-elimination` feature");
+}
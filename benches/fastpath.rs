@@ -0,0 +1,111 @@
+//! Criterion independent micro benchmark of the uncontended push and pop
+//! latency.
+//!
+//! Prints the average latency in nanoseconds per operation for each strategy
+//! and for a plain Treiber stack measured in the same run as a baseline. Exits
+//! with a non-zero status code in case any strategy exceeds the latency of the
+//! baseline by more than the maximum ratio, thus guarding the fast path in CI,
+//! see `.github/workflows/rust.yml`, independent of how fast the machine at
+//! hand is. The ratio defaults to [`DEFAULT_MAX_RATIO`] and can be overwritten
+//! via the `FASTPATH_MAX_RATIO` environment variable.
+//!
+//! ```sh
+//! FASTPATH_MAX_RATIO=1.2 cargo bench --features bench-internals --bench fastpath
+//! ```
+
+use elimination_backoff_stack::bench_internals::TreiberStack;
+use elimination_backoff_stack::{for_each_strategy, PopStrategy, PushStrategy, Stack};
+use std::hint::black_box;
+use std::process;
+use std::time::Instant;
+
+const DEFAULT_MAX_RATIO: f64 = 1.5;
+
+const WARM_UP_ITERATIONS: u64 = 10_000;
+const ITERATIONS: u64 = 1_000_000;
+
+struct Latency {
+    push_ns: f64,
+    pop_ns: f64,
+}
+
+impl Latency {
+    /// Ratio of the slower of push and pop to the respective `baseline`.
+    fn ratio(&self, baseline: &Latency) -> f64 {
+        (self.push_ns / baseline.push_ns).max(self.pop_ns / baseline.pop_ns)
+    }
+}
+
+fn measure(push: impl Fn(u64), pop: impl Fn() -> Option<u64>) -> Latency {
+    for i in 0..WARM_UP_ITERATIONS {
+        push(i);
+    }
+    for _ in 0..WARM_UP_ITERATIONS {
+        black_box(pop());
+    }
+
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        push(black_box(i));
+    }
+    let push_ns = start.elapsed().as_nanos() as f64 / ITERATIONS as f64;
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(pop());
+    }
+    let pop_ns = start.elapsed().as_nanos() as f64 / ITERATIONS as f64;
+
+    Latency { push_ns, pop_ns }
+}
+
+fn measure_stack<PushS: PushStrategy, PopS: PopStrategy>() -> Latency {
+    let stack = Stack::<u64, PushS, PopS>::new();
+    measure(|i| stack.push(i), || stack.pop())
+}
+
+fn measure_baseline() -> Latency {
+    let stack = TreiberStack::<u64>::new();
+    measure(|i| stack.push(i), || stack.pop())
+}
+
+fn main() {
+    let max_ratio = match std::env::var("FASTPATH_MAX_RATIO") {
+        Ok(ratio) => ratio
+            .parse()
+            .expect("FASTPATH_MAX_RATIO to be a floating point number"),
+        Err(_) => DEFAULT_MAX_RATIO,
+    };
+
+    let baseline = measure_baseline();
+    println!(
+        "{:<40} push: {:>8.1} ns/op  pop: {:>8.1} ns/op",
+        "baseline (Treiber stack)", baseline.push_ns, baseline.pop_ns
+    );
+
+    let mut results = vec![];
+    macro_rules! measure {
+        ($name:ident, $strategy:ty) => {
+            results.push((stringify!($name), measure_stack::<$strategy, $strategy>()));
+        };
+    }
+    for_each_strategy!(measure);
+
+    let mut exceeded = false;
+    for (name, latency) in results {
+        let ratio = latency.ratio(&baseline);
+        println!(
+            "{:<40} push: {:>8.1} ns/op  pop: {:>8.1} ns/op  ratio: {:>5.2}",
+            name, latency.push_ns, latency.pop_ns, ratio
+        );
+
+        if ratio > max_ratio {
+            eprintln!("{} exceeds {} times the baseline", name, max_ratio);
+            exceeded = true;
+        }
+    }
+
+    if exceeded {
+        process::exit(1);
+    }
+}
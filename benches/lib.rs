@@ -1,10 +1,17 @@
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{black_box, criterion_group, BenchmarkId, Criterion};
 use elimination_backoff_stack::{
-    strategy::{BackAndForthStrategy, ExpRetryStrategy, NoEliminationStrategy},
+    strategy::{
+        BackAndForthStrategy, CasFailure, Curve, ExpRetryStrategy, HighThroughput, LowLatency,
+        NoEliminationStrategy, Oversubscribed, Preset, SingleProducer, Strategy, Tuning,
+        WithPopInterest,
+    },
     PopStrategy, PushStrategy, Stack as EliminationBackoffStack,
 };
+use rand::{thread_rng, Rng};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
 trait Stack<T: Send>: Send + Sync + Clone {
     fn push(&self, item: T);
@@ -14,8 +21,8 @@ trait Stack<T: Send>: Send + Sync + Clone {
 impl<T, PushS, PopS> Stack<T> for Arc<EliminationBackoffStack<T, PushS, PopS>>
 where
     T: Send + Sync,
-    PushS: PushStrategy + Send + Sync,
-    PopS: PopStrategy + Send + Sync,
+    PushS: PushStrategy,
+    PopS: PopStrategy,
 {
     fn push(&self, item: T) {
         EliminationBackoffStack::push(self, item);
@@ -87,7 +94,7 @@ fn bench_stacks(c: &mut Criterion) {
             })
         });
         group.bench_with_input(
-            BenchmarkId::new("EliminationBackoffStack/back-and-forth", &i),
+            BenchmarkId::new("EliminationBackoffStack/back-and-forth", i),
             &i,
             |b, i| {
                 b.iter(|| {
@@ -110,6 +117,20 @@ fn bench_stacks(c: &mut Criterion) {
                 benchmark(stack, *i, item_count);
             })
         });
+        group.bench_with_input(
+            BenchmarkId::new("EliminationBackoffStack/pop-interest", i),
+            &i,
+            |b, i| {
+                b.iter(|| {
+                    let stack = Arc::new(EliminationBackoffStack::<
+                        _,
+                        WithPopInterest<ExpRetryStrategy>,
+                        WithPopInterest<ExpRetryStrategy>,
+                    >::new());
+                    benchmark(stack, *i, item_count);
+                })
+            },
+        );
         group.bench_with_input(
             BenchmarkId::new("EliminationBackoffStack", i),
             &i,
@@ -124,5 +145,778 @@ fn bench_stacks(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_stacks);
-criterion_main!(benches);
+/// Guard the size and latency of the uncontended fast path. A single thread
+/// never fails a compare-and-swap on the Treiber stack, thus any regression
+/// here stems from code creeping into the inlined fast path of `push` and `pop`.
+fn bench_fast_path(c: &mut Criterion) {
+    fn benchmark(stack: impl Stack<u64>, item_count: u64) {
+        for i in 0..item_count {
+            stack.push(i);
+        }
+
+        for _ in 0..item_count {
+            stack.pop().unwrap();
+        }
+    }
+
+    let mut group = c.benchmark_group("fast-path");
+
+    let item_count = 1_000;
+
+    group.bench_function("EliminationBackoffStack", |b| {
+        let stack = Arc::new(EliminationBackoffStack::<
+            _,
+            ExpRetryStrategy,
+            ExpRetryStrategy,
+        >::new());
+        b.iter(|| benchmark(stack.clone(), item_count))
+    });
+    group.bench_function("EliminationBackoffStack/back-and-forth", |b| {
+        let stack = Arc::new(EliminationBackoffStack::<
+            _,
+            BackAndForthStrategy,
+            BackAndForthStrategy,
+        >::new());
+        b.iter(|| benchmark(stack.clone(), item_count))
+    });
+    group.bench_function("TreiberStack", |b| {
+        let stack = Arc::new(EliminationBackoffStack::<
+            _,
+            NoEliminationStrategy,
+            NoEliminationStrategy,
+        >::new());
+        b.iter(|| benchmark(stack.clone(), item_count))
+    });
+
+    group.finish();
+}
+
+/// Compare picking a random exchanger with a fresh thread local random number
+/// generator handle per attempt against reusing a single handle across all
+/// attempts of an operation, as done by the elimination array. The difference
+/// is the saving per attempt on high-retry workloads.
+fn bench_exchanger_selection(c: &mut Criterion) {
+    let mut group = c.benchmark_group("exchanger-selection");
+
+    let attempts = 64;
+    let num_exchangers = num_cpus::get();
+
+    group.bench_function("thread_rng per attempt", |b| {
+        b.iter(|| {
+            for _ in 0..attempts {
+                black_box(thread_rng().gen_range(0, num_exchangers));
+            }
+        })
+    });
+    group.bench_function("thread_rng per operation", |b| {
+        b.iter(|| {
+            let mut rng = thread_rng();
+            for _ in 0..attempts {
+                black_box(rng.gen_range(0, num_exchangers));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+/// Separate the cost of the first operations after creating a stack from the
+/// steady state cost, relevant when creating short-lived stacks, e.g. one per
+/// request.
+///
+/// - `cold`: Fresh stack on a fresh thread, thus including the registration
+///   of the thread with the epoch garbage collector and allocator warm up.
+/// - `fresh-stack`: Fresh stack on a thread that used a stack before.
+/// - `warm`: Stack and thread in steady state.
+///
+/// Only the first `item_count` push and pop operations are timed, not the
+/// creation of the thread or the stack.
+fn bench_cold_start(c: &mut Criterion) {
+    fn operations<PushS: PushStrategy, PopS: PopStrategy>(
+        stack: &EliminationBackoffStack<u64, PushS, PopS>,
+        item_count: u64,
+    ) -> Duration {
+        let start = Instant::now();
+        for i in 0..item_count {
+            stack.push(i);
+        }
+        for _ in 0..item_count {
+            black_box(stack.pop());
+        }
+        start.elapsed()
+    }
+
+    fn benchmark<PushS: PushStrategy, PopS: PopStrategy>(
+        c: &mut Criterion,
+        name: &str,
+        item_count: u64,
+    ) {
+        let mut group = c.benchmark_group(format!("cold-start/{}", name));
+
+        group.bench_function("cold", |b| {
+            b.iter_custom(|iters| {
+                (0..iters)
+                    .map(|_| {
+                        thread::spawn(move || {
+                            let stack = EliminationBackoffStack::<u64, PushS, PopS>::new();
+                            operations(&stack, item_count)
+                        })
+                        .join()
+                        .unwrap()
+                    })
+                    .sum()
+            })
+        });
+        group.bench_function("fresh-stack", |b| {
+            b.iter_custom(|iters| {
+                (0..iters)
+                    .map(|_| {
+                        let stack = EliminationBackoffStack::<u64, PushS, PopS>::new();
+                        operations(&stack, item_count)
+                    })
+                    .sum()
+            })
+        });
+        group.bench_function("warm", |b| {
+            let stack = EliminationBackoffStack::<u64, PushS, PopS>::new();
+            operations(&stack, item_count);
+            b.iter_custom(|iters| (0..iters).map(|_| operations(&stack, item_count)).sum())
+        });
+
+        group.finish();
+    }
+
+    let item_count = 16;
+
+    benchmark::<ExpRetryStrategy, ExpRetryStrategy>(c, "EliminationBackoffStack", item_count);
+    benchmark::<NoEliminationStrategy, NoEliminationStrategy>(c, "TreiberStack", item_count);
+}
+
+/// Per call cost of [`EliminationBackoffStack::try_pop_weak`] compared to
+/// [`EliminationBackoffStack::pop`], on a non-empty stack, single threaded.
+fn bench_try_pop_weak(c: &mut Criterion) {
+    let mut group = c.benchmark_group("try-pop-weak");
+
+    let item_count = 1_000;
+
+    group.bench_function("pop", |b| {
+        let stack = EliminationBackoffStack::<u64>::new();
+        b.iter(|| {
+            for i in 0..item_count {
+                stack.push(i);
+            }
+            for _ in 0..item_count {
+                black_box(stack.pop());
+            }
+        })
+    });
+    group.bench_function("try_pop_weak", |b| {
+        let stack = EliminationBackoffStack::<u64>::new();
+        b.iter(|| {
+            for i in 0..item_count {
+                stack.push(i);
+            }
+            for _ in 0..item_count {
+                black_box(stack.try_pop_weak());
+            }
+            // Clean up after spurious failures.
+            while stack.pop().is_some() {}
+        })
+    });
+
+    group.finish();
+}
+
+/// Compare bulk producers pushing batches item by item, via
+/// [`EliminationBackoffStack::push_iter`] and via
+/// [`EliminationBackoffStack::push_iter_balanced`], with as many consumers
+/// popping single items concurrently.
+fn bench_bulk_push(c: &mut Criterion) {
+    #[derive(Clone, Copy)]
+    enum Push {
+        Single,
+        Iter,
+        IterBalanced,
+    }
+
+    fn benchmark(push: Push, threads: usize, batches: u64, batch_size: usize) {
+        let stack = Arc::new(EliminationBackoffStack::<u64>::new());
+        let item_count = batches * batch_size as u64;
+
+        let mut handlers = vec![];
+
+        for _ in 0..(threads / 2).max(1) {
+            let push_stack = stack.clone();
+            handlers.push(thread::spawn(move || {
+                for _ in 0..batches {
+                    let batch = 0..batch_size as u64;
+                    match push {
+                        Push::Single => batch.for_each(|i| push_stack.push(i)),
+                        Push::Iter => push_stack.push_iter(batch),
+                        Push::IterBalanced => push_stack.push_iter_balanced(batch),
+                    }
+                }
+            }));
+
+            let pop_stack = stack.clone();
+            handlers.push(thread::spawn(move || {
+                for _ in 0..item_count {
+                    while pop_stack.pop().is_none() {}
+                }
+            }))
+        }
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+    }
+
+    let mut group = c.benchmark_group("bulk-push");
+    group.sample_size(10);
+
+    let threads = num_cpus::get().max(2);
+    let (batches, batch_size) = (16, 64);
+
+    for (name, push) in [
+        ("push", Push::Single),
+        ("push_iter", Push::Iter),
+        ("push_iter_balanced", Push::IterBalanced),
+    ] {
+        group.bench_with_input(BenchmarkId::new(name, threads), &threads, |b, t| {
+            b.iter(|| benchmark(push, *t, batches, batch_size))
+        });
+    }
+
+    group.finish();
+}
+
+/// Compare the time producers take to push while a consumer drains the stack
+/// concurrently, either via a loop of [`EliminationBackoffStack::pop`] or via
+/// [`EliminationBackoffStack::try_pop_each_until_empty`], yielding between
+/// batches.
+fn bench_cooperative_drain(c: &mut Criterion) {
+    #[derive(Clone, Copy)]
+    enum Drain {
+        PopLoop,
+        Cooperative(usize),
+    }
+
+    fn benchmark(drain: Drain, producers: usize, item_count: u64) -> Duration {
+        let stack = Arc::new(EliminationBackoffStack::<u64>::new());
+        let done = Arc::new(AtomicBool::new(false));
+
+        let drainer = {
+            let (stack, done) = (stack.clone(), done.clone());
+            thread::spawn(move || {
+                while !done.load(Relaxed) {
+                    match drain {
+                        Drain::PopLoop => {
+                            black_box(stack.pop());
+                        }
+                        Drain::Cooperative(batch) => {
+                            stack.try_pop_each_until_empty(
+                                batch,
+                                |i| {
+                                    black_box(i);
+                                },
+                                || !done.load(Relaxed),
+                            );
+                        }
+                    }
+                }
+            })
+        };
+
+        let start = Instant::now();
+        let handlers = (0..producers)
+            .map(|_| {
+                let stack = stack.clone();
+                thread::spawn(move || {
+                    for i in 0..item_count {
+                        stack.push(i);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+        let elapsed = start.elapsed();
+
+        done.store(true, Relaxed);
+        drainer.join().unwrap();
+
+        elapsed
+    }
+
+    let mut group = c.benchmark_group("cooperative-drain");
+    group.sample_size(10);
+
+    let producers = (num_cpus::get() - 1).max(1);
+    let item_count = 10_000;
+
+    for (name, drain) in [
+        ("pop-loop", Drain::PopLoop),
+        ("cooperative/4", Drain::Cooperative(4)),
+        ("cooperative/32", Drain::Cooperative(32)),
+    ] {
+        group.bench_with_input(BenchmarkId::new(name, producers), &producers, |b, p| {
+            b.iter_custom(|iters| (0..iters).map(|_| benchmark(drain, *p, item_count)).sum())
+        });
+    }
+
+    group.finish();
+}
+
+/// Matrix of the [`Preset`]s against the scenarios they are tuned for:
+/// balanced producers and consumers on as many threads as cores as well as
+/// four times oversubscribed, and a single producer feeding the remaining
+/// threads.
+fn bench_presets(c: &mut Criterion) {
+    fn benchmark<P: Preset + Default>(producers: usize, consumers: usize, item_count: usize)
+    where
+        P::Push: 'static,
+        P::Pop: 'static,
+    {
+        let stack = Arc::new(
+            EliminationBackoffStack::<u64>::builder()
+                .preset(P::default())
+                .build(),
+        );
+        let popped = Arc::new(AtomicUsize::new(0));
+        let total = producers * item_count;
+
+        let mut handlers = vec![];
+
+        for _ in 0..producers {
+            let stack = stack.clone();
+            handlers.push(thread::spawn(move || {
+                for i in 0..item_count as u64 {
+                    stack.push(i);
+                }
+            }));
+        }
+
+        for _ in 0..consumers {
+            let stack = stack.clone();
+            let popped = popped.clone();
+            handlers.push(thread::spawn(move || {
+                while popped.load(Relaxed) < total {
+                    if stack.pop().is_some() {
+                        popped.fetch_add(1, Relaxed);
+                    }
+                }
+            }));
+        }
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+    }
+
+    fn bench<P: Preset + Default>(c: &mut Criterion, name: &str)
+    where
+        P::Push: 'static,
+        P::Pop: 'static,
+    {
+        let mut group = c.benchmark_group("presets");
+        group.sample_size(10);
+
+        let cpus = num_cpus::get().max(2);
+        let item_count = 1_000;
+
+        for (scenario, producers, consumers) in [
+            ("balanced", cpus / 2, cpus / 2),
+            ("oversubscribed", 2 * cpus, 2 * cpus),
+            ("single-producer", 1, cpus - 1),
+        ] {
+            group.bench_function(BenchmarkId::new(name, scenario), |b| {
+                b.iter(|| benchmark::<P>(producers, consumers, item_count))
+            });
+        }
+
+        group.finish();
+    }
+
+    bench::<LowLatency>(c, "LowLatency");
+    bench::<HighThroughput>(c, "HighThroughput");
+    bench::<Oversubscribed>(c, "Oversubscribed");
+    bench::<SingleProducer>(c, "SingleProducer");
+}
+
+static CAS_FAILURES: AtomicUsize = AtomicUsize::new(0);
+static OPERATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Wraps a [`Strategy`], counting failed compare-and-swaps and operations, and
+/// pausing after failed compare-and-swaps on the Treiber stack only if
+/// `PAUSE`.
+struct Counting<S, const PAUSE: bool>(S);
+
+impl<S: Strategy, const PAUSE: bool> Strategy for Counting<S, PAUSE> {
+    fn new() -> Self {
+        OPERATIONS.fetch_add(1, Relaxed);
+        Counting(S::new())
+    }
+
+    fn use_elimination_array(&mut self) -> bool {
+        self.0.use_elimination_array()
+    }
+
+    fn try_stack(&mut self) -> bool {
+        self.0.try_stack()
+    }
+
+    fn try_elimination_array(&mut self) -> bool {
+        self.0.try_elimination_array()
+    }
+
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        self.0.num_exchangers(total)
+    }
+
+    fn try_start_exchange(&mut self) -> bool {
+        self.0.try_start_exchange()
+    }
+
+    fn retry_check_exchanged(&mut self) -> bool {
+        self.0.retry_check_exchanged()
+    }
+
+    fn try_exchange(&mut self) -> bool {
+        self.0.try_exchange()
+    }
+
+    fn on_contention(&mut self) {
+        self.0.on_contention()
+    }
+
+    fn on_no_contention(&mut self) {
+        self.0.on_no_contention()
+    }
+
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        CAS_FAILURES.fetch_add(1, Relaxed);
+        self.0.on_cas_failure(reason)
+    }
+
+    fn on_cas_success(&mut self) {
+        self.0.on_cas_success()
+    }
+
+    fn after_cas_failure(&mut self) {
+        if PAUSE {
+            self.0.after_cas_failure()
+        }
+    }
+}
+
+/// Compare pausing after a failed compare-and-swap on the Treiber stack, via
+/// [`Strategy::after_cas_failure`], with retrying right away. Next to the
+/// timing, prints the failed compare-and-swaps per operation.
+fn bench_cas_backoff(c: &mut Criterion) {
+    fn benchmark<S: Strategy + 'static>(threads: usize, item_count: u64) {
+        let stack = Arc::new(EliminationBackoffStack::<u64, S, S>::new());
+
+        let handlers = (0..threads)
+            .map(|_| {
+                let stack = stack.clone();
+                thread::spawn(move || {
+                    for i in 0..item_count {
+                        stack.push(i);
+                        black_box(stack.pop());
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+    }
+
+    fn bench<S: Strategy + 'static>(c: &mut Criterion, name: &str, threads: usize) {
+        let mut group = c.benchmark_group("cas-backoff");
+        group.sample_size(10);
+
+        CAS_FAILURES.store(0, Relaxed);
+        OPERATIONS.store(0, Relaxed);
+        group.bench_with_input(BenchmarkId::new(name, threads), &threads, |b, t| {
+            b.iter(|| benchmark::<S>(*t, 1_000))
+        });
+        println!(
+            "cas-backoff/{}/{}: {:.3} failed compare-and-swaps per operation",
+            name,
+            threads,
+            CAS_FAILURES.load(Relaxed) as f64 / OPERATIONS.load(Relaxed).max(1) as f64,
+        );
+
+        group.finish();
+    }
+
+    let threads = num_cpus::get().max(2);
+    bench::<Counting<ExpRetryStrategy, true>>(c, "ExpRetryStrategy/pause", threads);
+    bench::<Counting<ExpRetryStrategy, false>>(c, "ExpRetryStrategy/no-pause", threads);
+    bench::<Counting<NoEliminationStrategy, true>>(c, "NoEliminationStrategy/pause", threads);
+    bench::<Counting<NoEliminationStrategy, false>>(c, "NoEliminationStrategy/no-pause", threads);
+}
+
+/// Compare the growth curves of the number of exchangers considered by the
+/// [`ExpRetryStrategy`] across thread counts, see [`Curve`].
+fn bench_exchanger_curves(c: &mut Criterion) {
+    fn benchmark(curve: Curve, threads: usize, item_count: u64) {
+        let stack = Arc::new(
+            EliminationBackoffStack::<u64>::builder()
+                .tuning(Tuning {
+                    curve,
+                    ..Tuning::default()
+                })
+                .build(),
+        );
+
+        let handlers = (0..threads)
+            .map(|_| {
+                let stack = stack.clone();
+                thread::spawn(move || {
+                    for i in 0..item_count {
+                        stack.push(i);
+                        black_box(stack.pop());
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+    }
+
+    let mut group = c.benchmark_group("exchanger-curves");
+    group.sample_size(10);
+
+    let mut threads = 2;
+    while threads <= num_cpus::get().max(2) {
+        for curve in Curve::ALL.iter().copied() {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", curve), threads),
+                &threads,
+                |b, t| b.iter(|| benchmark(curve, *t, 1_000)),
+            );
+        }
+        threads *= 2;
+    }
+    group.finish();
+}
+
+/// Wraps a [`Strategy`], freezing the calling thread for 1 to 10 ms once in a
+/// while if `FREEZE`, simulating preemption mid-operation. Freezes both while
+/// retrying on the Treiber stack and while parked in an exchanger slot, the
+/// latter leaving a partner waiting on the offer.
+struct Freezing<S, const FREEZE: bool>(S);
+
+impl<S, const FREEZE: bool> Freezing<S, FREEZE> {
+    /// Odds of freezing per decision.
+    const ODDS: u32 = 10_000;
+
+    fn maybe_freeze(&self) {
+        let mut rng = thread_rng();
+        if FREEZE && rng.gen_ratio(1, Self::ODDS) {
+            thread::sleep(Duration::from_millis(rng.gen_range(1, 11)));
+        }
+    }
+}
+
+impl<S: Strategy, const FREEZE: bool> Strategy for Freezing<S, FREEZE> {
+    fn new() -> Self {
+        Freezing(S::new())
+    }
+
+    fn use_elimination_array(&mut self) -> bool {
+        self.0.use_elimination_array()
+    }
+
+    fn try_stack(&mut self) -> bool {
+        self.maybe_freeze();
+        self.0.try_stack()
+    }
+
+    fn try_elimination_array(&mut self) -> bool {
+        self.0.try_elimination_array()
+    }
+
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        self.0.num_exchangers(total)
+    }
+
+    fn try_start_exchange(&mut self) -> bool {
+        self.0.try_start_exchange()
+    }
+
+    fn retry_check_exchanged(&mut self) -> bool {
+        self.maybe_freeze();
+        self.0.retry_check_exchanged()
+    }
+
+    fn try_exchange(&mut self) -> bool {
+        self.0.try_exchange()
+    }
+
+    fn on_contention(&mut self) {
+        self.0.on_contention()
+    }
+
+    fn on_no_contention(&mut self) {
+        self.0.on_no_contention()
+    }
+
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        self.0.on_cas_failure(reason)
+    }
+
+    fn on_cas_success(&mut self) {
+        self.0.on_cas_success()
+    }
+
+    fn after_cas_failure(&mut self) {
+        self.0.after_cas_failure()
+    }
+}
+
+/// Compare the throughput of each strategy with threads randomly frozen
+/// mid-operation, see [`Freezing`], to the one without. Next to the timing,
+/// prints the throughput retained under chaos, i.e. how well a strategy
+/// tolerates descheduled partners, e.g. parked in an exchanger slot.
+fn bench_chaos(c: &mut Criterion) {
+    fn benchmark<S: Strategy + 'static>(threads: usize, item_count: u64) -> Duration {
+        let stack = Arc::new(EliminationBackoffStack::<u64, S, S>::new());
+
+        let start = Instant::now();
+        let handlers = (0..threads)
+            .map(|_| {
+                let stack = stack.clone();
+                thread::spawn(move || {
+                    for i in 0..item_count {
+                        stack.push(i);
+                        black_box(stack.pop());
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+        start.elapsed()
+    }
+
+    fn bench<S: Strategy + 'static>(c: &mut Criterion, name: &str, threads: usize) {
+        let mut group = c.benchmark_group("chaos");
+        group.sample_size(10);
+
+        let mut per_iter = [Duration::default(); 2];
+        for (variant, per_iter) in ["calm", "chaos"].iter().zip(per_iter.iter_mut()) {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{}/{}", name, variant), threads),
+                &threads,
+                |b, t| {
+                    b.iter_custom(|iters| {
+                        let total = (0..iters)
+                            .map(|_| match *variant {
+                                "calm" => benchmark::<Freezing<S, false>>(*t, 1_000),
+                                _ => benchmark::<Freezing<S, true>>(*t, 1_000),
+                            })
+                            .sum::<Duration>();
+                        *per_iter = total / iters.max(1) as u32;
+                        total
+                    })
+                },
+            );
+        }
+        // Unless filtered out.
+        if per_iter.iter().all(|d| *d > Duration::default()) {
+            println!(
+                "chaos/{}/{}: {:.1}% of calm throughput retained",
+                name,
+                threads,
+                100.0 * per_iter[0].as_secs_f64() / per_iter[1].as_secs_f64(),
+            );
+        }
+
+        group.finish();
+    }
+
+    let threads = num_cpus::get().max(2);
+    bench::<ExpRetryStrategy>(c, "ExpRetryStrategy", threads);
+    bench::<BackAndForthStrategy>(c, "BackAndForthStrategy", threads);
+    bench::<WithPopInterest<ExpRetryStrategy>>(c, "WithPopInterest", threads);
+    bench::<NoEliminationStrategy>(c, "NoEliminationStrategy", threads);
+}
+
+criterion_group!(
+    benches,
+    bench_stacks,
+    bench_fast_path,
+    bench_exchanger_selection,
+    bench_cold_start,
+    bench_try_pop_weak,
+    bench_bulk_push,
+    bench_cooperative_drain,
+    bench_presets,
+    bench_cas_backoff,
+    bench_exchanger_curves,
+    bench_chaos
+);
+
+/// Record a contended workload via instrumented operations and write its
+/// contention report as JSON, for CI to store and diff across commits, see
+/// `.github/workflows/rust.yml`. Written to the path in `CONTENTION_REPORT`,
+/// defaulting to `target/contention-report.json`.
+#[cfg(feature = "serde")]
+fn write_contention_report() {
+    use elimination_backoff_stack::statistic::Report;
+
+    let item_count = 10_000;
+    let threads = num_cpus::get().max(2) / 2;
+    let stack = Arc::new(EliminationBackoffStack::<usize>::new());
+
+    let handlers = (0..threads)
+        .flat_map(|_| {
+            let push_stack = stack.clone();
+            let pop_stack = stack.clone();
+            [
+                thread::spawn(move || {
+                    let mut events = vec![];
+                    for i in 0..item_count {
+                        push_stack.instrumented_push(i, &mut events);
+                    }
+                    events
+                }),
+                thread::spawn(move || {
+                    let mut events = vec![];
+                    for _ in 0..item_count {
+                        pop_stack.instrumented_pop(&mut events);
+                    }
+                    events
+                }),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let events = handlers
+        .into_iter()
+        .flat_map(|handler| handler.join().unwrap())
+        .collect();
+
+    let path = std::env::var("CONTENTION_REPORT").unwrap_or_else(|_| {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/target/contention-report.json").to_string()
+    });
+    std::fs::write(&path, Report::new(events).to_json()).expect("report to be written");
+    println!("contention report written to {}", path);
+}
+
+// Like `criterion_main!`, though writing the contention report first.
+fn main() {
+    #[cfg(feature = "serde")]
+    write_contention_report();
+
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,43 @@
+/*
+ * C bindings of the elimination back-off stack, see `src/ffi.rs`.
+ *
+ * Build the `cdylib` with the `ffi` feature:
+ *
+ *     cargo rustc --release --features ffi --crate-type cdylib
+ */
+
+#ifndef ELIMINATION_BACKOFF_STACK_H
+#define ELIMINATION_BACKOFF_STACK_H
+
+#include <stdbool.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Stack of opaque pointers owned by the caller. Safe to share across threads. */
+struct ebs_stack;
+
+/* Allocate a new, empty stack. Never returns NULL. */
+struct ebs_stack *ebs_stack_new(void);
+
+/* Push `item`, which may be NULL. */
+void ebs_stack_push(const struct ebs_stack *stack, void *item);
+
+/* Pop an item into `*item`, returning whether the stack held one. */
+bool ebs_stack_pop(const struct ebs_stack *stack, void **item);
+
+/* Whether the stack appeared empty. */
+bool ebs_stack_is_empty(const struct ebs_stack *stack);
+
+/*
+ * Free the stack, calling `free_item`, unless NULL, on each item left. No
+ * other thread may use the stack concurrently or afterwards.
+ */
+void ebs_stack_free(struct ebs_stack *stack, void (*free_item)(void *));
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ELIMINATION_BACKOFF_STACK_H */
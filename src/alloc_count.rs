@@ -0,0 +1,126 @@
+//! Heap allocations per thread, counted by a global allocator, thus
+//! attributable to the operations of the thread, e.g. node and exchanger
+//! allocations, see [`crate::event::Operation::allocations`].
+//!
+//! Installed by the test binary of this crate. Other binaries install the
+//! [`CountingAllocator`] themselves, given the `alloc-count` feature.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Whether the [`CountingAllocator`] counted any allocation, i.e. is
+/// installed.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+    /// Whether allocations of this thread are currently excluded, see
+    /// [`uncounted`].
+    static UNCOUNTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Global allocator counting allocations of the current thread, delegating
+/// to [`System`].
+///
+/// Once installed, each [`Operation`](crate::event::Operation) of an
+/// instrumented push or pop operation carries its allocations, e.g. to
+/// confirm that a strategy or an item type avoids allocating nodes.
+///
+/// ```rust
+/// # use elimination_backoff_stack::alloc_count::CountingAllocator;
+/// # use elimination_backoff_stack::event::{Event, EventRecorder, Operation};
+/// # use elimination_backoff_stack::Stack;
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+///
+/// #[derive(Default)]
+/// struct Allocations(Vec<Option<u64>>);
+///
+/// impl EventRecorder for Allocations {
+///     fn record(&mut self, _event: Event) {}
+///
+///     fn record_operation(&mut self, operation: &Operation<'_>) {
+///         self.0.push(operation.allocations());
+///     }
+/// }
+///
+/// let stack = Stack::<u64>::new();
+/// // Registers the thread with the garbage collector, allocating once.
+/// stack.push(0);
+///
+/// let mut allocations = Allocations::default();
+/// stack.instrumented_push(1, &mut allocations);
+/// // The node of the item.
+/// assert_eq!(allocations.0, vec![Some(1)]);
+/// ```
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+fn count() {
+    if !INSTALLED.load(Relaxed) {
+        INSTALLED.store(true, Relaxed);
+    }
+    // Thread locals are unavailable while their thread is torn down, in which
+    // case the allocation is not attributable to any operation anyway.
+    let _ = UNCOUNTED.try_with(|uncounted| {
+        if !uncounted.get() {
+            let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+        }
+    });
+}
+
+/// Allocations of the current thread so far, `None` unless the
+/// [`CountingAllocator`] is installed.
+pub fn allocations() -> Option<u64> {
+    INSTALLED.load(Relaxed).then(|| ALLOCATIONS.with(Cell::get))
+}
+
+/// Run `f` without counting its allocations, e.g. the ones of an event
+/// recorder, which are not attributable to the recorded operation.
+pub(crate) fn uncounted<R>(f: impl FnOnce() -> R) -> R {
+    let previous = UNCOUNTED.with(|u| u.replace(true));
+    let result = f();
+    UNCOUNTED.with(|u| u.set(previous));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_allocations_unless_uncounted() {
+        let before = allocations().unwrap();
+        let boxed = std::hint::black_box(Box::new(1u64));
+        assert_eq!(allocations(), Some(before + 1));
+
+        uncounted(|| drop(std::hint::black_box(Box::new(2u64))));
+        drop(boxed);
+        assert_eq!(allocations(), Some(before + 1));
+    }
+}
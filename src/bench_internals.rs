@@ -0,0 +1,85 @@
+//! Internals exposed to `benches/exchanger.rs`, thus exchanger level changes
+//! can be measured in isolation from the full stack, and to
+//! `benches/fastpath.rs` as a baseline. Not part of the public API.
+//!
+//! The exchanger is not available with the `no-elimination` feature, which
+//! compiles it out.
+
+#[cfg(not(feature = "no-elimination"))]
+use crate::event::{NoOpRecorder, OperationId};
+#[cfg(not(feature = "no-elimination"))]
+use crate::exchanger;
+use crate::strategy::ExpRetryStrategy;
+use crate::treiber_stack::{self, PopResult};
+
+/// Single exchanger using the default strategy for each attempt.
+#[cfg(not(feature = "no-elimination"))]
+pub struct Exchanger<T>(exchanger::Exchanger<T>);
+
+#[cfg(not(feature = "no-elimination"))]
+impl<T> Exchanger<T> {
+    pub fn new() -> Self {
+        Exchanger(exchanger::Exchanger::new())
+    }
+
+    /// Offer `item` to a concurrent [`Exchanger::pop`], returning it if none
+    /// took it.
+    pub fn push(&self, item: T) -> Result<(), T> {
+        self.0.exchange_push(
+            item,
+            OperationId::next(),
+            &mut ExpRetryStrategy::new(),
+            &mut NoOpRecorder {},
+        )
+    }
+
+    /// Take the item offered by a concurrent [`Exchanger::push`], if any.
+    pub fn pop(&self) -> Option<T> {
+        self.0
+            .exchange_pop(
+                OperationId::next(),
+                &mut ExpRetryStrategy::new(),
+                &mut NoOpRecorder {},
+            )
+            .ok()
+    }
+}
+
+#[cfg(not(feature = "no-elimination"))]
+impl<T> Default for Exchanger<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Plain Treiber stack, retrying its compare-and-swap until it succeeds,
+/// without elimination, item hooks or instrumentation.
+pub struct TreiberStack<T>(treiber_stack::TreiberStack<T>);
+
+impl<T> TreiberStack<T> {
+    pub fn new() -> Self {
+        TreiberStack(treiber_stack::TreiberStack::new())
+    }
+
+    pub fn push(&self, mut item: T) {
+        while let Err(rejected) = self.0.push(item, &mut ExpRetryStrategy::new()) {
+            item = rejected;
+        }
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        loop {
+            match self.0.pop(&mut ExpRetryStrategy::new()) {
+                PopResult::Popped(item) => return Some(item),
+                PopResult::Empty => return None,
+                PopResult::Contended => {}
+            }
+        }
+    }
+}
+
+impl<T> Default for TreiberStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,317 @@
+//! Stack holding at most a fixed number of items, e.g. a buffer between
+//! producers and consumers bounding memory usage.
+//!
+//! Each push operation reserves a slot on a counter before touching the
+//! stack, each pop operation releases one after taking an item, thus the
+//! counter never exceeds the capacity. Items in flight, e.g. reserved though
+//! not pushed yet, or popped though not released yet, count as held, thus
+//! the stack might reject a push operation while appearing to have room,
+//! never the other way round.
+//!
+//! A push operation blocked on a full stack parks like a pop operation on an
+//! empty one, see `src/park.rs`, a read-modify-write on the counter taking the
+//! place of the one on the head of the Treiber stack. Likewise a task awaiting
+//! [`BoundedStack::push_async`] registers its waker, while one awaiting
+//! [`BoundedStack::pop_async`] releases a slot once it took an item.
+
+use crate::park::Park;
+use crate::pop_async::BoundedPopAsync;
+use crate::push_async::PushAsync;
+use crate::strategy::ExpRetryStrategy;
+use crate::{Config, PopStrategy, PushStrategy, Stack};
+use crossbeam::utils::CachePadded;
+use std::fmt;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::{AcqRel, Relaxed};
+
+/// [`Stack`] holding at most `capacity` items, rejecting or blocking push
+/// operations beyond.
+///
+/// ```rust
+/// # use elimination_backoff_stack::BoundedStack;
+/// let stack = BoundedStack::<u32>::new(2);
+/// stack.push(1).unwrap();
+/// stack.push(2).unwrap();
+///
+/// // E.g. shed load, or apply backpressure via `push_blocking`.
+/// let rejected = stack.push(3).unwrap_err();
+/// assert_eq!(rejected.into_inner(), 3);
+///
+/// assert_eq!(stack.pop(), Some(2));
+/// stack.push(3).unwrap();
+/// ```
+pub struct BoundedStack<T, PushS = ExpRetryStrategy, PopS = ExpRetryStrategy> {
+    pub(crate) stack: Stack<T, PushS, PopS>,
+    capacity: usize,
+    /// Number of slots reserved, see module documentation.
+    reserved: CachePadded<AtomicUsize>,
+    /// Push operations waiting for a slot, see
+    /// [`BoundedStack::push_blocking`] and [`BoundedStack::push_async`].
+    pub(crate) not_full: Park,
+}
+
+impl<T, PushS, PopS> BoundedStack<T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    /// Create a stack holding at most `capacity` items.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        BoundedStack::with_config(capacity, Config::default())
+    }
+
+    /// Create a stack holding at most `capacity` items, configured at run
+    /// time, see [`Stack::with_config`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero, or on an invalid configuration, see
+    /// [`Stack::with_config`].
+    pub fn with_config(capacity: usize, config: Config) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
+
+        BoundedStack {
+            stack: Stack::with_config(config),
+            capacity,
+            reserved: CachePadded::new(AtomicUsize::new(0)),
+            not_full: Park::new(),
+        }
+    }
+
+    /// Push `item`, handing it back wrapped in [`Full`] if the stack holds
+    /// `capacity` items already.
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn push(&self, item: T) -> Result<(), Full<T>> {
+        if !self.reserve() {
+            return Err(Full(item));
+        }
+        self.stack.push(item);
+        Ok(())
+    }
+
+    /// Push `item`, waiting for a pop operation while the stack holds
+    /// `capacity` items already.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::BoundedStack;
+    /// # use std::sync::Arc;
+    /// # use std::thread;
+    /// let stack = Arc::new(BoundedStack::<u8>::new(1));
+    /// stack.push(1).unwrap();
+    ///
+    /// let producer = {
+    ///     let stack = stack.clone();
+    ///     thread::spawn(move || stack.push_blocking(2))
+    /// };
+    ///
+    /// assert_eq!(stack.pop_blocking(), 1);
+    /// producer.join().unwrap();
+    /// assert_eq!(stack.pop(), Some(2));
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn push_blocking(&self, item: T) {
+        loop {
+            if self.reserve() {
+                return self.stack.push(item);
+            }
+
+            let registration = self.not_full.register();
+            // A pop operation releasing a slot from here on either is found
+            // by `confirm_full` or finds this thread registered.
+            if self.confirm_full() {
+                registration.wait();
+            }
+        }
+    }
+
+    /// Push `item`, awaiting a pop operation while the stack holds `capacity`
+    /// items already.
+    ///
+    /// Like [`BoundedStack::push_blocking`], though registers the waker of
+    /// the calling task instead of blocking the thread, see [`PushAsync`].
+    pub fn push_async(&self, item: T) -> PushAsync<'_, T, PushS, PopS> {
+        PushAsync::new(self, item)
+    }
+
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn pop(&self) -> Option<T> {
+        let item = self.stack.pop()?;
+        self.release();
+        Some(item)
+    }
+
+    /// Pop an item, awaiting a push operation while the stack is empty, see
+    /// [`Stack::pop_async`].
+    ///
+    /// Releases the slot of the item once it resolves, thus tasks awaiting
+    /// [`BoundedStack::push_async`] and [`BoundedStack::pop_async`] form a
+    /// bounded async work buffer.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::BoundedStack;
+    /// let stack = BoundedStack::<u8>::new(1);
+    ///
+    /// let (_, item) = futures::executor::block_on(async {
+    ///     futures::join!(
+    ///         async {
+    ///             stack.push_async(1).await;
+    ///             stack.push_async(2).await;
+    ///         },
+    ///         async { stack.pop_async().await + stack.pop_async().await },
+    ///     )
+    /// });
+    /// assert_eq!(item, 3);
+    /// ```
+    pub fn pop_async(&self) -> BoundedPopAsync<'_, T, PushS, PopS> {
+        BoundedPopAsync::new(self)
+    }
+
+    /// Pop an item, waiting for a push operation while the stack is empty,
+    /// see [`Stack::pop_blocking`].
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn pop_blocking(&self) -> T {
+        let item = self.stack.pop_blocking();
+        self.release();
+        item
+    }
+
+    /// Maximum number of items held.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// See [`Stack::len`].
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// See [`Stack::is_empty`].
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Whether a push operation would have been rejected, by the time it
+    /// returns possibly outdated like [`BoundedStack::len`].
+    pub fn is_full(&self) -> bool {
+        self.reserved.load(Relaxed) >= self.capacity
+    }
+
+    /// Reserve a slot, `false` if all are taken.
+    pub(crate) fn reserve(&self) -> bool {
+        self.reserved
+            .fetch_update(AcqRel, Relaxed, |reserved| {
+                (reserved < self.capacity).then(|| reserved + 1)
+            })
+            .is_ok()
+    }
+
+    /// Whether all slots are taken, via a read-modify-write, thus ordered
+    /// against the one of a pop operation releasing a slot.
+    pub(crate) fn confirm_full(&self) -> bool {
+        self.reserved.fetch_add(0, AcqRel) >= self.capacity
+    }
+
+    /// Release the slot of a popped item.
+    pub(crate) fn release(&self) {
+        self.reserved.fetch_sub(1, AcqRel);
+        self.not_full.wake_one();
+    }
+}
+
+/// Prints a diagnostic summary instead of the items, like the one of a
+/// [`Stack`].
+impl<T, PushS, PopS> fmt::Debug for BoundedStack<T, PushS, PopS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoundedStack")
+            .field("reserved", &self.reserved.load(Relaxed))
+            .field("capacity", &self.capacity)
+            .field("stack", &self.stack)
+            .finish()
+    }
+}
+
+/// Error of [`BoundedStack::push`], handing back the item the full stack
+/// rejected.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Full<T>(pub T);
+
+impl<T> Full<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// Omits the item, like the errors of the channels of the standard library.
+impl<T> fmt::Debug for Full<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Full(..)")
+    }
+}
+
+impl<T> fmt::Display for Full<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("push operation rejected as the stack is at capacity")
+    }
+}
+
+impl<T> std::error::Error for Full<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn rejects_beyond_capacity() {
+        let stack = BoundedStack::<u32>::new(3);
+        for i in 0..3 {
+            stack.push(i).unwrap();
+        }
+        assert!(stack.is_full());
+        assert_eq!(stack.push(3), Err(Full(3)));
+        assert_eq!(stack.len(), 3);
+
+        assert_eq!(stack.pop(), Some(2));
+        assert!(!stack.is_full());
+        stack.push(3).unwrap();
+        assert_eq!(stack.push(4), Err(Full(4)));
+    }
+
+    #[test]
+    fn concurrent_never_exceeds_capacity() {
+        let (threads, items_per_thread, capacity) = (4, 10_000, 8);
+        let stack = BoundedStack::<usize>::new(capacity);
+
+        let popped = thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    for i in 0..items_per_thread {
+                        stack.push_blocking(i);
+                        assert!(stack.len() <= capacity);
+                    }
+                });
+            }
+            let consumers = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..items_per_thread)
+                            .map(|_| stack.pop_blocking())
+                            .sum::<usize>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            consumers
+                .into_iter()
+                .map(|consumer| consumer.join().unwrap())
+                .sum::<usize>()
+        });
+
+        assert_eq!(popped, threads * (0..items_per_thread).sum::<usize>());
+        assert!(stack.is_empty());
+        assert_eq!(stack.reserved.load(Relaxed), 0);
+    }
+}
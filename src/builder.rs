@@ -0,0 +1,346 @@
+//! Step-wise construction of a [`Stack`], see [`Stack::builder`].
+//!
+//! The strategies are chosen at compile time via type parameters, e.g. via a
+//! [`Preset`]. Everything else, i.e. the elimination array and the
+//! [`Tuning`] of the strategies, is configured at run time, see [`Config`]. So are the
+//! strategies themselves with a [`DynStrategy`], see [`Builder::strategy`].
+
+use crate::strategy::{DynStrategy, ExpRetryStrategy, Preset, StrategyKind, Tuning};
+use crate::{ItemHooks, PopStrategy, PushStrategy, ResizeConfig, Stack};
+use std::marker::PhantomData;
+
+/// Run time configuration of a [`Stack`], see [`Stack::with_config`].
+///
+/// Everything not fixed by the type parameters, thus can be read from a
+/// configuration file with the `serde` feature, missing fields taking their
+/// defaults.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Config {
+    /// Number of exchangers of the elimination array, growing and shrinking
+    /// with contention.
+    pub resize: ResizeConfig,
+    /// Passed to the strategy of each operation.
+    pub tuning: Tuning,
+    /// Whether operations consider the elimination array at all. If not,
+    /// operations only retry the Treiber stack, whatever their strategy
+    /// decides, and the elimination array stays at
+    /// [`ResizeConfig::min_exchangers`].
+    ///
+    /// Unlike the `no-elimination` feature, the elimination array is still
+    /// compiled in, thus costs a branch per attempt.
+    pub elimination: bool,
+    /// Lowest [`ResizeConfig::parallelism`] at which operations consider the
+    /// elimination array. Below, e.g. on a single CPU, a push and a pop
+    /// operation only ever meet on an exchanger if one of them is preempted
+    /// while offering or taking, thus waiting for a partner is wasted, and the
+    /// stack behaves as if [`Config::elimination`] were `false`.
+    ///
+    /// Set to `1` to consider the elimination array regardless, e.g. with many
+    /// more threads than CPUs.
+    pub min_parallelism: usize,
+}
+
+impl Config {
+    /// Whether operations consider the elimination array, see
+    /// [`Config::elimination`] and [`Config::min_parallelism`].
+    pub(crate) fn eliminates(&self) -> bool {
+        self.elimination && self.resize.parallelism >= self.min_parallelism
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            resize: ResizeConfig::default(),
+            tuning: Tuning::default(),
+            elimination: true,
+            min_parallelism: 2,
+        }
+    }
+}
+
+/// Builder of a [`Stack`], created via [`Stack::builder`].
+///
+/// ```rust
+/// # use elimination_backoff_stack::{ResizeConfig, Stack};
+/// # use elimination_backoff_stack::strategy::HighThroughput;
+/// let stack = Stack::<u8>::builder()
+///     .preset(HighThroughput)
+///     .resize_config(ResizeConfig::with_parallelism(4))
+///     .build();
+///
+/// stack.push(1);
+/// assert_eq!(stack.pop(), Some(1));
+/// ```
+pub struct Builder<T, PushS = ExpRetryStrategy, PopS = ExpRetryStrategy> {
+    config: Config,
+    hooks: Option<Box<dyn ItemHooks<T>>>,
+    // See `Stack::phantom`.
+    item: PhantomData<fn() -> T>,
+    strategies: PhantomData<fn() -> (PushS, PopS)>,
+}
+
+impl<T, PushS, PopS> Builder<T, PushS, PopS> {
+    pub(crate) fn new() -> Self {
+        Builder {
+            config: Config::default(),
+            hooks: None,
+            item: PhantomData,
+            strategies: PhantomData,
+        }
+    }
+
+    /// Use the strategies and the elimination array configuration of
+    /// `preset`, replacing the ones chosen so far. Keeps the rest of the
+    /// [`Config`], as well as the seed, see [`Builder::seed`].
+    pub fn preset<P: Preset>(self, _preset: P) -> Builder<T, P::Push, P::Pop> {
+        Builder {
+            config: Config {
+                resize: ResizeConfig {
+                    seed: self.config.resize.seed,
+                    ..P::resize_config()
+                },
+                ..self.config
+            },
+            hooks: self.hooks,
+            item: PhantomData,
+            strategies: PhantomData,
+        }
+    }
+
+    /// Grow and shrink the elimination array as configured, see
+    /// [`Stack::with_resize_config`].
+    pub fn resize_config(mut self, config: ResizeConfig) -> Self {
+        self.config.resize = config;
+        self
+    }
+
+    /// Keep the elimination array at `num_exchangers` instead of growing and
+    /// shrinking it, otherwise keeping the [`ResizeConfig`].
+    pub fn exchangers(mut self, num_exchangers: usize) -> Self {
+        self.config.resize.min_exchangers = num_exchangers;
+        self.config.resize.max_exchangers = num_exchangers;
+        self
+    }
+
+    /// Draw the permutation operations select exchangers through from `seed`
+    /// instead of a random one, e.g. to reproduce a run, see
+    /// [`ResizeConfig::seed`].
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<u8>::builder().exchangers(4).seed(42).build();
+    /// # stack.push(1);
+    /// ```
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.resize.seed = Some(seed);
+        self
+    }
+
+    /// Tune the strategies of every operation on the stack, see [`Tuning`].
+    pub fn tuning(mut self, tuning: Tuning) -> Self {
+        self.config.tuning = tuning;
+        self
+    }
+
+    /// Consider the elimination array or not, see [`Config::elimination`].
+    pub fn elimination(mut self, elimination: bool) -> Self {
+        self.config.elimination = elimination;
+        self
+    }
+
+    /// Report every item entering and leaving the stack to `hooks`, see
+    /// [`ItemHooks`]. Replaces the hooks chosen so far.
+    pub fn hooks(mut self, hooks: impl ItemHooks<T> + 'static) -> Self {
+        self.hooks = Some(Box::new(hooks));
+        self
+    }
+
+    /// Consider the elimination array only given a
+    /// [`ResizeConfig::parallelism`] of at least `min_parallelism`, see
+    /// [`Config::min_parallelism`].
+    pub fn min_parallelism(mut self, min_parallelism: usize) -> Self {
+        self.config.min_parallelism = min_parallelism;
+        self
+    }
+
+    /// Replace the whole [`Config`] chosen so far.
+    pub fn config(self, config: Config) -> Self {
+        Builder { config, ..self }
+    }
+}
+
+impl<T> Builder<T, DynStrategy, DynStrategy> {
+    /// Have push and pop operations use the strategy of `kind`, see
+    /// [`Tuning::strategy`].
+    pub fn strategy(mut self, kind: StrategyKind) -> Self {
+        self.config.tuning.strategy = kind;
+        self
+    }
+}
+
+impl<T, PushS, PopS> Builder<T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    /// # Panics
+    ///
+    /// See [`Stack::with_config`].
+    pub fn build(self) -> Stack<T, PushS, PopS> {
+        let mut stack = Stack::with_config(self.config);
+        stack.hooks = self.hooks;
+        stack
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::{HighThroughput, Oversubscribed};
+
+    #[test]
+    fn preset_replaces_resize_config() {
+        let builder = Stack::<u8>::builder()
+            .resize_config(ResizeConfig::fixed(3))
+            .preset(Oversubscribed);
+        assert_eq!(builder.config.resize, ResizeConfig::fixed(1));
+
+        let builder = builder
+            .preset(HighThroughput)
+            .resize_config(ResizeConfig::fixed(3));
+        assert_eq!(builder.config.resize, ResizeConfig::fixed(3));
+
+        let stack = builder.build();
+        stack.push(1);
+        assert_eq!(stack.pop(), Some(1));
+    }
+
+    #[test]
+    fn preset_keeps_seed() {
+        let builder = Stack::<u8>::builder().seed(7).preset(HighThroughput);
+        assert_eq!(builder.config.resize.seed, Some(7));
+
+        #[cfg(not(feature = "no-elimination"))]
+        assert_eq!(
+            builder.build().elimination_array.resize_config().seed,
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn runtime_configuration() {
+        let tuning = Tuning {
+            retry_limit: 0,
+            ..Tuning::default()
+        };
+        let stack = Stack::<u8>::builder().exchangers(3).tuning(tuning).build();
+
+        #[cfg(not(feature = "no-elimination"))]
+        assert_eq!(stack.elimination_array.len(), 3);
+        assert_eq!(stack.tuning, tuning);
+        // Out of retries before the first attempt.
+        assert_eq!(stack.try_push(1), Err(1));
+        assert_eq!(stack.try_pop(), Err(crate::Contended));
+    }
+
+    #[test]
+    fn elimination_disabled() {
+        let stack = Stack::<u8>::builder().elimination(false).build();
+        assert!(!stack.elimination);
+
+        let mut strategy = ExpRetryStrategy::new();
+        let mut recorder = vec![];
+        let id = crate::event::OperationId::next();
+        let item = stack.ledger.wrap(1, id);
+        stack
+            .engine::<crate::engine::Push>()
+            .resume(item, id, &mut strategy, &mut recorder, |_| false)
+            .unwrap();
+        // Straight to the Treiber stack, though the strategy would have
+        // tried the elimination array first.
+        assert_eq!(recorder, vec![crate::event::Event::TryStack]);
+    }
+
+    #[test]
+    fn single_cpu_disables_elimination() {
+        let single_cpu =
+            || Stack::<usize>::builder().resize_config(ResizeConfig::with_parallelism(1));
+        let stack = single_cpu().build();
+        assert!(!stack.elimination);
+        #[cfg(not(feature = "no-elimination"))]
+        assert_eq!(stack.elimination_array.len(), 1);
+
+        let (threads, items_per_thread) = (4, 1_000);
+        let mut popped = std::thread::scope(|scope| {
+            let handles = (0..threads)
+                .map(|thread| {
+                    let stack = &stack;
+                    scope.spawn(move || {
+                        let (mut popped, mut recorder) = (vec![], vec![]);
+                        for i in 0..items_per_thread {
+                            stack.instrumented_push(thread * items_per_thread + i, &mut recorder);
+                            popped.extend(stack.instrumented_pop(&mut recorder));
+                        }
+                        assert!(!recorder.iter().any(|event| matches!(
+                            event,
+                            crate::event::Event::StartEliminationArrayPush
+                                | crate::event::Event::StartEliminationArrayPop
+                        )));
+                        popped
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        popped.extend(stack.take_all());
+        popped.sort_unstable();
+        assert_eq!(popped, (0..threads * items_per_thread).collect::<Vec<_>>());
+
+        assert!(single_cpu().min_parallelism(1).build().elimination);
+        assert!(
+            Stack::<usize>::builder()
+                .resize_config(ResizeConfig::with_parallelism(2))
+                .build()
+                .elimination
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn config_from_json_defaults_missing_fields() {
+        let config: Config =
+            serde_json::from_str(r#"{"elimination": false, "tuning": {"retry_limit": 8}}"#)
+                .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                tuning: Tuning {
+                    retry_limit: 8,
+                    ..Tuning::default()
+                },
+                elimination: false,
+                ..Config::default()
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "max_retry_exponent")]
+    fn invalid_tuning_panics() {
+        Stack::<u8>::builder()
+            .tuning(Tuning {
+                max_retry_exponent: Tuning::MAX_RETRY_EXPONENT + 1,
+                ..Tuning::default()
+            })
+            .build();
+    }
+}
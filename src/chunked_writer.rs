@@ -0,0 +1,203 @@
+//! Byte oriented bulk submission on top of [`Stack`], e.g. for log shipping.
+//!
+//! Producers write bytes through a [`ChunkedWriter`], which accumulates them in
+//! fixed size buffers and pushes each filled buffer as a whole. Consumers pop
+//! filled buffers via [`Chunks::pop`] and hand them back via
+//! [`Chunks::recycle`] once done. Empty buffers are pooled on a second stack,
+//! thus in steady state no buffer is allocated.
+//!
+//! ```rust
+//! # use elimination_backoff_stack::chunked_writer::Chunks;
+//! # use std::io::Write;
+//! let chunks = Chunks::new(4);
+//!
+//! let mut writer = chunks.writer();
+//! writer.write_all(b"hello").unwrap();
+//! writer.flush().unwrap();
+//!
+//! let mut received = vec![];
+//! while let Some(chunk) = chunks.pop() {
+//!     received.push(chunk.clone());
+//!     chunks.recycle(chunk);
+//! }
+//!
+//! // Most recently filled chunk first.
+//! assert_eq!(received, vec![b"o".to_vec(), b"hell".to_vec()]);
+//! ```
+//!
+//! Like any item on the stack, chunks are popped in LIFO order. Bytes of a
+//! single write exceeding the remaining space of the current buffer are split
+//! across chunks, thus consumers needing record boundaries have to frame their
+//! records themselves.
+
+use crate::Stack;
+use std::io;
+
+/// Filled buffers and the pool of empty buffers, shared by all writers and
+/// consumers.
+pub struct Chunks {
+    filled: Stack<Vec<u8>>,
+    pool: Stack<Vec<u8>>,
+    chunk_size: usize,
+}
+
+impl Chunks {
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn new(chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size to be non-zero");
+
+        Chunks {
+            filled: Stack::new(),
+            pool: Stack::new(),
+            chunk_size,
+        }
+    }
+
+    /// Writer pushing a chunk each time `chunk_size` bytes were written, as
+    /// well as on flush and on drop in case of a partially filled chunk.
+    pub fn writer(&self) -> ChunkedWriter<'_> {
+        ChunkedWriter {
+            chunks: self,
+            current: self.take_buffer(),
+        }
+    }
+
+    /// Pop the most recently pushed chunk.
+    pub fn pop(&self) -> Option<Vec<u8>> {
+        self.filled.pop()
+    }
+
+    /// Return a popped chunk to the pool of empty buffers.
+    pub fn recycle(&self, mut chunk: Vec<u8>) {
+        chunk.clear();
+        self.pool.push(chunk);
+    }
+
+    fn take_buffer(&self) -> Vec<u8> {
+        self.pool
+            .pop()
+            .unwrap_or_else(|| Vec::with_capacity(self.chunk_size))
+    }
+}
+
+/// [`io::Write`] implementation accumulating bytes into chunks of
+/// [`Chunks`].
+///
+/// Never fails, as pushing onto the stack never fails.
+pub struct ChunkedWriter<'a> {
+    chunks: &'a Chunks,
+    current: Vec<u8>,
+}
+
+impl ChunkedWriter<'_> {
+    fn submit(&mut self) {
+        let next = self.chunks.take_buffer();
+        let filled = std::mem::replace(&mut self.current, next);
+        self.chunks.filled.push(filled);
+    }
+}
+
+impl io::Write for ChunkedWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let space = self.chunks.chunk_size - self.current.len();
+        let n = buf.len().min(space);
+        self.current.extend_from_slice(&buf[..n]);
+
+        if self.current.len() == self.chunks.chunk_size {
+            self.submit();
+        }
+
+        Ok(n)
+    }
+
+    /// Push the partially filled chunk, if any.
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.current.is_empty() {
+            self.submit();
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ChunkedWriter<'_> {
+    fn drop(&mut self) {
+        if self.current.is_empty() {
+            let buffer = std::mem::take(&mut self.current);
+            self.chunks.recycle(buffer);
+        } else {
+            let filled = std::mem::take(&mut self.current);
+            self.chunks.filled.push(filled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn concurrent_writers_lose_no_bytes() {
+        let chunks = Arc::new(Chunks::new(64));
+
+        let writers = (0..4u8)
+            .map(|i| {
+                let chunks = chunks.clone();
+                thread::spawn(move || {
+                    let mut writer = chunks.writer();
+                    for _ in 0..1_000 {
+                        writer.write_all(&[i; 7]).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut counts = [0; 4];
+        let mut consume = |chunk: Vec<u8>| {
+            assert!(chunk.len() <= 64);
+            for byte in &chunk {
+                counts[*byte as usize] += 1;
+            }
+            chunks.recycle(chunk);
+        };
+
+        for writer in writers {
+            while !writer.is_finished() {
+                if let Some(chunk) = chunks.pop() {
+                    consume(chunk);
+                }
+            }
+            writer.join().unwrap();
+        }
+        while let Some(chunk) = chunks.pop() {
+            consume(chunk);
+        }
+
+        assert_eq!(counts, [7_000; 4]);
+    }
+
+    #[test]
+    fn buffers_are_reused() {
+        let chunks = Chunks::new(8);
+
+        let mut writer = chunks.writer();
+        writer.write_all(&[1; 8]).unwrap();
+        drop(writer);
+
+        let chunk = chunks.pop().unwrap();
+        let ptr = chunk.as_ptr();
+        chunks.recycle(chunk);
+
+        // The pool holds the recycled chunk and the spare buffer of the
+        // dropped writer. Most recently recycled first.
+        let mut writer = chunks.writer();
+        writer.write_all(&[2; 8]).unwrap();
+        let chunk = chunks.pop().unwrap();
+        assert_eq!(chunk.as_ptr(), ptr);
+    }
+}
@@ -0,0 +1,61 @@
+//! Common interface of concurrent LIFO stacks.
+//!
+//! [`ConcurrentStack`] allows code and tests to be written once and run
+//! against [`super::Stack`] as well as other concurrent stack implementations,
+//! e.g. to compare their semantics or performance. Implementations for stacks
+//! of other crates are a few lines each:
+//!
+//! ```rust
+//! # use elimination_backoff_stack::concurrent_stack::ConcurrentStack;
+//! # use std::sync::Mutex;
+//! /// Stand-in for a stack published by another crate.
+//! struct OtherStack<T>(Mutex<Vec<T>>);
+//!
+//! impl<T> ConcurrentStack<T> for OtherStack<T> {
+//!     fn push(&self, item: T) {
+//!         self.0.lock().unwrap().push(item);
+//!     }
+//!
+//!     fn pop(&self) -> Option<T> {
+//!         self.0.lock().unwrap().pop()
+//!     }
+//! }
+//! ```
+
+use crate::treiber::TreiberStack;
+use crate::{PopStrategy, PushStrategy, Stack};
+
+/// A stack that can be pushed to and popped from by many threads concurrently,
+/// given that it is [`Sync`].
+pub trait ConcurrentStack<T> {
+    fn push(&self, item: T);
+
+    /// Pop the most recently pushed item, `None` if the stack appeared empty.
+    fn pop(&self) -> Option<T>;
+}
+
+impl<T, PushS, PopS> ConcurrentStack<T> for Stack<T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    fn push(&self, item: T) {
+        Stack::push(self, item)
+    }
+
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    fn pop(&self) -> Option<T> {
+        Stack::pop(self)
+    }
+}
+
+impl<T> ConcurrentStack<T> for TreiberStack<T> {
+    fn push(&self, item: T) {
+        TreiberStack::push(self, item)
+    }
+
+    fn pop(&self) -> Option<T> {
+        TreiberStack::pop(self)
+    }
+}
@@ -0,0 +1,384 @@
+//! Runtime validation that every pushed item is popped exactly once, enabled
+//! via the `debug-conservation` feature.
+//!
+//! With the feature enabled, each item is wrapped with a sequence number on
+//! push. A global ledger tracks which operation pushed and which operation
+//! popped each sequence number. Popping an item twice panics right away,
+//! naming both pop operations. Items neither popped nor remaining on the stack
+//! once it is dropped are reported as lost, naming the push operation.
+//!
+//! Reports name the stack by a number unique per process, thus applications
+//! using several stacks can tell them apart, as well as the call sites of the
+//! operations involved. The public operations of [`crate::Stack`] are
+//! `#[track_caller]` with the feature enabled, thus the call sites are the
+//! ones in the application.
+//!
+//! Meant for diagnosing reports of duplicated or lost items, not for
+//! production use: every operation takes a global lock and the ledger keeps
+//! an entry per popped item until the stack is dropped. Zero-sized items are
+//! no longer zero-sized once wrapped, thus allocated like any other item.
+//!
+//! Without the feature, wrapping is the identity and [`Ledger`] zero-sized.
+
+use crate::event::OperationId;
+
+pub(crate) use imp::{Ledger, Stored};
+
+#[cfg(feature = "debug-conservation")]
+mod imp {
+    use super::OperationId;
+    use std::collections::BTreeMap;
+    use std::panic::Location;
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering::Relaxed};
+    use std::sync::{Mutex, MutexGuard};
+
+    static NEXT_SEQUENCE_NUMBER: AtomicU64 = AtomicU64::new(0);
+    static NEXT_LEDGER_ID: AtomicUsize = AtomicUsize::new(0);
+
+    static ENTRIES: Mutex<BTreeMap<u64, Entry>> = Mutex::new(BTreeMap::new());
+
+    struct Entry {
+        ledger: usize,
+        pushed_by: (OperationId, &'static Location<'static>),
+        popped_by: Option<(OperationId, &'static Location<'static>)>,
+    }
+
+    fn entries() -> MutexGuard<'static, BTreeMap<u64, Entry>> {
+        // A panic reporting a violation poisons the lock. Keep validating.
+        ENTRIES.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Item wrapped with the sequence number identifying it in the ledger.
+    #[derive(Clone, Copy, Debug)]
+    pub(crate) struct Stored<T> {
+        sequence_number: u64,
+        item: T,
+    }
+
+    /// Entries of a single stack.
+    pub(crate) struct Ledger {
+        /// Identifies the stack in reports.
+        pub(super) id: usize,
+    }
+
+    impl Ledger {
+        pub(crate) fn new() -> Self {
+            Ledger {
+                id: NEXT_LEDGER_ID.fetch_add(1, Relaxed),
+            }
+        }
+
+        #[track_caller]
+        pub(crate) fn wrap<T>(&self, item: T, id: OperationId) -> Stored<T> {
+            self.wrap_at(item, id, Location::caller())
+        }
+
+        /// [`Ledger::wrap`] creating the id of the operation only if needed,
+        /// i.e. with the feature enabled.
+        #[track_caller]
+        pub(crate) fn wrap_lazy<T>(&self, item: T, id: impl FnOnce() -> OperationId) -> Stored<T> {
+            self.wrap_at(item, id(), Location::caller())
+        }
+
+        /// [`Ledger::wrap`] with an explicit call site, for callers wrapping
+        /// within a closure, which does not forward the call site.
+        pub(crate) fn wrap_at<T>(
+            &self,
+            item: T,
+            id: OperationId,
+            at: &'static Location<'static>,
+        ) -> Stored<T> {
+            let sequence_number = NEXT_SEQUENCE_NUMBER.fetch_add(1, Relaxed);
+            entries().insert(
+                sequence_number,
+                Entry {
+                    ledger: self.id,
+                    pushed_by: (id, at),
+                    popped_by: None,
+                },
+            );
+
+            Stored {
+                sequence_number,
+                item,
+            }
+        }
+
+        /// Unwrap an item popped by the operation `id`.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the item was popped before, naming the stack as well as
+        /// the operations and call sites involved.
+        #[track_caller]
+        pub(crate) fn unwrap<T>(&self, stored: Stored<T>, id: OperationId) -> T {
+            self.unwrap_at(stored, id, Location::caller())
+        }
+
+        /// [`Ledger::unwrap`] with an explicit call site, see
+        /// [`Ledger::wrap_at`].
+        pub(crate) fn unwrap_at<T>(
+            &self,
+            stored: Stored<T>,
+            id: OperationId,
+            at: &'static Location<'static>,
+        ) -> T {
+            self.record_pop(stored.sequence_number, id, at);
+            stored.item
+        }
+
+        /// [`Ledger::unwrap_at`] creating the id of the operation only if
+        /// needed, see [`Ledger::wrap_lazy`].
+        pub(crate) fn unwrap_lazy_at<T>(
+            &self,
+            stored: Stored<T>,
+            id: impl FnOnce() -> OperationId,
+            at: &'static Location<'static>,
+        ) -> T {
+            self.unwrap_at(stored, id(), at)
+        }
+
+        /// [`Ledger::unwrap_at`] moving the item out of `stored` into `slot`
+        /// instead of returning it.
+        ///
+        /// # Safety
+        ///
+        /// `stored` has to be valid for reads and owned by the caller, who
+        /// gives up the item, and `slot` valid for writes.
+        pub(crate) unsafe fn unwrap_into_at<T>(
+            &self,
+            stored: *const Stored<T>,
+            slot: *mut T,
+            id: OperationId,
+            at: &'static Location<'static>,
+        ) {
+            self.record_pop((*stored).sequence_number, id, at);
+            std::ptr::copy_nonoverlapping(Self::peek_raw(stored), slot, 1);
+        }
+
+        fn record_pop(
+            &self,
+            sequence_number: u64,
+            id: OperationId,
+            at: &'static Location<'static>,
+        ) {
+            let mut entries = entries();
+            match entries.get_mut(&sequence_number) {
+                Some(Entry {
+                    pushed_by,
+                    popped_by: Some(previous),
+                    ..
+                }) => {
+                    let (pushed_by, previous) = (*pushed_by, *previous);
+                    drop(entries);
+                    panic!(
+                        "item {} of stack {} pushed by {:?} at {} popped twice, by {:?} at {} \
+                         and by {:?} at {}",
+                        sequence_number,
+                        self.id,
+                        pushed_by.0,
+                        pushed_by.1,
+                        previous.0,
+                        previous.1,
+                        id,
+                        at,
+                    );
+                }
+                Some(entry) => entry.popped_by = Some((id, at)),
+                None => {
+                    drop(entries);
+                    panic!(
+                        "item {} popped from stack {} by {:?} at {} was never pushed",
+                        sequence_number, self.id, id, at,
+                    );
+                }
+            }
+        }
+
+        /// Unwrap an item leaving the stack other than by a pop operation,
+        /// e.g. when dropping the stack.
+        pub(crate) fn discard<T>(&self, stored: Stored<T>) -> T {
+            entries().remove(&stored.sequence_number);
+            stored.item
+        }
+
+        /// Reference to the item, not changing the ledger.
+        pub(crate) fn peek<T>(stored: &Stored<T>) -> &T {
+            &stored.item
+        }
+
+        /// Pointer to the item, without dereferencing `stored`, see
+        /// [`crate::treiber_stack::TreiberStack::for_each_field`].
+        pub(crate) fn peek_raw<T>(stored: *const Stored<T>) -> *const T {
+            unsafe { std::ptr::addr_of!((*stored).item) }
+        }
+    }
+
+    /// Reports the first item of this stack that was neither popped nor
+    /// discarded. Expects all remaining items to be discarded before.
+    impl Drop for Ledger {
+        fn drop(&mut self) {
+            let mut entries = entries();
+            let mut lost = None;
+            entries.retain(|sequence_number, entry| {
+                if entry.ledger != self.id {
+                    return true;
+                }
+                if entry.popped_by.is_none() && lost.is_none() {
+                    lost = Some((*sequence_number, entry.pushed_by));
+                }
+                false
+            });
+            drop(entries);
+
+            if let Some((sequence_number, pushed_by)) = lost {
+                if !std::thread::panicking() {
+                    panic!(
+                        "item {} of stack {} pushed by {:?} at {} was lost",
+                        sequence_number, self.id, pushed_by.0, pushed_by.1,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "debug-conservation"))]
+mod imp {
+    use super::OperationId;
+    use std::panic::Location;
+
+    pub(crate) type Stored<T> = T;
+
+    pub(crate) struct Ledger {}
+
+    impl Ledger {
+        pub(crate) fn new() -> Self {
+            Ledger {}
+        }
+
+        #[inline(always)]
+        pub(crate) fn wrap<T>(&self, item: T, _id: OperationId) -> Stored<T> {
+            item
+        }
+
+        #[inline(always)]
+        pub(crate) fn wrap_at<T>(
+            &self,
+            item: T,
+            _id: OperationId,
+            _at: &'static Location<'static>,
+        ) -> Stored<T> {
+            item
+        }
+
+        #[inline(always)]
+        pub(crate) fn wrap_lazy<T>(&self, item: T, _id: impl FnOnce() -> OperationId) -> Stored<T> {
+            item
+        }
+
+        #[inline(always)]
+        pub(crate) fn unwrap<T>(&self, stored: Stored<T>, _id: OperationId) -> T {
+            stored
+        }
+
+        #[inline(always)]
+        pub(crate) fn unwrap_at<T>(
+            &self,
+            stored: Stored<T>,
+            _id: OperationId,
+            _at: &'static Location<'static>,
+        ) -> T {
+            stored
+        }
+
+        #[inline(always)]
+        pub(crate) fn unwrap_lazy_at<T>(
+            &self,
+            stored: Stored<T>,
+            _id: impl FnOnce() -> OperationId,
+            _at: &'static Location<'static>,
+        ) -> T {
+            stored
+        }
+
+        /// # Safety
+        ///
+        /// See the `debug-conservation` implementation.
+        #[inline(always)]
+        pub(crate) unsafe fn unwrap_into_at<T>(
+            &self,
+            stored: *const Stored<T>,
+            slot: *mut T,
+            _id: OperationId,
+            _at: &'static Location<'static>,
+        ) {
+            std::ptr::copy_nonoverlapping(stored, slot, 1);
+        }
+
+        #[inline(always)]
+        pub(crate) fn discard<T>(&self, stored: Stored<T>) -> T {
+            stored
+        }
+
+        #[inline(always)]
+        pub(crate) fn peek<T>(stored: &Stored<T>) -> &T {
+            stored
+        }
+
+        #[inline(always)]
+        pub(crate) fn peek_raw<T>(stored: *const Stored<T>) -> *const T {
+            stored
+        }
+    }
+}
+
+#[cfg(all(test, feature = "debug-conservation"))]
+mod tests {
+    use super::*;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    fn panic_message(f: impl FnOnce()) -> String {
+        let payload = catch_unwind(AssertUnwindSafe(f)).unwrap_err();
+        payload.downcast::<String>().map(|s| *s).unwrap_or_default()
+    }
+
+    #[test]
+    fn duplicate_pop_names_both_operations() {
+        let ledger = Ledger::new();
+        let stored = ledger.wrap(1, OperationId::next());
+        let (first, second) = (OperationId::next(), OperationId::next());
+
+        assert_eq!(ledger.unwrap(stored, first), 1);
+        let message = panic_message(|| {
+            ledger.unwrap(stored, second);
+        });
+
+        assert!(message.contains("popped twice"), "{}", message);
+        assert!(message.contains(&format!("{:?}", first)), "{}", message);
+        assert!(message.contains(&format!("{:?}", second)), "{}", message);
+        assert!(
+            message.contains(&format!("stack {}", ledger.id)),
+            "{}",
+            message
+        );
+        assert!(message.contains(file!()), "{}", message);
+    }
+
+    #[test]
+    fn lost_item_is_reported_on_drop() {
+        let ledger = Ledger::new();
+        let pushed_by = OperationId::next();
+        let _lost = ledger.wrap(1, pushed_by);
+        let remaining = ledger.wrap(2, OperationId::next());
+        ledger.discard(remaining);
+
+        let id = ledger.id;
+        let message = panic_message(|| drop(ledger));
+
+        assert!(message.contains("was lost"), "{}", message);
+        assert!(message.contains(&format!("{:?}", pushed_by)), "{}", message);
+        assert!(message.contains(&format!("stack {}", id)), "{}", message);
+        assert!(message.contains(file!()), "{}", message);
+    }
+}
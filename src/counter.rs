@@ -0,0 +1,104 @@
+//! Approximate counter, cheap to update concurrently, backing
+//! [`crate::Stack::len`].
+//!
+//! The count is spread across shards, each on its own cache line. A thread
+//! always updates the same shard, picked by its index, thus threads updating
+//! concurrently rarely contend on a cache line. Reading sums up all shards
+//! without synchronizing with concurrent updates, thus the sum is only
+//! approximate while updates are in flight, though exact once they completed.
+
+use crate::event::current_thread_id;
+use crate::resize::available_parallelism;
+use crossbeam::utils::CachePadded;
+use std::fmt;
+use std::sync::atomic::{AtomicIsize, Ordering::Relaxed};
+
+pub(crate) struct Counter {
+    /// Power of two many shards. A single shard might go negative, e.g. when
+    /// one thread pushes and another pops, only the sum is meaningful.
+    shards: Box<[CachePadded<AtomicIsize>]>,
+}
+
+impl Counter {
+    pub(crate) fn new() -> Self {
+        Counter {
+            shards: (0..available_parallelism().next_power_of_two())
+                .map(|_| CachePadded::new(AtomicIsize::new(0)))
+                .collect(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn add(&self, n: usize) {
+        self.shard().fetch_add(n as isize, Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn sub(&self, n: usize) {
+        self.shard().fetch_sub(n as isize, Relaxed);
+    }
+
+    /// Sum of all shards. A sum observed below zero, given updates in flight,
+    /// is reported as zero.
+    pub(crate) fn get(&self) -> usize {
+        let sum = self
+            .shards
+            .iter()
+            .map(|shard| shard.load(Relaxed))
+            .fold(0isize, isize::wrapping_add);
+        sum.max(0) as usize
+    }
+
+    #[inline]
+    fn shard(&self) -> &AtomicIsize {
+        &self.shards[current_thread_id() & (self.shards.len() - 1)]
+    }
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        Counter::new()
+    }
+}
+
+impl fmt::Debug for Counter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Counter").field(&self.get()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn sums_across_threads() {
+        let counter = Arc::new(Counter::new());
+
+        let handlers = (0..4)
+            .map(|i| {
+                let counter = counter.clone();
+                thread::spawn(move || {
+                    for _ in 0..1_000 {
+                        if i % 2 == 0 {
+                            counter.add(2);
+                        } else {
+                            counter.sub(1);
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+
+        assert_eq!(counter.get(), 2_000);
+
+        counter.sub(3_000);
+        assert_eq!(counter.get(), 0);
+    }
+}
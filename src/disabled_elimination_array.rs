@@ -0,0 +1,164 @@
+//! Stand-in for the elimination array with the `no-elimination` feature,
+//! compiling the stack down to the Treiber stack only.
+//!
+//! Keeps the interface of the elimination array, though every exchange attempt
+//! fails right away, thus the stack retries the Treiber stack instead. The
+//! exchanger is not compiled at all.
+
+use crate::event::{EventRecorder, OperationId};
+#[cfg(feature = "history")]
+use crate::history::History;
+use crate::resize::{ResizeConfig, ResizeController};
+use crossbeam::epoch::Guard;
+use std::marker::PhantomData;
+
+/// Whether the exchanger backs this elimination array.
+#[cfg(test)]
+pub const COMPILES_EXCHANGER: bool = false;
+
+pub struct EliminationArray<T> {
+    /// Stays empty, given that there is nothing to resize.
+    #[cfg(feature = "history")]
+    history: History,
+    phantom: PhantomData<T>,
+}
+
+impl<T> EliminationArray<T> {
+    pub fn new() -> Self {
+        EliminationArray::with_resize_config(ResizeConfig::default())
+    }
+
+    /// Validates `config` like the actual elimination array, though ignores it
+    /// otherwise.
+    pub fn with_resize_config(config: ResizeConfig) -> Self {
+        ResizeController::new(config);
+
+        EliminationArray {
+            #[cfg(feature = "history")]
+            history: History::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// No exchangers, given that none are compiled.
+    pub(crate) fn len(&self) -> usize {
+        0
+    }
+
+    /// Not kept, given that it has no effect without exchangers, thus the
+    /// default.
+    pub(crate) fn resize_config(&self) -> ResizeConfig {
+        ResizeConfig::default()
+    }
+
+    #[cfg(feature = "history")]
+    pub(crate) fn history(&self) -> &History {
+        &self.history
+    }
+
+    #[inline(always)]
+    pub(crate) fn exchange_push<S: PushStrategy, R: EventRecorder>(
+        &self,
+        item: T,
+        _id: OperationId,
+        _strategy: &mut S,
+        _recorder: &mut R,
+    ) -> Result<(), T> {
+        Err(item)
+    }
+
+    #[inline(always)]
+    pub(crate) fn exchange_pop<S: PopStrategy, R: EventRecorder>(
+        &self,
+        _id: OperationId,
+        _strategy: &mut S,
+        _recorder: &mut R,
+    ) -> Result<T, ()> {
+        Err(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn exchange_pop_if<S: PopStrategy, R: EventRecorder>(
+        &self,
+        _id: OperationId,
+        _strategy: &mut S,
+        _recorder: &mut R,
+        _accept: impl FnMut(&T) -> bool,
+    ) -> Result<T, ()> {
+        Err(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn exchange_pop_with<S: PopStrategy, R: EventRecorder, O>(
+        &self,
+        _id: OperationId,
+        _strategy: &mut S,
+        _recorder: &mut R,
+        _accept: impl FnMut(&T) -> bool,
+        _take: impl FnMut(*const T) -> O,
+    ) -> Result<O, ()> {
+        Err(())
+    }
+
+    /// No items are ever offered.
+    #[inline(always)]
+    pub(crate) fn take_waiting(&self, _id: OperationId, _f: impl FnMut(T)) {}
+
+    #[inline(always)]
+    pub(crate) fn announce_pop_interest<'g, S: PopStrategy>(
+        &self,
+        _strategy: &mut S,
+        _guard: &'g Guard,
+    ) -> PopInterest<'g, T> {
+        PopInterest {
+            phantom: PhantomData,
+        }
+    }
+}
+
+pub(crate) struct PopInterest<'g, T> {
+    phantom: PhantomData<&'g T>,
+}
+
+impl<T> PopInterest<'_, T> {
+    #[inline(always)]
+    pub(crate) fn exchange_pop_with<S: PopStrategy, R: EventRecorder, O>(
+        &self,
+        _id: OperationId,
+        _strategy: &mut S,
+        _recorder: &mut R,
+        _take: impl FnMut(*const T) -> O,
+    ) -> Result<O, ()> {
+        Err(())
+    }
+}
+
+impl<T> Default for EliminationArray<T> {
+    fn default() -> Self {
+        EliminationArray::new()
+    }
+}
+
+// Implemented by every strategy like the traits of the actual elimination
+// array, though never consulted.
+#[allow(dead_code)]
+pub trait PushStrategy {
+    fn try_push(&mut self) -> bool;
+
+    fn use_pop_interest(&mut self) -> bool {
+        false
+    }
+
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        total
+    }
+}
+
+#[allow(dead_code)]
+pub trait PopStrategy {
+    fn try_pop(&mut self) -> bool;
+
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        total
+    }
+}
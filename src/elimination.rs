@@ -0,0 +1,230 @@
+//! Lock-free elimination array, i.e. the back-off layer of [`crate::Stack`],
+//! usable on its own, e.g. in front of another contended structure like a
+//! queue, a pool or a counter.
+//!
+//! An operation failing on the underlying structure due to contention backs
+//! off to the elimination array instead of retrying right away. There a push
+//! operation offers its item on a random exchanger, while a pop operation
+//! takes the item on offer on a random exchanger, if any. A push and a pop
+//! operation meeting this way cancel each other out, thus neither has to
+//! touch the underlying structure. Whether that is a valid outcome is up to
+//! the structure, e.g. it is for a stack or a pool, not for a FIFO queue
+//! unless it is empty.
+//!
+//! Each operation consults its [`Strategy`] on how often to try the
+//! elimination array, see [`Strategy::try_elimination_array`], how many of the
+//! exchangers to consider, see [`Strategy::num_exchangers`], and how long to
+//! wait on an exchanger, see [`crate::exchange`]. The remaining methods of
+//! [`Strategy`] are only consulted by a [`crate::Stack`].
+//!
+//! Not available with the `no-elimination` feature, which compiles the
+//! elimination array out.
+
+use crate::elimination_array;
+use crate::event::{NoOpRecorder, OperationId};
+use crate::strategy::{ExpRetryStrategy, Strategy};
+use crate::ResizeConfig;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Array of exchangers eliminating concurrent push and pop operations, the
+/// attempts of each operation decided by a [`Strategy`], the
+/// [`ExpRetryStrategy`] by default.
+///
+/// Grows and shrinks with contention as configured by a [`ResizeConfig`],
+/// unless created with a fixed number of exchangers.
+///
+/// ```rust
+/// # use elimination_backoff_stack::elimination::EliminationArray;
+/// # use std::sync::Mutex;
+/// /// Pool guarded by a lock, backing off to the elimination array while
+/// /// the lock is contended.
+/// struct Pool {
+///     items: Mutex<Vec<u64>>,
+///     elimination: EliminationArray<u64>,
+/// }
+///
+/// impl Pool {
+///     fn put(&self, mut item: u64) {
+///         loop {
+///             if let Ok(mut items) = self.items.try_lock() {
+///                 return items.push(item);
+///             }
+///             match self.elimination.exchange_push(item) {
+///                 Ok(()) => return,
+///                 Err(i) => item = i,
+///             }
+///         }
+///     }
+///
+///     fn take(&self) -> Option<u64> {
+///         loop {
+///             if let Ok(mut items) = self.items.try_lock() {
+///                 return items.pop();
+///             }
+///             if let Some(item) = self.elimination.exchange_pop() {
+///                 return Some(item);
+///             }
+///         }
+///     }
+/// }
+///
+/// let pool = Pool {
+///     items: Mutex::new(vec![]),
+///     elimination: EliminationArray::with_exchangers(2),
+/// };
+/// pool.put(1);
+/// assert_eq!(pool.take(), Some(1));
+/// ```
+pub struct EliminationArray<T, S = ExpRetryStrategy> {
+    inner: elimination_array::EliminationArray<T>,
+    // See `Stack::phantom`.
+    strategy: PhantomData<fn() -> S>,
+}
+
+impl<T, S: Strategy> EliminationArray<T, S> {
+    /// Create an elimination array growing and shrinking with the
+    /// [`ResizeConfig::default`].
+    pub fn new() -> Self {
+        EliminationArray::with_resize_config(ResizeConfig::default())
+    }
+
+    /// Create an elimination array of `num_exchangers` exchangers, never
+    /// growing nor shrinking.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_exchangers` is zero.
+    pub fn with_exchangers(num_exchangers: usize) -> Self {
+        EliminationArray::with_resize_config(ResizeConfig::fixed(num_exchangers))
+    }
+
+    /// Create an elimination array growing and shrinking as configured.
+    ///
+    /// # Panics
+    ///
+    /// See [`crate::Stack::with_resize_config`].
+    pub fn with_resize_config(config: ResizeConfig) -> Self {
+        EliminationArray {
+            inner: elimination_array::EliminationArray::with_resize_config(config),
+            strategy: PhantomData,
+        }
+    }
+
+    /// Offer `item` to a concurrent [`EliminationArray::exchange_pop`] as long
+    /// as the strategy permits. Hands `item` back if no pop operation took
+    /// it.
+    pub fn exchange_push(&self, item: T) -> Result<(), T> {
+        self.inner.exchange_push(
+            item,
+            OperationId::next(),
+            &mut S::new(),
+            &mut NoOpRecorder {},
+        )
+    }
+
+    /// Take the item offered by a concurrent
+    /// [`EliminationArray::exchange_push`] as long as the strategy permits.
+    /// `None` if no item was taken.
+    pub fn exchange_pop(&self) -> Option<T> {
+        self.inner
+            .exchange_pop(OperationId::next(), &mut S::new(), &mut NoOpRecorder {})
+            .ok()
+    }
+
+    /// Number of exchangers currently in use.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Always `false`, given at least one exchanger, thus merely for
+    /// consistency with [`EliminationArray::len`].
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T, S: Strategy> Default for EliminationArray<T, S> {
+    fn default() -> Self {
+        EliminationArray::new()
+    }
+}
+
+impl<T, S> fmt::Debug for EliminationArray<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EliminationArray")
+            .field("exchangers", &self.inner.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Tries the elimination array as long as it takes, a push operation
+    /// waiting on its exchanger, a pop operation checking one exchanger after
+    /// the other.
+    #[derive(Default)]
+    struct Patient {
+        checked: bool,
+    }
+
+    impl Strategy for Patient {
+        fn new() -> Self {
+            Patient::default()
+        }
+        fn use_elimination_array(&mut self) -> bool {
+            true
+        }
+        fn try_stack(&mut self) -> bool {
+            false
+        }
+        fn try_elimination_array(&mut self) -> bool {
+            thread::yield_now();
+            true
+        }
+        fn try_start_exchange(&mut self) -> bool {
+            true
+        }
+        fn retry_check_exchanged(&mut self) -> bool {
+            thread::yield_now();
+            true
+        }
+        fn try_exchange(&mut self) -> bool {
+            self.checked = !self.checked;
+            self.checked
+        }
+    }
+
+    #[test]
+    fn gives_up_without_partner() {
+        let array = EliminationArray::<u32>::with_exchangers(3);
+        assert_eq!(array.len(), 3);
+        assert_eq!(array.exchange_pop(), None);
+        assert_eq!(array.exchange_push(1), Err(1));
+        assert_eq!(array.exchange_pop(), None);
+    }
+
+    #[test]
+    fn eliminates_every_item() {
+        let items = 100;
+        let array = EliminationArray::<u32, Patient>::with_exchangers(2);
+
+        let mut consumed = thread::scope(|scope| {
+            scope.spawn(|| {
+                for item in 0..items {
+                    array.exchange_push(item).unwrap();
+                }
+            });
+
+            (0..items)
+                .map(|_| array.exchange_pop().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        consumed.sort_unstable();
+        assert_eq!(consumed, (0..items).collect::<Vec<_>>());
+    }
+}
@@ -1,39 +1,96 @@
-use crate::event::{Event, EventRecorder, NoOpRecorder};
+use crate::event::{Event, EventRecorder, OperationId};
 use crate::exchanger::{self, Exchanger};
-use rand::{thread_rng, Rng};
+#[cfg(feature = "history")]
+use crate::history::{Change, History};
+use crate::resize::{Decision, ResizeConfig, ResizeController, Window};
+use crate::swappable_slice::SwappableSlice;
+use crossbeam::epoch::{self, Guard};
+use rand::{rngs::ThreadRng, thread_rng, Rng};
+use std::ptr;
+
+/// Whether the exchanger backs this elimination array.
+#[cfg(test)]
+pub const COMPILES_EXCHANGER: bool = true;
 
-#[derive(Default)]
 pub struct EliminationArray<T> {
-    exchangers: Vec<Exchanger<T>>,
+    // Operations load the exchangers anew on each attempt, thus picking up a
+    // replaced set of exchangers while in-flight operations on the previous
+    // set complete safely.
+    exchangers: SwappableSlice<Slot<T>>,
+    resize: ResizeController,
+    /// Seed of the permutation of the exchangers, see [`Slot`].
+    seed: u64,
+    #[cfg(feature = "history")]
+    history: History,
 }
 
 impl<T> EliminationArray<T> {
     pub fn new() -> Self {
-        // TODO: Is num_cpus or num_cpus / 2 the better init? The latter would
+        EliminationArray::with_resize_config(ResizeConfig::default())
+    }
+
+    pub fn with_resize_config(config: ResizeConfig) -> Self {
+        let seed = config.seed.unwrap_or_else(|| thread_rng().gen());
+        let resize = ResizeController::new(config);
+
+        // TODO: Is the parallelism or half of it the better init? The latter would
         // cause more heterogeneous as well as homogeneous collisions. The
         // former being good, the latter bad.
-        let exchangers = (0..num_cpus::get()).map(|_| Exchanger::new()).collect();
+        let exchangers = new_exchangers(resize.initial_len(), seed);
+
+        Self {
+            exchangers: SwappableSlice::new(exchangers),
+            resize,
+            seed,
+            #[cfg(feature = "history")]
+            history: History::new(),
+        }
+    }
+
+    /// Number of exchangers currently in use.
+    pub(crate) fn len(&self) -> usize {
+        self.exchangers.load(&epoch::pin()).items.len()
+    }
 
-        Self { exchangers }
+    /// Configuration the elimination array grows and shrinks with.
+    pub(crate) fn resize_config(&self) -> ResizeConfig {
+        self.resize.config().clone()
+    }
+
+    /// Resizes of this elimination array, see [`crate::history`].
+    #[cfg(feature = "history")]
+    pub(crate) fn history(&self) -> &History {
+        &self.history
     }
 
     pub(crate) fn exchange_push<S: PushStrategy, R: EventRecorder>(
         &self,
         item: T,
+        id: OperationId,
         strategy: &mut S,
         recorder: &mut R,
     ) -> Result<(), T> {
         recorder.record(Event::StartEliminationArrayPush);
 
+        // Obtain the thread local random number generator handle once and reuse
+        // it across attempts.
+        let mut rng = thread_rng();
         let mut item = item;
 
+        let guard = epoch::pin();
+
         while strategy.try_push() {
-            let num_exchangers = strategy.num_exchangers(self.exchangers.len());
+            let exchangers = self.exchangers.load(&guard).items;
+            let num_exchangers = considered(strategy.num_exchangers(exchangers.len()), exchangers);
             recorder.record(Event::NumExchangers(num_exchangers));
-            match self
-                .rnd_exchanger(num_exchangers)
-                .exchange_push(item, strategy, recorder)
-            {
+            let exchanger = if strategy.use_pop_interest() {
+                interested_exchanger(exchangers, &mut rng, num_exchangers)
+            } else {
+                rnd_exchanger(exchangers, &mut rng, num_exchangers)
+            };
+            let result = exchanger.exchange_push(item, id, strategy, recorder);
+            self.maybe_resize(exchanger, &guard, recorder);
+            match result {
                 Ok(()) => return Ok(()),
                 Err(i) => item = i,
             }
@@ -44,18 +101,52 @@ impl<T> EliminationArray<T> {
 
     pub(crate) fn exchange_pop<S: PopStrategy, R: EventRecorder>(
         &self,
+        id: OperationId,
         strategy: &mut S,
         recorder: &mut R,
     ) -> Result<T, ()> {
+        self.exchange_pop_if(id, strategy, recorder, |_| true)
+    }
+
+    /// [`EliminationArray::exchange_pop`] only taking an item `accept` holds
+    /// for, see [`Exchanger::exchange_pop_if`].
+    pub(crate) fn exchange_pop_if<S: PopStrategy, R: EventRecorder>(
+        &self,
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+        accept: impl FnMut(&T) -> bool,
+    ) -> Result<T, ()> {
+        self.exchange_pop_with(id, strategy, recorder, accept, |item| unsafe {
+            ptr::read(item)
+        })
+    }
+
+    /// [`EliminationArray::exchange_pop_if`] moving the item out via `take`,
+    /// see [`Exchanger::exchange_pop_with`].
+    pub(crate) fn exchange_pop_with<S: PopStrategy, R: EventRecorder, O>(
+        &self,
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+        mut accept: impl FnMut(&T) -> bool,
+        mut take: impl FnMut(*const T) -> O,
+    ) -> Result<O, ()> {
         recorder.record(Event::StartEliminationArrayPop);
 
+        let mut rng = thread_rng();
+
+        let guard = epoch::pin();
+
         while strategy.try_pop() {
-            let num_exchangers = strategy.num_exchangers(self.exchangers.len());
+            let exchangers = self.exchangers.load(&guard).items;
+            let num_exchangers = considered(strategy.num_exchangers(exchangers.len()), exchangers);
             recorder.record(Event::NumExchangers(num_exchangers));
-            if let Ok(item) = self
-                .rnd_exchanger(num_exchangers)
-                .exchange_pop(strategy, recorder)
-            {
+            let exchanger = rnd_exchanger(exchangers, &mut rng, num_exchangers);
+            let result =
+                exchanger.exchange_pop_with(id, strategy, recorder, &mut accept, &mut take);
+            self.maybe_resize(exchanger, &guard, recorder);
+            if let Ok(item) = result {
                 return Ok(item);
             }
         }
@@ -63,15 +154,202 @@ impl<T> EliminationArray<T> {
         Err(())
     }
 
-    fn rnd_exchanger(&self, range: usize) -> &Exchanger<T> {
-        let i = thread_rng().gen_range(0, range);
-        &self.exchangers[i]
+    /// Take the items offered by push operations on the current exchangers on
+    /// behalf of the operation `id`, calling `f` on each. A single attempt
+    /// per exchanger, thus items offered concurrently might be missed.
+    pub(crate) fn take_waiting(&self, id: OperationId, mut f: impl FnMut(T)) {
+        let guard = epoch::pin();
+
+        for slot in self.exchangers.load(&guard).items {
+            if let Some(item) = slot.exchanger.take_waiting(id) {
+                f(item);
+            }
+        }
+    }
+
+    /// Evaluate the resize controller once `exchanger` completed a window of
+    /// attempts and replace the exchangers if it decides to.
+    ///
+    /// Exchangers are replaced as a whole. Operations still in flight on the
+    /// previous exchangers complete on those. Once they are done, the previous
+    /// exchangers are empty and freed.
+    fn maybe_resize<R: EventRecorder>(
+        &self,
+        exchanger: &Exchanger<T>,
+        guard: &Guard,
+        recorder: &mut R,
+    ) {
+        if !self.resize.window_elapsed(exchanger.stats.attempts()) {
+            return;
+        }
+
+        let mut evaluation = match self.resize.try_evaluate() {
+            Some(evaluation) => evaluation,
+            None => return,
+        };
+
+        let exchangers = self.exchangers.load(guard).items;
+        let mut window = Window {
+            len: exchangers.len(),
+            ..Window::default()
+        };
+        for slot in exchangers {
+            let (attempts, busy_misses, empty_misses) = slot.exchanger.stats.take();
+            window.attempts += attempts;
+            window.busy_misses += busy_misses;
+            window.empty_misses += empty_misses;
+        }
+
+        let len = window.len;
+        if let Decision::Resize(new_len) = evaluation.decide(window) {
+            self.exchangers
+                .swap(new_exchangers(new_len, self.seed), guard);
+            recorder.record(Event::ResizeEliminationArray(len, new_len));
+            #[cfg(feature = "history")]
+            self.history.record(Change::ResizeEliminationArray {
+                from: len,
+                to: new_len,
+            });
+        }
+    }
+
+    /// Announce the interest of a pop operation in a random exchanger, which
+    /// concurrent push operations then prefer. The interest is withdrawn once
+    /// the returned [`PopInterest`] is dropped.
+    pub(crate) fn announce_pop_interest<'g, S: PopStrategy>(
+        &self,
+        strategy: &mut S,
+        guard: &'g Guard,
+    ) -> PopInterest<'g, T> {
+        let exchangers = self.exchangers.load(guard).items;
+        let num_exchangers = considered(strategy.num_exchangers(exchangers.len()), exchangers);
+        let exchanger = rnd_exchanger(exchangers, &mut thread_rng(), num_exchangers);
+
+        exchanger.announce_pop_interest();
+
+        PopInterest { exchanger }
+    }
+}
+
+/// Interest of a pop operation in a single exchanger, see
+/// [`EliminationArray::announce_pop_interest`].
+pub(crate) struct PopInterest<'g, T> {
+    exchanger: &'g Exchanger<T>,
+}
+
+impl<'g, T> PopInterest<'g, T> {
+    /// Try to take an item from the exchanger the interest was announced on,
+    /// moving it out via `take`, see [`Exchanger::exchange_pop_with`].
+    pub(crate) fn exchange_pop_with<S: PopStrategy, R: EventRecorder, O>(
+        &self,
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+        take: impl FnMut(*const T) -> O,
+    ) -> Result<O, ()> {
+        recorder.record(Event::StartEliminationArrayPop);
+
+        self.exchanger
+            .exchange_pop_with(id, strategy, recorder, |_| true, take)
+    }
+}
+
+impl<'g, T> Drop for PopInterest<'g, T> {
+    fn drop(&mut self) {
+        self.exchanger.withdraw_pop_interest();
+    }
+}
+
+impl<T> Default for EliminationArray<T> {
+    fn default() -> Self {
+        EliminationArray::new()
     }
 }
 
+/// Exchanger along with the index of the exchanger selected in its place.
+///
+/// Operations select among the first exchangers, see
+/// [`PushStrategy::num_exchangers`], through a permutation of the indices
+/// drawn per stack, thus stacks shared by the same threads narrow down to
+/// different exchangers instead of developing correlated hot ones. Drawn anew
+/// for each length, thus on each resize, from the seed of the stack, see
+/// [`ResizeConfig::seed`].
+struct Slot<T> {
+    exchanger: Exchanger<T>,
+    permuted: usize,
+}
+
+fn new_exchangers<T>(len: usize, seed: u64) -> Vec<Slot<T>> {
+    permutation(len, seed)
+        .into_iter()
+        .map(|permuted| Slot {
+            exchanger: Exchanger::new(),
+            permuted,
+        })
+        .collect()
+}
+
+/// Permutation of `0..len` drawn from `seed` via a Fisher-Yates shuffle on
+/// splitmix64, independent of the version of `rand`, thus reproducible.
+fn permutation(len: usize, seed: u64) -> Vec<usize> {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+
+    let mut order = (0..len).collect::<Vec<_>>();
+    for i in (1..len).rev() {
+        order.swap(i, (next() % (i as u64 + 1)) as usize);
+    }
+    order
+}
+
+/// Number of exchangers to pick from given the `requested` number of a
+/// strategy, at least one, e.g. for a strategy scaling the number down on low
+/// contention, at most all of them.
+fn considered<T>(requested: usize, exchangers: &[Slot<T>]) -> usize {
+    requested.clamp(1, exchangers.len())
+}
+
+/// Pick a random exchanger among the first `range` ones, permuted per stack,
+/// see [`Slot`].
+fn rnd_exchanger<'a, T>(
+    exchangers: &'a [Slot<T>],
+    rng: &mut ThreadRng,
+    range: usize,
+) -> &'a Exchanger<T> {
+    let i = rng.gen_range(0, range);
+    &exchangers[exchangers[i].permuted].exchanger
+}
+
+/// Pick two random exchangers and prefer the one a pop operation announced
+/// interest in.
+fn interested_exchanger<'a, T>(
+    exchangers: &'a [Slot<T>],
+    rng: &mut ThreadRng,
+    range: usize,
+) -> &'a Exchanger<T> {
+    let first = rnd_exchanger(exchangers, rng, range);
+    if first.has_pop_interest() {
+        return first;
+    }
+
+    rnd_exchanger(exchangers, rng, range)
+}
+
 pub trait PushStrategy: exchanger::PushStrategy {
     fn try_push(&mut self) -> bool;
 
+    /// Decide whether to prefer exchangers pop operations announced interest
+    /// in.
+    fn use_pop_interest(&mut self) -> bool {
+        false
+    }
+
     /// Decide how many of the `total` exchangers should be considered. On low
     /// contention one could only use the first x exchangers to increase the
     /// exchange-success rate.
@@ -94,7 +372,8 @@ pub trait PopStrategy: exchanger::PopStrategy {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::strategy::ExpRetryStrategy;
+    use crate::event::NoOpRecorder;
+    use crate::strategy::{ExpRetryStrategy, Strategy};
     use std::sync::Arc;
     use std::thread;
 
@@ -114,7 +393,7 @@ mod tests {
                 for _ in 0..item_count {
                     let mut strategy = ExpRetryStrategy::new();
                     while elimination_array
-                        .exchange_push((), &mut strategy, &mut recorder)
+                        .exchange_push((), OperationId::next(), &mut strategy, &mut recorder)
                         .is_err()
                     {}
                 }
@@ -130,7 +409,7 @@ mod tests {
                 for _ in 0..item_count {
                     let mut strategy = ExpRetryStrategy::new();
                     while elimination_array
-                        .exchange_pop(&mut strategy, &mut recorder)
+                        .exchange_pop(OperationId::next(), &mut strategy, &mut recorder)
                         .is_err()
                     {}
                 }
@@ -141,4 +420,117 @@ mod tests {
             handler.join().unwrap();
         }
     }
+
+    #[test]
+    fn considers_at_least_one_exchanger() {
+        /// Scales the number of exchangers down to none.
+        struct NoExchangers(ExpRetryStrategy);
+
+        impl Strategy for NoExchangers {
+            fn new() -> Self {
+                NoExchangers(ExpRetryStrategy::new())
+            }
+            fn use_elimination_array(&mut self) -> bool {
+                true
+            }
+            fn try_stack(&mut self) -> bool {
+                false
+            }
+            fn try_elimination_array(&mut self) -> bool {
+                self.0.try_elimination_array()
+            }
+            fn num_exchangers(&mut self, _total: usize) -> usize {
+                0
+            }
+            fn try_start_exchange(&mut self) -> bool {
+                self.0.try_start_exchange()
+            }
+            fn retry_check_exchanged(&mut self) -> bool {
+                self.0.retry_check_exchanged()
+            }
+            fn try_exchange(&mut self) -> bool {
+                self.0.try_exchange()
+            }
+        }
+
+        // A single exchanger, as sized for a single CPU.
+        let elimination_array =
+            EliminationArray::with_resize_config(ResizeConfig::with_parallelism(1));
+        let mut strategy = NoExchangers::new();
+        assert_eq!(
+            elimination_array.exchange_push(
+                1,
+                OperationId::next(),
+                &mut strategy,
+                &mut NoOpRecorder {}
+            ),
+            Err(1)
+        );
+        let mut strategy = NoExchangers::new();
+        assert!(elimination_array
+            .exchange_pop(OperationId::next(), &mut strategy, &mut NoOpRecorder {})
+            .is_err());
+    }
+
+    #[test]
+    fn shrinks_when_no_partner_shows_up() {
+        let elimination_array = EliminationArray::with_resize_config(ResizeConfig {
+            window: 1,
+            hysteresis: 1,
+            shrink_threshold: 0.5,
+            min_exchangers: 1,
+            max_exchangers: 8,
+            ..ResizeConfig::default()
+        });
+        elimination_array
+            .exchangers
+            .swap(new_exchangers(8, 0), &epoch::pin());
+
+        let mut events = vec![];
+        let mut strategy = ExpRetryStrategy::new();
+        let item = elimination_array
+            .exchange_push(1, OperationId::next(), &mut strategy, &mut events)
+            .unwrap_err();
+        assert_eq!(item, 1);
+
+        let resizes = events
+            .iter()
+            .filter_map(|e| match e {
+                Event::ResizeEliminationArray(from, to) => Some((*from, *to)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(resizes.first(), Some(&(8, 4)));
+        assert!(elimination_array.exchangers.load(&epoch::pin()).items.len() < 8);
+
+        #[cfg(feature = "history")]
+        assert_eq!(
+            elimination_array.history.snapshot().first().unwrap().change,
+            Change::ResizeEliminationArray { from: 8, to: 4 },
+        );
+    }
+
+    fn order(elimination_array: &EliminationArray<u32>) -> Vec<usize> {
+        let guard = epoch::pin();
+        let slots = elimination_array.exchangers.load(&guard).items;
+        slots.iter().map(|slot| slot.permuted).collect()
+    }
+
+    #[test]
+    fn seed_permutes_exchangers() {
+        let seeded = |seed| {
+            EliminationArray::with_resize_config(ResizeConfig {
+                seed: Some(seed),
+                ..ResizeConfig::fixed(8)
+            })
+        };
+
+        let order_1 = order(&seeded(1));
+        let mut sorted = order_1.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..8).collect::<Vec<_>>());
+
+        assert_eq!(order(&seeded(1)), order_1);
+        assert_ne!(order(&seeded(2)), order_1);
+    }
 }
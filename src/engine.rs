@@ -0,0 +1,367 @@
+//! The two-phase loop shared by all push and pop operations: alternating
+//! between the Treiber stack and the elimination array until the operation
+//! completes or its strategy gives up.
+//!
+//! What differs between a push and a pop operation is captured by a [`Role`],
+//! thus features like deadlines, budgets or event recording are implemented
+//! once by the [`OperationEngine`] instead of once per operation kind.
+
+use crate::elimination_array::EliminationArray;
+use crate::event::{Event, EventRecorder, OperationId};
+use crate::strategy::Strategy;
+use crate::treiber_stack::{PopResult, TreiberStack};
+use crossbeam::epoch;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+
+/// Push or pop specific attempts of an operation run by an
+/// [`OperationEngine`].
+pub(crate) trait Role<T>: Sized {
+    /// Carried from one attempt to the next: the item of a push operation,
+    /// nothing for a pop operation.
+    type Pending;
+    /// Result of a completed operation.
+    type Output;
+
+    /// Whether the elimination array can complete the operation at all.
+    fn eliminates() -> bool {
+        true
+    }
+
+    fn try_stack<S: Strategy, R: EventRecorder>(
+        engine: &OperationEngine<'_, T, Self>,
+        pending: Self::Pending,
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+    ) -> Result<Self::Output, Self::Pending>;
+
+    fn try_elimination_array<S: Strategy, R: EventRecorder>(
+        engine: &OperationEngine<'_, T, Self>,
+        pending: Self::Pending,
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+    ) -> Result<Self::Output, Self::Pending>;
+}
+
+/// Pushes the pending item, completing once it is either pushed onto the
+/// Treiber stack or taken by a pop operation on the elimination array.
+pub(crate) struct Push;
+
+/// Pops an item, completing once an item is either popped off the Treiber
+/// stack or taken from a push operation on the elimination array, or the
+/// Treiber stack is found empty.
+pub(crate) struct Pop;
+
+/// Pops an item like [`Pop`], though moves it out of the Treiber stack or the
+/// elimination array via the pending `take`, e.g. straight into storage of the
+/// caller, see [`TreiberStack::pop_with`]. Completes with whether an item was
+/// taken.
+pub(crate) struct PopWith<F>(PhantomData<F>);
+
+pub(crate) struct OperationEngine<'a, T, Ro> {
+    stack: &'a TreiberStack<T>,
+    elimination_array: &'a EliminationArray<T>,
+    /// Whether to consider the elimination array at all, see
+    /// [`crate::Config::elimination`].
+    eliminate: bool,
+    role: PhantomData<Ro>,
+}
+
+impl<'a, T, Ro: Role<T>> OperationEngine<'a, T, Ro> {
+    pub(crate) fn new(
+        stack: &'a TreiberStack<T>,
+        elimination_array: &'a EliminationArray<T>,
+        eliminate: bool,
+    ) -> Self {
+        OperationEngine {
+            stack,
+            elimination_array,
+            eliminate,
+            role: PhantomData,
+        }
+    }
+
+    /// Run the operation, starting with the Treiber stack. Gives up, handing
+    /// back what is pending, once `exhausted` holds after a failed attempt on
+    /// the Treiber stack.
+    pub(crate) fn run<S: Strategy, R: EventRecorder>(
+        &self,
+        pending: Ro::Pending,
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+        exhausted: impl Fn(&S) -> bool,
+    ) -> Result<Ro::Output, Ro::Pending> {
+        recorder.record(Event::TryStack);
+        match Ro::try_stack(self, pending, id, strategy, recorder) {
+            Ok(output) => Ok(output),
+            Err(pending) => self.resume(pending, id, strategy, recorder, exhausted),
+        }
+    }
+
+    /// Continue the operation after a failed attempt on the Treiber stack,
+    /// alternating between the elimination array and the Treiber stack. See
+    /// [`OperationEngine::run`].
+    pub(crate) fn resume<S: Strategy, R: EventRecorder>(
+        &self,
+        mut pending: Ro::Pending,
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+        exhausted: impl Fn(&S) -> bool,
+    ) -> Result<Ro::Output, Ro::Pending> {
+        loop {
+            if exhausted(strategy) {
+                return Err(pending);
+            }
+
+            if Ro::eliminates() && self.eliminate && strategy.use_elimination_array() {
+                recorder.record(Event::TryEliminationArray);
+                match Ro::try_elimination_array(self, pending, id, strategy, recorder) {
+                    Ok(output) => return Ok(output),
+                    Err(p) => pending = p,
+                }
+            }
+
+            recorder.record(Event::TryStack);
+            match Ro::try_stack(self, pending, id, strategy, recorder) {
+                Ok(output) => return Ok(output),
+                Err(p) => pending = p,
+            }
+        }
+    }
+}
+
+impl<T> Role<T> for Push {
+    type Pending = T;
+    type Output = ();
+
+    fn try_stack<S: Strategy, R: EventRecorder>(
+        engine: &OperationEngine<'_, T, Self>,
+        item: T,
+        _id: OperationId,
+        strategy: &mut S,
+        _recorder: &mut R,
+    ) -> Result<(), T> {
+        engine.stack.push(item, strategy)
+    }
+
+    fn try_elimination_array<S: Strategy, R: EventRecorder>(
+        engine: &OperationEngine<'_, T, Self>,
+        item: T,
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+    ) -> Result<(), T> {
+        engine
+            .elimination_array
+            .exchange_push(item, id, strategy, recorder)
+    }
+}
+
+impl<T> Role<T> for Pop {
+    type Pending = ();
+    type Output = Option<T>;
+
+    /// Pushing a zero-sized item never fails, thus no push operation ever
+    /// waits on the elimination array.
+    fn eliminates() -> bool {
+        mem::size_of::<T>() != 0
+    }
+
+    fn try_stack<S: Strategy, R: EventRecorder>(
+        engine: &OperationEngine<'_, T, Self>,
+        _pending: (),
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+    ) -> Result<Option<T>, ()> {
+        try_stack_with(
+            engine.stack,
+            engine.elimination_array,
+            id,
+            strategy,
+            recorder,
+            |item| unsafe { ptr::read(item) },
+        )
+    }
+
+    fn try_elimination_array<S: Strategy, R: EventRecorder>(
+        engine: &OperationEngine<'_, T, Self>,
+        _pending: (),
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+    ) -> Result<Option<T>, ()> {
+        engine
+            .elimination_array
+            .exchange_pop(id, strategy, recorder)
+            .map(Some)
+    }
+}
+
+impl<T, F: FnMut(*const T)> Role<T> for PopWith<F> {
+    type Pending = F;
+    type Output = bool;
+
+    /// See [`Pop`].
+    fn eliminates() -> bool {
+        <Pop as Role<T>>::eliminates()
+    }
+
+    fn try_stack<S: Strategy, R: EventRecorder>(
+        engine: &OperationEngine<'_, T, Self>,
+        mut take: F,
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+    ) -> Result<bool, F> {
+        match try_stack_with(
+            engine.stack,
+            engine.elimination_array,
+            id,
+            strategy,
+            recorder,
+            &mut take,
+        ) {
+            Ok(taken) => Ok(taken.is_some()),
+            Err(()) => Err(take),
+        }
+    }
+
+    fn try_elimination_array<S: Strategy, R: EventRecorder>(
+        engine: &OperationEngine<'_, T, Self>,
+        mut take: F,
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+    ) -> Result<bool, F> {
+        match engine.elimination_array.exchange_pop_with(
+            id,
+            strategy,
+            recorder,
+            |_| true,
+            &mut take,
+        ) {
+            Ok(()) => Ok(true),
+            Err(()) => Err(take),
+        }
+    }
+}
+
+/// Pop attempt on the Treiber stack shared by [`Pop`] and [`PopWith`], moving
+/// the item out via `take`.
+///
+/// With [`Strategy::use_pop_interest`], announces the interest in an exchanger
+/// while retrying the Treiber stack and tries that exchanger right after a
+/// failed retry.
+fn try_stack_with<T, O, S: Strategy, R: EventRecorder>(
+    stack: &TreiberStack<T>,
+    elimination_array: &EliminationArray<T>,
+    id: OperationId,
+    strategy: &mut S,
+    recorder: &mut R,
+    mut take: impl FnMut(*const T) -> O,
+) -> Result<Option<O>, ()> {
+    if !<Pop as Role<T>>::eliminates() || !strategy.use_pop_interest() {
+        return match stack.pop_with(strategy, take) {
+            PopResult::Popped(taken) => Ok(Some(taken)),
+            PopResult::Empty => Ok(None),
+            PopResult::Contended => Err(()),
+        };
+    }
+
+    let guard = epoch::pin();
+    let interest = elimination_array.announce_pop_interest(strategy, &guard);
+
+    match stack.pop_with(strategy, &mut take) {
+        PopResult::Popped(taken) => return Ok(Some(taken)),
+        PopResult::Empty => return Ok(None),
+        PopResult::Contended => {}
+    }
+
+    recorder.record(Event::TryEliminationArray);
+    interest
+        .exchange_pop_with(id, strategy, recorder, take)
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::NoOpRecorder;
+    use crate::strategy::ExpRetryStrategy;
+
+    #[test]
+    fn push_then_pop() {
+        let (stack, elimination_array) = (TreiberStack::new(), EliminationArray::new());
+        let push = OperationEngine::<_, Push>::new(&stack, &elimination_array, true);
+        let pop = OperationEngine::<_, Pop>::new(&stack, &elimination_array, true);
+        let mut strategy = ExpRetryStrategy::new();
+        let mut recorder = NoOpRecorder {};
+
+        let result = push.run(1, OperationId::next(), &mut strategy, &mut recorder, |_| {
+            false
+        });
+        assert_eq!(result, Ok(()));
+
+        let result = pop.run(
+            (),
+            OperationId::next(),
+            &mut strategy,
+            &mut recorder,
+            |_| false,
+        );
+        assert_eq!(result, Ok(Some(1)));
+        let result = pop.run(
+            (),
+            OperationId::next(),
+            &mut strategy,
+            &mut recorder,
+            |_| false,
+        );
+        assert_eq!(result, Ok(None));
+    }
+
+    #[test]
+    fn exhausted_hands_back_pending_item() {
+        /// Denies every attempt.
+        struct Deny;
+
+        impl Strategy for Deny {
+            fn new() -> Self {
+                Deny
+            }
+            fn use_elimination_array(&mut self) -> bool {
+                false
+            }
+            fn try_stack(&mut self) -> bool {
+                false
+            }
+            fn try_elimination_array(&mut self) -> bool {
+                false
+            }
+            fn try_start_exchange(&mut self) -> bool {
+                false
+            }
+            fn retry_check_exchanged(&mut self) -> bool {
+                false
+            }
+            fn try_exchange(&mut self) -> bool {
+                false
+            }
+        }
+
+        let (stack, elimination_array) = (TreiberStack::new(), EliminationArray::new());
+        let push = OperationEngine::<_, Push>::new(&stack, &elimination_array, true);
+
+        let mut events = vec![];
+        let result = push.run(1, OperationId::next(), &mut Deny, &mut events, |_| true);
+
+        assert_eq!(result, Err(1));
+        assert_eq!(events, vec![Event::TryStack]);
+    }
+}
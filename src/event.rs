@@ -1,42 +1,371 @@
-#[derive(Clone, Debug)]
-pub(crate) enum Event {
-    StartPush,
+//! Events recorded while executing push and pop operations, describing the
+//! path an operation takes through the Treiber stack and the elimination array.
+//!
+//! Traces of events are meant to be exported and analyzed by tools outside of
+//! this crate. Thus the trace schema is versioned via [`SCHEMA_VERSION`] and
+//! each kind of event carries a stable numeric id, see [`Event::id`].
+//!
+//! Traces are collected per operation via [`crate::Stack::instrumented_push`]
+//! and [`crate::Stack::instrumented_pop`], passing an [`EventRecorder`], e.g.
+//! a `Vec<Event>` for full traces or an [`AggregatingRecorder`] for counts
+//! only. Both are cheap enough for production use, the latter not allocating
+//! at all.
+//!
+//! Events of an operation are buffered in a small fixed size array on the
+//! stack of the calling thread and handed to the recorder at once when the
+//! operation finishes, together with its id and duration, see
+//! [`EventRecorder::record_operation`].
+//!
+//! ```rust
+//! # use elimination_backoff_stack::Stack;
+//! # use elimination_backoff_stack::event::{AggregatingRecorder, Event};
+//! let stack = Stack::<u8>::new();
+//!
+//! let mut trace = vec![];
+//! stack.instrumented_push(1, &mut trace);
+//! assert!(matches!(trace[0], Event::StartPush(_)));
+//!
+//! let mut counts = AggregatingRecorder::new();
+//! assert_eq!(stack.instrumented_pop(&mut counts), Some(1));
+//! assert_eq!(counts.count(&Event::FinishPop(true)), 1);
+//! ```
+
+use std::cell::{Cell, RefCell};
+#[cfg(any(test, feature = "serde"))]
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use std::time::{Duration, Instant};
+
+static NEXT_THREAD_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD_ID: usize = NEXT_THREAD_ID.fetch_add(1, Relaxed);
+    static NEXT_NONCE: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Index of the calling thread, dense across all threads of the process, see
+/// [`OperationId::thread_id`].
+pub(crate) fn current_thread_id() -> usize {
+    THREAD_ID.with(|id| *id)
+}
+
+/// Version of the trace schema, i.e. the set of [`Event`]s, their ids and the
+/// data they carry.
+///
+/// Bumped whenever an event is added or the data of an event changes. The id
+/// of an event is never changed nor reused, thus consumers can skip events of
+/// unknown ids.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Unique identifier of a single push or pop operation, enabling correlation
+/// of events across threads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OperationId {
+    thread_id: usize,
+    nonce: u64,
+}
+
+impl OperationId {
+    /// Returns a new identifier, unique across all threads.
+    pub(crate) fn next() -> Self {
+        OperationId {
+            thread_id: current_thread_id(),
+            nonce: NEXT_NONCE.with(|nonce| {
+                let n = nonce.get();
+                nonce.set(n + 1);
+                n
+            }),
+        }
+    }
+
+    /// Index of the thread that started the operation.
+    pub fn thread_id(&self) -> usize {
+        self.thread_id
+    }
+
+    /// Sequence number of the operation among the operations of its thread.
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+}
+
+/// Single step of a push or pop operation.
+///
+/// New events are added over time, bumping [`SCHEMA_VERSION`], thus matching
+/// on an event requires a wildcard arm.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// Push operation with the given id started.
+    StartPush(OperationId),
     StartEliminationArrayPush,
+    /// Push operation tries to offer its item on an exchanger.
     StartExchangerPush,
-    StartPop,
+    /// Pop operation with the given id started.
+    StartPop(OperationId),
     StartEliminationArrayPop,
+    /// Pop operation tries to take an item from an exchanger.
     StartExchangerPop,
+    /// Operation tries the Treiber stack.
     TryStack,
+    /// Operation tries the elimination array.
     TryEliminationArray,
     FinishPush,
-    FinishPop,
+    /// Pop operation finished, either with an item or finding the stack
+    /// empty.
+    FinishPop(bool),
+    /// Number of exchangers the operation considers on the elimination array.
     NumExchangers(usize),
+    /// Item was exchanged with the operation with the given id.
+    ExchangedWith(OperationId),
+    /// Elimination array was resized from the first to the second number of
+    /// exchangers.
+    ResizeEliminationArray(usize, usize),
+    /// Strategy of the operation took the decision, permitting it if `true`.
+    /// Only recorded for strategies wrapped in
+    /// [`Traced`](crate::strategy::Traced).
+    Decision(Decision, bool),
+}
+
+/// Decision of a [`crate::strategy::Strategy`], named after the method taking
+/// it, see [`Event::Decision`].
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decision {
+    UseEliminationArray,
+    TryStack,
+    TryEliminationArray,
+    TryStartExchange,
+    RetryCheckExchanged,
+    TryExchange,
+    UsePopInterest,
 }
 
-pub(crate) fn print_padded(e: &Event) {
+/// Number of distinct [`Event`] kinds, ignoring any data they carry.
+pub(crate) const NUM_EVENT_KINDS: usize = 14;
+
+impl Event {
+    /// Stable numeric id of the event's kind, ignoring any data it carries.
+    ///
+    /// Ids are dense, i.e. within `0..n` for `n` kinds of events, and never
+    /// change across versions of this crate. New events get the next free
+    /// id.
+    pub fn id(&self) -> u16 {
+        match self {
+            Event::StartPush(_) => 0,
+            Event::StartEliminationArrayPush => 1,
+            Event::StartExchangerPush => 2,
+            Event::StartPop(_) => 3,
+            Event::StartEliminationArrayPop => 4,
+            Event::StartExchangerPop => 5,
+            Event::TryStack => 6,
+            Event::TryEliminationArray => 7,
+            Event::FinishPush => 8,
+            Event::FinishPop(_) => 9,
+            Event::NumExchangers(_) => 10,
+            Event::ExchangedWith(_) => 11,
+            Event::ResizeEliminationArray(..) => 12,
+            Event::Decision(..) => 13,
+        }
+    }
+}
+
+#[cfg(any(test, feature = "serde"))]
+pub(crate) fn write_padded(f: &mut fmt::Formatter<'_>, e: &Event) -> fmt::Result {
     let padding = match e {
-        Event::StartPush => 0,
+        Event::StartPush(_) => 0,
         Event::StartEliminationArrayPush => 2,
         Event::StartExchangerPush => 3,
-        Event::StartPop => 0,
+        Event::StartPop(_) => 0,
         Event::StartEliminationArrayPop => 2,
         Event::StartExchangerPop => 3,
         Event::TryStack => 1,
         Event::TryEliminationArray => 1,
         Event::FinishPush => 0,
-        Event::FinishPop => 0,
+        Event::FinishPop(_) => 0,
         Event::NumExchangers(_) => 3,
+        Event::ExchangedWith(_) => 3,
+        Event::ResizeEliminationArray(..) => 2,
+        // Nested one level below the event the decision leads to.
+        Event::Decision(decision, _) => match decision {
+            Decision::UseEliminationArray | Decision::UsePopInterest => 1,
+            Decision::TryStack => 2,
+            Decision::TryEliminationArray => 3,
+            Decision::TryStartExchange | Decision::RetryCheckExchanged | Decision::TryExchange => 4,
+        },
     };
 
-    for padding in 0..padding {
-        print!("\t");
+    for _ in 0..padding {
+        write!(f, "\t")?;
     }
 
-    print!("{:?}\n", e);
+    writeln!(f, "{:?}", e)
 }
 
-pub(crate) trait EventRecorder {
-    fn record(&mut self, e: Event);
+/// Sink of the events of operations, see
+/// [`crate::Stack::instrumented_push`] and
+/// [`crate::Stack::instrumented_pop`].
+///
+/// Called synchronously on the hot path of the operation, thus should be
+/// cheap, e.g. buffering events for later export.
+pub trait EventRecorder {
+    fn record(&mut self, event: Event);
+
+    /// Record the events of a finished operation at once, e.g. as a single
+    /// record of a trace shared across threads.
+    ///
+    /// Calls [`EventRecorder::record`] per event by default. An operation
+    /// recording more events than fit into its buffer, e.g. retrying many
+    /// times under contention, hands the events exceeding it to
+    /// [`EventRecorder::record`] early, thus `operation` holds only the
+    /// remaining ones.
+    fn record_operation(&mut self, operation: &Operation<'_>) {
+        for event in operation.events() {
+            self.record(*event);
+        }
+    }
+}
+
+impl<R: EventRecorder + ?Sized> EventRecorder for &mut R {
+    fn record(&mut self, event: Event) {
+        (**self).record(event)
+    }
+
+    fn record_operation(&mut self, operation: &Operation<'_>) {
+        (**self).record_operation(operation)
+    }
+}
+
+/// Envelope of the events of a single finished operation, see
+/// [`EventRecorder::record_operation`].
+#[derive(Clone, Copy, Debug)]
+pub struct Operation<'a> {
+    id: OperationId,
+    duration: Duration,
+    allocations: Option<u64>,
+    events: &'a [Event],
+}
+
+impl<'a> Operation<'a> {
+    pub fn id(&self) -> OperationId {
+        self.id
+    }
+
+    /// Time from the start to the finish of the operation, including the
+    /// time spent recording its events.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Heap allocations of the operation, e.g. of nodes and of exchanger
+    /// states, not counting the ones of recording its events. `None` unless
+    /// the [`CountingAllocator`](crate::alloc_count::CountingAllocator) is
+    /// installed, requiring the `alloc-count` feature.
+    pub fn allocations(&self) -> Option<u64> {
+        self.allocations
+    }
+
+    pub fn events(&self) -> &'a [Event] {
+        self.events
+    }
+
+    /// Final event of the operation, i.e. [`Event::FinishPush`] or
+    /// [`Event::FinishPop`].
+    pub fn outcome(&self) -> Option<&'a Event> {
+        self.events
+            .last()
+            .filter(|event| matches!(event, Event::FinishPush | Event::FinishPop(_)))
+    }
+}
+
+/// Number of events buffered per operation by a [`Batch`].
+const BATCH_CAPACITY: usize = 32;
+
+/// Recorder buffering the events of a single operation, handing them to the
+/// wrapped recorder at once via [`Batch::finish`].
+pub(crate) struct Batch<'r, R: ?Sized> {
+    recorder: &'r mut R,
+    id: OperationId,
+    start: Instant,
+    /// Allocations of the thread at the start, see [`allocations`].
+    allocations: Option<u64>,
+    events: [Event; BATCH_CAPACITY],
+    len: usize,
+}
+
+impl<'r, R: EventRecorder + ?Sized> Batch<'r, R> {
+    pub(crate) fn new(id: OperationId, recorder: &'r mut R) -> Self {
+        // Discards decisions left behind by an operation that unwound.
+        PENDING_DECISIONS.with(|pending| *pending.borrow_mut() = Some(Vec::new()));
+        Batch {
+            recorder,
+            id,
+            start: Instant::now(),
+            allocations: allocations(),
+            events: [Event::TryStack; BATCH_CAPACITY],
+            len: 0,
+        }
+    }
+
+    fn buffer(&mut self, event: Event) {
+        if self.len == BATCH_CAPACITY {
+            // Growing the recorder is not attributable to the recorded
+            // operation.
+            let (recorder, events) = (&mut *self.recorder, &self.events);
+            uncounted(|| events.iter().for_each(|event| recorder.record(*event)));
+            self.len = 0;
+        }
+        self.events[self.len] = event;
+        self.len += 1;
+    }
+
+    /// Hand the events buffered to the wrapped recorder, preceded by the
+    /// decisions of a traced strategy, see [`record_decision`].
+    pub(crate) fn finish(mut self) {
+        let decisions = PENDING_DECISIONS.with(|pending| pending.borrow_mut().take());
+        if let Some(decisions) = decisions.filter(|decisions| !decisions.is_empty()) {
+            // Keep the outcome last, see `Operation::outcome`.
+            let outcome = self.len.checked_sub(1).map(|last| {
+                self.len = last;
+                self.events[last]
+            });
+            for decision in decisions {
+                self.buffer(decision);
+            }
+            outcome.into_iter().for_each(|outcome| self.buffer(outcome));
+        }
+        let operation = Operation {
+            id: self.id,
+            duration: self.start.elapsed(),
+            allocations: self
+                .allocations
+                .zip(allocations())
+                .map(|(start, end)| end - start),
+            events: &self.events[..self.len],
+        };
+        let recorder = self.recorder;
+        uncounted(|| recorder.record_operation(&operation));
+    }
+}
+
+impl<R: EventRecorder + ?Sized> EventRecorder for Batch<'_, R> {
+    fn record(&mut self, event: Event) {
+        self.buffer(event);
+    }
+}
+
+// Runs `f` without counting its allocations, see `src/alloc_count.rs`.
+#[cfg(any(test, feature = "alloc-count"))]
+use crate::alloc_count::{allocations, uncounted};
+
+#[cfg(not(any(test, feature = "alloc-count")))]
+fn uncounted<R>(f: impl FnOnce() -> R) -> R {
+    f()
+}
+
+#[cfg(not(any(test, feature = "alloc-count")))]
+fn allocations() -> Option<u64> {
+    None
 }
 
 pub(crate) struct NoOpRecorder {}
@@ -45,8 +374,238 @@ impl EventRecorder for NoOpRecorder {
     fn record(&mut self, _event: Event) {}
 }
 
+/// Recorder counting events per kind in a fixed size array.
+///
+/// Neither allocates nor grows while recording, thus a middle ground between
+/// not recording at all and recording full traces via `Vec<Event>`. Recorders of
+/// different threads can be combined via [`AggregatingRecorder::merge`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AggregatingRecorder {
+    counts: [u64; NUM_EVENT_KINDS],
+}
+
+impl AggregatingRecorder {
+    pub fn new() -> Self {
+        AggregatingRecorder::default()
+    }
+
+    /// Number of events recorded of the same kind as `event`.
+    pub fn count(&self, event: &Event) -> u64 {
+        self.counts[usize::from(event.id())]
+    }
+
+    /// Point-in-time copy of the counters.
+    pub fn snapshot(&self) -> Self {
+        *self
+    }
+
+    /// Add the counters of `other` to the counters of `self`.
+    pub fn merge(&mut self, other: &AggregatingRecorder) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other;
+        }
+    }
+}
+
+impl EventRecorder for AggregatingRecorder {
+    fn record(&mut self, event: Event) {
+        self.counts[usize::from(event.id())] += 1;
+    }
+}
+
 impl EventRecorder for Vec<Event> {
     fn record(&mut self, event: Event) {
         self.push(event);
     }
+
+    fn record_operation(&mut self, operation: &Operation<'_>) {
+        self.extend_from_slice(operation.events());
+    }
+}
+
+thread_local! {
+    /// Decisions of traced strategies of this thread not yet recorded, see
+    /// [`record_decision`]. `None` unless an instrumented operation is in
+    /// flight on this thread.
+    static PENDING_DECISIONS: RefCell<Option<Vec<Event>>> = const { RefCell::new(None) };
+}
+
+/// Record a decision of a traced strategy, see
+/// [`crate::strategy::Traced`].
+///
+/// Strategies are created per operation without access to the recorder of the
+/// operation. Thus decisions are buffered per thread and drained once by
+/// [`Batch::finish`], keeping the thread local off the path of every other
+/// event. Dropped outside of instrumented operations, e.g. of
+/// [`crate::Stack::push`].
+pub(crate) fn record_decision(decision: Decision, permitted: bool) {
+    PENDING_DECISIONS.with(|pending| {
+        if let Some(pending) = pending.borrow_mut().as_mut() {
+            // Buffering the decision is not attributable to the operation
+            // either.
+            uncounted(|| pending.push(Event::Decision(decision, permitted)));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregating_recorder_counts_and_merges() {
+        let id = OperationId::next();
+
+        let mut a = AggregatingRecorder::new();
+        a.record(Event::StartPush(id));
+        a.record(Event::TryStack);
+        a.record(Event::NumExchangers(4));
+        a.record(Event::NumExchangers(8));
+
+        let snapshot = a.snapshot();
+        a.record(Event::FinishPush);
+        assert_eq!(snapshot.count(&Event::FinishPush), 0);
+
+        let mut b = AggregatingRecorder::new();
+        b.record(Event::StartPush(id));
+        b.record(Event::FinishPush);
+
+        a.merge(&b);
+
+        assert_eq!(a.count(&Event::StartPush(id)), 2);
+        assert_eq!(a.count(&Event::TryStack), 1);
+        assert_eq!(a.count(&Event::NumExchangers(0)), 2);
+        assert_eq!(a.count(&Event::FinishPush), 2);
+        assert_eq!(a.count(&Event::StartPop(id)), 0);
+    }
+
+    #[test]
+    fn batch_hands_over_operation_at_once() {
+        #[derive(Default)]
+        struct Envelopes {
+            records: usize,
+            operations: Vec<(OperationId, Vec<Event>, Option<Event>)>,
+        }
+
+        impl EventRecorder for Envelopes {
+            fn record(&mut self, _event: Event) {
+                self.records += 1;
+            }
+
+            fn record_operation(&mut self, operation: &Operation<'_>) {
+                self.operations.push((
+                    operation.id(),
+                    operation.events().to_vec(),
+                    operation.outcome().copied(),
+                ));
+            }
+        }
+
+        let stack = crate::Stack::<u8>::new();
+        let mut envelopes = Envelopes::default();
+        stack.instrumented_push(1, &mut envelopes);
+        stack.instrumented_pop(&mut envelopes);
+
+        assert_eq!(envelopes.records, 0);
+        assert_eq!(envelopes.operations.len(), 2);
+        let (id, events, outcome) = &envelopes.operations[1];
+        assert_eq!(events.first(), Some(&Event::StartPop(*id)));
+        assert_eq!(*outcome, Some(Event::FinishPop(true)));
+
+        // Events beyond the capacity of the batch are handed over early.
+        let id = OperationId::next();
+        let mut batch = Batch::new(id, &mut envelopes);
+        for _ in 0..BATCH_CAPACITY + 2 {
+            batch.record(Event::TryStack);
+        }
+        batch.finish();
+        assert_eq!(envelopes.records, BATCH_CAPACITY);
+        assert_eq!(envelopes.operations[2].1.len(), 2);
+        assert_eq!(envelopes.operations[2].2, None);
+    }
+
+    #[test]
+    fn operations_carry_allocations() {
+        struct Allocations(Vec<Option<u64>>);
+
+        impl EventRecorder for Allocations {
+            fn record(&mut self, _event: Event) {}
+
+            fn record_operation(&mut self, operation: &Operation<'_>) {
+                self.0.push(operation.allocations());
+            }
+        }
+
+        let stack = crate::Stack::<u64>::new();
+        stack.push(0);
+        let mut allocations = Allocations(Vec::with_capacity(2));
+        stack.instrumented_push(1, &mut allocations);
+        stack.instrumented_pop(&mut allocations);
+        // The node of the pushed item, none for popping.
+        assert_eq!(allocations.0, vec![Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn decisions_only_recorded_by_instrumented_operations() {
+        type S = crate::strategy::Traced<crate::strategy::ExpRetryStrategy>;
+        let stack = crate::Stack::<u8, S, S>::new();
+        stack.push(1);
+
+        let mut trace = vec![];
+        stack.instrumented_pop(&mut trace);
+        // Not preceded by the decisions of the push operation.
+        assert!(matches!(trace[0], Event::StartPop(_)), "{:?}", trace);
+        assert!(trace.contains(&Event::Decision(Decision::TryStack, true)));
+        // Drained once the operation finished, thus right before the outcome.
+        assert!(
+            matches!(trace[trace.len() - 2], Event::Decision(..)),
+            "{:?}",
+            trace
+        );
+        assert_eq!(trace.last(), Some(&Event::FinishPop(true)));
+    }
+
+    #[test]
+    fn event_ids_are_dense() {
+        let id = OperationId::next();
+        let mut ids = vec![
+            Event::StartPush(id),
+            Event::StartEliminationArrayPush,
+            Event::StartExchangerPush,
+            Event::StartPop(id),
+            Event::StartEliminationArrayPop,
+            Event::StartExchangerPop,
+            Event::TryStack,
+            Event::TryEliminationArray,
+            Event::FinishPush,
+            Event::FinishPop(true),
+            Event::NumExchangers(1),
+            Event::ExchangedWith(id),
+            Event::ResizeEliminationArray(1, 2),
+            Event::Decision(Decision::TryStack, true),
+        ]
+        .iter()
+        .map(Event::id)
+        .collect::<Vec<_>>();
+
+        ids.sort_unstable();
+        assert_eq!(ids, (0..NUM_EVENT_KINDS as u16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn operation_ids_are_unique() {
+        let mut ids: Vec<OperationId> = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| (0..100).map(|_| OperationId::next()).collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+
+        ids.sort();
+        ids.dedup();
+
+        assert_eq!(ids.len(), 400);
+    }
 }
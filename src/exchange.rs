@@ -0,0 +1,173 @@
+//! Lock-free single slot exchanger, i.e. a single exchanger of the
+//! elimination array of [`crate::Stack`], usable on its own, e.g. to hand
+//! items from a producer thread to a consumer thread without a queue.
+//!
+//! A push operation offers its item on the slot and waits for a pop
+//! operation to take it, withdrawing the offer once its [`Strategy`] gives
+//! up. A pop operation takes the item on offer, if any, retrying while its
+//! [`Strategy`] permits. Neither blocks, thus the caller decides what to do
+//! with an item not exchanged, e.g. retry or fall back to a slower channel.
+//!
+//! Not available with the `no-elimination` feature, which compiles the
+//! exchanger out.
+
+use crate::event::{NoOpRecorder, OperationId};
+use crate::exchanger;
+use crate::strategy::{ExpRetryStrategy, Strategy};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Single slot exchanging items between a push and a pop operation, waiting
+/// and retrying as decided by a [`Strategy`] per operation, the
+/// [`ExpRetryStrategy`] by default.
+///
+/// Waiting for a partner a few spin loop iterations at most, the default
+/// strategy suits partners running concurrently on other CPUs. A strategy
+/// waiting until a partner shows up turns the exchanger into a rendezvous
+/// channel:
+///
+/// ```rust
+/// # use elimination_backoff_stack::exchange::Exchanger;
+/// # use elimination_backoff_stack::strategy::Strategy;
+/// # use std::thread;
+/// /// Waits for a partner as long as it takes, yielding in between.
+/// struct Rendezvous;
+///
+/// impl Strategy for Rendezvous {
+///     fn new() -> Self {
+///         Rendezvous
+///     }
+///     fn try_start_exchange(&mut self) -> bool {
+///         true
+///     }
+///     fn retry_check_exchanged(&mut self) -> bool {
+///         thread::yield_now();
+///         true
+///     }
+///     fn try_exchange(&mut self) -> bool {
+///         thread::yield_now();
+///         true
+///     }
+///     // Only consulted by a `Stack`.
+///     fn use_elimination_array(&mut self) -> bool { false }
+///     fn try_stack(&mut self) -> bool { false }
+///     fn try_elimination_array(&mut self) -> bool { false }
+/// }
+///
+/// let exchanger = Exchanger::<u32, Rendezvous>::new();
+/// thread::scope(|scope| {
+///     scope.spawn(|| exchanger.exchange_push(42).unwrap());
+///     assert_eq!(exchanger.exchange_pop(), Some(42));
+/// });
+/// ```
+pub struct Exchanger<T, S = ExpRetryStrategy> {
+    inner: exchanger::Exchanger<T>,
+    // See `Stack::phantom`.
+    strategy: PhantomData<fn() -> S>,
+}
+
+impl<T, S: Strategy> Exchanger<T, S> {
+    pub fn new() -> Self {
+        Exchanger {
+            inner: exchanger::Exchanger::new(),
+            strategy: PhantomData,
+        }
+    }
+
+    /// Offer `item` to a concurrent [`Exchanger::exchange_pop`], waiting for
+    /// it to take the item as long as the strategy permits. Hands `item` back
+    /// if no pop operation took it, or if the slot was in use by another
+    /// push operation.
+    pub fn exchange_push(&self, item: T) -> Result<(), T> {
+        self.inner.exchange_push(
+            item,
+            OperationId::next(),
+            &mut S::new(),
+            &mut NoOpRecorder {},
+        )
+    }
+
+    /// Take the item offered by a concurrent [`Exchanger::exchange_push`],
+    /// retrying as long as the strategy permits. `None` if no item was on
+    /// offer.
+    pub fn exchange_pop(&self) -> Option<T> {
+        self.inner
+            .exchange_pop(OperationId::next(), &mut S::new(), &mut NoOpRecorder {})
+            .ok()
+    }
+}
+
+impl<T, S: Strategy> Default for Exchanger<T, S> {
+    fn default() -> Self {
+        Exchanger::new()
+    }
+}
+
+impl<T, S> fmt::Debug for Exchanger<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Exchanger").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Waits for a partner as long as it takes.
+    struct Rendezvous;
+
+    impl Strategy for Rendezvous {
+        fn new() -> Self {
+            Rendezvous
+        }
+        fn use_elimination_array(&mut self) -> bool {
+            false
+        }
+        fn try_stack(&mut self) -> bool {
+            false
+        }
+        fn try_elimination_array(&mut self) -> bool {
+            false
+        }
+        fn try_start_exchange(&mut self) -> bool {
+            true
+        }
+        fn retry_check_exchanged(&mut self) -> bool {
+            thread::yield_now();
+            true
+        }
+        fn try_exchange(&mut self) -> bool {
+            thread::yield_now();
+            true
+        }
+    }
+
+    #[test]
+    fn gives_up_without_partner() {
+        let exchanger = Exchanger::<u32>::new();
+        assert_eq!(exchanger.exchange_pop(), None);
+        assert_eq!(exchanger.exchange_push(1), Err(1));
+        assert_eq!(exchanger.exchange_pop(), None);
+    }
+
+    #[test]
+    fn hands_items_from_producer_to_consumer() {
+        let items = 100;
+        let exchanger = Exchanger::<u32, Rendezvous>::new();
+
+        let consumed = thread::scope(|scope| {
+            scope.spawn(|| {
+                for item in 0..items {
+                    exchanger.exchange_push(item).unwrap();
+                }
+            });
+
+            (0..items)
+                .map(|_| exchanger.exchange_pop().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(consumed, (0..items).collect::<Vec<_>>());
+    }
+}
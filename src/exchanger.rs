@@ -1,178 +1,452 @@
-use crate::event::{Event, EventRecorder, NoOpRecorder};
-use crossbeam::epoch::{self, Atomic, Owned};
+use crate::event::{Event, EventRecorder, OperationId};
+use crate::leak::{LeakDetector, Resolution};
+use crate::ordering::{AcqRel, Acquire, Relaxed, Release};
+use crate::strategy::CasFailure;
+use crossbeam::epoch::{self, Atomic, Guard, Owned, Shared};
 use std::mem::ManuallyDrop;
 use std::ptr;
-use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::atomic::AtomicUsize;
 
 // TODO: crossbeam::epoch::Shared has a with_tag method. Can this mirror the
 // Java AtomicStampedReference?
 enum Item<T> {
     Empty,
-    // TODO: ManuallyDrop necessary here?
-    Waiting(ManuallyDrop<T>),
-    Busy,
+    /// Item offered by the push operation with the given id.
+    ///
+    /// `ManuallyDrop`, as a replaced `Waiting` item is freed via
+    /// `defer_destroy` after the item was moved out by either the pop
+    /// operation taking it or the push operation withdrawing it. Only
+    /// `Drop for Exchanger` drops the item in place, see [`crate::leak`].
+    Waiting(ManuallyDrop<T>, OperationId),
+    /// Item taken by the pop operation with the given id.
+    Busy(OperationId),
 }
 
 pub struct Exchanger<T> {
     item: Atomic<Item<T>>,
+    /// Number of pop operations intending to exchange on this exchanger soon.
+    /// Merely a hint for push operations picking an exchanger.
+    pop_interest: AtomicUsize,
+    pub(crate) stats: SlotStats,
+    leaks: LeakDetector,
+}
+
+/// Outcomes of the exchange attempts on a single exchanger since the counters
+/// were last taken.
+#[derive(Debug, Default)]
+pub(crate) struct SlotStats {
+    attempts: AtomicUsize,
+    /// Attempts failing while the exchanger was in use by other operations.
+    busy_misses: AtomicUsize,
+    /// Attempts failing while no partner showed up.
+    empty_misses: AtomicUsize,
+}
+
+impl SlotStats {
+    fn record(&self, outcome: Outcome) {
+        self.attempts.fetch_add(1, Relaxed);
+        match outcome {
+            Outcome::Exchanged => {}
+            Outcome::BusyMiss => {
+                self.busy_misses.fetch_add(1, Relaxed);
+            }
+            Outcome::EmptyMiss => {
+                self.empty_misses.fetch_add(1, Relaxed);
+            }
+        }
+    }
+
+    pub(crate) fn attempts(&self) -> usize {
+        self.attempts.load(Relaxed)
+    }
+
+    /// Returns and resets the number of attempts, busy misses and empty
+    /// misses.
+    pub(crate) fn take(&self) -> (usize, usize, usize) {
+        (
+            self.attempts.swap(0, Relaxed),
+            self.busy_misses.swap(0, Relaxed),
+            self.empty_misses.swap(0, Relaxed),
+        )
+    }
+}
+
+enum Outcome {
+    Exchanged,
+    BusyMiss,
+    EmptyMiss,
+}
+
+impl Outcome {
+    fn miss(busy: bool) -> Self {
+        if busy {
+            Outcome::BusyMiss
+        } else {
+            Outcome::EmptyMiss
+        }
+    }
 }
 
 impl<T> Exchanger<T> {
     pub fn new() -> Self {
         Self {
             item: Atomic::new(Item::Empty),
+            pop_interest: AtomicUsize::new(0),
+            stats: SlotStats::default(),
+            leaks: LeakDetector::new(),
         }
     }
 
+    pub(crate) fn announce_pop_interest(&self) {
+        self.pop_interest.fetch_add(1, Relaxed);
+    }
+
+    pub(crate) fn withdraw_pop_interest(&self) {
+        self.pop_interest.fetch_sub(1, Relaxed);
+    }
+
+    pub(crate) fn has_pop_interest(&self) -> bool {
+        self.pop_interest.load(Relaxed) != 0
+    }
+
     pub(crate) fn exchange_push<S: PushStrategy, R: EventRecorder>(
         &self,
         item: T,
+        id: OperationId,
         strategy: &mut S,
         recorder: &mut R,
     ) -> Result<(), T> {
         recorder.record(Event::StartExchangerPush);
 
-        let mut new_item = Owned::new(Item::Waiting(ManuallyDrop::new(item)));
+        let mut new_item = Owned::new(Item::Waiting(ManuallyDrop::new(item), id));
 
         // TODO: Should we reuse this guard? Might be better performing when
         // calling `exchange_push` in a loop.
         let guard = epoch::pin();
 
-        loop {
+        // Whether the exchanger was found in use by other operations.
+        let mut busy = false;
+
+        let offer = loop {
             if !strategy.try_start_exchange() {
                 let item = match std::mem::replace(&mut *new_item, Item::Empty) {
                     Item::Empty => unreachable!(),
-                    Item::Waiting(item) => ManuallyDrop::into_inner(item),
-                    Item::Busy => unreachable!(),
+                    Item::Waiting(item, _) => ManuallyDrop::into_inner(item),
+                    Item::Busy(_) => unreachable!(),
                 };
 
+                self.stats.record(Outcome::miss(busy));
                 return Err(item);
             }
 
             // Assume using `Relaxed` is correct, given that the actual
-            // synchronization happens further below with `compare_and_set`.
+            // synchronization happens further below with `compare_exchange`.
             let current_item = self.item.load(Relaxed, &guard);
 
             match unsafe { current_item.as_ref() } {
-                Some(&Item::Empty) => {
+                Some(Item::Empty) => {
+                    // Record the offer before publishing it, given that a pop
+                    // operation might take the item right after.
+                    self.leaks.offered(id);
                     match self
                         .item
                         // Assume using `Release` is correct here, given that
                         // one needs to enforce that `new_item` is written
                         // before being accessible by other threads through this
-                        // `compare_and_set`.
-                        .compare_and_set(current_item, new_item, Release, &guard)
+                        // `compare_exchange`.
+                        .compare_exchange(current_item, new_item, Release, Relaxed, &guard)
                     {
-                        Ok(_) => {
+                        Ok(waiting) => {
+                            strategy.on_cas_success();
                             unsafe { guard.defer_destroy(current_item) };
-                            break;
+                            break Offer {
+                                exchanger: self,
+                                waiting,
+                                guard: &guard,
+                            };
+                        }
+                        Err(e) => {
+                            self.leaks.resolved(id, Resolution::Unpublished);
+                            busy = true;
+                            new_item = e.new;
+                            strategy.on_cas_failure(CasFailure::LostRace);
                         }
-                        Err(e) => new_item = e.new,
                     }
                 }
-                Some(&Item::Waiting(_)) => continue,
-                Some(&Item::Busy) => continue,
-                None => unimplemented!(),
+                Some(Item::Waiting(..)) | Some(Item::Busy(_)) => {
+                    busy = true;
+                    strategy.on_cas_failure(CasFailure::SlotBusy);
+                    continue;
+                }
+                None => unreachable!("an exchanger always holds an item"),
             }
-        }
+        };
 
-        loop {
-            // Assume using `Relaxed` is correct, given that the actual
-            // synchronization happens further below with `compare_and_set`.
-            let current_item = self.item.load(Relaxed, &guard);
+        let taken = loop {
+            if let Some(taken) = offer.taken() {
+                break taken;
+            }
 
-            match unsafe { current_item.as_ref() } {
-                Some(&Item::Empty) => {
-                    panic!("only we can set it back to empty");
-                }
-                Some(&Item::Waiting(ref item)) => {
-                    if strategy.retry_check_exchanged() {
-                        continue;
-                    }
+            if strategy.retry_check_exchanged() {
+                continue;
+            }
 
-                    if self
-                        .item
-                        // Assume using `Release` is correct, given that
-                        // correctness depends on the fact that the previous
-                        // `compare_and_set` going from `Empty` to `Waiting`
-                        // happens before this instruction. Otherwise nothing
-                        // enforces, that the `Exchanger` was filled by this
-                        // push operation and not by a different push operation.
-                        .compare_and_set(current_item, Owned::new(Item::Empty), Release, &guard)
-                        .is_ok()
-                    {
-                        unsafe {
-                            guard.defer_destroy(current_item);
-                            return Err(ManuallyDrop::into_inner(ptr::read(&(*item))));
-                        }
-                    }
-                }
-                Some(&Item::Busy) => {
-                    self.item
-                        // Assume using `Release` is correct, given that
-                        // correctness depends on the fact that the previous
-                        // `compare_and_set` going from `Empty` to `Waiting`
-                        // happens before this instruction. Otherwise nothing
-                        // enforces, that the `Exchanger` was filled by this
-                        // push operation and not by a different push operation.
-                        .compare_and_set(current_item, Owned::new(Item::Empty), Release, &guard)
-                        .expect("we should be the only one compare and swapping this value");
-                    unsafe { guard.defer_destroy(current_item) };
-                    return Ok(());
+            match offer.withdraw() {
+                Ok(item) => {
+                    // No pop operation showed up in time.
+                    self.stats.record(Outcome::EmptyMiss);
+                    return Err(item);
                 }
-                None => unimplemented!(),
+                Err(taken) => break taken,
             }
-        }
+        };
+
+        recorder.record(Event::ExchangedWith(taken.partner()));
+        taken.release();
+        self.stats.record(Outcome::Exchanged);
+        Ok(())
     }
 
     pub(crate) fn exchange_pop<S: PopStrategy, R: EventRecorder>(
         &self,
+        id: OperationId,
         strategy: &mut S,
         recorder: &mut R,
     ) -> Result<T, ()> {
+        self.exchange_pop_if(id, strategy, recorder, |_| true)
+    }
+
+    /// [`Exchanger::exchange_pop`] only taking an item `accept` holds for,
+    /// treating an exchanger offering any other item as busy.
+    ///
+    /// `accept` inspects the item in place, which a push operation withdrawing
+    /// its offer might move out concurrently, thus must only read plain data
+    /// of it, e.g. a sequence number next to the actual item.
+    pub(crate) fn exchange_pop_if<S: PopStrategy, R: EventRecorder>(
+        &self,
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+        accept: impl FnMut(&T) -> bool,
+    ) -> Result<T, ()> {
+        self.exchange_pop_with(id, strategy, recorder, accept, |item| unsafe {
+            ptr::read(item)
+        })
+    }
+
+    /// [`Exchanger::exchange_pop_if`] moving the item out via `take`, given a
+    /// pointer to it within the offer, see
+    /// [`crate::treiber_stack::TreiberStack::pop_with`]. Calls `take` at most
+    /// once.
+    pub(crate) fn exchange_pop_with<S: PopStrategy, R: EventRecorder, O>(
+        &self,
+        id: OperationId,
+        strategy: &mut S,
+        recorder: &mut R,
+        mut accept: impl FnMut(&T) -> bool,
+        mut take: impl FnMut(*const T) -> O,
+    ) -> Result<O, ()> {
         recorder.record(Event::StartExchangerPop);
 
         let guard = epoch::pin();
 
+        // Whether the exchanger was found in use by other operations.
+        let mut busy = false;
+
         while strategy.try_exchange() {
             // Assume using `Relaxed` is correct, given that the actual
-            // synchronization happens further below with `compare_and_set`.
+            // synchronization happens further below with `compare_exchange`.
             let current_item = self.item.load(Relaxed, &guard);
 
             match unsafe { current_item.as_ref() } {
-                Some(&Item::Empty) => {
+                Some(Item::Empty) => {
                     strategy.on_no_contention();
                     continue;
                 }
-                Some(&Item::Waiting(ref item)) => {
+                Some(Item::Waiting(item, _)) if !accept(item) => {
+                    busy = true;
+                    strategy.on_contention();
+                    continue;
+                }
+                Some(Item::Waiting(item, partner)) => {
                     match self
                         .item
-                        // Assume using `Acquire` is correct, given that this
-                        // operation does not depend on any previous operations
-                        // happening before, but past operations (returning the
-                        // item) happening after.
-                        .compare_and_set(current_item, Owned::new(Item::Busy), Acquire, &guard)
-                    {
+                        // Assume using `AcqRel` is correct, given that past
+                        // operations (returning the item) need to happen after
+                        // and the pushing thread reads the `Busy` item written
+                        // before.
+                        .compare_exchange(
+                            current_item,
+                            Owned::new(Item::Busy(id)),
+                            AcqRel,
+                            Relaxed,
+                            &guard,
+                        ) {
                         Ok(_) => unsafe {
+                            strategy.on_cas_success();
+                            self.stats.record(Outcome::Exchanged);
+                            recorder.record(Event::ExchangedWith(*partner));
+                            self.leaks.resolved(*partner, Resolution::Taken);
+                            let taken = take(&**item);
                             guard.defer_destroy(current_item);
-                            return Ok(ManuallyDrop::into_inner(ptr::read(&(*item))));
+                            return Ok(taken);
                         },
-                        Err(_) => strategy.on_contention(),
+                        Err(e) => {
+                            busy = true;
+                            strategy.on_cas_failure(match unsafe { e.current.as_ref() } {
+                                Some(Item::Empty) => CasFailure::SlotEmptied,
+                                _ => CasFailure::LostRace,
+                            });
+                            strategy.on_contention();
+                        }
                     }
                 }
-                Some(&Item::Busy) => {
+                Some(Item::Busy(_)) => {
+                    busy = true;
+                    strategy.on_cas_failure(CasFailure::SlotBusy);
                     strategy.on_contention();
                     continue;
                 }
-                None => unimplemented!(),
+                None => unreachable!("an exchanger always holds an item"),
             }
         }
 
+        self.stats.record(Outcome::miss(busy));
         Err(())
     }
+
+    /// Take the item offered by a push operation, if any, with a single
+    /// attempt on behalf of the operation `id`. Unlike
+    /// [`Exchanger::exchange_pop`] neither waits for an offer nor counts
+    /// towards the statistics, thus does not skew resizing.
+    pub(crate) fn take_waiting(&self, id: OperationId) -> Option<T> {
+        let guard = epoch::pin();
+
+        // See `exchange_pop` for the orderings.
+        let current_item = self.item.load(Relaxed, &guard);
+        let (item, partner) = match unsafe { current_item.as_ref() } {
+            Some(Item::Waiting(item, partner)) => (item, partner),
+            _ => return None,
+        };
+
+        self.item
+            .compare_exchange(
+                current_item,
+                Owned::new(Item::Busy(id)),
+                AcqRel,
+                Relaxed,
+                &guard,
+            )
+            .ok()?;
+
+        self.leaks.resolved(*partner, Resolution::Taken);
+        unsafe {
+            guard.defer_destroy(current_item);
+            Some(ManuallyDrop::into_inner(ptr::read(item)))
+        }
+    }
 }
 
-// TODO: Rethink this implementation. What about the ManuallyDrop wrapping Item?
+/// Item offered on an exchanger by a push operation, i.e. the exchanger being
+/// `Waiting` with the item of the push operation holding the offer.
+struct Offer<'g, T> {
+    exchanger: &'g Exchanger<T>,
+    waiting: Shared<'g, Item<T>>,
+    guard: &'g Guard,
+}
+
+impl<'g, T> Offer<'g, T> {
+    /// Returns [`Taken`] in case a pop operation took the item.
+    fn taken(&self) -> Option<Taken<'g, T>> {
+        // Assume using `Acquire` is correct, given that a `Busy` item is
+        // written by the pop operation and read here.
+        let current_item = self.exchanger.item.load(Acquire, self.guard);
+
+        match unsafe { current_item.as_ref() } {
+            Some(Item::Waiting(..)) => None,
+            Some(Item::Busy(_)) => Some(Taken {
+                exchanger: self.exchanger,
+                busy: current_item,
+                guard: self.guard,
+            }),
+            Some(Item::Empty) => unreachable!("only the offering push operation empties"),
+            None => unreachable!("an exchanger always holds an item"),
+        }
+    }
+
+    /// Take the item back unless a pop operation took it in the meantime.
+    fn withdraw(self) -> Result<T, Taken<'g, T>> {
+        match self.exchanger.item.compare_exchange(
+            self.waiting,
+            Owned::new(Item::Empty),
+            // Assume using `Release` is correct, given that correctness
+            // depends on the fact that the previous `compare_exchange` going
+            // from `Empty` to `Waiting` happens before this instruction.
+            // Otherwise nothing enforces, that the `Exchanger` was filled by
+            // this push operation and not by a different push operation.
+            //
+            // `Acquire` on failure, given that the `Busy` item written by the
+            // pop operation is read on failure.
+            Release,
+            Acquire,
+            self.guard,
+        ) {
+            Ok(_) => unsafe {
+                self.guard.defer_destroy(self.waiting);
+                match self.waiting.deref() {
+                    Item::Waiting(item, id) => {
+                        self.exchanger.leaks.resolved(*id, Resolution::Withdrawn);
+                        Ok(ManuallyDrop::into_inner(ptr::read(item)))
+                    }
+                    _ => unreachable!(),
+                }
+            },
+            // Only a pop operation replaces a `Waiting` item, always with a
+            // `Busy` one.
+            Err(e) => Err(Taken {
+                exchanger: self.exchanger,
+                busy: e.current,
+                guard: self.guard,
+            }),
+        }
+    }
+}
+
+/// Proof that a pop operation took the item offered by the push operation
+/// holding this token, i.e. the exchanger being `Busy`.
+///
+/// Only ever held by the push operation that offered the item. While the
+/// exchanger is `Busy` no other operation writes to it: push operations only
+/// replace `Empty` items and pop operations only replace `Waiting` items.
+/// Thus releasing the exchanger is a plain store instead of a
+/// compare-and-swap that could fail.
+struct Taken<'g, T> {
+    exchanger: &'g Exchanger<T>,
+    busy: Shared<'g, Item<T>>,
+    guard: &'g Guard,
+}
+
+impl<'g, T> Taken<'g, T> {
+    /// Id of the pop operation that took the item.
+    fn partner(&self) -> OperationId {
+        match unsafe { self.busy.deref() } {
+            Item::Busy(partner) => *partner,
+            _ => unreachable!("`Taken` is only created from a `Busy` item"),
+        }
+    }
+
+    /// Reset the exchanger to `Empty`, thus usable by other operations again.
+    fn release(self) {
+        // Assume using `Release` is correct, given that a push operation
+        // replacing the `Empty` item needs to observe the exchange being
+        // complete.
+        self.exchanger.item.store(Owned::new(Item::Empty), Release);
+        unsafe { self.guard.defer_destroy(self.busy) };
+    }
+}
+
+/// Drops an item still `Waiting`, i.e. left behind by a push operation that
+/// unwound mid-offer. Any other item was moved out before, see [`Item`].
 impl<T> Drop for Exchanger<T> {
     fn drop(&mut self) {
         let owned: Owned<_>;
@@ -188,19 +462,22 @@ impl<T> Drop for Exchanger<T> {
         // Make sure to access `Item<_>` and not `ManuallyDrop<Item<_>>`.
         match item {
             Item::Empty => {}
-            Item::Busy => {}
-            Item::Waiting(ref mut item) => {
+            Item::Busy(_) => {}
+            Item::Waiting(ref mut item, id) => {
+                self.leaks.resolved(id, Resolution::Dropped);
                 unsafe { ManuallyDrop::drop(item) };
             }
         }
-
-        drop(item);
     }
 }
 
 pub trait PushStrategy {
     fn try_start_exchange(&mut self) -> bool;
     fn retry_check_exchanged(&mut self) -> bool;
+
+    fn on_cas_failure(&mut self, _reason: CasFailure) {}
+
+    fn on_cas_success(&mut self) {}
 }
 
 pub trait PopStrategy {
@@ -208,13 +485,19 @@ pub trait PopStrategy {
 
     fn on_contention(&mut self) {}
     fn on_no_contention(&mut self) {}
+
+    fn on_cas_failure(&mut self, _reason: CasFailure) {}
+
+    fn on_cas_success(&mut self) {}
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::event::NoOpRecorder;
     use crate::strategy::ExpRetryStrategy;
-    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+    use std::sync::{Arc, Mutex};
     use std::thread;
 
     #[test]
@@ -226,7 +509,12 @@ mod tests {
         let mut push_strategy = ExpRetryStrategy::new();
         let t1 = thread::spawn(move || {
             while t1_exchanger
-                .exchange_push((), &mut push_strategy, &mut t1_recorder)
+                .exchange_push(
+                    (),
+                    OperationId::next(),
+                    &mut push_strategy,
+                    &mut t1_recorder,
+                )
                 .is_err()
             {}
         });
@@ -234,13 +522,81 @@ mod tests {
         let mut t2_recorder = NoOpRecorder {};
         let mut pop_strategy = ExpRetryStrategy::new();
         while exchanger
-            .exchange_pop(&mut pop_strategy, &mut t2_recorder)
+            .exchange_pop(OperationId::next(), &mut pop_strategy, &mut t2_recorder)
             .is_err()
         {}
 
         t1.join().unwrap();
     }
 
+    #[test]
+    fn exchange_records_partner_id() {
+        let exchanger = Arc::new(Exchanger::new());
+        let push_id = OperationId::next();
+
+        let t1_exchanger = exchanger.clone();
+        let t1 = thread::spawn(move || {
+            let mut recorder = vec![];
+            let mut strategy = ExpRetryStrategy::new();
+            while t1_exchanger
+                .exchange_push((), push_id, &mut strategy, &mut recorder)
+                .is_err()
+            {}
+            recorder
+        });
+
+        let pop_id = OperationId::next();
+        let mut pop_recorder = vec![];
+        let mut strategy = ExpRetryStrategy::new();
+        while exchanger
+            .exchange_pop(pop_id, &mut strategy, &mut pop_recorder)
+            .is_err()
+        {}
+
+        let push_recorder = t1.join().unwrap();
+
+        let partners = |events: Vec<Event>| -> Vec<OperationId> {
+            events
+                .into_iter()
+                .filter_map(|e| match e {
+                    Event::ExchangedWith(id) => Some(id),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(partners(push_recorder), vec![pop_id]);
+        assert_eq!(partners(pop_recorder), vec![push_id]);
+    }
+
+    #[test]
+    fn take_waiting_completes_push() {
+        let exchanger = Arc::new(Exchanger::new());
+        assert_eq!(exchanger.take_waiting(OperationId::next()), None);
+
+        let t1_exchanger = exchanger.clone();
+        let t1 = thread::spawn(move || {
+            let mut strategy = ExpRetryStrategy::new();
+            let mut item = 1;
+            while let Err(i) = t1_exchanger.exchange_push(
+                item,
+                OperationId::next(),
+                &mut strategy,
+                &mut NoOpRecorder {},
+            ) {
+                item = i;
+            }
+        });
+
+        let taken = loop {
+            if let Some(item) = exchanger.take_waiting(OperationId::next()) {
+                break item;
+            }
+        };
+
+        t1.join().unwrap();
+        assert_eq!(taken, 1);
+    }
+
     #[test]
     fn push_pop_4_threads() {
         let mut handlers = vec![];
@@ -251,7 +607,7 @@ mod tests {
         let mut t1_recorder = NoOpRecorder {};
         handlers.push(thread::spawn(move || {
             while t1_exchanger
-                .exchange_push((), &mut t1_strategy, &mut t1_recorder)
+                .exchange_push((), OperationId::next(), &mut t1_strategy, &mut t1_recorder)
                 .is_err()
             {}
         }));
@@ -261,7 +617,7 @@ mod tests {
         let mut t2_recorder = NoOpRecorder {};
         handlers.push(thread::spawn(move || {
             while t2_exchanger
-                .exchange_push((), &mut t2_strategy, &mut t2_recorder)
+                .exchange_push((), OperationId::next(), &mut t2_strategy, &mut t2_recorder)
                 .is_err()
             {}
         }));
@@ -271,7 +627,7 @@ mod tests {
         let mut t3_recorder = NoOpRecorder {};
         handlers.push(thread::spawn(move || {
             while t3_exchanger
-                .exchange_pop(&mut t3_strategy, &mut t3_recorder)
+                .exchange_pop(OperationId::next(), &mut t3_strategy, &mut t3_recorder)
                 .is_err()
             {}
         }));
@@ -279,7 +635,7 @@ mod tests {
         let mut t4_strategy = ExpRetryStrategy::new();
         let mut t4_recorder = NoOpRecorder {};
         while exchanger
-            .exchange_pop(&mut t4_strategy, &mut t4_recorder)
+            .exchange_pop(OperationId::next(), &mut t4_strategy, &mut t4_recorder)
             .is_err()
         {}
 
@@ -287,4 +643,190 @@ mod tests {
             handler.join().unwrap();
         }
     }
+
+    /// Scenario: A push operation gives up waiting and tries to take its item
+    /// back (`Waiting` -> `Empty`) while a pop operation concurrently tries to
+    /// take the item (`Waiting` -> `Busy`).
+    ///
+    /// Ensure exactly one of the two wins, thus each item ends up either
+    /// returned to the pusher or handed to a popper, never both and never
+    /// neither.
+    ///
+    /// Both compare-and-swaps compare against the very same `Waiting` pointer.
+    /// Given that the pusher keeps its epoch pinned across the whole exchange,
+    /// that allocation can not be reclaimed and reused in between, thus no
+    /// additional stamp or tag is needed to rule out ABA.
+    #[test]
+    fn abandoned_push_is_never_popped() {
+        let item_count = 10_000;
+
+        let exchanger = Arc::new(Exchanger::new());
+        let pusher_done = Arc::new(AtomicBool::new(false));
+        let popped_items = Arc::new(Mutex::new(vec![]));
+
+        let mut handlers = vec![];
+
+        for _ in 0..2 {
+            let exchanger = exchanger.clone();
+            let pusher_done = pusher_done.clone();
+            let popped_items = popped_items.clone();
+            handlers.push(thread::spawn(move || {
+                let mut recorder = NoOpRecorder {};
+                let mut items = vec![];
+                while !pusher_done.load(SeqCst) {
+                    let mut strategy = ExpRetryStrategy::new();
+                    if let Ok(item) =
+                        exchanger.exchange_pop(OperationId::next(), &mut strategy, &mut recorder)
+                    {
+                        items.push(item);
+                    }
+                }
+
+                popped_items.lock().unwrap().extend(items);
+            }));
+        }
+
+        // Use a fresh strategy for every item. With a retry exponent of zero
+        // the pusher abandons its offer right after placing it, maximizing the
+        // chance of racing a popper.
+        let mut recorder = NoOpRecorder {};
+        let mut returned_items = vec![];
+        for item in 0..item_count {
+            let mut strategy = ExpRetryStrategy::new();
+            if let Err(item) =
+                exchanger.exchange_push(item, OperationId::next(), &mut strategy, &mut recorder)
+            {
+                returned_items.push(item);
+            }
+        }
+
+        pusher_done.store(true, SeqCst);
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+
+        let mut items = Arc::try_unwrap(popped_items).unwrap().into_inner().unwrap();
+        items.extend(returned_items);
+        items.sort();
+
+        assert_eq!(items, (0..item_count).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn busy_exchanger_reports_slot_busy() {
+        /// Tries every decision exactly once, recording failure reasons.
+        #[derive(Default)]
+        struct Once {
+            tried: bool,
+            failures: Vec<CasFailure>,
+        }
+
+        impl Once {
+            fn try_once(&mut self) -> bool {
+                !std::mem::replace(&mut self.tried, true)
+            }
+        }
+
+        impl PushStrategy for Once {
+            fn try_start_exchange(&mut self) -> bool {
+                self.try_once()
+            }
+
+            fn retry_check_exchanged(&mut self) -> bool {
+                false
+            }
+
+            fn on_cas_failure(&mut self, reason: CasFailure) {
+                self.failures.push(reason);
+            }
+        }
+
+        impl PopStrategy for Once {
+            fn try_exchange(&mut self) -> bool {
+                self.try_once()
+            }
+
+            fn on_cas_failure(&mut self, reason: CasFailure) {
+                self.failures.push(reason);
+            }
+        }
+
+        let exchanger = Exchanger::new();
+        let guard = epoch::pin();
+        let empty =
+            exchanger
+                .item
+                .swap(Owned::new(Item::Busy(OperationId::next())), SeqCst, &guard);
+        unsafe { drop(empty.into_owned()) };
+
+        let mut strategy = Once::default();
+        assert!(exchanger
+            .exchange_push((), OperationId::next(), &mut strategy, &mut NoOpRecorder {})
+            .is_err());
+        assert_eq!(strategy.failures, vec![CasFailure::SlotBusy]);
+
+        let mut strategy = Once::default();
+        assert!(exchanger
+            .exchange_pop(OperationId::next(), &mut strategy, &mut NoOpRecorder {})
+            .is_err());
+        assert_eq!(strategy.failures, vec![CasFailure::SlotBusy]);
+    }
+
+    /// Scenario: Push operations unwind mid-offer, leaving their items
+    /// `Waiting` in the exchangers, which are dropped afterwards.
+    ///
+    /// Ensure each parked item is dropped exactly once. With the `debug-leaks`
+    /// feature, dropping the exchangers additionally validates that every
+    /// offer was resolved.
+    #[test]
+    fn parked_items_are_dropped_with_exchanger() {
+        /// Offers once, then unwinds instead of waiting for a partner.
+        struct Unwind;
+
+        impl PushStrategy for Unwind {
+            fn try_start_exchange(&mut self) -> bool {
+                true
+            }
+
+            fn retry_check_exchanged(&mut self) -> bool {
+                panic!("unwinding mid-offer");
+            }
+        }
+
+        struct Payload(Arc<AtomicUsize>);
+
+        impl Drop for Payload {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, SeqCst);
+            }
+        }
+
+        let threads = 4;
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let exchangers = Arc::new((0..threads).map(|_| Exchanger::new()).collect::<Vec<_>>());
+
+        let handlers = (0..threads)
+            .map(|i| {
+                let exchangers = exchangers.clone();
+                let payload = Payload(dropped.clone());
+                thread::spawn(move || {
+                    let _ = exchangers[i].exchange_push(
+                        payload,
+                        OperationId::next(),
+                        &mut Unwind,
+                        &mut NoOpRecorder {},
+                    );
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handler in handlers {
+            assert!(handler.join().is_err());
+        }
+
+        assert_eq!(dropped.load(SeqCst), 0);
+        drop(Arc::try_unwrap(exchangers).ok().unwrap());
+        assert_eq!(dropped.load(SeqCst), threads);
+    }
 }
@@ -0,0 +1,144 @@
+//! C bindings, e.g. for C and C++ services linking the crate as a `cdylib`.
+//!
+//! A stack holds opaque pointers, owned by the caller. The stack never
+//! dereferences nor frees them, thus the caller decides what an item is, e.g.
+//! a pointer to a request or a length-prefixed buffer, and when to free it.
+//!
+//! ```c
+//! struct ebs_stack *stack = ebs_stack_new();
+//!
+//! ebs_stack_push(stack, request);
+//!
+//! void *item;
+//! if (ebs_stack_pop(stack, &item)) {
+//!     handle(item);
+//! }
+//!
+//! ebs_stack_free(stack, free_request);
+//! ```
+//!
+//! See `include/elimination_backoff_stack.h` for the declarations. Requires
+//! the `ffi` feature.
+//!
+//! The crate builds as an `rlib` only by default, thus Rust dependents do not
+//! pay for linking a `cdylib`. Build the shared library explicitly:
+//!
+//! ```sh
+//! cargo rustc --release --features ffi --crate-type cdylib
+//! ```
+
+use crate::Stack;
+use std::ffi::c_void;
+
+/// Opaque pointer handed across the boundary.
+struct Item(*mut c_void);
+
+// The stack never dereferences the pointer, thus it is up to the caller to
+// share only what is safe to share across threads.
+unsafe impl Send for Item {}
+unsafe impl Sync for Item {}
+
+/// Stack handed to C as an opaque pointer, see [`ebs_stack_new`].
+pub struct EbsStack(Stack<Item>);
+
+/// Allocate a new, empty stack. Never returns null. Free via
+/// [`ebs_stack_free`].
+#[no_mangle]
+pub extern "C" fn ebs_stack_new() -> *mut EbsStack {
+    Box::into_raw(Box::new(EbsStack(Stack::new())))
+}
+
+/// Push `item`, which may be null.
+///
+/// # Safety
+///
+/// `stack` must be a stack returned by [`ebs_stack_new`] and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn ebs_stack_push(stack: *const EbsStack, item: *mut c_void) {
+    (*stack).0.push(Item(item));
+}
+
+/// Pop an item into `item`, returning whether the stack held one. Leaves
+/// `item` untouched given an empty stack.
+///
+/// # Safety
+///
+/// `stack` must be a stack returned by [`ebs_stack_new`] and not yet freed.
+/// `item` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ebs_stack_pop(stack: *const EbsStack, item: *mut *mut c_void) -> bool {
+    match (*stack).0.pop() {
+        Some(Item(popped)) => {
+            item.write(popped);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Whether the stack appeared empty, see [`Stack::is_empty`].
+///
+/// # Safety
+///
+/// `stack` must be a stack returned by [`ebs_stack_new`] and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn ebs_stack_is_empty(stack: *const EbsStack) -> bool {
+    (*stack).0.is_empty()
+}
+
+/// Free the stack, calling `free_item`, unless null, on each item left,
+/// top first.
+///
+/// # Safety
+///
+/// `stack` must be a stack returned by [`ebs_stack_new`] and not yet freed,
+/// or null, in which case nothing happens. No other thread may use the stack
+/// concurrently or afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ebs_stack_free(
+    stack: *mut EbsStack,
+    free_item: Option<unsafe extern "C" fn(*mut c_void)>,
+) {
+    if stack.is_null() {
+        return;
+    }
+
+    let stack = Box::from_raw(stack);
+    for Item(item) in stack.0 {
+        if let Some(free_item) = free_item {
+            free_item(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+    #[test]
+    fn push_pop_free() {
+        static FREED: AtomicUsize = AtomicUsize::new(0);
+
+        unsafe extern "C" fn free_item(item: *mut c_void) {
+            FREED.fetch_add(item as usize, Relaxed);
+        }
+
+        let stack = ebs_stack_new();
+        unsafe {
+            assert!(ebs_stack_is_empty(stack));
+            for i in 1..=3 {
+                ebs_stack_push(stack, i as *mut c_void);
+            }
+
+            let mut item = ptr::null_mut();
+            assert!(ebs_stack_pop(stack, &mut item));
+            assert_eq!(item as usize, 3);
+
+            ebs_stack_free(stack, Some(free_item));
+            ebs_stack_free(ptr::null_mut(), None);
+        }
+        assert_eq!(FREED.load(Relaxed), 1 + 2);
+    }
+}
@@ -0,0 +1,101 @@
+//! Bounded audit trail of structural changes to a stack, enabled via the
+//! `history` feature.
+//!
+//! Unlike the event traces of [`crate::event`], which cover every step of
+//! every operation and are only recorded on request, the history is always
+//! recorded and only covers the rare changes to the structure of the stack,
+//! e.g. resizes of the elimination array. Thus it is cheap enough to be
+//! enabled in production and queried via [`crate::Stack::history`], or
+//! extracted via [`crate::Stack::into_history`] for a postmortem.
+//!
+//! Only the most recent [`CAPACITY`] changes are retained.
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// Number of changes retained, older ones are dropped first.
+pub const CAPACITY: usize = 64;
+
+/// Structural change to a stack.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    /// The elimination array was replaced by one with a different number of
+    /// exchangers, see [`crate::ResizeConfig`].
+    ResizeEliminationArray { from: usize, to: usize },
+}
+
+/// A [`Change`] along with the time it happened.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// Wall clock time, to correlate with e.g. logs of the application.
+    pub at: SystemTime,
+    pub change: Change,
+}
+
+pub(crate) struct History {
+    entries: Mutex<VecDeque<Entry>>,
+}
+
+impl History {
+    pub(crate) fn new() -> Self {
+        History {
+            entries: Mutex::new(VecDeque::with_capacity(CAPACITY)),
+        }
+    }
+
+    // Nothing to resize without the elimination array.
+    #[cfg_attr(feature = "no-elimination", allow(dead_code))]
+    pub(crate) fn record(&self, change: Change) {
+        let mut entries = self.entries();
+        if entries.len() == CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(Entry {
+            at: SystemTime::now(),
+            change,
+        });
+    }
+
+    /// Retained entries, oldest first.
+    pub(crate) fn snapshot(&self) -> Vec<Entry> {
+        self.entries().iter().cloned().collect()
+    }
+
+    /// Retained entries, oldest first, leaving the history empty.
+    pub(crate) fn take(&self) -> Vec<Entry> {
+        std::mem::take(&mut *self.entries()).into()
+    }
+
+    fn entries(&self) -> MutexGuard<'_, VecDeque<Entry>> {
+        // Entries are complete once pushed, thus still valid after a panic
+        // while holding the lock.
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retains_most_recent_changes() {
+        let history = History::new();
+        for to in 0..CAPACITY + 2 {
+            history.record(Change::ResizeEliminationArray { from: 1, to });
+        }
+
+        let entries = history.snapshot();
+        assert_eq!(entries.len(), CAPACITY);
+        assert_eq!(
+            entries[0].change,
+            Change::ResizeEliminationArray { from: 1, to: 2 }
+        );
+        assert!(entries.windows(2).all(|w| w[0].at <= w[1].at));
+
+        assert_eq!(history.take().len(), CAPACITY);
+        assert!(history.snapshot().is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+//! Observing every item entering and leaving a [`Stack`], see
+//! [`Builder::hooks`].
+//!
+//! [`Stack`]: crate::Stack
+//! [`Builder::hooks`]: crate::Builder::hooks
+
+use std::sync::Arc;
+
+/// Callbacks invoked as ownership of an item passes to and from a
+/// [`Stack`](crate::Stack), e.g. for a leak detector or a quota tracker
+/// accounting for the items held, without wrapping every call site.
+///
+/// [`ItemHooks::on_item_enter`] is called exactly once per item handed to the
+/// stack, by any push operation, before the item is visible to other threads.
+/// [`ItemHooks::on_item_exit`] is called exactly once per item handed back,
+/// be it popped off the Treiber stack, exchanged directly with a concurrent
+/// push operation on the elimination array, handed back by a push operation
+/// giving up, e.g. [`Stack::try_push`](crate::Stack::try_push), removed via
+/// [`Stack::clear`](crate::Stack::clear) or
+/// [`Stack::take_all`](crate::Stack::take_all), or dropped along with the
+/// stack. Thus the number of items entered minus the number exited is the
+/// number of items the stack owns.
+///
+/// Called on the thread of the operation, in the middle of it, thus meant to
+/// be cheap, e.g. updating an atomic counter. A panic propagates to the
+/// caller of the operation, the item dropped.
+///
+/// ```rust
+/// # use elimination_backoff_stack::{ItemHooks, Stack};
+/// # use std::sync::atomic::{AtomicIsize, Ordering::Relaxed};
+/// # use std::sync::Arc;
+/// /// Bytes held by the stack.
+/// #[derive(Default)]
+/// struct Quota(AtomicIsize);
+///
+/// impl ItemHooks<Vec<u8>> for Quota {
+///     fn on_item_enter(&self, item: &Vec<u8>) {
+///         self.0.fetch_add(item.len() as isize, Relaxed);
+///     }
+///
+///     fn on_item_exit(&self, item: &Vec<u8>) {
+///         self.0.fetch_sub(item.len() as isize, Relaxed);
+///     }
+/// }
+///
+/// // Shared with the stack, which drops its hooks along with it.
+/// let quota = Arc::new(Quota::default());
+/// let stack = Stack::<Vec<u8>>::builder().hooks(quota.clone()).build();
+///
+/// stack.push(vec![0; 16]);
+/// stack.push(vec![0; 8]);
+/// assert_eq!(quota.0.load(Relaxed), 24);
+///
+/// stack.pop();
+/// assert_eq!(quota.0.load(Relaxed), 16);
+///
+/// drop(stack);
+/// assert_eq!(quota.0.load(Relaxed), 0);
+/// ```
+pub trait ItemHooks<T>: Send + Sync {
+    /// Called with each item handed to the stack.
+    fn on_item_enter(&self, _item: &T) {}
+
+    /// Called with each item handed back by the stack, or dropped with it.
+    fn on_item_exit(&self, _item: &T) {}
+}
+
+/// Hooks shared with the caller, e.g. to read the counters of a quota
+/// tracker.
+impl<T, H: ItemHooks<T> + ?Sized> ItemHooks<T> for Arc<H> {
+    fn on_item_enter(&self, item: &T) {
+        (**self).on_item_enter(item)
+    }
+
+    fn on_item_exit(&self, item: &T) {
+        (**self).on_item_exit(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::ExpRetryStrategy;
+    use crate::tests::Refuse;
+    use crate::Stack;
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+    use std::thread;
+
+    #[derive(Default)]
+    struct Count {
+        entered: AtomicUsize,
+        exited: AtomicUsize,
+    }
+
+    impl ItemHooks<usize> for Count {
+        fn on_item_enter(&self, _item: &usize) {
+            self.entered.fetch_add(1, Relaxed);
+        }
+
+        fn on_item_exit(&self, _item: &usize) {
+            self.exited.fetch_add(1, Relaxed);
+        }
+    }
+
+    impl Count {
+        fn get(&self) -> (usize, usize) {
+            (self.entered.load(Relaxed), self.exited.load(Relaxed))
+        }
+    }
+
+    #[test]
+    fn every_transfer_is_reported() {
+        let (threads, items_per_thread) = (4, 1_000);
+        let count = Arc::new(Count::default());
+        let stack = Stack::<usize>::builder().hooks(count.clone()).build();
+
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    for i in 0..items_per_thread {
+                        stack.push(i);
+                        stack.pop();
+                    }
+                });
+            }
+        });
+        // Left over by pop operations racing each other.
+        stack.clear();
+        assert_eq!(
+            count.get(),
+            (threads * items_per_thread, threads * items_per_thread)
+        );
+
+        stack.push_iter(0..3);
+        stack.push(3);
+        assert_eq!(stack.clear(), 4);
+        stack.push_iter(0..2);
+        assert_eq!(stack.take_all().len(), 2);
+        let total = threads * items_per_thread + 6;
+        assert_eq!(count.get(), (total, total));
+
+        stack.push(1);
+        stack.push(2);
+        assert_eq!(stack.into_vec(), vec![2, 1]);
+        let stack = Stack::<usize>::builder().hooks(count.clone()).build();
+        stack.push(1);
+        drop(stack);
+        assert_eq!(count.get(), (total + 3, total + 3));
+    }
+
+    #[test]
+    fn item_handed_back_exits() {
+        let count = Arc::new(Count::default());
+        let stack = Stack::<usize, Refuse, ExpRetryStrategy>::builder()
+            .hooks(count.clone())
+            .build();
+
+        assert_eq!(stack.try_push(1), Err(1));
+        assert_eq!(count.get(), (1, 1));
+    }
+}
@@ -0,0 +1,308 @@
+//! Stack and elimination of nodes linked by their index instead of by
+//! pointer, shared by [`crate::InlineStack`] and the stack within a shared
+//! memory segment of the `shm` feature.
+//!
+//! Neither relies on epoch based garbage collection. Nodes are never
+//! deallocated while the stack is in use, but recycled via a free list, thus
+//! an operation reading a node unlinked concurrently reads a valid, though
+//! stale, node. Each link carries a tag incremented on every change, thus a
+//! compare-and-swap based on a stale read fails, guarding against ABA.
+//!
+//! Items are stored inline in their node and copied out by the operation
+//! owning the node, thus are restricted to `Copy` types.
+
+use crate::strategy::CasFailure;
+use crate::{PopStrategy, PushStrategy};
+use rand::{thread_rng, Rng};
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{
+    AtomicU32, AtomicU64,
+    Ordering::{AcqRel, Acquire, Relaxed},
+};
+
+/// Index of no node, e.g. the next node of the bottom one.
+pub(crate) const NIL: u32 = u32::MAX;
+
+#[repr(C)]
+pub(crate) struct Node<T> {
+    pub(crate) next: AtomicU32,
+    pub(crate) item: UnsafeCell<MaybeUninit<T>>,
+}
+
+impl<T> Node<T> {
+    pub(crate) fn new(next: u32) -> Self {
+        Node {
+            next: AtomicU32::new(next),
+            item: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+}
+
+pub(crate) fn pack(tag: u32, index: u32) -> u64 {
+    u64::from(tag) << 32 | u64::from(index)
+}
+
+fn tag(tagged: u64) -> u32 {
+    (tagged >> 32) as u32
+}
+
+fn index(tagged: u64) -> u32 {
+    tagged as u32
+}
+
+/// Storage of the nodes, resolving an index to its node.
+pub(crate) trait Nodes<T> {
+    /// Only called with indices handed out by the storage, linked or not.
+    fn node(&self, index: u32) -> &Node<T>;
+}
+
+impl<T> Nodes<T> for [Node<T>] {
+    fn node(&self, index: u32) -> &Node<T> {
+        &self[index as usize]
+    }
+}
+
+/// Outcome of a single attempt to unlink the top node of a list.
+enum Unlinked {
+    Node(u32),
+    Empty,
+    Contended,
+}
+
+/// The lists of a stack: the stack itself, the free list and the exchangers
+/// of the elimination array, each slot holding the tagged index of the node
+/// on offer, if any.
+pub(crate) struct Lists<'a, T, N: ?Sized> {
+    head: &'a AtomicU64,
+    free: &'a AtomicU64,
+    slots: &'a [AtomicU64],
+    nodes: &'a N,
+    item: PhantomData<fn() -> T>,
+}
+
+impl<'a, T, N> Lists<'a, T, N>
+where
+    T: Copy,
+    N: Nodes<T> + ?Sized,
+{
+    pub(crate) fn new(
+        head: &'a AtomicU64,
+        free: &'a AtomicU64,
+        slots: &'a [AtomicU64],
+        nodes: &'a N,
+    ) -> Self {
+        Lists {
+            head,
+            free,
+            slots,
+            nodes,
+            item: PhantomData,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        index(self.head.load(Acquire)) == NIL
+    }
+
+    /// Push `item` within `node`.
+    ///
+    /// # Safety
+    ///
+    /// The node must be owned by the caller, i.e. taken via
+    /// [`Lists::alloc`] or never linked before.
+    pub(crate) unsafe fn push<S: PushStrategy>(&self, node: u32, item: T, strategy: &mut S) {
+        (*self.nodes.node(node).item.get()).write(item);
+
+        if self.try_link(self.head, node) {
+            return;
+        }
+
+        loop {
+            if strategy.use_elimination_array() && self.offer(node, strategy) {
+                return;
+            }
+
+            while strategy.try_stack() {
+                if self.try_link(self.head, node) {
+                    strategy.on_cas_success();
+                    return;
+                }
+                strategy.on_cas_failure(CasFailure::LostRace);
+                strategy.after_cas_failure();
+            }
+        }
+    }
+
+    /// Pop an item, `None` if the stack was found empty. Puts the node of the
+    /// item back onto the free list.
+    pub(crate) fn pop<S: PopStrategy>(&self, strategy: &mut S) -> Option<T> {
+        let node = match self.try_unlink(self.head) {
+            Unlinked::Node(node) => node,
+            Unlinked::Empty => return None,
+            Unlinked::Contended => 'retry: loop {
+                if strategy.use_elimination_array() {
+                    if let Some(node) = self.take(strategy) {
+                        break node;
+                    }
+                }
+
+                while strategy.try_stack() {
+                    match self.try_unlink(self.head) {
+                        Unlinked::Node(node) => {
+                            strategy.on_cas_success();
+                            break 'retry node;
+                        }
+                        Unlinked::Empty => return None,
+                        Unlinked::Contended => {
+                            strategy.on_cas_failure(CasFailure::LostRace);
+                            strategy.after_cas_failure();
+                        }
+                    }
+                }
+            },
+        };
+
+        // Safe given that the node is owned by this operation until freed,
+        // its item written before it was linked or offered.
+        let item = unsafe { (*self.nodes.node(node).item.get()).assume_init_read() };
+        self.release(node);
+        Some(item)
+    }
+
+    /// Offer `node` on a random exchanger until a pop operation takes it or
+    /// the strategy gives up. Returns whether the node was taken.
+    fn offer<S: PushStrategy>(&self, node: u32, strategy: &mut S) -> bool {
+        let mut rng = thread_rng();
+
+        while strategy.try_elimination_array() {
+            let range = strategy.num_exchangers(self.slots.len()).max(1);
+            let slot = &self.slots[rng.gen_range(0, range)];
+
+            while strategy.try_start_exchange() {
+                let current = slot.load(Acquire);
+                if index(current) != NIL {
+                    strategy.on_cas_failure(CasFailure::SlotBusy);
+                    continue;
+                }
+
+                let offered = pack(tag(current).wrapping_add(1), node);
+                if slot
+                    .compare_exchange(current, offered, AcqRel, Acquire)
+                    .is_err()
+                {
+                    strategy.on_cas_failure(CasFailure::LostRace);
+                    continue;
+                }
+                strategy.on_cas_success();
+
+                // Only a pop operation taking the node or this operation
+                // withdrawing it changes the slot from here on.
+                loop {
+                    if slot.load(Acquire) != offered {
+                        return true;
+                    }
+                    if !strategy.retry_check_exchanged() {
+                        break;
+                    }
+                }
+
+                let withdrawn = pack(tag(offered).wrapping_add(1), NIL);
+                return slot
+                    .compare_exchange(offered, withdrawn, AcqRel, Acquire)
+                    .is_err();
+            }
+        }
+
+        false
+    }
+
+    /// Take a node offered by a push operation on a random exchanger, until
+    /// the strategy gives up.
+    fn take<S: PopStrategy>(&self, strategy: &mut S) -> Option<u32> {
+        let mut rng = thread_rng();
+
+        while strategy.try_elimination_array() {
+            let range = strategy.num_exchangers(self.slots.len()).max(1);
+            let slot = &self.slots[rng.gen_range(0, range)];
+
+            while strategy.try_exchange() {
+                let current = slot.load(Acquire);
+                if index(current) == NIL {
+                    strategy.on_no_contention();
+                    continue;
+                }
+
+                let taken = pack(tag(current).wrapping_add(1), NIL);
+                match slot.compare_exchange(current, taken, AcqRel, Acquire) {
+                    Ok(_) => {
+                        strategy.on_cas_success();
+                        return Some(index(current));
+                    }
+                    Err(_) => {
+                        strategy.on_contention();
+                        strategy.on_cas_failure(CasFailure::LostRace);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Take a node off the free list, `None` if the free list is empty.
+    pub(crate) fn alloc(&self) -> Option<u32> {
+        loop {
+            match self.try_unlink(self.free) {
+                Unlinked::Node(node) => return Some(node),
+                Unlinked::Empty => return None,
+                Unlinked::Contended => std::hint::spin_loop(),
+            }
+        }
+    }
+
+    /// Number of nodes on the free list, counting at most `limit`, e.g. the
+    /// number of nodes, as a traversal racing with concurrent operations
+    /// might follow a node moved onto another list.
+    pub(crate) fn free_len(&self, limit: usize) -> usize {
+        let mut node = index(self.free.load(Acquire));
+        let mut len = 0;
+        while node != NIL && len < limit {
+            len += 1;
+            node = self.nodes.node(node).next.load(Relaxed);
+        }
+        len
+    }
+
+    /// Put a node no longer in use back onto the free list.
+    fn release(&self, node: u32) {
+        while !self.try_link(self.free, node) {
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Single compare-and-swap linking `node` on top of `list`.
+    fn try_link(&self, list: &AtomicU64, node: u32) -> bool {
+        let top = list.load(Acquire);
+        self.nodes.node(node).next.store(index(top), Relaxed);
+        list.compare_exchange(top, pack(tag(top).wrapping_add(1), node), AcqRel, Relaxed)
+            .is_ok()
+    }
+
+    /// Single compare-and-swap unlinking the top node of `list`.
+    fn try_unlink(&self, list: &AtomicU64) -> Unlinked {
+        let top = list.load(Acquire);
+        if index(top) == NIL {
+            return Unlinked::Empty;
+        }
+
+        // Possibly stale if the node was unlinked concurrently, in which case
+        // the tag changed and the compare-and-swap fails.
+        let next = self.nodes.node(index(top)).next.load(Relaxed);
+        match list.compare_exchange(top, pack(tag(top).wrapping_add(1), next), AcqRel, Relaxed) {
+            Ok(_) => Unlinked::Node(index(top)),
+            Err(_) => Unlinked::Contended,
+        }
+    }
+}
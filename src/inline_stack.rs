@@ -0,0 +1,433 @@
+//! Stack storing small `Copy` items inline in recycled nodes, e.g. work
+//! tokens, instead of allocating a node per push operation.
+//!
+//! A standalone alternative to a [`Stack`](crate::Stack), not a
+//! specialization of it, thus a [`Stack`](crate::Stack) of small `Copy` items
+//! still allocates a node per push operation. Switching to an [`InlineStack`]
+//! is up to the user.
+//!
+//! A [`Stack`](crate::Stack) allocates a node per pushed item and frees it
+//! via epoch based garbage collection once popped. An [`InlineStack`] instead
+//! keeps popped nodes on a free list for the next push operation, allocating
+//! only when the free list is empty, in chunks of doubling size. Thus the
+//! nodes allocated stay at the largest number of items ever held at once.
+//! [`InlineStack::reserve_nodes`] allocates nodes ahead of a burst instead,
+//! [`InlineStack::reclaim`] frees the ones not in use.
+//! Nodes are linked by index, see `src/indexed.rs`, exactly like within the
+//! shared memory segment of the `shm` feature.
+//!
+//! Restricted to `Copy` items no larger than a `usize`, copied out of their
+//! node by the pop operation owning it, thus never dropped. Larger items are
+//! rejected at compile time, given that each node holds its item next to the
+//! link to the next node, thus larger items grow every node, pooled or not.
+//! Lacks the instrumentation and the extended API of a
+//! [`Stack`](crate::Stack).
+
+use crate::indexed::{pack, Lists, Node, Nodes, NIL};
+use crate::resize::available_parallelism;
+use crate::strategy::ExpRetryStrategy;
+use crate::{PopStrategy, PushStrategy};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{
+    AtomicPtr, AtomicU32, AtomicU64,
+    Ordering::{AcqRel, Acquire, Relaxed},
+};
+
+/// Nodes of the first chunk, each further chunk doubling the nodes.
+const FIRST_CHUNK: usize = 32;
+
+/// Chunks needed for [`NIL`] many nodes.
+const CHUNKS: usize = 28;
+
+/// Elimination back-off stack of `Copy` items stored inline in nodes
+/// recycled via a free list, thus allocating only while growing beyond the
+/// largest number of items held so far.
+///
+/// Like a [`Stack`](crate::Stack), though without its instrumentation and
+/// extended API, and restricted to items no larger than a `usize`, see
+/// module documentation.
+///
+/// ```rust
+/// # use elimination_backoff_stack::InlineStack;
+/// let stack = InlineStack::<u32>::new();
+///
+/// for token in 0..100 {
+///     stack.push(token);
+/// }
+/// while stack.pop().is_some() {}
+///
+/// // Reuses the nodes of the tokens popped, thus allocates nothing.
+/// stack.push(0);
+/// ```
+///
+/// Items larger than a `usize` fail to compile:
+///
+/// ```rust,compile_fail
+/// # use elimination_backoff_stack::InlineStack;
+/// let stack = InlineStack::<[usize; 2]>::new();
+/// ```
+pub struct InlineStack<T, PushS = ExpRetryStrategy, PopS = ExpRetryStrategy> {
+    /// Tagged index of the top node of the stack.
+    head: AtomicU64,
+    /// Tagged index of the top node of the free list.
+    free: AtomicU64,
+    slots: Box<[AtomicU64]>,
+    nodes: Chunks<T>,
+    // See `Stack::phantom`.
+    phantom: PhantomData<fn() -> (PushS, PopS)>,
+}
+
+// Items are only ever accessed by the single operation owning their node.
+unsafe impl<T: Copy + Send, PushS, PopS> Send for InlineStack<T, PushS, PopS> {}
+unsafe impl<T: Copy + Send, PushS, PopS> Sync for InlineStack<T, PushS, PopS> {}
+
+impl<T, PushS, PopS> InlineStack<T, PushS, PopS>
+where
+    T: Copy,
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    /// Evaluated on construction, thus failing the build for items larger
+    /// than a `usize`.
+    const SMALL_ITEM: () = assert!(
+        mem::size_of::<T>() <= mem::size_of::<usize>(),
+        "InlineStack items must not be larger than a usize",
+    );
+
+    /// Empty stack with an exchanger per available CPU.
+    pub fn new() -> Self {
+        InlineStack::with_exchangers(available_parallelism())
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `exchangers` is zero.
+    pub fn with_exchangers(exchangers: usize) -> Self {
+        let () = Self::SMALL_ITEM;
+        assert!(exchangers > 0, "stack needs at least one exchanger");
+
+        InlineStack {
+            head: AtomicU64::new(pack(0, NIL)),
+            free: AtomicU64::new(pack(0, NIL)),
+            slots: (0..exchangers)
+                .map(|_| AtomicU64::new(pack(0, NIL)))
+                .collect(),
+            nodes: Chunks::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the stack would hold more than `u32::MAX - 1` items at once.
+    pub fn push(&self, item: T) {
+        let lists = self.lists();
+        let node = lists.alloc().unwrap_or_else(|| self.nodes.fresh());
+        // Safe given that the node is owned by this operation until linked.
+        unsafe { lists.push(node, item, &mut PushS::new()) };
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        self.lists().pop(&mut PopS::new())
+    }
+
+    /// Whether the stack appeared empty, see [`crate::Stack::is_empty`].
+    pub fn is_empty(&self) -> bool {
+        self.lists().is_empty()
+    }
+
+    /// Number of nodes allocated so far, i.e. at least the largest number of
+    /// items held at once.
+    pub fn allocated(&self) -> usize {
+        self.nodes.allocated()
+    }
+
+    /// Number of nodes available to push operations without allocating, i.e.
+    /// on the free list or never handed out. Approximate in the presence of
+    /// concurrent operations.
+    pub fn pooled(&self) -> usize {
+        let allocated = self.nodes.allocated();
+        let free = self.lists().free_len(allocated);
+        allocated.saturating_sub(self.nodes.handed_out()) + free
+    }
+
+    /// Allocate nodes up front such that the next `n` push operations do not
+    /// allocate, e.g. ahead of a latency critical burst, unless concurrent
+    /// push operations take the nodes first.
+    ///
+    /// Not offered by a [`Stack`](crate::Stack), which has no pool of nodes
+    /// to reserve into: it allocates a node per push operation and hands it
+    /// to the epoch based garbage collector once popped.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::InlineStack;
+    /// let stack = InlineStack::<u32>::new();
+    /// stack.reserve_nodes(1_000);
+    /// assert!(stack.pooled() >= 1_000);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stack would hold more than `u32::MAX - 1` nodes.
+    pub fn reserve_nodes(&self, n: usize) {
+        self.nodes
+            .reserve(self.nodes.handed_out().saturating_add(n));
+    }
+
+    /// Free the nodes not holding an item, returning the number of nodes
+    /// freed. Takes `&mut self`, as nodes are otherwise never freed before
+    /// the stack is dropped.
+    ///
+    /// Moves the items into freshly allocated nodes, keeping their order.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::InlineStack;
+    /// let mut stack = InlineStack::<u32>::new();
+    /// stack.reserve_nodes(1_000);
+    /// stack.push(1);
+    ///
+    /// assert!(stack.reclaim() > 0);
+    /// assert_eq!(stack.pop(), Some(1));
+    /// ```
+    pub fn reclaim(&mut self) -> usize {
+        let before = self.nodes.allocated();
+
+        // Top first. No operation is in flight, thus no item on offer on an
+        // exchanger.
+        let items = std::iter::from_fn(|| self.pop()).collect::<Vec<_>>();
+        *self = InlineStack::with_exchangers(self.slots.len());
+        for item in items.into_iter().rev() {
+            self.push(item);
+        }
+
+        before.saturating_sub(self.nodes.allocated())
+    }
+
+    fn lists(&self) -> Lists<'_, T, Chunks<T>> {
+        Lists::new(&self.head, &self.free, &self.slots, &self.nodes)
+    }
+}
+
+impl<T, PushS, PopS> Default for InlineStack<T, PushS, PopS>
+where
+    T: Copy,
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    fn default() -> Self {
+        InlineStack::new()
+    }
+}
+
+impl<T, PushS, PopS> fmt::Debug for InlineStack<T, PushS, PopS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InlineStack")
+            .field("allocated", &self.nodes.allocated())
+            .field("exchangers", &self.slots.len())
+            .finish()
+    }
+}
+
+/// Nodes in chunks of doubling size, never moved nor freed before the stack
+/// is dropped, thus an index resolves to the same node for the lifetime of
+/// the stack.
+struct Chunks<T> {
+    chunks: [AtomicPtr<Node<T>>; CHUNKS],
+    /// Number of indices handed out by [`Chunks::fresh`].
+    next: AtomicU32,
+}
+
+impl<T> Chunks<T> {
+    fn new() -> Self {
+        Chunks {
+            chunks: Default::default(),
+            next: AtomicU32::new(0),
+        }
+    }
+
+    /// Chunk of `index` and the offset of `index` within it.
+    fn locate(index: u32) -> (usize, usize) {
+        let n = index as usize / FIRST_CHUNK + 1;
+        let chunk = (usize::BITS - 1 - n.leading_zeros()) as usize;
+        (chunk, index as usize - FIRST_CHUNK * ((1 << chunk) - 1))
+    }
+
+    fn len(chunk: usize) -> usize {
+        FIRST_CHUNK << chunk
+    }
+
+    /// Index of a node never handed out before, allocating its chunk if need
+    /// be.
+    fn fresh(&self) -> u32 {
+        let index = self.next.fetch_add(1, Relaxed);
+        assert!(index < NIL, "stack holding too many items");
+
+        self.ensure(Self::locate(index).0);
+        index
+    }
+
+    /// Allocate the chunks of the first `len` indices, if not allocated
+    /// already.
+    fn reserve(&self, len: usize) {
+        assert!(len <= NIL as usize, "stack holding too many items");
+
+        (0..CHUNKS)
+            .take_while(|chunk| FIRST_CHUNK * ((1 << chunk) - 1) < len)
+            .for_each(|chunk| self.ensure(chunk));
+    }
+
+    fn ensure(&self, chunk: usize) {
+        if self.chunks[chunk].load(Acquire).is_null() {
+            let nodes = (0..Self::len(chunk))
+                .map(|_| Node::new(NIL))
+                .collect::<Box<[Node<T>]>>();
+            let nodes = Box::into_raw(nodes) as *mut Node<T>;
+            if self.chunks[chunk]
+                .compare_exchange(ptr::null_mut(), nodes, AcqRel, Acquire)
+                .is_err()
+            {
+                // Allocated concurrently, e.g. for another index within the
+                // chunk.
+                drop(unsafe { Self::boxed(nodes, chunk) });
+            }
+        }
+    }
+
+    fn handed_out(&self) -> usize {
+        self.next.load(Relaxed) as usize
+    }
+
+    fn allocated(&self) -> usize {
+        (0..CHUNKS)
+            .filter(|chunk| !self.chunks[*chunk].load(Relaxed).is_null())
+            .map(Self::len)
+            .sum()
+    }
+
+    /// # Safety
+    ///
+    /// `nodes` must be the chunk `chunk` as allocated by [`Chunks::fresh`].
+    unsafe fn boxed(nodes: *mut Node<T>, chunk: usize) -> Box<[Node<T>]> {
+        Box::from_raw(ptr::slice_from_raw_parts_mut(nodes, Self::len(chunk)))
+    }
+}
+
+impl<T> Nodes<T> for Chunks<T> {
+    fn node(&self, index: u32) -> &Node<T> {
+        let (chunk, offset) = Self::locate(index);
+        // Allocated before the index was handed out, published either by the
+        // compare-and-swap allocating the chunk or by the one linking the
+        // node.
+        let nodes = self.chunks[chunk].load(Acquire);
+        debug_assert!(!nodes.is_null());
+        unsafe { &*nodes.add(offset) }
+    }
+}
+
+impl<T> Drop for Chunks<T> {
+    fn drop(&mut self) {
+        for (chunk, nodes) in self.chunks.iter_mut().enumerate() {
+            let nodes = *nodes.get_mut();
+            if !nodes.is_null() {
+                drop(unsafe { Self::boxed(nodes, chunk) });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::thread;
+
+    #[test]
+    fn chunks_locate_indices() {
+        assert_eq!(Chunks::<u32>::locate(0), (0, 0));
+        assert_eq!(Chunks::<u32>::locate(31), (0, 31));
+        assert_eq!(Chunks::<u32>::locate(32), (1, 0));
+        assert_eq!(Chunks::<u32>::locate(95), (1, 63));
+        assert_eq!(Chunks::<u32>::locate(96), (2, 0));
+        assert_eq!(Chunks::<u32>::locate(NIL - 1).0, CHUNKS - 1);
+    }
+
+    #[test]
+    fn recycles_nodes_without_allocating() {
+        let stack = InlineStack::<u32>::with_exchangers(1);
+        assert_eq!(stack.pop(), None);
+
+        for i in 0..100 {
+            stack.push(i);
+        }
+        assert_eq!(stack.allocated(), 32 + 64 + 128);
+        for i in (0..100).rev() {
+            assert_eq!(stack.pop(), Some(i));
+        }
+        assert!(stack.is_empty());
+
+        let before = crate::alloc_count::allocations();
+        for i in 0..1_000 {
+            stack.push(i);
+            stack.push(i);
+            assert_eq!(stack.pop(), Some(i));
+            assert_eq!(stack.pop(), Some(i));
+        }
+        assert_eq!(crate::alloc_count::allocations(), before);
+        assert_eq!(stack.allocated(), 32 + 64 + 128);
+    }
+
+    #[test]
+    fn reserve_nodes_then_reclaim() {
+        let mut stack = InlineStack::<u32>::with_exchangers(1);
+        stack.reserve_nodes(100);
+        assert_eq!(stack.allocated(), 32 + 64 + 128);
+        assert_eq!(stack.pooled(), 32 + 64 + 128);
+
+        let before = crate::alloc_count::allocations();
+        for i in 0..100 {
+            stack.push(i);
+        }
+        assert_eq!(crate::alloc_count::allocations(), before);
+        assert_eq!(stack.pooled(), 32 + 64 + 128 - 100);
+
+        for _ in 0..70 {
+            stack.pop();
+        }
+        assert_eq!(stack.pooled(), 32 + 64 + 128 - 30);
+        assert_eq!(stack.reclaim(), 64 + 128);
+        assert_eq!(stack.pooled(), 2);
+        for i in (0..30).rev() {
+            assert_eq!(stack.pop(), Some(i));
+        }
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn concurrent_no_duplicates_no_losses() {
+        let (threads, items_per_thread) = (4, 10_000);
+        let stack = InlineStack::<u32>::new();
+        let popped = Mutex::new(vec![]);
+
+        thread::scope(|scope| {
+            for thread in 0..threads {
+                let (stack, popped) = (&stack, &popped);
+                scope.spawn(move || {
+                    let mut own = vec![];
+                    for i in 0..items_per_thread {
+                        stack.push(thread * items_per_thread + i);
+                        own.extend(stack.pop());
+                    }
+                    popped.lock().unwrap().extend(own);
+                });
+            }
+        });
+
+        let mut popped = popped.into_inner().unwrap();
+        popped.extend(std::iter::from_fn(|| stack.pop()));
+        popped.sort_unstable();
+        assert_eq!(popped, (0..threads * items_per_thread).collect::<Vec<_>>());
+    }
+}
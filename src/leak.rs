@@ -0,0 +1,158 @@
+//! Runtime validation that every item offered on an exchanger leaves it
+//! exactly once, enabled via the `debug-leaks` feature.
+//!
+//! An item offered by a push operation sits in the exchanger as a `Waiting`
+//! item wrapped in [`std::mem::ManuallyDrop`]. Replaced `Waiting` items are
+//! freed without dropping the item, as the item was moved out before: taken by
+//! a pop operation or withdrawn by the offering push operation. Only an item
+//! still `Waiting` once the exchanger is dropped, e.g. left behind by a push
+//! operation unwinding mid-offer, is dropped in place.
+//!
+//! With the feature enabled, each exchanger tracks the push operations with
+//! an outstanding offer. Resolving an offer twice panics right away. Dropping
+//! an exchanger with an offer neither taken, withdrawn nor dropped panics,
+//! naming the push operation.
+//!
+//! Meant for diagnosing the exchanger, not for production use: every offer
+//! takes a lock. Without the feature, [`LeakDetector`] is zero-sized.
+
+use crate::event::OperationId;
+
+pub(crate) use imp::LeakDetector;
+
+/// How an offered item left the exchanger.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Resolution {
+    /// The compare-and-swap publishing the offer failed.
+    Unpublished,
+    Taken,
+    Withdrawn,
+    Dropped,
+}
+
+#[cfg(feature = "debug-leaks")]
+mod imp {
+    use super::{OperationId, Resolution};
+    use std::sync::{Mutex, MutexGuard};
+
+    /// Outstanding offers of a single exchanger.
+    #[derive(Default)]
+    pub(crate) struct LeakDetector {
+        offers: Mutex<Vec<OperationId>>,
+    }
+
+    impl LeakDetector {
+        pub(crate) fn new() -> Self {
+            Self::default()
+        }
+
+        fn offers(&self) -> MutexGuard<'_, Vec<OperationId>> {
+            // A panic reporting a violation poisons the lock. Keep validating.
+            self.offers.lock().unwrap_or_else(|e| e.into_inner())
+        }
+
+        /// Record the item of the push operation `id` to be `Waiting`.
+        pub(crate) fn offered(&self, id: OperationId) {
+            self.offers().push(id);
+        }
+
+        /// Record the item of the push operation `id` to have left the
+        /// exchanger.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the offer was resolved before or never made.
+        pub(crate) fn resolved(&self, id: OperationId, resolution: Resolution) {
+            let mut offers = self.offers();
+            match offers.iter().position(|offer| *offer == id) {
+                Some(i) => {
+                    offers.swap_remove(i);
+                }
+                None => {
+                    drop(offers);
+                    panic!(
+                        "item offered by {:?} {:?} without an outstanding offer",
+                        id, resolution,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Reports the first offer that was neither taken, withdrawn nor dropped.
+    /// Expects an item still `Waiting` to be resolved as dropped before.
+    impl Drop for LeakDetector {
+        fn drop(&mut self) {
+            let leaked = self.offers().first().copied();
+            if let Some(id) = leaked {
+                if !std::thread::panicking() {
+                    panic!(
+                        "item offered by {:?} was neither taken, withdrawn nor dropped",
+                        id,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "debug-leaks"))]
+mod imp {
+    use super::{OperationId, Resolution};
+
+    pub(crate) struct LeakDetector {}
+
+    impl LeakDetector {
+        pub(crate) fn new() -> Self {
+            LeakDetector {}
+        }
+
+        #[inline(always)]
+        pub(crate) fn offered(&self, _id: OperationId) {}
+
+        #[inline(always)]
+        pub(crate) fn resolved(&self, _id: OperationId, _resolution: Resolution) {}
+    }
+}
+
+#[cfg(all(test, feature = "debug-leaks"))]
+mod tests {
+    use super::*;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    fn panic_message(f: impl FnOnce()) -> String {
+        let payload = catch_unwind(AssertUnwindSafe(f)).unwrap_err();
+        payload.downcast::<String>().map(|s| *s).unwrap_or_default()
+    }
+
+    #[test]
+    fn double_resolution_names_operation() {
+        let detector = LeakDetector::new();
+        let id = OperationId::next();
+        detector.offered(id);
+        detector.resolved(id, Resolution::Taken);
+
+        let message = panic_message(|| detector.resolved(id, Resolution::Withdrawn));
+
+        assert!(
+            message.contains("without an outstanding offer"),
+            "{}",
+            message
+        );
+        assert!(message.contains(&format!("{:?}", id)), "{}", message);
+    }
+
+    #[test]
+    fn leaked_offer_is_reported_on_drop() {
+        let detector = LeakDetector::new();
+        let (leaked, dropped) = (OperationId::next(), OperationId::next());
+        detector.offered(leaked);
+        detector.offered(dropped);
+        detector.resolved(dropped, Resolution::Dropped);
+
+        let message = panic_message(|| drop(detector));
+
+        assert!(message.contains("neither taken"), "{}", message);
+        assert!(message.contains(&format!("{:?}", leaked)), "{}", message);
+    }
+}
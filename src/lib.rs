@@ -1,23 +1,118 @@
+#[cfg(any(test, feature = "alloc-count"))]
+pub mod alloc_count;
+#[cfg(feature = "bench-internals")]
+#[doc(hidden)]
+pub mod bench_internals;
+mod bounded;
+mod builder;
+pub mod chunked_writer;
+pub mod concurrent_stack;
+mod conservation;
+mod counter;
+#[cfg(feature = "no-elimination")]
+mod disabled_elimination_array;
+#[cfg(not(feature = "no-elimination"))]
+pub mod elimination;
+#[cfg(not(feature = "no-elimination"))]
 mod elimination_array;
-mod event;
+mod engine;
+pub mod event;
+#[cfg(not(feature = "no-elimination"))]
+pub mod exchange;
+#[cfg(not(feature = "no-elimination"))]
 mod exchanger;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "history")]
+pub mod history;
+mod hooks;
+mod indexed;
+mod inline_stack;
+#[cfg(not(feature = "no-elimination"))]
+mod leak;
+#[cfg(test)]
+mod model;
+mod ms_queue;
+mod ordering;
+mod park;
+mod pop_async;
+mod push_async;
+mod queue;
+#[cfg_attr(feature = "no-elimination", allow(dead_code))]
+mod resize;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "shm")]
+pub mod shm;
+mod snapshot;
 pub mod strategy;
+#[cfg(not(feature = "no-elimination"))]
+mod swappable_slice;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+mod timestamped;
+pub mod treiber;
 mod treiber_stack;
+mod watch;
 
-#[cfg(test)]
-mod statistic;
+#[cfg(any(test, feature = "serde"))]
+pub mod statistic;
 
+use conservation::{Ledger, Stored};
+#[cfg(feature = "no-elimination")]
+use disabled_elimination_array as elimination_array;
 use elimination_array::EliminationArray;
-use event::{Event, EventRecorder, NoOpRecorder};
+use engine::OperationEngine;
+use event::{Batch, Event, EventRecorder, NoOpRecorder, OperationId};
+use park::Park;
+use std::fmt;
+use std::iter::FromIterator;
 use std::marker::PhantomData;
-use strategy::ExpRetryStrategy;
-use treiber_stack::TreiberStack;
+use std::mem::MaybeUninit;
+use std::ops::ControlFlow;
+use std::panic::Location;
+use std::time::Duration;
+use strategy::{
+    Bounded, Deadline, ExpRetryStrategy, Strategy, Tuning, UrgentStrategy, BRIEF_OFFER_DECISIONS,
+};
+use treiber_stack::{Chain, PopResult, TreiberStack};
 
-#[derive(Default)]
+pub use bounded::{BoundedStack, Full};
+pub use builder::{Builder, Config};
+pub use hooks::ItemHooks;
+pub use inline_stack::InlineStack;
+pub use pop_async::{BoundedPopAsync, PopAsync};
+pub use push_async::PushAsync;
+pub use queue::Queue;
+pub use resize::{available_parallelism, ResizeConfig};
+pub use snapshot::StackSnapshot;
+pub use timestamped::TimestampedStack;
+pub use watch::{Changed, Watch};
+
+/// Lock-free elimination back-off stack.
+///
+/// On drop the remaining items are dropped top to bottom. In case dropping an
+/// item panics, the items below are still dropped before the panic is
+/// propagated. A second panic while doing so aborts the process.
+///
+/// Zero-sized items, e.g. `()`, are not allocated but merely counted, turning
+/// the stack into a lock-free counter with the same interface.
 pub struct Stack<T, PushS = ExpRetryStrategy, PopS = ExpRetryStrategy> {
-    stack: TreiberStack<T>,
-    elimination_array: EliminationArray<T>,
-    phantom: PhantomData<(PushS, PopS)>,
+    stack: TreiberStack<Stored<T>>,
+    elimination_array: EliminationArray<Stored<T>>,
+    /// Validation of item conservation, see [`conservation`].
+    ledger: Ledger,
+    /// Pop operations waiting for an item, see [`Stack::pop_blocking`].
+    park: Park,
+    /// Passed to the strategy of each operation, see [`Strategy::with_tuning`].
+    tuning: Tuning,
+    /// See [`Config::elimination`].
+    elimination: bool,
+    /// See [`Builder::hooks`].
+    hooks: Option<Box<dyn ItemHooks<T>>>,
+    // Strategies are instantiated per operation and never stored, thus `fn()`
+    // to not have them influence auto traits like `Send` and `Sync`.
+    phantom: PhantomData<fn() -> (PushS, PopS)>,
 }
 
 impl<T, PushS, PopS> Stack<T, PushS, PopS>
@@ -26,317 +121,2460 @@ where
     PopS: PopStrategy,
 {
     pub fn new() -> Self {
+        Stack::with_config(Config::default())
+    }
+
+    /// Create a stack growing and shrinking its elimination array as
+    /// configured, instead of with the [`ResizeConfig::default`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config.window` is zero or unless `0 < config.min_exchangers
+    /// <= config.max_exchangers`.
+    pub fn with_resize_config(config: ResizeConfig) -> Self {
+        Stack::with_config(Config {
+            resize: config,
+            ..Config::default()
+        })
+    }
+
+    /// Create a stack configured at run time, e.g. per deployment from a
+    /// configuration file, see [`Config`].
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::{Config, Stack};
+    /// let mut config = Config::default();
+    /// config.resize.min_exchangers = 2;
+    /// config.resize.max_exchangers = 2;
+    /// config.tuning.max_retry_exponent = 3;
+    ///
+    /// let stack = Stack::<u8>::with_config(config);
+    /// stack.push(1);
+    /// assert_eq!(stack.pop(), Some(1));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics on an invalid [`ResizeConfig`], see
+    /// [`Stack::with_resize_config`], or an invalid [`Tuning`].
+    pub fn with_config(config: Config) -> Self {
+        config.tuning.validate();
+
         Self {
             stack: TreiberStack::new(),
-            elimination_array: EliminationArray::new(),
+            elimination: config.eliminates(),
+            // Kept at its minimum size if unused, see `Config::elimination`.
+            elimination_array: EliminationArray::with_resize_config(config.resize),
+            ledger: Ledger::new(),
+            park: Park::new(),
+            tuning: config.tuning,
+            hooks: None,
+            phantom: PhantomData,
+        }
+    }
+
+    fn engine<Ro: engine::Role<Stored<T>>>(&self) -> OperationEngine<'_, Stored<T>, Ro> {
+        OperationEngine::new(&self.stack, &self.elimination_array, self.elimination)
+    }
+
+    /// Configure a stack step by step, e.g. from one of the
+    /// [`strategy::Preset`]s.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// # use elimination_backoff_stack::strategy::SingleProducer;
+    /// let stack = Stack::<u8>::builder().preset(SingleProducer).build();
+    /// ```
+    pub fn builder() -> Builder<T, PushS, PopS> {
+        Builder::new()
+    }
+
+    /// Create a stack holding `items`, the last item on top, as if pushed one
+    /// by one, e.g. for pools that start out full.
+    ///
+    /// Cheaper than pushing each item, given that the stack is not shared yet
+    /// and thus no synchronization is needed.
+    ///
+    /// ```
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<_>::with_items(vec![1, 2, 3]);
+    ///
+    /// assert_eq!(stack.pop(), Some(3));
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn with_items(items: impl IntoIterator<Item = T>) -> Self {
+        let ledger = Ledger::new();
+        let caller = Location::caller();
+        let items = items
+            .into_iter()
+            .map(|item| ledger.wrap_at(item, OperationId::next(), caller));
+        let config = Config::default();
+
+        Self {
+            stack: TreiberStack::from_items(items),
+            elimination: config.eliminates(),
+            elimination_array: EliminationArray::with_resize_config(config.resize),
+            ledger,
+            park: Park::new(),
+            tuning: Tuning::default(),
+            hooks: None,
             phantom: PhantomData,
         }
     }
 
-    pub fn push(&self, item: T) {
-        self.instrumented_push(item, &mut NoOpRecorder {});
+    #[inline]
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn push(&self, item: T) {
+        self.push_recorded(item, None, &mut NoOpRecorder {});
+    }
+
+    /// [`Stack::push`] recording the events of the operation into `recorder`,
+    /// see [`event`].
+    #[inline]
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn instrumented_push<R: EventRecorder>(&self, item: T, recorder: &mut R) {
+        let id = OperationId::next();
+        let mut batch = Batch::new(id, recorder);
+        self.push_recorded(item, Some(id), &mut batch);
+        batch.finish();
+    }
+
+    #[inline]
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    /// Without a recorder, `id` is `None` and only created once needed, i.e.
+    /// by the ledger or once the operation leaves the fast path, thus an
+    /// uncontended operation does not pay for [`OperationId::next`].
+    fn push_recorded<R: EventRecorder>(
+        &self,
+        item: T,
+        mut id: Option<OperationId>,
+        recorder: &mut R,
+    ) {
+        if let Some(id) = id {
+            recorder.record(Event::StartPush(id));
+        }
+        let item = self.ledger.wrap_lazy(self.enter(item), || {
+            *id.get_or_insert_with(OperationId::next)
+        });
+
+        let mut strategy = PushS::with_tuning(&self.tuning);
+
+        // Fast path: an uncontended push succeeds on its first compare-and-swap
+        // on the Treiber stack. Keep everything else out of line.
+        recorder.record(Event::TryStack);
+        if let Err(item) = self.stack.push(item, &mut strategy) {
+            let id = *id.get_or_insert_with(OperationId::next);
+            self.push_slow(item, id, &mut strategy, recorder);
+        }
+        self.park.wake_one();
+
+        recorder.record(Event::FinishPush);
+    }
+
+    /// Alternate between the elimination array and the Treiber stack until the
+    /// item is either exchanged or pushed.
+    #[cold]
+    #[inline(never)]
+    fn push_slow<R: EventRecorder>(
+        &self,
+        item: Stored<T>,
+        id: OperationId,
+        strategy: &mut PushS,
+        recorder: &mut R,
+    ) {
+        self.engine::<engine::Push>()
+            .resume(item, id, strategy, recorder, |_| false)
+            .unwrap_or_else(|_| unreachable!("never exhausted"))
+    }
+
+    /// Push an item given exclusive access, e.g. while filling the stack
+    /// before sharing it, thus without any compare-and-swap or elimination.
+    ///
+    /// Together with [`Stack::pop_mut`] strictly LIFO, unlike concurrent
+    /// operations, which might be eliminated out of order.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// # use std::sync::Arc;
+    /// let mut stack = Stack::<u8>::new();
+    /// stack.push_mut(1);
+    /// stack.push_mut(2);
+    ///
+    /// let mut stack = Arc::new(stack);
+    /// // Shared from here on, e.g. with other threads.
+    /// stack.push(3);
+    ///
+    /// // Exclusive again once all other handles are gone.
+    /// let stack = Arc::get_mut(&mut stack).unwrap();
+    /// assert_eq!(stack.pop_mut(), Some(3));
+    /// assert_eq!(stack.pop_mut(), Some(2));
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn push_mut(&mut self, item: T) {
+        let item = self.ledger.wrap(self.enter(item), OperationId::next());
+        self.stack.push_exclusive(item);
+    }
+
+    /// Push all `items`, the last item on top, as if pushed one by one, though
+    /// with a single compare-and-swap on the Treiber stack.
+    ///
+    /// The items are linked locally first, thus a concurrent pop operation
+    /// observes either none or all of them. Skips the elimination array, which
+    /// exchanges single items only.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<u8>::new();
+    /// stack.push_iter(vec![1, 2, 3]);
+    ///
+    /// assert_eq!(stack.pop(), Some(3));
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn push_iter(&self, items: impl IntoIterator<Item = T>) {
+        let id = OperationId::next();
+        let caller = Location::caller();
+        let chain = Chain::new(
+            items
+                .into_iter()
+                .map(|item| self.ledger.wrap_at(self.enter(item), id, caller)),
+        );
+        self.push_chain(chain);
+    }
+
+    /// Push all `items` like [`Stack::push_iter`], though first offering each
+    /// item briefly on the elimination array, handing it directly to a
+    /// concurrent pop operation if any. Only the items not taken are pushed
+    /// onto the Treiber stack at once in the end.
+    ///
+    /// Meant for bulk producers in workloads with active consumers, thus
+    /// maximizing direct handoffs during the batch. Items taken from the
+    /// elimination array are popped before the batch is visible on the stack,
+    /// thus the order across the batch is not preserved.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<u8>::new();
+    /// stack.push_iter_balanced(vec![1, 2, 3]);
+    ///
+    /// let mut items = std::iter::from_fn(|| stack.pop()).collect::<Vec<_>>();
+    /// items.sort();
+    /// assert_eq!(items, vec![1, 2, 3]);
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn push_iter_balanced(&self, items: impl IntoIterator<Item = T>) {
+        let id = OperationId::next();
+        let caller = Location::caller();
+        let chain = Chain::new(items.into_iter().filter_map(|item| {
+            let item = self.ledger.wrap_at(self.enter(item), id, caller);
+            // Pushing a zero-sized item never fails, thus no pop operation
+            // ever waits on the elimination array.
+            if std::mem::size_of::<T>() == 0 {
+                return Some(item);
+            }
+
+            self.elimination_array
+                .exchange_push(
+                    item,
+                    id,
+                    &mut Bounded::<PushS>::with_decisions(BRIEF_OFFER_DECISIONS, &self.tuning),
+                    &mut NoOpRecorder {},
+                )
+                .err()
+        }));
+        self.push_chain(chain);
+    }
+
+    fn push_chain(&self, mut chain: Chain<Stored<T>>) {
+        let len = chain.len();
+
+        // Unlike a single item, a chain can not be eliminated. Retry the
+        // Treiber stack with a fresh strategy each time one gives up.
+        while let Err(c) = self
+            .stack
+            .push_chain(chain, &mut PushS::with_tuning(&self.tuning))
+        {
+            chain = c;
+        }
+
+        for _ in 0..len {
+            self.park.wake_one();
+        }
+    }
+
+    /// Push `item` with a bounded amount of work, handing it back in case
+    /// neither the Treiber stack nor the elimination array accepted it in
+    /// time.
+    ///
+    /// Unlike [`Stack::push`], which retries until it succeeds, thus might
+    /// spin for long under pathological contention, this gives up after a
+    /// fixed number of decisions of the push strategy, each permitting at most
+    /// one compare-and-swap or exchanger check.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<u8>::new();
+    ///
+    /// if let Err(item) = stack.try_push(1) {
+    ///     // E.g. drop, or hand to a slower path.
+    ///     drop(item);
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn try_push(&self, item: T) -> Result<(), T> {
+        let id = OperationId::next();
+        let item = self.ledger.wrap(self.enter(item), id);
+        let mut strategy = Bounded::<PushS>::with_tuning(&self.tuning);
+
+        match self.engine::<engine::Push>().run(
+            item,
+            id,
+            &mut strategy,
+            &mut NoOpRecorder {},
+            Bounded::exhausted,
+        ) {
+            Ok(()) => {
+                self.park.wake_one();
+                Ok(())
+            }
+            Err(item) => Err(self.exit(self.ledger.discard(item))),
+        }
+    }
+
+    #[inline]
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn pop(&self) -> Option<T> {
+        self.pop_recorded(None, &mut NoOpRecorder {})
+    }
+
+    /// [`Stack::pop`] recording the events of the operation into `recorder`,
+    /// see [`event`].
+    #[inline]
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn instrumented_pop<R: EventRecorder>(&self, recorder: &mut R) -> Option<T> {
+        let id = OperationId::next();
+        let mut batch = Batch::new(id, recorder);
+        let item = self.pop_recorded(Some(id), &mut batch);
+        batch.finish();
+        item
+    }
+
+    #[inline]
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    /// See [`Stack::push_recorded`] for `id`.
+    fn pop_recorded<R: EventRecorder>(
+        &self,
+        mut id: Option<OperationId>,
+        recorder: &mut R,
+    ) -> Option<T> {
+        if let Some(id) = id {
+            recorder.record(Event::StartPop(id));
+        }
+        let caller = Location::caller();
+
+        let mut strategy = PopS::with_tuning(&self.tuning);
+
+        // Fast path: an uncontended pop succeeds on its first compare-and-swap
+        // on the Treiber stack. Keep everything else out of line.
+        recorder.record(Event::TryStack);
+        let item = match self.stack.pop(&mut strategy) {
+            PopResult::Popped(item) => Some(item),
+            PopResult::Empty => None,
+            PopResult::Contended => {
+                let id = *id.get_or_insert_with(OperationId::next);
+                self.pop_slow(id, &mut strategy, recorder)
+            }
+        }
+        .map(|item| {
+            let id = || *id.get_or_insert_with(OperationId::next);
+            self.exit(self.ledger.unwrap_lazy_at(item, id, caller))
+        });
+
+        recorder.record(Event::FinishPop(item.is_some()));
+
+        item
+    }
+
+    /// Alternate between the elimination array and the Treiber stack until an
+    /// item is either exchanged or popped, or the stack is found empty.
+    #[cold]
+    #[inline(never)]
+    fn pop_slow<R: EventRecorder>(
+        &self,
+        id: OperationId,
+        strategy: &mut PopS,
+        recorder: &mut R,
+    ) -> Option<Stored<T>> {
+        self.engine::<engine::Pop>()
+            .resume((), id, strategy, recorder, |_| false)
+            .unwrap_or_else(|()| unreachable!("never exhausted"))
+    }
+
+    /// Pop the top item given exclusive access, e.g. while tearing the stack
+    /// down, thus without any compare-and-swap or elimination. See
+    /// [`Stack::push_mut`].
+    ///
+    /// Frees the node of the item right away instead of deferring it to the
+    /// garbage collector.
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn pop_mut(&mut self) -> Option<T> {
+        let caller = Location::caller();
+        self.stack
+            .pop_exclusive()
+            .map(|item| self.exit(self.ledger.unwrap_at(item, OperationId::next(), caller)))
+    }
+
+    /// Pop an item, giving up after a bounded amount of work instead of
+    /// retrying until the stack is found empty.
+    ///
+    /// Returns `Ok(None)` if the stack was found empty and `Err(Contended)` if
+    /// the attempt was abandoned due to contention, in which case the stack
+    /// might or might not hold items. Meant for e.g. work-stealing schedulers
+    /// moving on to the next victim on contention while treating an empty
+    /// stack as idle.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::{Contended, Stack};
+    /// let stack = Stack::<u8>::new();
+    /// assert_eq!(stack.try_pop(), Ok(None));
+    ///
+    /// stack.push(1);
+    /// match stack.try_pop() {
+    ///     Ok(item) => assert_eq!(item, Some(1)),
+    ///     // E.g. try another stack.
+    ///     Err(Contended) => {}
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn try_pop(&self) -> Result<Option<T>, Contended> {
+        let id = OperationId::next();
+        let caller = Location::caller();
+        let mut strategy = Bounded::<PopS>::with_tuning(&self.tuning);
+
+        let item = self
+            .engine::<engine::Pop>()
+            .run(
+                (),
+                id,
+                &mut strategy,
+                &mut NoOpRecorder {},
+                Bounded::exhausted,
+            )
+            .map_err(|()| Contended)?;
+
+        Ok(item.map(|item| self.exit(self.ledger.unwrap_at(item, id, caller))))
+    }
+
+    /// Pop an item, retrying the Treiber stack and the elimination array for
+    /// at most `timeout` instead of until the stack is found empty.
+    ///
+    /// Returns `None` if the stack was found empty or `timeout` elapsed, in
+    /// which case the stack might or might not hold items. Unlike
+    /// [`Stack::try_pop`], bounds the latency of the call by wall-clock time
+    /// instead of by the number of attempts.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// # use std::time::Duration;
+    /// let stack = Stack::<u8>::new();
+    /// assert_eq!(stack.pop_timeout(Duration::from_millis(1)), None);
+    ///
+    /// stack.push(1);
+    /// assert_eq!(stack.pop_timeout(Duration::from_millis(1)), Some(1));
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        self.pop_within(timeout).unwrap_or(None)
+    }
+
+    /// [`Stack::pop_timeout`], though telling a contended stack apart from an
+    /// empty one like [`Stack::try_pop`].
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub(crate) fn pop_within(&self, timeout: Duration) -> Result<Option<T>, Contended> {
+        let id = OperationId::next();
+        let caller = Location::caller();
+        let mut strategy = Deadline::<PopS>::after(timeout, &self.tuning);
+
+        let item = self
+            .engine::<engine::Pop>()
+            .run(
+                (),
+                id,
+                &mut strategy,
+                &mut NoOpRecorder {},
+                Deadline::exhausted,
+            )
+            .map_err(|()| Contended)?;
+
+        Ok(item.map(|item| self.exit(self.ledger.unwrap_at(item, id, caller))))
+    }
+
+    /// Pop up to `max` items at once, appending them to `buf`, top first, as if
+    /// popped one by one. Returns the number of items appended, zero if the
+    /// stack was found empty.
+    ///
+    /// Detaches the items from the Treiber stack with a single
+    /// compare-and-swap, thus draining in batches pays for pinning the epoch
+    /// and the compare-and-swap once per batch instead of once per item.
+    /// Skips the elimination array, which exchanges single items only.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<_>::with_items(vec![1, 2, 3]);
+    ///
+    /// let mut buf = vec![];
+    /// assert_eq!(stack.pop_n(&mut buf, 2), 2);
+    /// assert_eq!(buf, vec![3, 2]);
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn pop_n(&self, buf: &mut Vec<T>, max: usize) -> usize {
+        if max == 0 {
+            return 0;
+        }
+
+        let id = OperationId::next();
+        let caller = Location::caller();
+        loop {
+            let result = self
+                .stack
+                .pop_n(max, &mut PopS::with_tuning(&self.tuning), |item| {
+                    buf.push(self.exit(self.ledger.unwrap_at(item, id, caller)))
+                });
+
+            match result {
+                PopResult::Popped(n) => return n,
+                PopResult::Empty => return 0,
+                // Unlike a single item, a batch can not be eliminated. Retry
+                // the Treiber stack with a fresh strategy.
+                PopResult::Contended => {}
+            }
+        }
+    }
+
+    /// Pop items in batches of up to `batch` items until the stack is found
+    /// empty or `proceed` returns `false`, calling `f` on each item, top
+    /// first. Returns the number of items popped.
+    ///
+    /// Unlike a loop of [`Stack::pop`], the draining thread yields after each
+    /// batch, handing the cache line of the top of the stack to concurrent
+    /// producers instead of holding on to it for the whole drain. `proceed` is
+    /// checked after yielding, e.g. to stop at shutdown or after a budget.
+    /// Batches are popped like by [`Stack::pop_n`].
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<_>::with_items(0..100);
+    ///
+    /// let mut sum = 0;
+    /// let n = stack.try_pop_each_until_empty(16, |i| sum += i, || true);
+    /// assert_eq!((n, sum), (100, 4950));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch` is zero.
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn try_pop_each_until_empty(
+        &self,
+        batch: usize,
+        mut f: impl FnMut(T),
+        mut proceed: impl FnMut() -> bool,
+    ) -> usize {
+        assert!(batch > 0, "batch must be non-zero");
+
+        let id = OperationId::next();
+        let caller = Location::caller();
+        let mut n = 0;
+        loop {
+            let result = self
+                .stack
+                .pop_n(batch, &mut PopS::with_tuning(&self.tuning), |item| {
+                    f(self.exit(self.ledger.unwrap_at(item, id, caller)))
+                });
+
+            match result {
+                PopResult::Popped(popped) => n += popped,
+                PopResult::Empty => return n,
+                // Producers are active. Yield to them all the more.
+                PopResult::Contended => {}
+            }
+
+            std::thread::yield_now();
+            if !proceed() {
+                return n;
+            }
+        }
+    }
+
+    /// Pop an item into `slot`, e.g. storage owned by a caller across an FFI
+    /// boundary. Returns whether the stack held an item, in which case `slot`
+    /// is initialized, otherwise it is left untouched.
+    ///
+    /// A value previously held by `slot` is overwritten without being dropped.
+    ///
+    /// The item is copied straight from the node of the Treiber stack, or the
+    /// offer of a push operation on the elimination array, into `slot`,
+    /// instead of being moved through the stack frames of [`Stack::pop`].
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// # use std::mem::MaybeUninit;
+    /// let stack = Stack::<_>::with_items(vec![[1u8; 4096]]);
+    ///
+    /// let mut slot = MaybeUninit::uninit();
+    /// assert!(stack.pop_into(&mut slot));
+    /// assert_eq!(unsafe { slot.assume_init() }, [1; 4096]);
+    /// assert!(!stack.pop_into(&mut slot));
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn pop_into(&self, slot: &mut MaybeUninit<T>) -> bool {
+        let id = OperationId::next();
+        let caller = Location::caller();
+        let slot_ptr = slot.as_mut_ptr();
+        // Captures plain data only, thus is `Copy` and handed to both paths.
+        let take = |stored: *const Stored<T>| unsafe {
+            self.ledger.unwrap_into_at(stored, slot_ptr, id, caller)
+        };
+
+        let mut strategy = PopS::with_tuning(&self.tuning);
+        let popped = match self.stack.pop_with(&mut strategy, take) {
+            PopResult::Popped(()) => true,
+            PopResult::Empty => false,
+            PopResult::Contended => self
+                .engine::<engine::PopWith<_>>()
+                .resume(take, id, &mut strategy, &mut NoOpRecorder {}, |_| false)
+                .unwrap_or_else(|_| unreachable!("never exhausted")),
+        };
+
+        if popped {
+            self.exit_in_place(unsafe { &*slot.as_ptr() });
+        }
+        popped
+    }
+
+    /// Pop up to `slots.len()` items at once into `slots`, top first, like
+    /// [`Stack::pop_n`], though into caller provided storage instead of a
+    /// [`Vec`]. Returns the number of items popped, i.e. the number of leading
+    /// slots initialized, zero if the stack was found empty.
+    ///
+    /// Values previously held by the slots are overwritten without being
+    /// dropped.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// # use std::mem::MaybeUninit;
+    /// let stack = Stack::<_>::with_items(vec![1, 2, 3]);
+    ///
+    /// let mut slots = [MaybeUninit::uninit(); 2];
+    /// assert_eq!(stack.pop_into_slice(&mut slots), 2);
+    /// assert_eq!(unsafe { [slots[0].assume_init(), slots[1].assume_init()] }, [3, 2]);
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn pop_into_slice(&self, slots: &mut [MaybeUninit<T>]) -> usize {
+        if slots.is_empty() {
+            return 0;
+        }
+
+        let id = OperationId::next();
+        let caller = Location::caller();
+        loop {
+            let mut slots = slots.iter_mut();
+            let result = self.stack.pop_n_with(
+                slots.len(),
+                &mut PopS::with_tuning(&self.tuning),
+                |stored| {
+                    // `pop_n_with` hands out at most `slots.len()` items.
+                    let slot = slots.next().unwrap();
+                    unsafe {
+                        self.ledger
+                            .unwrap_into_at(stored, slot.as_mut_ptr(), id, caller);
+                        self.exit_in_place(&*slot.as_ptr());
+                    }
+                },
+            );
+
+            match result {
+                PopResult::Popped(n) => return n,
+                PopResult::Empty => return 0,
+                // See `Stack::pop_n`.
+                PopResult::Contended => {}
+            }
+        }
+    }
+
+    /// Iterator popping items, top first, until the stack is found empty, e.g.
+    /// to drain remaining work at shutdown.
+    ///
+    /// Each item is popped via [`Stack::pop`] as the iterator advances, thus
+    /// draining is safe concurrently with other operations. Items pushed while
+    /// draining might or might not be yielded. Advancing the iterator after it
+    /// returned `None` pops again.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<_>::with_items(vec![1, 2, 3]);
+    ///
+    /// assert_eq!(stack.drain().collect::<Vec<_>>(), vec![3, 2, 1]);
+    /// assert!(stack.is_empty());
+    /// ```
+    pub fn drain(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(move || self.pop())
+    }
+
+    /// Drop all items on the stack, as well as the items currently offered by
+    /// push operations on the elimination array. Returns the number of items
+    /// dropped.
+    ///
+    /// Detaches all items of the Treiber stack with a single atomic swap of
+    /// its head instead of popping them one by one, thus a concurrent push
+    /// operation either lands below the swap, being cleared, or above it,
+    /// remaining on the stack. Push operations waiting on the elimination
+    /// array complete as if their item was popped.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<_>::with_items(vec![1, 2, 3]);
+    ///
+    /// assert_eq!(stack.clear(), 3);
+    /// assert!(stack.is_empty());
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn clear(&self) -> usize {
+        self.detach_all(drop)
+    }
+
+    /// Take all items on the stack, top first, followed by the items
+    /// currently offered by push operations on the elimination array, e.g. to
+    /// hand a batch of work to another subsystem.
+    ///
+    /// Detaches the items like [`Stack::clear`], thus with a single atomic
+    /// swap instead of a pop operation per item.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<_>::with_items(vec![1, 2, 3]);
+    ///
+    /// assert_eq!(stack.take_all(), vec![3, 2, 1]);
+    /// assert!(stack.is_empty());
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn take_all(&self) -> Vec<T> {
+        let mut items = Vec::with_capacity(self.len());
+        self.detach_all(|item| items.push(item));
+        items
+    }
+
+    /// Consume the stack, returning its items top first, followed by any
+    /// items left on the elimination array.
+    ///
+    /// Given ownership, walks the Treiber stack without any compare-and-swap,
+    /// unlike [`Stack::take_all`] or a loop of pop operations, e.g. for tests
+    /// and shutdown paths. See also [`IntoIterator`].
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<_>::with_items(vec![1, 2, 3]);
+    ///
+    /// assert_eq!(stack.into_vec(), vec![3, 2, 1]);
+    /// ```
+    pub fn into_vec(mut self) -> Vec<T> {
+        let mut items = Vec::with_capacity(self.stack.len());
+        while let Some(item) = self.stack.pop_exclusive() {
+            items.push(self.ledger.discard(item));
+        }
+        let ledger = &self.ledger;
+        self.elimination_array
+            .take_waiting(OperationId::next(), |item| items.push(ledger.discard(item)));
+        items.into_iter().map(|item| self.exit(item)).collect()
+    }
+
+    /// Detach the items of the Treiber stack and of the push operations
+    /// waiting on the elimination array, calling `f` on each. Returns the
+    /// number of items.
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    fn detach_all(&self, mut f: impl FnMut(T)) -> usize {
+        let id = OperationId::next();
+        let caller = Location::caller();
+
+        let mut n = self
+            .stack
+            .take_all(|item| f(self.exit(self.ledger.unwrap_at(item, id, caller))));
+        self.elimination_array.take_waiting(id, |item| {
+            f(self.exit(self.ledger.unwrap_at(item, id, caller)));
+            n += 1;
+        });
+        n
+    }
+
+    /// Pop an item, blocking the calling thread while the stack is empty.
+    ///
+    /// Instead of spinning, the thread parks on the primitive of the operating
+    /// system, e.g. a futex on Linux, and is woken by the next push operation.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// # use std::sync::Arc;
+    /// # use std::thread;
+    /// let stack = Arc::new(Stack::<u8>::new());
+    ///
+    /// let consumer = {
+    ///     let stack = stack.clone();
+    ///     thread::spawn(move || stack.pop_blocking())
+    /// };
+    ///
+    /// stack.push(1);
+    /// assert_eq!(consumer.join().unwrap(), 1);
+    /// ```
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn pop_blocking(&self) -> T {
+        loop {
+            if let Some(item) = self.pop() {
+                return item;
+            }
+
+            let registration = self.park.register();
+            // A push operation completing from here on either is found by
+            // `confirm_empty` or finds this thread registered.
+            if self.stack.confirm_empty() {
+                registration.wait();
+            }
+        }
+    }
+
+    /// Pop an item, awaiting a push operation while the stack is empty.
+    ///
+    /// Like [`Stack::pop_blocking`], though registers the waker of the
+    /// calling task instead of blocking the thread, see [`PopAsync`].
+    pub fn pop_async(&self) -> PopAsync<'_, T, PushS, PopS> {
+        PopAsync::new(self)
+    }
+
+    /// Handle to wait for the stack to transition from empty to non-empty
+    /// without popping, see [`Watch`].
+    pub fn watch(&self) -> Watch<'_, T, PushS, PopS> {
+        Watch::new(self)
+    }
+
+    /// Push `item` skipping the elimination array, retrying the Treiber stack
+    /// with a more aggressive, though bounded, budget before falling back to
+    /// [`Stack::push`].
+    ///
+    /// Meant for call sites where latency matters more than reducing
+    /// contention.
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn push_urgent(&self, item: T) {
+        let id = OperationId::next();
+        let item = self.ledger.wrap(self.enter(item), id);
+        match self.stack.push(item, &mut UrgentStrategy::new()) {
+            Ok(()) => self.park.wake_one(),
+            Err(item) => {
+                let mut strategy = PushS::with_tuning(&self.tuning);
+                self.push_slow(item, id, &mut strategy, &mut NoOpRecorder {});
+                self.park.wake_one();
+            }
+        }
+    }
+
+    /// Pop an item skipping the elimination array, retrying the Treiber stack
+    /// with a more aggressive, though bounded, budget before falling back to
+    /// [`Stack::pop`].
+    ///
+    /// Meant for call sites where latency matters more than reducing
+    /// contention, e.g. a shutdown path draining the remaining items.
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn pop_urgent(&self) -> Option<T> {
+        match self.stack.pop(&mut UrgentStrategy::new()) {
+            PopResult::Popped(item) => {
+                Some(self.exit(self.ledger.unwrap(item, OperationId::next())))
+            }
+            PopResult::Empty => None,
+            PopResult::Contended => self.pop(),
+        }
+    }
+
+    /// Pop the top item only if `accept` holds for it, e.g. to only take tasks
+    /// up to a given priority, without disturbing the order of the items by
+    /// popping and pushing back.
+    ///
+    /// Returns `None` if the stack was found empty or `accept` rejected the
+    /// top item. `accept` is called at least once per attempt, thus possibly
+    /// on several items under contention, and the item popped is the one last
+    /// accepted.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<_>::with_items(vec![(1, "urgent"), (5, "later")]);
+    ///
+    /// assert_eq!(stack.pop_if(|(priority, _)| *priority <= 2), None);
+    /// assert_eq!(stack.pop_if(|(priority, _)| *priority <= 5), Some((5, "later")));
+    /// ```
+    ///
+    /// Only considers the top of the Treiber stack, never the elimination
+    /// array. Requires `T: Copy`, given that `accept` inspects the item in
+    /// place, see [`Stack::clone_contents`].
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn pop_if(&self, mut accept: impl FnMut(&T) -> bool) -> Option<T>
+    where
+        T: Copy,
+    {
+        let id = OperationId::next();
+        let caller = Location::caller();
+
+        loop {
+            let mut strategy = PopS::with_tuning(&self.tuning);
+            match self
+                .stack
+                .pop_if(&mut strategy, |item| accept(Ledger::peek(item)))
+            {
+                PopResult::Popped(item) => {
+                    return Some(self.exit(self.ledger.unwrap_at(item, id, caller)))
+                }
+                PopResult::Empty => return None,
+                // The elimination array cannot help, given that an exchanged
+                // item is not on top of the stack.
+                PopResult::Contended => continue,
+            }
+        }
+    }
+
+    /// Single, cheapest possible attempt to pop an item, allowed to fail
+    /// spuriously, akin to C++'s `compare_exchange_weak`.
+    ///
+    /// Does a single load of the top of the Treiber stack and a single weak
+    /// compare-and-swap, never retrying and never touching the elimination
+    /// array. Thus `None` does not imply the stack being empty. Meant to be
+    /// embedded in a caller's own spin loop, which decides when to give up and
+    /// fall back to [`Stack::pop`]:
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<u8>::new();
+    /// stack.push(1);
+    ///
+    /// let item = (0..16)
+    ///     .find_map(|_| stack.try_pop_weak())
+    ///     .or_else(|| stack.pop());
+    /// assert_eq!(item, Some(1));
+    /// ```
+    ///
+    /// Pinning the epoch is reentrant, thus cheaper when the calling thread
+    /// already pinned it.
+    #[inline]
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn try_pop_weak(&self) -> Option<T> {
+        match self.stack.pop_weak() {
+            PopResult::Popped(item) => {
+                Some(self.exit(self.ledger.unwrap(item, OperationId::next())))
+            }
+            PopResult::Empty | PopResult::Contended => None,
+        }
+    }
+
+    /// Returns a copy of the items currently on the stack, top first, without
+    /// removing them.
+    ///
+    /// Concurrent operations are not disturbed. The result is not a
+    /// linearizable snapshot: items pushed or popped concurrently may or may
+    /// not be included and items in flight on the elimination array are
+    /// never included.
+    ///
+    /// Meant for e.g. debugging and crash dumps, taken without stopping
+    /// producers or consumers, thus the snapshot of the stack.
+    ///
+    /// There is deliberately no snapshot for `T: Clone`. A concurrent pop
+    /// operation moves an item out of its node while it might still be read
+    /// here, and the popping thread might drop it right after. A `Clone`
+    /// implementation, e.g. the one of `String` or `Arc`, would then read the
+    /// memory owned by the item after it was freed. The epoch guard only keeps
+    /// the node alive, not what the item points to. Copying a `T: Copy` item
+    /// reads the node only, thus is sound regardless.
+    ///
+    /// Calling it without using the result is most likely a mistake, e.g. one
+    /// meant to drain the stack instead:
+    ///
+    /// ```compile_fail
+    /// #![deny(unused_must_use)]
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<u8>::new();
+    /// stack.clone_contents();
+    /// ```
+    #[must_use]
+    #[doc(alias = "snapshot")]
+    pub fn clone_contents(&self) -> Vec<T>
+    where
+        T: Copy,
+    {
+        self.stack
+            .clone_contents()
+            .iter()
+            .map(|item| *Ledger::peek(item))
+            .collect()
+    }
+
+    /// Calls `f` on each item currently on the stack, top first, without
+    /// removing them, stopping early once `f` returns [`ControlFlow::Break`].
+    ///
+    /// Traverses the stack under a single epoch guard and never hands out a
+    /// cursor that could outlive it. Like [`Stack::clone_contents`] the
+    /// traversal is weakly consistent: items pushed or popped concurrently may
+    /// or may not be visited and items in flight on the elimination array are
+    /// never visited.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// # use std::ops::ControlFlow;
+    /// let stack = Stack::<u64>::new();
+    /// stack.push(3);
+    /// stack.push(5);
+    ///
+    /// let mut pending_weight = 0;
+    /// let _ = stack.for_each_ref(|weight| {
+    ///     pending_weight += weight;
+    ///     ControlFlow::<()>::Continue(())
+    /// });
+    /// assert_eq!(pending_weight, 8);
+    /// ```
+    pub fn for_each_ref<B, F>(&self, f: F) -> ControlFlow<B>
+    where
+        T: Copy,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        let mut f = f;
+        self.stack.for_each_ref(|item| f(Ledger::peek(item)))
+    }
+
+    /// Iterator over references to the items currently on the stack, top
+    /// first, without removing them, e.g. for metrics or debugging.
+    ///
+    /// The items stay valid to reference as long as `guard` is held, even if
+    /// popped concurrently in the meantime. Like [`Stack::clone_contents`] the
+    /// traversal is weakly consistent and requires `T: Copy`.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::{Guard, Stack};
+    /// let stack = Stack::<_>::with_items(vec![1, 2, 3]);
+    ///
+    /// let guard = Guard::pin();
+    /// let sum: u32 = stack.iter(&guard).sum();
+    /// assert_eq!(sum, 6);
+    /// assert_eq!(stack.iter(&guard).next(), Some(&3));
+    /// ```
+    ///
+    /// Holding the guard for long delays freeing the memory of popped items
+    /// across all stacks, thus meant for short traversals.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, T>
+    where
+        T: Copy,
+    {
+        Iter {
+            inner: self.stack.iter(&guard.0),
+        }
+    }
+
+    /// Approximate number of items on the stack.
+    ///
+    /// Maintained by counters sharded per thread, thus cheap to update, though
+    /// only exact in the absence of concurrent operations. Items exchanged on
+    /// the elimination array are never on the stack, thus never counted. Meant
+    /// for e.g. metrics and backpressure decisions, not for synchronization.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<_>::with_items(vec![1, 2, 3]);
+    /// assert_eq!(stack.len(), 3);
+    ///
+    /// stack.pop();
+    /// assert_eq!(stack.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Whether the stack appeared empty, probing the top of the Treiber stack
+    /// without attempting a pop, thus without disturbing the order of the
+    /// items.
+    ///
+    /// A single load, though by the time it returns a concurrent operation
+    /// might have pushed or popped an item. Items in flight on the
+    /// elimination array are never on the stack, thus not considered.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<u8>::new();
+    /// assert!(stack.is_empty());
+    ///
+    /// stack.push(1);
+    /// assert!(!stack.is_empty());
+    /// ```
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Returns a copy of the item currently on top of the stack without
+    /// removing it, `None` if the stack appeared empty.
+    ///
+    /// Meant for e.g. monitoring or a scheduler inspecting the next task
+    /// before committing to pop it. By the time the copy is returned, a
+    /// concurrent operation might have popped the item or pushed another one
+    /// on top. Items in flight on the elimination array are never returned.
+    ///
+    /// Requires `T: Copy` instead of `T: Clone` for the same reason as
+    /// [`Stack::clone_contents`], e.g. a task index instead of the task.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<u8>::new();
+    /// assert_eq!(stack.peek(), None);
+    ///
+    /// stack.push(1);
+    /// assert_eq!(stack.peek(), Some(1));
+    /// assert_eq!(stack.pop(), Some(1));
+    /// ```
+    #[must_use]
+    pub fn peek(&self) -> Option<T>
+    where
+        T: Copy,
+    {
+        match self.for_each_ref(|item| ControlFlow::Break(*item)) {
+            ControlFlow::Break(item) => Some(item),
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
+    /// Most recent structural changes to the stack, oldest first, see
+    /// [`history`].
+    #[cfg(feature = "history")]
+    pub fn history(&self) -> Vec<history::Entry> {
+        self.elimination_array.history().snapshot()
+    }
+
+    /// Drop the stack, returning its most recent structural changes, oldest
+    /// first, see [`history`].
+    ///
+    /// E.g. to attach to the report of an incident when tearing down the
+    /// stack.
+    #[cfg(feature = "history")]
+    pub fn into_history(self) -> Vec<history::Entry> {
+        self.elimination_array.history().take()
+    }
+}
+
+impl<T, PushS, PopS> Stack<T, PushS, PopS> {
+    /// Report `item` to the [`ItemHooks`], if any, as it enters the stack.
+    #[inline]
+    fn enter(&self, item: T) -> T {
+        if let Some(hooks) = &self.hooks {
+            hooks.on_item_enter(&item);
+        }
+        item
+    }
+
+    /// Report `item` to the [`ItemHooks`], if any, as it leaves the stack.
+    #[inline]
+    fn exit(&self, item: T) -> T {
+        self.exit_in_place(&item);
+        item
+    }
+
+    /// [`Stack::exit`] for an item already moved into storage of the caller.
+    #[inline]
+    fn exit_in_place(&self, item: &T) {
+        if let Some(hooks) = &self.hooks {
+            hooks.on_item_exit(item);
+        }
+    }
+}
+
+/// Yields the remaining items top first, e.g. to hand leftover work elsewhere
+/// once the stack is no longer shared.
+///
+/// Given exclusive ownership, items are unlinked without compare-and-swaps or
+/// pinning the epoch.
+///
+/// ```rust
+/// # use elimination_backoff_stack::Stack;
+/// let stack = Stack::<_>::with_items(vec![1, 2, 3]);
+///
+/// assert_eq!(stack.into_iter().collect::<Vec<_>>(), vec![3, 2, 1]);
+/// ```
+impl<T, PushS, PopS> IntoIterator for Stack<T, PushS, PopS> {
+    type Item = T;
+    type IntoIter = IntoIter<T, PushS, PopS>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { stack: self }
+    }
+}
+
+/// Iterator over the remaining items of a [`Stack`] consumed via
+/// [`IntoIterator`], top first. Items not yielded are dropped with the
+/// iterator.
+pub struct IntoIter<T, PushS = ExpRetryStrategy, PopS = ExpRetryStrategy> {
+    stack: Stack<T, PushS, PopS>,
+}
+
+impl<T, PushS, PopS> Iterator for IntoIter<T, PushS, PopS> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let item = self.stack.stack.pop_exclusive()?;
+        Some(self.stack.exit(self.stack.ledger.discard(item)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Exact, given that no operation runs concurrently.
+        let len = self.stack.stack.len();
+        (len, Some(len))
+    }
+}
+
+impl<T, PushS, PopS> ExactSizeIterator for IntoIter<T, PushS, PopS> {}
+
+/// Pinned epoch of the garbage collector, keeping items popped concurrently
+/// valid to reference while held, see [`Stack::iter`].
+pub struct Guard(crossbeam::epoch::Guard);
+
+impl Guard {
+    /// Pin the epoch of the calling thread. Reentrant, thus cheap if already
+    /// pinned.
+    pub fn pin() -> Self {
+        Guard(crossbeam::epoch::pin())
+    }
+}
+
+impl fmt::Debug for Guard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Guard").finish_non_exhaustive()
+    }
+}
+
+/// Iterator of [`Stack::iter`].
+pub struct Iter<'g, T> {
+    inner: treiber_stack::Iter<'g, Stored<T>>,
+}
+
+impl<'g, T: Copy> Iterator for Iter<'g, T> {
+    type Item = &'g T;
+
+    fn next(&mut self) -> Option<&'g T> {
+        self.inner.next().map(Ledger::peek)
+    }
+}
+
+/// Error of [`Stack::try_pop`], returned when the pop operation was abandoned
+/// due to contention before either popping an item or finding the stack empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Contended;
+
+impl fmt::Display for Contended {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("pop operation abandoned due to contention")
+    }
+}
+
+impl std::error::Error for Contended {}
+
+/// Discards the remaining items before the ledger checks for lost ones, and
+/// reports each to the [`ItemHooks`], if any. Otherwise leaves the items to
+/// the Treiber stack.
+impl<T, PushS, PopS> Drop for Stack<T, PushS, PopS> {
+    fn drop(&mut self) {
+        if !cfg!(feature = "debug-conservation") && self.hooks.is_none() {
+            return;
+        }
+        while let Some(item) = self.stack.pop_exclusive() {
+            drop(self.exit(self.ledger.discard(item)));
+        }
+    }
+}
+
+/// Prints a diagnostic summary instead of the items, which can not be read
+/// without racing concurrent pop operations: the approximate number of items
+/// (see [`Stack::len`]), the number of exchangers of the elimination array
+/// and the strategies.
+impl<T, PushS, PopS> fmt::Debug for Stack<T, PushS, PopS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stack")
+            .field("len", &self.stack.len())
+            .field("exchangers", &self.elimination_array.len())
+            .field("push_strategy", &std::any::type_name::<PushS>())
+            .field("pop_strategy", &std::any::type_name::<PopS>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, PushS, PopS> Default for Stack<T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    fn default() -> Self {
+        Stack::new()
+    }
+}
+
+/// Collects the items into a new stack, the last item on top, see
+/// [`Stack::with_items`].
+///
+/// ```rust
+/// # use elimination_backoff_stack::Stack;
+/// let stack = (1..=3).collect::<Stack<_>>();
+///
+/// assert_eq!(stack.pop(), Some(3));
+/// ```
+impl<T, PushS, PopS> FromIterator<T> for Stack<T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    fn from_iter<I: IntoIterator<Item = T>>(items: I) -> Self {
+        Stack::with_items(items)
+    }
+}
+
+/// Pushes the items with a single compare-and-swap, the last item on top, see
+/// [`Stack::push_iter`].
+///
+/// ```rust
+/// # use elimination_backoff_stack::Stack;
+/// let mut stack = Stack::<_>::new();
+/// stack.extend(vec![1, 2, 3]);
+///
+/// assert_eq!(stack.pop(), Some(3));
+/// ```
+impl<T, PushS, PopS> Extend<T> for Stack<T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, items: I) {
+        self.push_iter(items)
+    }
+}
+
+/// See [`Stack::into_vec`].
+///
+/// ```rust
+/// # use elimination_backoff_stack::Stack;
+/// let stack = Stack::<_>::with_items(vec![1, 2, 3]);
+///
+/// assert_eq!(Vec::from(stack), vec![3, 2, 1]);
+/// ```
+impl<T, PushS, PopS> From<Stack<T, PushS, PopS>> for Vec<T>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    fn from(stack: Stack<T, PushS, PopS>) -> Self {
+        stack.into_vec()
+    }
+}
+
+/// Strategy for push operations.
+///
+/// Implemented for every [`Strategy`]. Implement [`Strategy`] instead.
+pub trait PushStrategy: Strategy {}
+
+impl<S: Strategy> PushStrategy for S {}
+
+/// Strategy for pop operations.
+///
+/// Implemented for every [`Strategy`]. Implement [`Strategy`] instead.
+pub trait PopStrategy: Strategy {}
+
+impl<S: Strategy> PopStrategy for S {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::{quickcheck, Arbitrary, Gen, TestResult};
+    use rand::Rng;
+    use std::convert::TryInto;
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use strategy::ExpRetryStrategy;
+
+    // TODO: Say each thread adds monotonically increasing numbers onto the
+    // stack. Add a test that ensures that after witnessing an empty stack, one
+    // can not see anything lower than one popped off from other threads or
+    // pushed onto the stack oneself before.
+    //
+    // A bit like quiescent consistency.
+
+    #[derive(Clone, Debug)]
+    enum Operation<T> {
+        Push(T),
+        Pop,
+    }
+
+    impl<T: Arbitrary> Arbitrary for Operation<T> {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            if g.gen::<bool>() {
+                Operation::Push(Arbitrary::arbitrary(g))
+            } else {
+                Operation::Pop
+            }
+        }
+    }
+
+    fn quickcheck_single_threaded_compare_to_vec<PushS: PushStrategy, PopS: PopStrategy>() {
+        fn prop<PushS: PushStrategy, PopS: PopStrategy>(operations: Vec<Operation<usize>>) {
+            let elimination_backoff_stack: Stack<usize, PushS, PopS> = Stack::new();
+            let mut vec_stack: Vec<usize> = vec![];
+
+            for operation in operations {
+                match operation {
+                    Operation::Push(item) => {
+                        elimination_backoff_stack.push(item);
+                        vec_stack.push(item);
+                    }
+                    Operation::Pop => assert_eq!(elimination_backoff_stack.pop(), vec_stack.pop()),
+                }
+            }
+        }
+
+        quickcheck(prop::<PushS, PopS> as fn(_));
+    }
+
+    crate::for_each_strategy!(test quickcheck_single_threaded_compare_to_vec);
+
+    fn quickcheck_multithreaded_no_duplicates<
+        PushS: PushStrategy + 'static,
+        PopS: PopStrategy + 'static,
+    >() {
+        #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+        struct Item {
+            thread_id: u8,
+            nonce: u32,
+        }
+
+        fn prop<PushS: PushStrategy + 'static, PopS: PopStrategy + 'static>(
+            num_threads: usize,
+            mut operations: Vec<Vec<Operation<()>>>,
+        ) -> TestResult {
+            if num_threads > num_cpus::get() * 2 || operations.len() < num_threads {
+                return TestResult::discard();
+            }
+
+            let mut handlers = vec![];
+            let stack = Arc::new(Stack::<Item, PushS, PopS>::new());
+            let popped_items = Arc::new(Mutex::new(vec![]));
+
+            // Spawn threads pushing to and popping from stack.
+            for thread_id in 0..num_threads {
+                let stack = stack.clone();
+                let popped_items = popped_items.clone();
+                let operations = operations.pop().unwrap();
+                if operations.is_empty() {
+                    continue;
+                }
+
+                handlers.push(thread::spawn(move || {
+                    let mut items = vec![];
+
+                    for (nonce, operation) in operations.into_iter().enumerate() {
+                        match operation {
+                            Operation::Push(_) => {
+                                stack.push(Item {
+                                    thread_id: thread_id.try_into().unwrap(),
+                                    nonce: nonce.try_into().unwrap(),
+                                });
+                            }
+                            Operation::Pop => {
+                                if let Some(item) = stack.pop() {
+                                    items.push(item);
+                                }
+                            }
+                        };
+                    }
+
+                    popped_items.lock().unwrap().extend_from_slice(&items);
+                }))
+            }
+
+            for handler in handlers {
+                handler.join().unwrap();
+            }
+
+            let mut popped_items = Arc::try_unwrap(popped_items).unwrap().into_inner().unwrap();
+            if popped_items.is_empty() {
+                return TestResult::passed();
+            }
+
+            // Check for duplicates.
+            popped_items.sort();
+            let mut prev_item = popped_items.pop().unwrap();
+            for item in popped_items {
+                if item == prev_item {
+                    panic!("Got two equal items: {:?} and {:?}", prev_item, item);
+                }
+
+                prev_item = item;
+            }
+
+            TestResult::passed()
+        }
+
+        quickcheck(prop::<PushS, PopS> as fn(_, _) -> _);
+    }
+
+    crate::for_each_strategy!(test quickcheck_multithreaded_no_duplicates);
+
+    /// Scenario: A push or pop operation fails on the lock-free stack due to
+    /// contention on the `head` pointer and thus eludes to the elimination
+    /// array. In case contention is gone instantly all opposite operations will
+    /// hit the lock-free stack directly. Thereby the push or pop operation
+    /// starves on the elimination array.
+    ///
+    /// Ensure that push or pop operation don't starve, e.g. by falling back to
+    /// trying the lock-free stack from the elimination array.
+    ///
+    /// Tested here by spawning only threads that push or pop to the stack.
+    /// Probability for contention is high, thus resulting in some starved
+    /// threads on the elimination array.
+    fn ensure_push_or_pop_does_not_starve_on_array<
+        PushS: PushStrategy + 'static,
+        PopS: PopStrategy + 'static,
+    >() {
+        enum Operation {
+            Push,
+            Pop,
+        }
+
+        for operation in [Operation::Push, Operation::Pop].iter() {
+            let item_count = 100_000;
+
+            let mut handlers = vec![];
+            let stack = Arc::new(Stack::<(), PushS, PopS>::new());
+
+            // When we test `pop` push some values onto stack beforehand to make
+            // `pop` operation more involved, thus cause more contention further
+            // below.
+            if let Operation::Pop = operation {
+                for _ in 0..item_count {
+                    stack.push(());
+                }
+            }
+
+            for _ in 0..num_cpus::get() {
+                let stack = stack.clone();
+
+                handlers.push(thread::spawn(move || {
+                    for _ in 0..item_count {
+                        match operation {
+                            Operation::Push => {
+                                stack.push(());
+                            }
+                            Operation::Pop => {
+                                stack.pop();
+                            }
+                        };
+                    }
+                }))
+            }
+
+            for handler in handlers {
+                handler.join().unwrap();
+            }
+        }
+    }
+
+    crate::for_each_strategy!(test ensure_push_or_pop_does_not_starve_on_array);
+
+    /// Like [`ensure_push_or_pop_does_not_starve_on_array`], though in
+    /// reproducible interleavings, additionally checking that each item is
+    /// popped exactly once.
+    fn deterministic_no_starvation_no_duplicates<PushS: Strategy, PopS: Strategy>() {
+        use crate::test_util::{Pool, Scheduled};
+
+        let (threads, item_count) = (3, 20);
+
+        for seed in 0..20 {
+            let stack = Stack::<usize, Scheduled<PushS>, Scheduled<PopS>>::new();
+            let popped = std::sync::Mutex::new(vec![]);
+
+            Pool::new(threads, seed).run(|thread| {
+                for i in 0..item_count {
+                    if thread % 2 == 0 {
+                        stack.push(thread * item_count + i);
+                    } else if let Some(item) = stack.pop() {
+                        popped.lock().unwrap().push(item);
+                    }
+                }
+            });
+
+            let mut popped = popped.into_inner().unwrap();
+            popped.extend(stack.drain());
+            popped.sort_unstable();
+            let expected: Vec<usize> = (0..threads)
+                .step_by(2)
+                .flat_map(|thread| thread * item_count..(thread + 1) * item_count)
+                .collect();
+            assert_eq!(popped, expected, "seed {}", seed);
+        }
+    }
+
+    crate::for_each_strategy!(test deterministic_no_starvation_no_duplicates);
+
+    #[test]
+    fn exclusive_push_and_pop_are_lifo() {
+        let mut stack = Stack::<usize>::new();
+        for i in 0..10 {
+            stack.push_mut(i);
+        }
+        assert_eq!(stack.len(), 10);
+
+        // Shared in between.
+        let mut stack = Arc::new(stack);
+        let handle = {
+            let stack = stack.clone();
+            thread::spawn(move || stack.push(10))
+        };
+        handle.join().unwrap();
+
+        let stack = Arc::get_mut(&mut stack).unwrap();
+        let popped: Vec<usize> = std::iter::from_fn(|| stack.pop_mut()).collect();
+        assert_eq!(popped, (0..=10).rev().collect::<Vec<_>>());
+        assert!(stack.is_empty());
+
+        let mut zsts = Stack::<()>::new();
+        zsts.push_mut(());
+        assert_eq!(zsts.pop_mut(), Some(()));
+        assert_eq!(zsts.pop_mut(), None);
+    }
+
+    #[test]
+    fn urgent_push_and_pop() {
+        let stack = Arc::new(Stack::<usize>::new());
+
+        let mut handlers = vec![];
+        for thread_id in 0..2 {
+            let stack = stack.clone();
+            handlers.push(thread::spawn(move || {
+                for i in 0..1_000 {
+                    stack.push_urgent(thread_id * 1_000 + i);
+                }
+            }));
+        }
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+
+        let mut items = vec![];
+        while let Some(item) = stack.pop_urgent() {
+            items.push(item);
+        }
+        items.sort_unstable();
+
+        assert_eq!(items, (0..2_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn pop_interest_conserves_items() {
+        type S = strategy::WithPopInterest<ExpRetryStrategy>;
+
+        let item_count = 10_000;
+        let threads = num_cpus::get().max(2) / 2;
+
+        let stack = Arc::new(Stack::<usize, S, S>::new());
+        let popped = Arc::new(Mutex::new(vec![]));
+        let remaining = Arc::new(AtomicUsize::new(item_count * threads));
+
+        let mut handlers = vec![];
+        for thread_id in 0..threads {
+            let push_stack = stack.clone();
+            handlers.push(thread::spawn(move || {
+                for i in 0..item_count {
+                    push_stack.push(thread_id * item_count + i);
+                }
+            }));
+
+            let pop_stack = stack.clone();
+            let popped = popped.clone();
+            let remaining = remaining.clone();
+            handlers.push(thread::spawn(move || {
+                let mut items = vec![];
+                while remaining.load(SeqCst) != 0 {
+                    if let Some(item) = pop_stack.pop() {
+                        remaining.fetch_sub(1, SeqCst);
+                        items.push(item);
+                    }
+                }
+                popped.lock().unwrap().extend(items);
+            }));
+        }
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+
+        let mut popped = Arc::try_unwrap(popped).unwrap().into_inner().unwrap();
+        popped.sort_unstable();
+        assert_eq!(popped, (0..item_count * threads).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn clone_contents_does_not_drain() {
+        let stack = Stack::<usize>::new();
+        for i in 0..10 {
+            stack.push(i);
+        }
+
+        assert_eq!(stack.clone_contents(), (0..10).rev().collect::<Vec<_>>());
+
+        for i in (0..10).rev() {
+            assert_eq!(stack.pop(), Some(i));
+        }
+        assert!(stack.clone_contents().is_empty());
+    }
+
+    #[test]
+    fn drops_remaining_items_top_to_bottom() {
+        struct Item {
+            id: usize,
+            panic: bool,
+            dropped: Arc<Mutex<Vec<usize>>>,
+        }
+
+        impl Drop for Item {
+            fn drop(&mut self) {
+                self.dropped.lock().unwrap().push(self.id);
+                if self.panic {
+                    panic!("dropping item {}", self.id);
+                }
+            }
+        }
+
+        for panicking_id in [None, Some(0), Some(5), Some(9)].iter() {
+            let dropped = Arc::new(Mutex::new(vec![]));
+
+            let stack = Stack::<Item>::new();
+            for id in 0..10 {
+                stack.push(Item {
+                    id,
+                    panic: Some(id) == *panicking_id,
+                    dropped: dropped.clone(),
+                });
+            }
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(stack)));
+            assert_eq!(result.is_err(), panicking_id.is_some());
+
+            assert_eq!(*dropped.lock().unwrap(), (0..10).rev().collect::<Vec<_>>());
+        }
+    }
+
+    /// Scenario: The stack is dropped by whichever thread releases it last,
+    /// while the other threads may still be pushing onto and popping off it.
+    ///
+    /// Ensure every item is dropped exactly once, remaining on the stack or
+    /// not. With the `debug-leaks` feature, dropping the exchangers
+    /// additionally validates that every offer was resolved.
+    fn dropped_by_last_thread_drops_every_item<
+        PushS: PushStrategy + 'static,
+        PopS: PopStrategy + 'static,
+    >() {
+        struct Payload(Arc<AtomicUsize>);
+
+        impl Drop for Payload {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, SeqCst);
+            }
+        }
+
+        let threads = 4;
+        let pushes_per_thread = 1_000;
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let stack = Arc::new(Stack::<Payload, PushS, PopS>::new());
+
+        let handlers = (0..threads)
+            .map(|_| {
+                let stack = stack.clone();
+                let dropped = dropped.clone();
+                thread::spawn(move || {
+                    let mut rng = rand::thread_rng();
+                    for _ in 0..pushes_per_thread {
+                        stack.push(Payload(dropped.clone()));
+                        if rng.gen::<bool>() {
+                            stack.pop();
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(stack);
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+
+        assert_eq!(dropped.load(SeqCst), threads * pushes_per_thread);
+    }
+
+    crate::for_each_strategy!(test dropped_by_last_thread_drops_every_item);
+
+    #[test]
+    fn try_pop_each_until_empty_checks_proceed_between_batches() {
+        let stack = Stack::<_>::with_items(0..10);
+
+        let (mut popped, mut batches) = (vec![], 0);
+        let n = stack.try_pop_each_until_empty(
+            4,
+            |i| popped.push(i),
+            || {
+                batches += 1;
+                batches < 2
+            },
+        );
+        assert_eq!(n, 8);
+        assert_eq!(popped, (2..10).rev().collect::<Vec<_>>());
+
+        assert_eq!(stack.try_pop_each_until_empty(4, drop, || true), 2);
+        assert_eq!(stack.try_pop_each_until_empty(4, drop, || true), 0);
     }
 
-    fn instrumented_push<R: EventRecorder>(&self, item: T, recorder: &mut R) {
-        recorder.record(Event::StartPush);
+    #[test]
+    fn pop_into_slice_initializes_leading_slots() {
+        let stack = Stack::<String>::with_items((0..3).map(|i| i.to_string()));
 
-        let mut strategy = PushS::new();
+        let mut slots = (0..4).map(|_| MaybeUninit::uninit()).collect::<Vec<_>>();
+        assert_eq!(stack.pop_into_slice(&mut slots), 3);
+        let popped = slots
+            .iter_mut()
+            .take(3)
+            .map(|slot| unsafe { slot.assume_init_read() })
+            .collect::<Vec<_>>();
+        assert_eq!(popped, vec!["2", "1", "0"]);
 
-        let mut item = item;
+        assert_eq!(stack.pop_into_slice(&mut slots), 0);
+        assert_eq!(stack.pop_into_slice(&mut []), 0);
+    }
 
-        loop {
-            recorder.record(Event::TryStack);
-            match self.stack.push(item, &mut strategy) {
-                Ok(()) => break,
-                Err(i) => item = i,
-            };
+    /// Skips the Treiber stack, thus completes by exchanging only.
+    #[cfg(not(feature = "no-elimination"))]
+    struct EliminateOnly;
 
-            if strategy.use_elimination_array() {
-                recorder.record(Event::TryEliminationArray);
-                match self
-                    .elimination_array
-                    .exchange_push(item, &mut strategy, recorder)
-                {
-                    Ok(()) => break,
-                    Err(i) => item = i,
-                };
-            }
+    #[cfg(not(feature = "no-elimination"))]
+    impl Strategy for EliminateOnly {
+        fn new() -> Self {
+            EliminateOnly
+        }
+        fn use_elimination_array(&mut self) -> bool {
+            true
+        }
+        fn try_stack(&mut self) -> bool {
+            false
+        }
+        fn try_elimination_array(&mut self) -> bool {
+            true
+        }
+        fn try_start_exchange(&mut self) -> bool {
+            true
+        }
+        fn retry_check_exchanged(&mut self) -> bool {
+            true
         }
+        fn try_exchange(&mut self) -> bool {
+            true
+        }
+    }
 
-        recorder.record(Event::FinishPush);
+    #[cfg(not(feature = "no-elimination"))]
+    #[test]
+    fn pop_into_takes_offered_item() {
+        let stack = Arc::new(
+            Stack::<String, EliminateOnly, EliminateOnly>::builder()
+                .resize_config(ResizeConfig::fixed(1))
+                .min_parallelism(1)
+                .build(),
+        );
+        let pusher = {
+            let stack = stack.clone();
+            thread::spawn(move || stack.push("offered".to_string()))
+        };
+
+        let mut slot = MaybeUninit::uninit();
+        assert!(stack.pop_into(&mut slot));
+        assert_eq!(unsafe { slot.assume_init() }, "offered");
+
+        pusher.join().unwrap();
+        assert!(stack.is_empty());
     }
 
-    pub fn pop(&self) -> Option<T> {
-        self.instrumented_pop(&mut NoOpRecorder {})
+    #[test]
+    fn debug_prints_summary() {
+        let stack = Stack::<usize, ExpRetryStrategy, strategy::NoEliminationStrategy>::builder()
+            .resize_config(ResizeConfig::fixed(2))
+            .build();
+        stack.push_iter(0..3);
+
+        let debug = format!("{:?}", stack);
+        assert!(debug.contains("len: 3"), "{}", debug);
+        #[cfg(not(feature = "no-elimination"))]
+        assert!(debug.contains("exchangers: 2"), "{}", debug);
+        assert!(
+            debug.contains(
+                "push_strategy: \"elimination_backoff_stack::strategy::ExpRetryStrategy\""
+            ),
+            "{}",
+            debug
+        );
+        assert!(debug.contains("NoEliminationStrategy"), "{}", debug);
+    }
+
+    #[test]
+    fn collect_and_extend_keep_push_order() {
+        let mut stack = (0..5).collect::<Stack<usize>>();
+        stack.extend(5..10);
+
+        assert_eq!(
+            stack.into_iter().collect::<Vec<_>>(),
+            (0..10).rev().collect::<Vec<_>>()
+        );
     }
 
-    fn instrumented_pop<R: EventRecorder>(&self, recorder: &mut R) -> Option<T> {
-        recorder.record(Event::StartPop);
+    #[test]
+    fn into_iter_yields_remaining_items_top_first() {
+        let stack = Stack::<usize>::with_items(0..10);
+        assert_eq!(stack.pop(), Some(9));
+
+        let mut items = stack.into_iter();
+        assert_eq!(items.len(), 9);
+        assert_eq!(items.next(), Some(8));
+        assert_eq!(items.collect::<Vec<_>>(), (0..8).rev().collect::<Vec<_>>());
 
-        let mut strategy = PopS::new();
+        let zsts = Stack::<()>::with_items(vec![(); 3]);
+        zsts.pop();
+        assert_eq!(zsts.into_iter().count(), 2);
+    }
+
+    #[test]
+    fn try_pop_weak_eventually_pops_every_item() {
+        let stack = Stack::<usize>::new();
+        for i in 0..100 {
+            stack.push(i);
+        }
 
-        let item = loop {
-            recorder.record(Event::TryStack);
-            match self.stack.pop(&mut strategy) {
-                Ok(item) => break item,
-                Err(()) => {}
+        for i in (0..100).rev() {
+            let item = loop {
+                if let Some(item) = stack.try_pop_weak() {
+                    break item;
+                }
             };
+            assert_eq!(item, i);
+        }
 
-            if strategy.use_elimination_array() {
-                recorder.record(Event::TryEliminationArray);
-                match self
-                    .elimination_array
-                    .exchange_pop(&mut strategy, recorder)
-                {
-                    Ok(item) => break Some(item),
-                    Err(()) => {}
-                };
-            }
+        assert_eq!(stack.try_pop_weak(), None);
+    }
+
+    #[test]
+    fn iter_references_items_popped_concurrently() {
+        let stack = Arc::new(Stack::<usize>::with_items(0..1_000));
+
+        let guard = Guard::pin();
+        let mut iter = stack.iter(&guard);
+        let top = iter.next();
+
+        let popper = {
+            let stack = stack.clone();
+            thread::spawn(move || while stack.pop().is_some() {})
         };
+        popper.join().unwrap();
 
-        recorder.record(Event::FinishPop);
+        // Still valid to reference while the guard is held, though gone from
+        // the stack.
+        assert_eq!(top, Some(&999));
+        assert!(stack.is_empty());
+        let rest: Vec<usize> = iter.copied().collect();
+        assert!(rest.len() <= 999);
+        assert!(rest.windows(2).all(|w| w[0] > w[1]), "{:?}", rest);
+        drop(guard);
 
-        item
+        let zsts = Stack::<()>::with_items(vec![(); 3]);
+        assert_eq!(zsts.iter(&Guard::pin()).count(), 3);
     }
-}
 
-/// Strategy for push operations.
-pub trait PushStrategy: treiber_stack::PushStrategy + elimination_array::PushStrategy {
-    fn new() -> Self;
+    #[test]
+    fn pop_if_only_pops_accepted_top() {
+        let stack = Arc::new(Stack::<usize>::with_items(0..2_000));
 
-    /// Decide whether the stack should try eliminating the push operation on
-    /// the elimination array next. Is called each time such elimination is
-    /// possible.
-    fn use_elimination_array(&mut self) -> bool;
-}
+        // Threads concurrently take even items via `pop_if` and odd items via
+        // `pop` once `pop_if` rejects them.
+        let consumers: Vec<_> = (0..2)
+            .map(|_| {
+                let stack = stack.clone();
+                thread::spawn(move || {
+                    let (mut even, mut other) = (vec![], vec![]);
+                    while !stack.is_empty() {
+                        match stack.pop_if(|item| item % 2 == 0) {
+                            Some(item) => even.push(item),
+                            None => other.extend(stack.pop()),
+                        }
+                    }
+                    (even, other)
+                })
+            })
+            .collect();
 
-/// Strategy for pop operations.
-pub trait PopStrategy: treiber_stack::PopStrategy + elimination_array::PopStrategy {
-    fn new() -> Self;
+        let mut popped = vec![];
+        for consumer in consumers {
+            let (even, other) = consumer.join().unwrap();
+            assert!(even.iter().all(|item| item % 2 == 0), "{:?}", even);
+            popped.extend(even);
+            popped.extend(other);
+        }
+        popped.sort_unstable();
+        assert_eq!(popped, (0..2_000).collect::<Vec<_>>());
 
-    /// Decide whether the stack should try eliminating the pop operation on the
-    /// elimination array next. Is called each time such elimination is
-    /// possible.
-    fn use_elimination_array(&mut self) -> bool;
-}
+        let zsts = Stack::<()>::with_items(vec![(); 2]);
+        assert_eq!(zsts.pop_if(|_| false), None);
+        assert_eq!(zsts.pop_if(|_| true), Some(()));
+        assert_eq!(zsts.len(), 1);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use quickcheck::{quickcheck, Arbitrary, Gen, TestResult};
-    use rand::Rng;
-    use std::convert::TryInto;
-    use std::sync::{Arc, Mutex};
-    use std::thread;
-    use strategy::ExpRetryStrategy;
+    #[test]
+    fn drain_yields_items_pushed_concurrently() {
+        let stack = Arc::new(Stack::<usize>::with_items(0..100));
 
-    // TODO: Say each thread adds monotonically increasing numbers onto the
-    // stack. Add a test that ensures that after witnessing an empty stack, one
-    // can not see anything lower than one popped off from other threads or
-    // pushed onto the stack oneself before.
-    //
-    // A bit like quiescent consistency.
+        let producer = {
+            let stack = stack.clone();
+            thread::spawn(move || {
+                for i in 100..200 {
+                    stack.push(i);
+                }
+            })
+        };
 
-    #[derive(Clone, Debug)]
-    enum Operation<T> {
-        Push(T),
-        Pop,
+        let mut items = stack.drain().collect::<Vec<_>>();
+        producer.join().unwrap();
+        items.extend(stack.drain());
+
+        items.sort_unstable();
+        assert_eq!(items, (0..200).collect::<Vec<_>>());
+        assert!(stack.is_empty());
     }
 
-    impl<T: Arbitrary> Arbitrary for Operation<T> {
-        fn arbitrary<G: Gen>(g: &mut G) -> Self {
-            if g.gen::<bool>() {
-                Operation::Push(Arbitrary::arbitrary(g))
-            } else {
-                Operation::Pop
+    #[test]
+    fn clear_drops_items_pushed_concurrently() {
+        struct Payload(Arc<AtomicUsize>);
+
+        impl Drop for Payload {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, SeqCst);
             }
         }
-    }
 
-    #[test]
-    fn quickcheck_single_threaded_compare_to_vec() {
-        fn prop(operations: Vec<Operation<usize>>) {
-            let elimination_backoff_stack: Stack<usize> = Stack::new();
-            let mut vec_stack: Vec<usize> = vec![];
+        let drops = Arc::new(AtomicUsize::new(0));
+        let stack = Arc::new(Stack::<Payload>::new());
 
-            for operation in operations {
-                match operation {
-                    Operation::Push(item) => {
-                        elimination_backoff_stack.push(item.clone());
-                        vec_stack.push(item);
+        let producers = (0..2)
+            .map(|_| {
+                let (stack, drops) = (stack.clone(), drops.clone());
+                thread::spawn(move || {
+                    for _ in 0..1_000 {
+                        stack.push(Payload(drops.clone()));
                     }
-                    Operation::Pop => assert_eq!(elimination_backoff_stack.pop(), vec_stack.pop()),
-                }
-            }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        while drops.load(SeqCst) < 1_000 {
+            stack.clear();
+        }
+        for producer in producers {
+            producer.join().unwrap();
         }
+        stack.clear();
 
-        quickcheck(prop as fn(_));
+        assert_eq!(drops.load(SeqCst), 2_000);
+        assert!(stack.is_empty());
+        assert_eq!(stack.len(), 0);
     }
 
     #[test]
-    fn quickcheck_multithreaded_no_duplicates() {
-        #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
-        struct Item {
-            thread_id: u8,
-            nonce: u32,
+    fn take_all_takes_items_pushed_concurrently() {
+        let stack = Arc::new(Stack::<usize>::new());
+
+        let producers = (0..2)
+            .map(|thread| {
+                let stack = stack.clone();
+                thread::spawn(move || {
+                    for i in 0..1_000 {
+                        stack.push(thread * 1_000 + i);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut taken = vec![];
+        while taken.len() < 1_000 {
+            taken.extend(stack.take_all());
         }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        taken.extend(stack.take_all());
 
-        fn prop(num_threads: usize, mut operations: Vec<Vec<Operation<()>>>) -> TestResult {
-            if num_threads > num_cpus::get() * 2 || operations.len() < num_threads {
-                return TestResult::discard();
-            }
+        taken.sort_unstable();
+        assert_eq!(taken, (0..2_000).collect::<Vec<_>>());
+        assert!(stack.is_empty());
+    }
 
-            let mut handlers = vec![];
-            let stack = Arc::new(Stack::<Item>::new());
-            let popped_items = Arc::new(Mutex::new(vec![]));
+    #[test]
+    fn into_vec_returns_items_top_first() {
+        let stack = Arc::new(Stack::<usize>::new());
 
-            // Spawn threads pushing to and popping from stack.
-            for thread_id in 0..num_threads {
+        let producers = (0..2)
+            .map(|thread| {
                 let stack = stack.clone();
-                let popped_items = popped_items.clone();
-                let operations = operations.pop().unwrap();
-                if operations.is_empty() {
-                    continue;
-                }
+                thread::spawn(move || {
+                    for i in 0..1_000 {
+                        stack.push(thread * 1_000 + i);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for producer in producers {
+            producer.join().unwrap();
+        }
 
-                handlers.push(thread::spawn(move || {
-                    let mut items = vec![];
+        let mut items = Arc::try_unwrap(stack).unwrap().into_vec();
+        assert_eq!(items.len(), 2_000);
+        items.sort_unstable();
+        assert_eq!(items, (0..2_000).collect::<Vec<_>>());
 
-                    for (nonce, operation) in operations.into_iter().enumerate() {
-                        match operation {
-                            Operation::Push(_) => {
-                                stack.push(Item {
-                                    thread_id: thread_id.try_into().unwrap(),
-                                    nonce: nonce.try_into().unwrap(),
-                                });
-                            }
-                            Operation::Pop => {
-                                if let Some(item) = stack.pop() {
-                                    items.push(item);
-                                }
-                            }
-                        };
-                    }
+        let stack = Stack::<_>::with_items(0..100);
+        stack.push(100);
+        assert_eq!(Vec::from(stack), (0..=100).rev().collect::<Vec<_>>());
+        assert!(Stack::<u8>::new().into_vec().is_empty());
+    }
 
-                    popped_items.lock().unwrap().extend_from_slice(&items);
-                }))
-            }
+    #[test]
+    fn for_each_ref_stops_early() {
+        let stack = Stack::<usize>::new();
+        for i in 0..10 {
+            stack.push(i);
+        }
 
-            for handler in handlers {
-                handler.join().unwrap();
+        let mut visited = vec![];
+        let result = stack.for_each_ref(|item| {
+            visited.push(*item);
+            if *item == 7 {
+                ControlFlow::Break(*item)
+            } else {
+                ControlFlow::Continue(())
             }
+        });
 
-            let mut popped_items = Arc::try_unwrap(popped_items).unwrap().into_inner().unwrap();
-            if popped_items.is_empty() {
-                return TestResult::passed();
-            }
+        assert_eq!(result, ControlFlow::Break(7));
+        assert_eq!(visited, vec![9, 8, 7]);
+        assert_eq!(stack.clone_contents().len(), 10);
+    }
 
-            // Check for duplicates.
-            popped_items.sort();
-            let mut prev_item = popped_items.pop().unwrap();
-            for item in popped_items {
-                if item == prev_item {
-                    panic!("Got two equal items: {:?} and {:?}", prev_item, item);
-                }
+    #[test]
+    fn pop_blocking_is_woken_by_every_push() {
+        let stack = Arc::new(Stack::<usize>::new());
+        let consumers = (0..4)
+            .map(|_| {
+                let stack = stack.clone();
+                thread::spawn(move || (0..250).map(|_| stack.pop_blocking()).sum::<usize>())
+            })
+            .collect::<Vec<_>>();
 
-                prev_item = item;
+        for i in 0..1_000 {
+            if i % 2 == 0 {
+                stack.push(i);
+            } else {
+                stack.push_urgent(i);
             }
+        }
 
-            TestResult::passed()
+        let popped: usize = consumers.into_iter().map(|h| h.join().unwrap()).sum();
+        assert_eq!(popped, (0..1_000).sum::<usize>());
+        assert_eq!(stack.pop(), None);
+    }
+
+    /// Never permits an attempt.
+    pub(crate) struct Refuse;
+
+    impl Strategy for Refuse {
+        fn new() -> Self {
+            Refuse
+        }
+        fn use_elimination_array(&mut self) -> bool {
+            true
+        }
+        fn try_stack(&mut self) -> bool {
+            false
+        }
+        fn try_elimination_array(&mut self) -> bool {
+            false
+        }
+        fn try_start_exchange(&mut self) -> bool {
+            false
+        }
+        fn retry_check_exchanged(&mut self) -> bool {
+            false
+        }
+        fn try_exchange(&mut self) -> bool {
+            false
         }
+    }
+
+    #[test]
+    fn try_push_and_try_pop_give_up() {
+        let stack = Stack::<u8, Refuse, ExpRetryStrategy>::new();
+        assert_eq!(stack.try_push(1), Err(1));
+        assert_eq!(stack.pop(), None);
+
+        let stack = Stack::<u8>::new();
+        assert_eq!(stack.try_push(1), Ok(()));
+        assert_eq!(stack.pop(), Some(1));
 
-        quickcheck(prop as fn(_, _) -> _);
+        let stack = Stack::<u8, ExpRetryStrategy, Refuse>::new();
+        stack.push(1);
+        assert_eq!(stack.try_pop(), Err(Contended));
+        assert_eq!(stack.pop_urgent(), Some(1));
+
+        let stack = Stack::<u8>::new();
+        assert_eq!(stack.try_pop(), Ok(None));
+        stack.push(1);
+        assert_eq!(stack.try_pop(), Ok(Some(1)));
     }
 
-    /// Scenario: A push or pop operation fails on the lock-free stack due to
-    /// contention on the `head` pointer and thus eludes to the elimination
-    /// array. In case contention is gone instantly all opposite operations will
-    /// hit the lock-free stack directly. Thereby the push or pop operation
-    /// starves on the elimination array.
-    ///
-    /// Ensure that push or pop operation don't starve, e.g. by falling back to
-    /// trying the lock-free stack from the elimination array.
-    ///
-    /// Tested here by spawning only threads that push or pop to the stack.
-    /// Probability for contention is high, thus resulting in some starved
-    /// threads on the elimination array.
     #[test]
-    fn ensure_push_or_pop_does_not_starve_on_array() {
-        enum Operation {
-            Push,
-            Pop,
-        }
+    fn pop_timeout_gives_up() {
+        let timeout = Duration::from_millis(10);
 
-        for operation in [Operation::Push, Operation::Pop].iter() {
-            let item_count = 100_000;
+        let stack = Stack::<u8, ExpRetryStrategy, Refuse>::new();
+        stack.push(1);
+        let start = std::time::Instant::now();
+        assert_eq!(stack.pop_timeout(timeout), None);
+        assert!(start.elapsed() >= timeout);
+        assert_eq!(stack.pop_urgent(), Some(1));
 
-            let mut handlers = vec![];
-            let stack = Arc::new(Stack::<()>::new());
+        let stack = Stack::<u8>::new();
+        assert_eq!(stack.pop_timeout(timeout), None);
+        stack.push(1);
+        assert_eq!(stack.pop_timeout(timeout), Some(1));
+    }
 
-            // When we test `pop` push some values onto stack beforehand to make
-            // `pop` operation more involved, thus cause more contention further
-            // below.
-            if let Operation::Pop = operation {
-                for _ in 0..item_count {
-                    stack.push(());
-                }
+    #[test]
+    fn zero_sized_items_are_counted() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Token;
+
+        impl Drop for Token {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, SeqCst);
             }
+        }
 
-            for _ in 0..num_cpus::get() {
+        let stack = Arc::new(Stack::<Token>::new());
+
+        let handlers = (0..4)
+            .map(|_| {
                 let stack = stack.clone();
+                thread::spawn(move || {
+                    let mut popped = 0;
+                    for _ in 0..1_000 {
+                        stack.push(Token);
+                        if stack.pop().is_some() {
+                            popped += 1;
+                        }
+                    }
+                    popped
+                })
+            })
+            .collect::<Vec<_>>();
 
-                handlers.push(thread::spawn(move || {
+        let popped: usize = handlers.into_iter().map(|h| h.join().unwrap()).sum();
+        assert_eq!(DROPPED.load(SeqCst), popped);
+
+        stack.push(Token);
+        drop(Arc::try_unwrap(stack).ok().unwrap());
+        assert_eq!(DROPPED.load(SeqCst), 4_001);
+
+        let units = Stack::<()>::new();
+        units.push(());
+        units.push(());
+        assert_eq!(units.clone_contents(), vec![(), ()]);
+        assert_eq!(units.pop(), Some(()));
+        assert_eq!(units.pop(), Some(()));
+        assert_eq!(units.pop(), None);
+    }
+
+    /// Share of the operations trying the elimination array that exchanged
+    /// their item, with `threads` threads pushing and as many popping. `None`
+    /// if too few operations tried the elimination array to tell, e.g. on a
+    /// machine with a single CPU.
+    #[cfg(not(feature = "no-elimination"))]
+    fn elimination_hit_rate(config: ResizeConfig, threads: usize) -> Option<f64> {
+        let stack = Arc::new(Stack::<usize>::with_resize_config(config));
+        let item_count = 10_000;
+
+        let handlers = (0..threads * 2)
+            .map(|i| {
+                let stack = stack.clone();
+                thread::spawn(move || {
+                    let mut recorder = vec![];
                     for _ in 0..item_count {
-                        match operation {
-                            Operation::Push => {
-                                stack.push(());
-                            }
-                            Operation::Pop => {
-                                stack.pop();
-                            }
-                        };
+                        if i % 2 == 0 {
+                            stack.instrumented_push(1, &mut recorder);
+                        } else {
+                            stack.instrumented_pop(&mut recorder);
+                        }
                     }
-                }))
-            }
+                    recorder
+                })
+            })
+            .collect::<Vec<_>>();
 
-            for handler in handlers {
-                handler.join().unwrap();
+        let (mut tried, mut exchanged) = (0, 0);
+        for handler in handlers {
+            let events = handler.join().unwrap();
+            let operations =
+                events.split(|e| matches!(e, Event::StartPush(_) | Event::StartPop(_)));
+            for operation in operations {
+                if operation.contains(&Event::TryEliminationArray) {
+                    tried += 1;
+                    if operation
+                        .iter()
+                        .any(|e| matches!(e, Event::ExchangedWith(_)))
+                    {
+                        exchanged += 1;
+                    }
+                }
             }
         }
+
+        if tried < 1_000 {
+            return None;
+        }
+        Some(exchanged as f64 / tried as f64)
+    }
+
+    /// Scenario: The stack is sized for more parallelism than actually used,
+    /// e.g. a few threads on a large machine, simulated here by sizing for
+    /// many times the number of threads spawned. Pairs of operations are then
+    /// spread across too many exchangers to meet each other.
+    ///
+    /// Expected: Strategies start out on a subset of the exchangers and the
+    /// elimination array shrinks, thus the hit rate does not collapse compared
+    /// to a stack sized for the actual number of threads.
+    #[cfg(not(feature = "no-elimination"))]
+    #[test]
+    fn elimination_survives_reduced_parallelism() {
+        let threads = 2;
+
+        let sized = elimination_hit_rate(ResizeConfig::with_parallelism(threads), threads);
+        let oversized = elimination_hit_rate(ResizeConfig::with_parallelism(threads * 16), threads);
+
+        if let (Some(sized), Some(oversized)) = (sized, oversized) {
+            assert!(
+                oversized >= sized / 4.0,
+                "hit rate collapsed from {} to {}",
+                sized,
+                oversized,
+            );
+        }
+    }
+
+    /// The `no-elimination` feature swaps in the stand-in elimination array,
+    /// which does not compile the exchanger. The stand-in occupies no memory.
+    #[test]
+    fn no_elimination_compiles_out_exchanger() {
+        assert_eq!(
+            crate::elimination_array::COMPILES_EXCHANGER,
+            !cfg!(feature = "no-elimination"),
+        );
+
+        #[cfg(all(feature = "no-elimination", not(feature = "history")))]
+        assert_eq!(std::mem::size_of::<EliminationArray<u64>>(), 0);
     }
 
     #[test]
     fn event_recording() {
-        let stack = Arc::new(Stack::<Vec<u8>, ExpRetryStrategy, ExpRetryStrategy>::new());
-        // let stack = Arc::new(Stack::<Vec<u8>>::new());
-        let item = b"my_test_item".to_vec();
+        use statistic::{TimedKind, TimedOperation};
+        use std::time::Instant;
+
+        /// Id of the operation last started in `recorder`.
+        fn last_id(recorder: &[Event]) -> OperationId {
+            recorder
+                .iter()
+                .rev()
+                .find_map(|e| match e {
+                    Event::StartPush(id) | Event::StartPop(id) => Some(*id),
+                    _ => None,
+                })
+                .expect("operation to be recorded")
+        }
+
+        // Numbers unique per pushed item, thus the popped order can be
+        // compared against the pushed order.
+        // Traced, thus the report explains the longest operations via the
+        // decisions of their strategy.
+        type S = strategy::Traced<ExpRetryStrategy>;
+        let stack = Arc::new(Stack::<usize, S, S>::new());
         let item_count = 10_000;
 
         let mut handlers = vec![];
         let events = Arc::new(Mutex::new(vec![]));
+        let timed = Arc::new(Mutex::new(vec![]));
 
-        for _ in 0..(num_cpus::get() / 2) {
+        for thread in 0..(num_cpus::get() / 2).max(1) {
             let push_stack = stack.clone();
-            let item = item.clone();
             let push_events = events.clone();
+            let push_timed = timed.clone();
             handlers.push(thread::spawn(move || {
                 let mut recorder = vec![];
-                for _ in 0..item_count {
-                    push_stack.instrumented_push(item.clone(), &mut recorder);
+                let mut operations = vec![];
+                for i in 0..item_count {
+                    let item = thread * item_count + i;
+                    let (start, allocations) = (Instant::now(), alloc_count::allocations());
+                    push_stack.instrumented_push(item, &mut recorder);
+                    operations.push(TimedOperation {
+                        id: last_id(&recorder),
+                        start,
+                        end: Instant::now(),
+                        kind: TimedKind::Push(item),
+                        allocations: alloc_count::allocations()
+                            .zip(allocations)
+                            .map(|(end, start)| end - start),
+                    });
                 }
 
                 push_events.lock().unwrap().push(recorder);
+                push_timed.lock().unwrap().extend(operations);
             }));
 
             let pop_stack = stack.clone();
             let pop_events = events.clone();
+            let pop_timed = timed.clone();
             handlers.push(thread::spawn(move || {
                 let mut recorder = vec![];
+                let mut operations = vec![];
                 for _ in 0..item_count {
-                    pop_stack.instrumented_pop(&mut recorder);
+                    let (start, allocations) = (Instant::now(), alloc_count::allocations());
+                    let item = pop_stack.instrumented_pop(&mut recorder);
+                    operations.push(TimedOperation {
+                        id: last_id(&recorder),
+                        start,
+                        end: Instant::now(),
+                        kind: TimedKind::Pop(item),
+                        allocations: alloc_count::allocations()
+                            .zip(allocations)
+                            .map(|(end, start)| end - start),
+                    });
                 }
 
                 pop_events.lock().unwrap().push(recorder);
+                pop_timed.lock().unwrap().extend(operations);
             }))
         }
 
@@ -345,7 +2583,8 @@ mod tests {
         }
 
         let events = Arc::try_unwrap(events).unwrap().into_inner().unwrap();
+        let timed = Arc::try_unwrap(timed).unwrap().into_inner().unwrap();
 
-        statistic::print_report(events.into_iter().flatten().collect());
+        statistic::print_report(events.into_iter().flatten().collect(), &timed);
     }
 }
@@ -0,0 +1,535 @@
+//! Exhaustive check of the protocol of the stack as a whole, i.e. the Treiber
+//! stack, the elimination array and its exchangers, on an explicit state
+//! machine.
+//!
+//! Unlike `tests/loom.rs`, which runs a copy of the exchanger under every
+//! interleaving of its atomic operations, this enumerates every reachable
+//! state of a small bounded model: two exchangers, three threads, two items.
+//! Each transition is a single atomic step of one thread, each choice of the
+//! strategies, e.g. whether to retry the Treiber stack or back off to an
+//! exchanger, and which one, a separate transition:
+//!
+//! - A push operation loads the head of the Treiber stack, then attempts to
+//!   swing it to its item. Having lost the race, it either retries or offers
+//!   its item on an `Empty` exchanger, turning it `Waiting`.
+//! - While `Waiting`, the push operation either finds the exchanger `Busy`,
+//!   i.e. its item taken, or withdraws its offer via a compare-and-swap back
+//!   to `Empty`, failing in case a pop operation took the item in the
+//!   meantime. Having withdrawn, it retries the Treiber stack.
+//! - A pop operation loads the head of the Treiber stack, returning `None` if
+//!   empty, then attempts to swing it to the next item. Having lost the race,
+//!   it either retries or takes the item of a `Waiting` exchanger, turning it
+//!   `Busy`.
+//! - The push operation whose item was taken resets the exchanger to `Empty`,
+//!   as no other operation writes to a `Busy` exchanger.
+//!
+//! Every reachable state is checked for each item being in exactly one place
+//! and for the exchangers being consistent with the push operations offering
+//! on them. Every final state is checked for the history of operations being
+//! linearizable to a sequential LIFO stack, both with and without elimination.
+//!
+//! A change to the protocol is to be reflected here first, thus checked
+//! before being implemented.
+
+use std::collections::HashSet;
+
+const EXCHANGERS: usize = 2;
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Push(u8),
+    Pop,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Exchanger {
+    Empty,
+    /// Item offered by the push operation with the given index.
+    Waiting {
+        item: u8,
+        op: usize,
+    },
+    Busy,
+}
+
+/// Atomic step a thread takes next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Step {
+    /// Between operations, or done once its program is.
+    Idle,
+    PushLoad {
+        item: u8,
+    },
+    /// `seen` being the version of the head loaded before.
+    PushCas {
+        item: u8,
+        seen: u8,
+    },
+    PushOffer {
+        item: u8,
+        exchanger: usize,
+    },
+    /// Item on offer, though still known to the push operation, like the
+    /// `Waiting` node the actual one withdraws via a compare-and-swap.
+    PushWaiting {
+        item: u8,
+        exchanger: usize,
+    },
+    /// Item taken, the exchanger to be reset.
+    PushTaken {
+        exchanger: usize,
+    },
+    PopLoad,
+    PopCas {
+        seen: u8,
+    },
+    PopTake {
+        exchanger: usize,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Record {
+    NotStarted,
+    /// `after` being the set of operations completed before this one
+    /// started, i.e. the ones it has to be ordered after.
+    Started {
+        after: u32,
+    },
+    Done {
+        after: u32,
+        /// The item popped, if any.
+        popped: Option<u8>,
+        eliminated: bool,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Thread {
+    /// Index of the current, or next, operation into the program.
+    pc: usize,
+    step: Step,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct State {
+    threads: Vec<Thread>,
+    /// Bottom first.
+    stack: Vec<u8>,
+    /// Bumped by each successful compare-and-swap on the head, thus a
+    /// compare-and-swap succeeds iff the head is unchanged since its load.
+    version: u8,
+    exchangers: [Exchanger; EXCHANGERS],
+    /// By index of the operation, see [`Model::op`].
+    records: Vec<Record>,
+}
+
+/// How a push operation withdraws its offer.
+#[derive(Clone, Copy, Debug)]
+enum Withdraw {
+    CompareAndSwap,
+    /// Overwriting the exchanger regardless of a pop operation having taken
+    /// the item, i.e. the bug the compare-and-swap rules out. Only there to
+    /// confirm the checker catches it.
+    Store,
+}
+
+struct Model {
+    /// Operations of each thread, in program order.
+    programs: Vec<Vec<Op>>,
+    elimination: bool,
+    withdraw: Withdraw,
+}
+
+#[derive(Debug, Default)]
+struct Report {
+    states: usize,
+    final_states: usize,
+    /// Final states in which at least one pair of operations eliminated.
+    eliminating_final_states: usize,
+}
+
+impl Model {
+    fn new(programs: &[&[Op]], elimination: bool) -> Self {
+        let ops = programs.iter().map(|p| p.len()).sum::<usize>();
+        assert!(ops <= 32, "operations are tracked in a `u32` bit set");
+
+        Model {
+            programs: programs.iter().map(|p| p.to_vec()).collect(),
+            elimination,
+            withdraw: Withdraw::CompareAndSwap,
+        }
+    }
+
+    /// Index of the operation at `pc` of `thread` across all threads.
+    fn op(&self, thread: usize, pc: usize) -> usize {
+        self.programs[..thread]
+            .iter()
+            .map(|p| p.len())
+            .sum::<usize>()
+            + pc
+    }
+
+    fn ops(&self) -> impl Iterator<Item = Op> + '_ {
+        self.programs.iter().flatten().copied()
+    }
+
+    fn initial(&self) -> State {
+        State {
+            threads: vec![
+                Thread {
+                    pc: 0,
+                    step: Step::Idle,
+                };
+                self.programs.len()
+            ],
+            stack: vec![],
+            version: 0,
+            exchangers: [Exchanger::Empty; EXCHANGERS],
+            records: vec![Record::NotStarted; self.ops().count()],
+        }
+    }
+
+    /// Visit every reachable state, returning the first violation found.
+    fn check(&self) -> Result<Report, String> {
+        let mut report = Report::default();
+        let mut visited = HashSet::new();
+        let mut pending = vec![self.initial()];
+
+        while let Some(state) = pending.pop() {
+            if !visited.insert(state.clone()) {
+                continue;
+            }
+            report.states += 1;
+            self.check_state(&state)
+                .map_err(|e| format!("{} in {:?}", e, state))?;
+
+            let successors = self.successors(&state);
+            if successors.is_empty() {
+                self.check_final(&state)
+                    .map_err(|e| format!("{} in {:?}", e, state))?;
+                report.final_states += 1;
+                if state.records.iter().any(|r| {
+                    matches!(
+                        r,
+                        Record::Done {
+                            eliminated: true,
+                            ..
+                        }
+                    )
+                }) {
+                    report.eliminating_final_states += 1;
+                }
+            }
+            pending.extend(successors);
+        }
+
+        Ok(report)
+    }
+
+    fn successors(&self, state: &State) -> Vec<State> {
+        let mut successors = vec![];
+
+        for (t, thread) in state.threads.iter().enumerate() {
+            let mut next = |f: &mut dyn FnMut(&mut State)| {
+                let mut s = state.clone();
+                f(&mut s);
+                successors.push(s);
+            };
+            let op = self.op(t, thread.pc);
+
+            match thread.step {
+                Step::Idle => {
+                    let step = match self.programs[t].get(thread.pc) {
+                        Some(Op::Push(item)) => Step::PushLoad { item: *item },
+                        Some(Op::Pop) => Step::PopLoad,
+                        None => continue,
+                    };
+                    next(&mut |s| {
+                        let after = done(&s.records);
+                        s.records[op] = Record::Started { after };
+                        s.threads[t].step = step;
+                    });
+                }
+                Step::PushLoad { item } => next(&mut |s| {
+                    s.threads[t].step = Step::PushCas {
+                        item,
+                        seen: s.version,
+                    }
+                }),
+                Step::PushCas { item, seen } if seen == state.version => next(&mut |s| {
+                    s.stack.push(item);
+                    s.version += 1;
+                    complete(s, t, op, None, false);
+                }),
+                Step::PushCas { item, .. } => {
+                    next(&mut |s| s.threads[t].step = Step::PushLoad { item });
+                    if self.elimination {
+                        for exchanger in 0..EXCHANGERS {
+                            next(&mut |s| s.threads[t].step = Step::PushOffer { item, exchanger });
+                        }
+                    }
+                }
+                Step::PushOffer { item, exchanger } => next(&mut |s| {
+                    s.threads[t].step = if s.exchangers[exchanger] == Exchanger::Empty {
+                        s.exchangers[exchanger] = Exchanger::Waiting { item, op };
+                        Step::PushWaiting { item, exchanger }
+                    } else {
+                        // In use by other operations, back to the Treiber
+                        // stack.
+                        Step::PushLoad { item }
+                    };
+                }),
+                Step::PushWaiting { item, exchanger } => {
+                    if state.exchangers[exchanger] == Exchanger::Busy {
+                        next(&mut |s| s.threads[t].step = Step::PushTaken { exchanger });
+                    }
+                    next(&mut |s| {
+                        let withdrawn = match self.withdraw {
+                            Withdraw::CompareAndSwap => {
+                                s.exchangers[exchanger] == Exchanger::Waiting { item, op }
+                            }
+                            Withdraw::Store => true,
+                        };
+                        s.threads[t].step = if withdrawn {
+                            s.exchangers[exchanger] = Exchanger::Empty;
+                            Step::PushLoad { item }
+                        } else {
+                            Step::PushTaken { exchanger }
+                        };
+                    });
+                }
+                Step::PushTaken { exchanger } => next(&mut |s| {
+                    s.exchangers[exchanger] = Exchanger::Empty;
+                    complete(s, t, op, None, true);
+                }),
+                Step::PopLoad => next(&mut |s| {
+                    if s.stack.is_empty() {
+                        complete(s, t, op, None, false);
+                    } else {
+                        s.threads[t].step = Step::PopCas { seen: s.version };
+                    }
+                }),
+                Step::PopCas { seen } if seen == state.version => next(&mut |s| {
+                    let item = s.stack.pop();
+                    s.version += 1;
+                    complete(s, t, op, item, false);
+                }),
+                Step::PopCas { .. } => {
+                    next(&mut |s| s.threads[t].step = Step::PopLoad);
+                    if self.elimination {
+                        for exchanger in 0..EXCHANGERS {
+                            next(&mut |s| s.threads[t].step = Step::PopTake { exchanger });
+                        }
+                    }
+                }
+                Step::PopTake { exchanger } => next(&mut |s| {
+                    match s.exchangers[exchanger] {
+                        Exchanger::Waiting { item, .. } => {
+                            s.exchangers[exchanger] = Exchanger::Busy;
+                            complete(s, t, op, Some(item), true);
+                        }
+                        Exchanger::Empty | Exchanger::Busy => s.threads[t].step = Step::PopLoad,
+                    };
+                }),
+            }
+        }
+
+        successors
+    }
+
+    /// Each item is in exactly one place, and each exchanger is consistent
+    /// with the push operations offering on it.
+    fn check_state(&self, state: &State) -> Result<(), String> {
+        let mut places = vec![];
+        for (op, record) in self.ops().zip(&state.records) {
+            match (op, record) {
+                (Op::Push(item), Record::NotStarted) => places.push(item),
+                (Op::Pop, Record::Done { popped, .. }) => places.extend(popped),
+                _ => {}
+            }
+        }
+        for thread in &state.threads {
+            match thread.step {
+                Step::PushLoad { item }
+                | Step::PushCas { item, .. }
+                | Step::PushOffer { item, .. } => places.push(item),
+                _ => {}
+            }
+        }
+        for exchanger in &state.exchangers {
+            if let Exchanger::Waiting { item, .. } = exchanger {
+                places.push(*item);
+            }
+        }
+        places.extend(&state.stack);
+
+        for item in self.ops().filter_map(|op| match op {
+            Op::Push(item) => Some(item),
+            Op::Pop => None,
+        }) {
+            match places.iter().filter(|i| **i == item).count() {
+                0 => return Err(format!("item {} lost", item)),
+                1 => {}
+                _ => return Err(format!("item {} duplicated", item)),
+            }
+        }
+
+        for (e, exchanger) in state.exchangers.iter().enumerate() {
+            let holders = state
+                .threads
+                .iter()
+                .enumerate()
+                .filter(|(t, thread)| match (thread.step, exchanger) {
+                    (Step::PushWaiting { exchanger, .. }, Exchanger::Waiting { op, .. }) => {
+                        exchanger == e && self.op(*t, thread.pc) == *op
+                    }
+                    (
+                        Step::PushWaiting { exchanger, .. } | Step::PushTaken { exchanger },
+                        Exchanger::Busy,
+                    ) => exchanger == e,
+                    _ => false,
+                })
+                .count();
+            let expected = match exchanger {
+                Exchanger::Empty => 0,
+                Exchanger::Waiting { .. } | Exchanger::Busy => 1,
+            };
+            if holders != expected {
+                return Err(format!(
+                    "exchanger {} {:?} held by {} push operations",
+                    e, exchanger, holders
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Every operation completed, and the history is linearizable.
+    fn check_final(&self, state: &State) -> Result<(), String> {
+        for (t, thread) in state.threads.iter().enumerate() {
+            if thread.pc != self.programs[t].len() {
+                return Err(format!("thread {} stuck at {:?}", t, thread.step));
+            }
+        }
+
+        let ops = self.ops().collect::<Vec<_>>();
+        if !linearizable(&ops, &state.records, 0, &mut vec![], &state.stack) {
+            return Err("history not linearizable to a LIFO stack".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Set of the operations completed.
+fn done(records: &[Record]) -> u32 {
+    records
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| matches!(r, Record::Done { .. }))
+        .fold(0, |set, (op, _)| set | 1 << op)
+}
+
+fn complete(state: &mut State, thread: usize, op: usize, popped: Option<u8>, eliminated: bool) {
+    let after = match state.records[op] {
+        Record::Started { after } => after,
+        r => unreachable!("completing {:?}", r),
+    };
+    state.records[op] = Record::Done {
+        after,
+        popped,
+        eliminated,
+    };
+    state.threads[thread].step = Step::Idle;
+    state.threads[thread].pc += 1;
+}
+
+/// Whether the completed operations, of which those in `placed` are ordered
+/// already, leaving `stack` behind, can be ordered such that each starts
+/// after those completed before it started and each pop operation returns
+/// the top of a sequential stack, leaving `remaining` behind.
+fn linearizable(
+    ops: &[Op],
+    records: &[Record],
+    placed: u32,
+    stack: &mut Vec<u8>,
+    remaining: &[u8],
+) -> bool {
+    if placed.count_ones() as usize == ops.len() {
+        return stack == remaining;
+    }
+
+    for (i, (op, record)) in ops.iter().zip(records).enumerate() {
+        let (after, popped) = match record {
+            Record::Done { after, popped, .. } => (*after, *popped),
+            r => unreachable!("linearizing {:?}", r),
+        };
+        if placed & 1 << i != 0 || after & !placed != 0 {
+            continue;
+        }
+
+        let ordered = match op {
+            Op::Push(item) => {
+                stack.push(*item);
+                let ordered = linearizable(ops, records, placed | 1 << i, stack, remaining);
+                stack.pop();
+                ordered
+            }
+            Op::Pop => {
+                let top = stack.pop();
+                let ordered =
+                    top == popped && linearizable(ops, records, placed | 1 << i, stack, remaining);
+                stack.extend(top);
+                ordered
+            }
+        };
+        if ordered {
+            return true;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Op::{Pop, Push};
+
+    /// Three threads, two items. A push and a pop operation only back off
+    /// after losing a race each, thus elimination takes a third operation
+    /// succeeding on the Treiber stack in between.
+    const PROGRAMS: &[&[&[Op]]] = &[
+        &[&[Push(0)], &[Push(1), Pop], &[Pop]],
+        &[&[Push(0), Pop], &[Push(1)], &[Pop]],
+        &[&[Push(0), Pop], &[Push(1), Pop], &[Pop]],
+    ];
+
+    #[test]
+    fn lifo_without_elimination() {
+        for programs in PROGRAMS {
+            let report = Model::new(programs, false).check().unwrap();
+            assert_eq!(report.eliminating_final_states, 0);
+        }
+    }
+
+    #[test]
+    fn exactly_once_with_elimination() {
+        for programs in PROGRAMS {
+            let report = Model::new(programs, true).check().unwrap();
+            // Otherwise the model would not exercise the elimination array.
+            assert!(report.eliminating_final_states > 0, "{:?}", report);
+            assert!(report.final_states > report.eliminating_final_states);
+        }
+    }
+
+    #[test]
+    fn catches_withdrawal_racing_a_pop() {
+        let mut model = Model::new(PROGRAMS[0], true);
+        model.withdraw = Withdraw::Store;
+        let violation = model.check().unwrap_err();
+        assert!(violation.starts_with("item 0 duplicated"), "{}", violation);
+    }
+}
@@ -0,0 +1,201 @@
+//! Michael and Scott's lock-free FIFO queue [1], the central queue of
+//! [`crate::Queue`].
+//!
+//! Each node carries a sequence number, one more than the one of its
+//! predecessor, thus the sequence number of the tail is the number of items
+//! ever enqueued, the one of the sentinel at the head the number of items
+//! ever dequeued. The elimination array consults both, see `src/queue.rs`.
+//!
+//! [1]: Michael, Maged M., and Michael L. Scott. "Simple, fast, and practical
+//! non-blocking and blocking concurrent queue algorithms." Proceedings of the
+//! fifteenth annual ACM symposium on Principles of distributed computing.
+//! 1996.
+
+use crate::ordering::{Acquire, Relaxed, Release};
+use crate::strategy::CasFailure;
+use crate::treiber_stack::{PopResult, PopStrategy, PushStrategy};
+use crossbeam::epoch::{self, Atomic, Guard, Owned, Shared};
+use crossbeam::utils::CachePadded;
+use std::mem::MaybeUninit;
+use std::ptr;
+
+struct Node<T> {
+    /// Uninitialized for the sentinel, initialized for every other node.
+    item: MaybeUninit<T>,
+    seq: u64,
+    next: Atomic<Node<T>>,
+}
+
+pub(crate) struct MsQueue<T> {
+    /// Sentinel, its successor holding the oldest item.
+    head: CachePadded<Atomic<Node<T>>>,
+    /// The newest node, or its predecessor while an enqueue is in flight.
+    tail: CachePadded<Atomic<Node<T>>>,
+}
+
+impl<T> MsQueue<T> {
+    pub(crate) fn new() -> Self {
+        let queue = MsQueue {
+            head: CachePadded::new(Atomic::null()),
+            tail: CachePadded::new(Atomic::null()),
+        };
+        let sentinel = Owned::new(Node {
+            item: MaybeUninit::uninit(),
+            seq: 0,
+            next: Atomic::null(),
+        });
+        // Not shared yet.
+        let sentinel = sentinel.into_shared(unsafe { epoch::unprotected() });
+        queue.head.store(sentinel, Relaxed);
+        queue.tail.store(sentinel, Relaxed);
+        queue
+    }
+
+    /// Append `item`, handing it back along with the sequence number of the
+    /// tail last observed in case the strategy gave up.
+    pub(crate) fn enqueue<S: PushStrategy>(
+        &self,
+        item: T,
+        strategy: &mut S,
+    ) -> Result<(), (T, u64)> {
+        let mut node = Owned::new(Node {
+            item: MaybeUninit::new(item),
+            seq: 0,
+            next: Atomic::null(),
+        });
+
+        let guard = epoch::pin();
+        let mut tail_seq = self.tail(&guard).seq;
+
+        while strategy.try_push() {
+            let tail = self.tail.load(Acquire, &guard);
+            let t = unsafe { tail.deref() };
+            tail_seq = t.seq;
+            let next = t.next.load(Acquire, &guard);
+
+            if !next.is_null() {
+                // Help the enqueue in flight swing the tail, then retry.
+                let _ = self
+                    .tail
+                    .compare_exchange(tail, next, Release, Relaxed, &guard);
+                strategy.on_cas_failure(CasFailure::LostRace);
+                continue;
+            }
+
+            node.seq = t.seq + 1;
+            match t
+                .next
+                .compare_exchange(Shared::null(), node, Release, Relaxed, &guard)
+            {
+                Ok(node) => {
+                    strategy.on_cas_success();
+                    // Fails only if another operation helped already.
+                    let _ = self
+                        .tail
+                        .compare_exchange(tail, node, Release, Relaxed, &guard);
+                    return Ok(());
+                }
+                Err(e) => {
+                    node = e.new;
+                    strategy.on_cas_failure(CasFailure::LostRace);
+                    strategy.after_cas_failure();
+                }
+            }
+        }
+
+        let node = node.into_box();
+        Err((unsafe { node.item.assume_init() }, tail_seq))
+    }
+
+    /// Remove the oldest item.
+    pub(crate) fn dequeue<S: PopStrategy>(&self, strategy: &mut S) -> PopResult<T> {
+        let guard = epoch::pin();
+
+        while strategy.try_pop() {
+            let head = self.head.load(Acquire, &guard);
+            let h = unsafe { head.deref() };
+            let next = h.next.load(Acquire, &guard);
+            let n = match unsafe { next.as_ref() } {
+                Some(n) => n,
+                None => return PopResult::Empty,
+            };
+
+            // Keep the tail from falling behind the head.
+            let tail = self.tail.load(Relaxed, &guard);
+            if tail == head {
+                let _ = self
+                    .tail
+                    .compare_exchange(tail, next, Release, Relaxed, &guard);
+            }
+
+            if self
+                .head
+                .compare_exchange(head, next, Release, Relaxed, &guard)
+                .is_ok()
+            {
+                strategy.on_cas_success();
+                unsafe {
+                    // The former successor is the sentinel from now on, thus
+                    // its item is not read again.
+                    guard.defer_destroy(head);
+                    return PopResult::Popped(ptr::read(n.item.as_ptr()));
+                }
+            }
+
+            strategy.on_cas_failure(CasFailure::LostRace);
+            strategy.after_cas_failure();
+        }
+
+        PopResult::Contended
+    }
+
+    /// Number of items ever dequeued. Never decreases.
+    pub(crate) fn dequeued(&self) -> u64 {
+        self.head(&epoch::pin()).seq
+    }
+
+    /// Approximate number of items, exact without concurrent operations.
+    pub(crate) fn len(&self) -> usize {
+        let guard = epoch::pin();
+        let head = self.head(&guard).seq;
+        // The tail might lag one node behind.
+        let mut tail = self.tail(&guard);
+        while let Some(next) = unsafe { tail.next.load(Acquire, &guard).as_ref() } {
+            tail = next;
+        }
+        tail.seq.saturating_sub(head) as usize
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        let guard = epoch::pin();
+        self.head(&guard).next.load(Acquire, &guard).is_null()
+    }
+
+    fn head<'g>(&self, guard: &'g Guard) -> &'g Node<T> {
+        // Never null, given the sentinel.
+        unsafe { self.head.load(Acquire, guard).deref() }
+    }
+
+    fn tail<'g>(&self, guard: &'g Guard) -> &'g Node<T> {
+        unsafe { self.tail.load(Acquire, guard).deref() }
+    }
+}
+
+/// Drops the remaining items oldest first.
+impl<T> Drop for MsQueue<T> {
+    fn drop(&mut self) {
+        // No other thread holds a reference.
+        unsafe {
+            let guard = epoch::unprotected();
+            let mut node = self.head.load(Relaxed, guard).into_owned().into_box();
+            while let Some(next) = node.next.load(Relaxed, guard).try_into_owned() {
+                node = next.into_box();
+                ptr::drop_in_place(node.item.as_mut_ptr());
+            }
+        }
+    }
+}
+
+// Items are moved between threads, never shared.
+unsafe impl<T: Send> Send for MsQueue<T> {}
+unsafe impl<T: Send> Sync for MsQueue<T> {}
@@ -0,0 +1,14 @@
+//! Memory orderings of the Treiber stack and of the exchanger.
+//!
+//! Each one is [`SeqCst`](std::sync::atomic::Ordering::SeqCst) with the
+//! `seq-cst` feature, thus a suspected bug in the choice of orderings, e.g.
+//! only showing on weakly ordered architectures like ARM, can be ruled in or
+//! out by enabling the feature instead of patching the crate. Note that crossbeam-epoch keeps its own
+//! orderings either way.
+
+#[cfg(not(feature = "seq-cst"))]
+pub(crate) use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release};
+#[cfg(feature = "seq-cst")]
+pub(crate) use std::sync::atomic::Ordering::{
+    SeqCst as Relaxed, SeqCst as Acquire, SeqCst as Release, SeqCst as AcqRel,
+};
@@ -0,0 +1,374 @@
+//! Parking of pop operations on an empty stack until a push operation makes an
+//! item available.
+//!
+//! Waits directly on a 32 bit word via the primitive of the operating system,
+//! i.e. `futex` on Linux, `WaitOnAddress` on Windows and `__ulock_wait` on
+//! macOS, instead of depending on a parking lot implementation. Each wake up
+//! bumps the word, thus a waiter that read the word before registering itself
+//! can not miss a wake up issued in between.
+//!
+//! A waiter registers itself, then confirms the stack to be empty via a
+//! read-modify-write on the head of the Treiber stack. A push operation
+//! acquires the head via its own compare-and-swap, thus either the waiter finds
+//! the pushed item or the push operation finds the waiter registered.
+//!
+//! Tasks awaiting [`crate::Stack::pop_async`] or [`crate::Watch::changed`]
+//! register a [`Waker`] instead, counted among the sleepers as well, thus the
+//! same reasoning applies.
+
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize};
+use std::sync::Mutex;
+use std::task::Waker;
+
+pub(crate) struct Park {
+    /// Bumped on each wake up, waited on by parked threads.
+    word: AtomicU32,
+    /// Number of threads registered to wait.
+    sleepers: AtomicUsize,
+    /// Number of the `sleepers` watching, see [`Park::register_watcher`].
+    watchers: AtomicUsize,
+    /// Wakers of the tasks among the `sleepers` not woken since their last
+    /// registration, by key, see [`Park::register_task`].
+    wakers: Mutex<Vec<(u64, Waker)>>,
+    next_key: AtomicU64,
+}
+
+/// Registration of a thread about to wait, see [`Park::register`].
+pub(crate) struct Registration<'a> {
+    park: &'a Park,
+    word: u32,
+    watcher: bool,
+}
+
+/// Registration of a task awaiting an item, see [`Park::register_task`].
+/// Stays among the sleepers until dropped, across wake ups.
+pub(crate) struct TaskRegistration<'a> {
+    park: &'a Park,
+    key: u64,
+    /// Value of [`Park::word`] when registering.
+    word: u32,
+}
+
+impl Park {
+    pub(crate) fn new() -> Self {
+        Park {
+            word: AtomicU32::new(0),
+            sleepers: AtomicUsize::new(0),
+            watchers: AtomicUsize::new(0),
+            wakers: Mutex::new(Vec::new()),
+            next_key: AtomicU64::new(0),
+        }
+    }
+
+    /// Register the calling thread as about to wait. The caller has to
+    /// re-check its condition afterwards, before calling
+    /// [`Registration::wait`].
+    pub(crate) fn register(&self) -> Registration<'_> {
+        self.register_as(false)
+    }
+
+    /// Like [`Park::register`], though for a thread waiting for a wake up
+    /// without taking the item, e.g. [`crate::Watch::wait`]. A wake up
+    /// reaching a watcher instead of a popping thread would leave the item
+    /// unclaimed, thus wake ups reach every waiting thread while there is a
+    /// watcher.
+    pub(crate) fn register_watcher(&self) -> Registration<'_> {
+        self.register_as(true)
+    }
+
+    fn register_as(&self, watcher: bool) -> Registration<'_> {
+        let word = self.word.load(Acquire);
+        // Published to push operations by the read-modify-write on the Treiber
+        // stack confirming it to be empty, see module documentation.
+        self.sleepers.fetch_add(1, Relaxed);
+        if watcher {
+            self.watchers.fetch_add(1, Relaxed);
+        }
+        Registration {
+            park: self,
+            word,
+            watcher,
+        }
+    }
+
+    /// Like [`Park::register`], though for a task to be woken via `waker`
+    /// instead of blocking a thread. Re-register via
+    /// [`TaskRegistration::update`] on each poll.
+    pub(crate) fn register_task(&self, waker: &Waker) -> TaskRegistration<'_> {
+        let word = self.word.load(Acquire);
+        let key = self.next_key.fetch_add(1, Relaxed);
+        self.wakers.lock().unwrap().push((key, waker.clone()));
+        // See `Park::register_as`.
+        self.sleepers.fetch_add(1, Relaxed);
+        TaskRegistration {
+            park: self,
+            key,
+            word,
+        }
+    }
+
+    /// Wake a single waiting thread, if any, or every one while any of them
+    /// is a watcher, as well as every waiting task. A task might be dropped
+    /// instead of polled once woken, thus waking a single one could leave the
+    /// item unclaimed.
+    ///
+    /// To be called after a successful push on the Treiber stack. Only a
+    /// relaxed load in the common case of no waiting threads.
+    #[inline]
+    pub(crate) fn wake_one(&self) {
+        if self.sleepers.load(Relaxed) != 0 {
+            self.wake_one_slow();
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn wake_one_slow(&self) {
+        self.word.fetch_add(1, Release);
+        if self.watchers.load(Relaxed) != 0 {
+            sys::wake_all(&self.word);
+        } else {
+            sys::wake_one(&self.word);
+        }
+
+        let wakers = std::mem::take(&mut *self.wakers.lock().unwrap());
+        for (_, waker) in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Number of threads registered to wait.
+    #[cfg(test)]
+    pub(crate) fn sleepers(&self) -> usize {
+        self.sleepers.load(Relaxed)
+    }
+}
+
+impl Registration<'_> {
+    /// Block until woken, returning early in case any wake up happened since
+    /// registering. Might return spuriously, thus returns whether any wake up
+    /// happened since registering.
+    pub(crate) fn wait(self) -> bool {
+        sys::wait(&self.park.word, self.word);
+        self.park.word.load(Acquire) != self.word
+    }
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        if self.watcher {
+            self.park.watchers.fetch_sub(1, Relaxed);
+        }
+        self.park.sleepers.fetch_sub(1, Relaxed);
+    }
+}
+
+impl TaskRegistration<'_> {
+    /// Whether any wake up happened since registering, see
+    /// [`Registration::wait`].
+    pub(crate) fn woken(&self) -> bool {
+        self.park.word.load(Acquire) != self.word
+    }
+
+    /// Wake `waker` on the next wake up, replacing the waker registered
+    /// before, if not woken since. The caller has to re-check its condition
+    /// afterwards.
+    pub(crate) fn update(&self, waker: &Waker) {
+        let mut wakers = self.park.wakers.lock().unwrap();
+        match wakers.iter_mut().find(|(key, _)| *key == self.key) {
+            Some((_, registered)) => {
+                if !registered.will_wake(waker) {
+                    *registered = waker.clone();
+                }
+            }
+            None => wakers.push((self.key, waker.clone())),
+        }
+    }
+}
+
+impl Drop for TaskRegistration<'_> {
+    fn drop(&mut self) {
+        self.park
+            .wakers
+            .lock()
+            .unwrap()
+            .retain(|(key, _)| *key != self.key);
+        self.park.sleepers.fetch_sub(1, Relaxed);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::ptr;
+    use std::sync::atomic::AtomicU32;
+
+    pub(super) fn wait(word: &AtomicU32, expected: u32) {
+        // Returns on wake up, on `word` not being `expected` and on
+        // interruption, all of which the caller handles.
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                word.as_ptr(),
+                libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+                expected,
+                ptr::null::<libc::timespec>(),
+            );
+        }
+    }
+
+    pub(super) fn wake_one(word: &AtomicU32) {
+        wake(word, 1);
+    }
+
+    pub(super) fn wake_all(word: &AtomicU32) {
+        wake(word, i32::MAX);
+    }
+
+    fn wake(word: &AtomicU32, n: i32) {
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                word.as_ptr(),
+                libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+                n,
+            );
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::ffi::c_void;
+    use std::sync::atomic::AtomicU32;
+
+    const INFINITE: u32 = u32::MAX;
+
+    #[link(name = "synchronization")]
+    extern "system" {
+        fn WaitOnAddress(
+            address: *const c_void,
+            compare_address: *const c_void,
+            address_size: usize,
+            milliseconds: u32,
+        ) -> i32;
+        fn WakeByAddressSingle(address: *const c_void);
+        fn WakeByAddressAll(address: *const c_void);
+    }
+
+    pub(super) fn wait(word: &AtomicU32, expected: u32) {
+        unsafe {
+            WaitOnAddress(
+                word.as_ptr() as *const c_void,
+                &expected as *const u32 as *const c_void,
+                4,
+                INFINITE,
+            );
+        }
+    }
+
+    pub(super) fn wake_one(word: &AtomicU32) {
+        unsafe { WakeByAddressSingle(word.as_ptr() as *const c_void) };
+    }
+
+    pub(super) fn wake_all(word: &AtomicU32) {
+        unsafe { WakeByAddressAll(word.as_ptr() as *const c_void) };
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    use std::ffi::c_void;
+    use std::sync::atomic::AtomicU32;
+
+    const UL_COMPARE_AND_WAIT: u32 = 1;
+    const ULF_WAKE_ALL: u32 = 0x100;
+
+    extern "C" {
+        fn __ulock_wait(operation: u32, address: *mut c_void, value: u64, timeout_us: u32) -> i32;
+        fn __ulock_wake(operation: u32, address: *mut c_void, wake_value: u64) -> i32;
+    }
+
+    pub(super) fn wait(word: &AtomicU32, expected: u32) {
+        unsafe {
+            __ulock_wait(
+                UL_COMPARE_AND_WAIT,
+                word.as_ptr() as *mut c_void,
+                u64::from(expected),
+                0,
+            );
+        }
+    }
+
+    pub(super) fn wake_one(word: &AtomicU32) {
+        unsafe { __ulock_wake(UL_COMPARE_AND_WAIT, word.as_ptr() as *mut c_void, 0) };
+    }
+
+    pub(super) fn wake_all(word: &AtomicU32) {
+        unsafe {
+            __ulock_wake(
+                UL_COMPARE_AND_WAIT | ULF_WAKE_ALL,
+                word.as_ptr() as *mut c_void,
+                0,
+            )
+        };
+    }
+}
+
+/// Fallback without an operating system primitive: yield instead of blocking,
+/// thus waiting threads poll.
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+mod sys {
+    use std::sync::atomic::AtomicU32;
+    use std::thread;
+
+    pub(super) fn wait(_word: &AtomicU32, _expected: u32) {
+        thread::yield_now();
+    }
+
+    pub(super) fn wake_one(_word: &AtomicU32) {}
+
+    pub(super) fn wake_all(_word: &AtomicU32) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering::AcqRel;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn wait_returns_after_wake_up_since_registering() {
+        let park = Park::new();
+        let registration = park.register();
+
+        park.wake_one();
+
+        // Would block forever, had the wake up been missed.
+        registration.wait();
+        assert_eq!(park.sleepers.load(Relaxed), 0);
+    }
+
+    #[test]
+    fn wake_one_unparks_waiting_thread() {
+        let park = Arc::new(Park::new());
+        let woken = Arc::new(AtomicU32::new(0));
+
+        let handler = {
+            let (park, woken) = (park.clone(), woken.clone());
+            // Confirm via read-modify-write, as done on the Treiber stack.
+            thread::spawn(move || loop {
+                let registration = park.register();
+                if woken.fetch_add(0, AcqRel) != 0 {
+                    break;
+                }
+                registration.wait();
+            })
+        };
+
+        woken.swap(1, AcqRel);
+        park.wake_one();
+        handler.join().unwrap();
+    }
+}
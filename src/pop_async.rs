@@ -0,0 +1,278 @@
+//! Pop operation awaiting an item on an empty stack, see [`Stack::pop_async`].
+//!
+//! Shares the parking of [`Stack::pop_blocking`], see `src/park.rs`, though
+//! registers the [`Waker`](std::task::Waker) of the task instead of blocking
+//! the thread, thus usable within any executor, e.g. as a backlog of work.
+//!
+//! A poll retries a contended stack like [`Stack::pop`], thus might occupy
+//! the worker thread of the executor for long during a contention storm.
+//! [`PopAsync::yield_after`] bounds the time spent per poll instead, yielding
+//! back to the executor once it elapsed, e.g. within the cooperative budget
+//! of the runtime.
+
+use crate::park::TaskRegistration;
+use crate::{BoundedStack, Contended, PopStrategy, PushStrategy, Stack};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Future of a pop operation, resolving to the item once available. Created
+/// via [`Stack::pop_async`].
+///
+/// ```rust
+/// # use elimination_backoff_stack::Stack;
+/// # use std::sync::Arc;
+/// # use std::thread;
+/// let stack = Arc::new(Stack::<u8>::new());
+///
+/// let producer = {
+///     let stack = stack.clone();
+///     thread::spawn(move || stack.push(1))
+/// };
+///
+/// let item = futures::executor::block_on(stack.pop_async());
+/// assert_eq!(item, 1);
+/// # producer.join().unwrap();
+/// ```
+#[must_use = "futures do nothing unless polled"]
+pub struct PopAsync<'a, T, PushS, PopS> {
+    stack: &'a Stack<T, PushS, PopS>,
+    registration: Option<TaskRegistration<'a>>,
+    /// Time a poll may spend retrying a contended stack, see
+    /// [`PopAsync::yield_after`].
+    slice: Option<Duration>,
+}
+
+impl<'a, T, PushS, PopS> PopAsync<'a, T, PushS, PopS> {
+    pub(crate) fn new(stack: &'a Stack<T, PushS, PopS>) -> Self {
+        PopAsync {
+            stack,
+            registration: None,
+            slice: None,
+        }
+    }
+
+    /// Yield back to the executor once a poll spent `slice` retrying a
+    /// contended stack, consulting the deadline between attempts like
+    /// [`Stack::pop_timeout`]. Wakes the task right away, thus the executor
+    /// polls it again after running other tasks.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// # use std::time::Duration;
+    /// let stack = Stack::<u8>::with_items(vec![1]);
+    ///
+    /// let pop = stack.pop_async().yield_after(Duration::from_micros(50));
+    /// assert_eq!(futures::executor::block_on(pop), 1);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slice` is zero, given that no poll could attempt to pop.
+    pub fn yield_after(mut self, slice: Duration) -> Self {
+        assert!(slice > Duration::ZERO, "slice needs to permit an attempt");
+        self.slice = Some(slice);
+        self
+    }
+}
+
+impl<T, PushS, PopS> Future for PopAsync<'_, T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let stack = self.stack;
+        loop {
+            let popped = match self.slice {
+                Some(slice) => stack.pop_within(slice),
+                None => Ok(stack.pop()),
+            };
+            match popped {
+                Ok(Some(item)) => {
+                    self.registration = None;
+                    return Poll::Ready(item);
+                }
+                Ok(None) => {}
+                Err(Contended) => {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+            }
+
+            match &self.registration {
+                Some(registration) => registration.update(cx.waker()),
+                None => self.registration = Some(stack.park.register_task(cx.waker())),
+            }
+            // See `Stack::pop_blocking`.
+            if stack.stack.confirm_empty() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl<T, PushS, PopS> fmt::Debug for PopAsync<'_, T, PushS, PopS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PopAsync")
+            .field("registered", &self.registration.is_some())
+            .field("slice", &self.slice)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Future of a pop operation on a [`BoundedStack`], resolving to the item
+/// once available and releasing its slot, thus waking a task awaiting
+/// [`BoundedStack::push_async`]. Created via [`BoundedStack::pop_async`].
+#[must_use = "futures do nothing unless polled"]
+pub struct BoundedPopAsync<'a, T, PushS, PopS> {
+    stack: &'a BoundedStack<T, PushS, PopS>,
+    pop: PopAsync<'a, T, PushS, PopS>,
+}
+
+impl<'a, T, PushS, PopS> BoundedPopAsync<'a, T, PushS, PopS> {
+    pub(crate) fn new(stack: &'a BoundedStack<T, PushS, PopS>) -> Self {
+        BoundedPopAsync {
+            stack,
+            pop: PopAsync::new(&stack.stack),
+        }
+    }
+
+    /// See [`PopAsync::yield_after`].
+    pub fn yield_after(mut self, slice: Duration) -> Self {
+        self.pop = self.pop.yield_after(slice);
+        self
+    }
+}
+
+impl<T, PushS, PopS> Future for BoundedPopAsync<'_, T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let item = match Pin::new(&mut self.pop).poll(cx) {
+            Poll::Ready(item) => item,
+            Poll::Pending => return Poll::Pending,
+        };
+        self.stack.release();
+        Poll::Ready(item)
+    }
+}
+
+impl<T, PushS, PopS> fmt::Debug for BoundedPopAsync<'_, T, PushS, PopS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoundedPopAsync")
+            .field("pop", &self.pop)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::strategy::ExpRetryStrategy;
+    use crate::tests::Refuse;
+    use crate::Stack;
+    use futures::executor::block_on;
+    use futures::task::{noop_waker_ref, waker_ref, ArcWake};
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn push_wakes_awaiting_task() {
+        let stack = Arc::new(Stack::<u8>::new());
+
+        let consumer = {
+            let stack = stack.clone();
+            thread::spawn(move || block_on(stack.pop_async()))
+        };
+        while stack.park.sleepers() < 1 {
+            thread::yield_now();
+        }
+
+        stack.push(1);
+        assert_eq!(consumer.join().unwrap(), 1);
+        assert_eq!(stack.park.sleepers(), 0);
+    }
+
+    #[test]
+    fn dropped_future_deregisters() {
+        let stack = Stack::<u8>::new();
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let mut pop = stack.pop_async();
+        assert_eq!(Pin::new(&mut pop).poll(&mut cx), Poll::Pending);
+        assert_eq!(Pin::new(&mut pop).poll(&mut cx), Poll::Pending);
+        assert_eq!(stack.park.sleepers(), 1);
+
+        drop(pop);
+        assert_eq!(stack.park.sleepers(), 0);
+        stack.push(1);
+        assert_eq!(block_on(stack.pop_async()), 1);
+    }
+
+    #[test]
+    fn yields_to_executor_given_contention() {
+        struct Woken(AtomicUsize);
+
+        impl ArcWake for Woken {
+            fn wake_by_ref(arc_self: &Arc<Self>) {
+                arc_self.0.fetch_add(1, Relaxed);
+            }
+        }
+
+        let mut stack = Stack::<u8, ExpRetryStrategy, Refuse>::new();
+        stack.push_mut(1);
+        let woken = Arc::new(Woken(AtomicUsize::new(0)));
+        let waker = waker_ref(&woken);
+        let mut cx = Context::from_waker(&waker);
+
+        let mut pop = stack.pop_async().yield_after(Duration::from_millis(1));
+        assert_eq!(Pin::new(&mut pop).poll(&mut cx), Poll::Pending);
+        assert_eq!(woken.0.load(Relaxed), 1);
+        assert_eq!(stack.park.sleepers(), 0);
+        drop(pop);
+        assert_eq!(stack.pop_mut(), Some(1));
+    }
+
+    #[test]
+    fn push_wakes_every_task_and_thread() {
+        let stack = Arc::new(Stack::<u8>::new());
+
+        let tasks = (0..2)
+            .map(|_| {
+                let stack = stack.clone();
+                thread::spawn(move || block_on(stack.pop_async()))
+            })
+            .collect::<Vec<_>>();
+        let thread = {
+            let stack = stack.clone();
+            thread::spawn(move || stack.pop_blocking())
+        };
+        while stack.park.sleepers() < 3 {
+            thread::yield_now();
+        }
+
+        for item in 0..3 {
+            stack.push(item);
+        }
+        let mut popped = tasks
+            .into_iter()
+            .chain(Some(thread))
+            .map(|consumer| consumer.join().unwrap())
+            .collect::<Vec<_>>();
+        popped.sort_unstable();
+        assert_eq!(popped, vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,199 @@
+//! Push operation awaiting a slot on a full [`BoundedStack`], see
+//! [`BoundedStack::push_async`].
+//!
+//! Shares the parking of [`BoundedStack::push_blocking`], like
+//! [`crate::PopAsync`] shares the one of [`crate::Stack::pop_blocking`].
+//! Together with [`BoundedStack::pop_async`] a bounded stack serves as an
+//! async work buffer, producers awaiting a slot and consumers awaiting an
+//! item.
+
+use crate::park::TaskRegistration;
+use crate::{BoundedStack, PopStrategy, PushStrategy};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Future of a push operation, resolving once the item is on the stack.
+/// Created via [`BoundedStack::push_async`].
+///
+/// Dropping the future before it resolved drops the item.
+///
+/// ```rust
+/// # use elimination_backoff_stack::BoundedStack;
+/// # use std::sync::Arc;
+/// # use std::thread;
+/// let stack = Arc::new(BoundedStack::<u8>::new(1));
+/// stack.push(1).unwrap();
+///
+/// let consumer = {
+///     let stack = stack.clone();
+///     thread::spawn(move || stack.pop_blocking())
+/// };
+///
+/// futures::executor::block_on(stack.push_async(2));
+/// assert_eq!(consumer.join().unwrap(), 1);
+/// assert_eq!(stack.pop(), Some(2));
+/// ```
+#[must_use = "futures do nothing unless polled"]
+pub struct PushAsync<'a, T, PushS, PopS> {
+    stack: &'a BoundedStack<T, PushS, PopS>,
+    /// `None` once pushed.
+    item: Option<T>,
+    registration: Option<TaskRegistration<'a>>,
+}
+
+impl<'a, T, PushS, PopS> PushAsync<'a, T, PushS, PopS> {
+    pub(crate) fn new(stack: &'a BoundedStack<T, PushS, PopS>, item: T) -> Self {
+        PushAsync {
+            stack,
+            item: Some(item),
+            registration: None,
+        }
+    }
+}
+
+// The item is moved onto the stack, never pinned.
+impl<T, PushS, PopS> Unpin for PushAsync<'_, T, PushS, PopS> {}
+
+impl<T, PushS, PopS> Future for PushAsync<'_, T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let stack = self.stack;
+        loop {
+            if stack.reserve() {
+                self.registration = None;
+                let item = self.item.take().expect("polled after completion");
+                stack.stack.push(item);
+                return Poll::Ready(());
+            }
+
+            match &self.registration {
+                Some(registration) => registration.update(cx.waker()),
+                None => self.registration = Some(stack.not_full.register_task(cx.waker())),
+            }
+            // See `BoundedStack::push_blocking`.
+            if stack.confirm_full() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl<T, PushS, PopS> fmt::Debug for PushAsync<'_, T, PushS, PopS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PushAsync")
+            .field("pushed", &self.item.is_none())
+            .field("registered", &self.registration.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BoundedStack;
+    use futures::executor::block_on;
+    use futures::task::noop_waker_ref;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::thread;
+
+    #[test]
+    fn pop_wakes_awaiting_task() {
+        let stack = Arc::new(BoundedStack::<u8>::new(1));
+        stack.push(1).unwrap();
+
+        let producer = {
+            let stack = stack.clone();
+            thread::spawn(move || block_on(stack.push_async(2)))
+        };
+        while stack.not_full.sleepers() < 1 {
+            thread::yield_now();
+        }
+
+        assert_eq!(stack.pop(), Some(1));
+        producer.join().unwrap();
+        assert_eq!(stack.not_full.sleepers(), 0);
+        assert_eq!(stack.pop(), Some(2));
+    }
+
+    #[test]
+    fn dropped_future_deregisters_and_drops_item() {
+        let item = Arc::new(());
+        let stack = BoundedStack::<Arc<()>>::new(1);
+        stack.push(item.clone()).unwrap();
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let mut push = stack.push_async(item.clone());
+        assert_eq!(Pin::new(&mut push).poll(&mut cx), Poll::Pending);
+        assert_eq!(Pin::new(&mut push).poll(&mut cx), Poll::Pending);
+        assert_eq!(stack.not_full.sleepers(), 1);
+
+        drop(push);
+        assert_eq!(stack.not_full.sleepers(), 0);
+        assert_eq!(Arc::strong_count(&item), 2);
+        assert_eq!(stack.len(), 1);
+    }
+
+    #[test]
+    fn bounded_async_buffer() {
+        let (items, capacity) = (1_000, 4);
+        let stack = BoundedStack::<usize>::new(capacity);
+
+        let popped = thread::scope(|scope| {
+            scope.spawn(|| {
+                block_on(async {
+                    for item in 0..items {
+                        stack.push_async(item).await;
+                    }
+                })
+            });
+            let mut popped = 0;
+            block_on(async {
+                for _ in 0..items {
+                    popped += stack.pop_async().await;
+                }
+            });
+            popped
+        });
+
+        assert_eq!(popped, (0..items).sum::<usize>());
+        assert!(stack.is_empty());
+        assert!(!stack.is_full());
+    }
+
+    #[test]
+    fn bounded_async_buffer_single_executor() {
+        let (items, capacity) = (1_000, 4);
+        let stack = BoundedStack::<usize>::new(capacity);
+
+        let ((), popped) = block_on(async {
+            futures::join!(
+                async {
+                    for item in 0..items {
+                        stack.push_async(item).await;
+                        assert!(stack.len() <= capacity);
+                    }
+                },
+                async {
+                    let mut popped = 0;
+                    for _ in 0..items {
+                        popped += stack.pop_async().await;
+                    }
+                    popped
+                },
+            )
+        });
+
+        assert_eq!(popped, (0..items).sum::<usize>());
+        assert!(stack.is_empty());
+        assert_eq!(stack.not_full.sleepers(), 0);
+    }
+}
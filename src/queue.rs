@@ -0,0 +1,357 @@
+//! Lock-free elimination back-off FIFO queue, following Moir et al. [1].
+//!
+//! Like [`Stack`](crate::Stack) on top of the Treiber stack, [`Queue`] puts an
+//! elimination array in front of a central queue, Michael and Scott's
+//! lock-free queue, see `src/ms_queue.rs`. An operation failing on the
+//! central queue due to contention backs off to the elimination array, where
+//! an enqueue and a dequeue operation might cancel each other out.
+//!
+//! Unlike on a stack, an enqueue and a dequeue operation can not always
+//! cancel out, given that the dequeue operation is to return the oldest item,
+//! not the newest. Thus an enqueue operation offers its item along with the
+//! number of items enqueued before it started, and a dequeue operation only
+//! takes an item once at least as many items were dequeued from the central
+//! queue, i.e. once every item enqueued before is gone. The enqueue operation
+//! is then ordered right before the dequeue operation, as if it had been
+//! enqueued on an empty queue and dequeued right after. Thus elimination
+//! mostly pays off while the queue is close to empty, e.g. with consumers
+//! keeping up with producers.
+//!
+//! [1]: Moir, Mark, et al. "Using elimination to implement scalable and
+//! lock-free fifo queues." Proceedings of the seventeenth annual ACM symposium
+//! on Parallelism in algorithms and architectures. 2005.
+
+use crate::elimination_array::EliminationArray;
+use crate::event::{NoOpRecorder, OperationId};
+use crate::ms_queue::MsQueue;
+use crate::strategy::{ExpRetryStrategy, Tuning};
+use crate::treiber_stack::PopResult;
+use crate::{Config, PopStrategy, PushStrategy};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Lock-free elimination back-off FIFO queue.
+///
+/// Enqueue operations consult a [`PushStrategy`], dequeue operations a
+/// [`PopStrategy`], exactly like push and pop operations on a
+/// [`Stack`](crate::Stack), the central queue taking the place of the Treiber
+/// stack.
+///
+/// ```rust
+/// # use elimination_backoff_stack::Queue;
+/// # use std::sync::Arc;
+/// # use std::thread;
+/// let queue = Arc::new(Queue::<u32>::new());
+///
+/// let producer = {
+///     let queue = queue.clone();
+///     thread::spawn(move || {
+///         for i in 0..3 {
+///             queue.enqueue(i);
+///         }
+///     })
+/// };
+/// producer.join().unwrap();
+///
+/// assert_eq!(queue.dequeue(), Some(0));
+/// assert_eq!(queue.dequeue(), Some(1));
+/// assert_eq!(queue.dequeue(), Some(2));
+/// assert_eq!(queue.dequeue(), None);
+/// ```
+pub struct Queue<T, PushS = ExpRetryStrategy, PopS = ExpRetryStrategy> {
+    queue: MsQueue<T>,
+    /// Items of enqueue operations backing off, each with the number of items
+    /// enqueued before, see [`Queue::dequeue`].
+    elimination_array: EliminationArray<(T, u64)>,
+    /// Passed to the strategy of each operation.
+    tuning: Tuning,
+    /// See [`Config::elimination`].
+    elimination: bool,
+    // See `Stack::phantom`.
+    phantom: PhantomData<fn() -> (PushS, PopS)>,
+}
+
+impl<T, PushS, PopS> Queue<T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    pub fn new() -> Self {
+        Queue::with_config(Config::default())
+    }
+
+    /// Create a queue configured at run time like a stack, see
+    /// [`crate::Stack::with_config`].
+    ///
+    /// # Panics
+    ///
+    /// See [`crate::Stack::with_config`].
+    pub fn with_config(config: Config) -> Self {
+        config.tuning.validate();
+
+        Queue {
+            queue: MsQueue::new(),
+            elimination: config.eliminates(),
+            elimination_array: EliminationArray::with_resize_config(config.resize),
+            tuning: config.tuning,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Append `item` to the back of the queue.
+    pub fn enqueue(&self, item: T) {
+        let id = OperationId::next();
+        let mut strategy = PushS::with_tuning(&self.tuning);
+        let mut item = item;
+
+        loop {
+            let enqueued_before = match self.queue.enqueue(item, &mut strategy) {
+                Ok(()) => return,
+                Err((i, enqueued_before)) => {
+                    item = i;
+                    enqueued_before
+                }
+            };
+
+            if self.elimination && strategy.use_elimination_array() {
+                match self.elimination_array.exchange_push(
+                    (item, enqueued_before),
+                    id,
+                    &mut strategy,
+                    &mut NoOpRecorder {},
+                ) {
+                    Ok(()) => return,
+                    Err((i, _)) => item = i,
+                }
+            }
+        }
+    }
+
+    /// Remove the item at the front of the queue, `None` if the queue was
+    /// found empty.
+    pub fn dequeue(&self) -> Option<T> {
+        let id = OperationId::next();
+        let mut strategy = PopS::with_tuning(&self.tuning);
+
+        loop {
+            match self.queue.dequeue(&mut strategy) {
+                PopResult::Popped(item) => return Some(item),
+                PopResult::Empty => return None,
+                PopResult::Contended => {}
+            }
+
+            if self.elimination && strategy.use_elimination_array() {
+                // Only an enqueue operation whose predecessors are all gone
+                // can be ordered right before this one, see the module
+                // documentation. Never decreases, thus a stale value merely
+                // rejects more.
+                let dequeued = self.queue.dequeued();
+                if let Ok((item, _)) = self.elimination_array.exchange_pop_if(
+                    id,
+                    &mut strategy,
+                    &mut NoOpRecorder {},
+                    |(_, enqueued_before)| *enqueued_before <= dequeued,
+                ) {
+                    return Some(item);
+                }
+            }
+        }
+    }
+
+    /// Approximate number of items in the queue, exact without concurrent
+    /// operations. Ignores items of enqueue operations backing off on the
+    /// elimination array.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Whether the queue appeared empty, see [`Queue::len`].
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+impl<T, PushS, PopS> Default for Queue<T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+/// Prints a diagnostic summary instead of the items, like the one of a
+/// [`Stack`](crate::Stack).
+impl<T, PushS, PopS> fmt::Debug for Queue<T, PushS, PopS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Queue")
+            .field("len", &self.queue.len())
+            .field("exchangers", &self.elimination_array.len())
+            .field("push_strategy", &std::any::type_name::<PushS>())
+            .field("pop_strategy", &std::any::type_name::<PopS>())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ResizeConfig;
+    use std::sync::Mutex;
+    use std::thread;
+
+    /// Elimination considered as if on a machine with a few CPUs.
+    fn eliminating() -> Config {
+        Config {
+            resize: ResizeConfig::with_parallelism(4),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn first_in_first_out() {
+        let queue = Queue::<u32>::new();
+        assert!(queue.is_empty());
+
+        for i in 0..100 {
+            queue.enqueue(i);
+        }
+        assert_eq!(queue.len(), 100);
+        assert_eq!(
+            std::iter::from_fn(|| queue.dequeue()).collect::<Vec<_>>(),
+            (0..100).collect::<Vec<_>>()
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn drops_remaining_items() {
+        let item = std::sync::Arc::new(());
+        let queue = Queue::<_>::new();
+        for _ in 0..3 {
+            queue.enqueue(item.clone());
+        }
+        drop(queue.dequeue());
+        drop(queue);
+        assert_eq!(std::sync::Arc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn concurrent_keeps_order_per_producer() {
+        let (producers, items_per_producer) = (2, 10_000);
+        let queue = Queue::<(usize, usize)>::with_config(eliminating());
+        let consumed = Mutex::new(vec![]);
+
+        thread::scope(|scope| {
+            for producer in 0..producers {
+                let queue = &queue;
+                scope.spawn(move || {
+                    for i in 0..items_per_producer {
+                        queue.enqueue((producer, i));
+                    }
+                });
+            }
+            for _ in 0..2 {
+                let (queue, consumed) = (&queue, &consumed);
+                scope.spawn(move || {
+                    let mut own = vec![];
+                    while own.len() < items_per_producer {
+                        own.extend(queue.dequeue());
+                    }
+                    // Dequeued by a single consumer, thus in the order of
+                    // each producer.
+                    for producer in 0..producers {
+                        let items = own.iter().filter(|(p, _)| *p == producer);
+                        assert!(items
+                            .clone()
+                            .zip(items.skip(1))
+                            .all(|((_, a), (_, b))| a < b));
+                    }
+                    consumed.lock().unwrap().extend(own);
+                });
+            }
+        });
+
+        let mut consumed = consumed.into_inner().unwrap();
+        consumed.sort_unstable();
+        let expected = (0..producers)
+            .flat_map(|p| (0..items_per_producer).map(move |i| (p, i)))
+            .collect::<Vec<_>>();
+        assert_eq!(consumed, expected);
+        assert!(queue.is_empty());
+    }
+
+    #[cfg(not(feature = "no-elimination"))]
+    #[test]
+    fn eliminates_only_once_predecessors_are_gone() {
+        use crate::strategy::Strategy;
+
+        /// Waits for a partner as long as it takes, though checks for an
+        /// item on offer a bounded number of times.
+        struct Patient(u32);
+
+        impl Strategy for Patient {
+            fn new() -> Self {
+                Patient(1_000)
+            }
+            fn use_elimination_array(&mut self) -> bool {
+                true
+            }
+            fn try_stack(&mut self) -> bool {
+                false
+            }
+            fn try_elimination_array(&mut self) -> bool {
+                self.0 > 0
+            }
+            fn try_start_exchange(&mut self) -> bool {
+                true
+            }
+            fn retry_check_exchanged(&mut self) -> bool {
+                thread::yield_now();
+                true
+            }
+            fn try_exchange(&mut self) -> bool {
+                thread::yield_now();
+                self.0 = self.0.saturating_sub(1);
+                self.0 > 0
+            }
+        }
+
+        let queue = Queue::<&str, Patient, Patient>::with_config(Config {
+            resize: ResizeConfig::fixed(1),
+            min_parallelism: 1,
+            ..Config::default()
+        });
+        // Linked directly, as the central queue never gives up otherwise.
+        queue
+            .queue
+            .enqueue("older", &mut ExpRetryStrategy::new())
+            .unwrap();
+
+        thread::scope(|scope| {
+            // Backs off to the elimination array right away, one item
+            // enqueued before.
+            scope.spawn(|| queue.enqueue("newer"));
+
+            let accept = |dequeued: u64| {
+                move |(_, enqueued_before): &(&str, u64)| *enqueued_before <= dequeued
+            };
+            let pop_if = |dequeued| {
+                queue.elimination_array.exchange_pop_if(
+                    OperationId::next(),
+                    &mut Patient::new(),
+                    &mut NoOpRecorder {},
+                    accept(dequeued),
+                )
+            };
+
+            assert!(pop_if(queue.queue.dequeued()).is_err());
+            assert_eq!(
+                queue.queue.dequeue(&mut ExpRetryStrategy::new()),
+                PopResult::Popped("older")
+            );
+            assert_eq!(pop_if(queue.queue.dequeued()), Ok(("newer", 1)));
+        });
+    }
+}
@@ -0,0 +1,316 @@
+//! Controller growing and shrinking the elimination array based on the
+//! outcome of exchange attempts on its exchangers.
+//!
+//! Each exchanger counts the exchange attempts on it, as well as the ones that
+//! failed because the exchanger was busy, i.e. in use by an operation of the
+//! same kind, or empty, i.e. no partner showed up. Once an exchanger saw a
+//! window of attempts, the counters of all exchangers are collected and
+//! compared against the thresholds of the [`ResizeConfig`]. Many busy misses
+//! indicate too few exchangers, many empty misses too many.
+
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+
+/// Parameters of the elimination array resize controller.
+///
+/// Pass to [`crate::Stack::with_resize_config`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct ResizeConfig {
+    /// Number of exchange attempts on a single exchanger after which the
+    /// controller evaluates the counters of all exchangers.
+    pub window: usize,
+    /// Grow once the share of attempts failing on a busy exchanger exceeds
+    /// this threshold.
+    pub grow_threshold: f64,
+    /// Shrink once the share of attempts failing on an empty exchanger
+    /// exceeds this threshold.
+    pub shrink_threshold: f64,
+    /// Number of consecutive windows a threshold needs to be exceeded before
+    /// growing or shrinking, preventing oscillation.
+    pub hysteresis: u32,
+    /// Lower bound on the number of exchangers.
+    pub min_exchangers: usize,
+    /// Upper bound on the number of exchangers.
+    pub max_exchangers: usize,
+    /// Number of threads expected to operate on the stack concurrently, the
+    /// initial number of exchangers, bounded by `min_exchangers` and
+    /// `max_exchangers`.
+    ///
+    /// Defaults to [`available_parallelism`]. Override e.g. when only a few
+    /// threads of a large machine share the stack.
+    pub parallelism: usize,
+    /// Seed of the permutation operations select exchangers through, drawn
+    /// anew for each number of exchangers. `None` picks a random seed per
+    /// stack, set one to reproduce the order of the exchangers, see
+    /// [`crate::Builder::seed`].
+    pub seed: Option<u64>,
+}
+
+impl ResizeConfig {
+    /// Configuration keeping the elimination array at `num_exchangers`.
+    pub fn fixed(num_exchangers: usize) -> Self {
+        ResizeConfig {
+            min_exchangers: num_exchangers,
+            max_exchangers: num_exchangers,
+            ..ResizeConfig::default()
+        }
+    }
+
+    /// Default configuration sized for `parallelism` instead of the
+    /// [`available_parallelism`].
+    pub fn with_parallelism(parallelism: usize) -> Self {
+        ResizeConfig {
+            parallelism,
+            max_exchangers: parallelism.max(1) * 2,
+            ..ResizeConfig::default()
+        }
+    }
+}
+
+impl Default for ResizeConfig {
+    fn default() -> Self {
+        let parallelism = available_parallelism();
+
+        ResizeConfig {
+            window: 1024,
+            grow_threshold: 0.5,
+            shrink_threshold: 0.9,
+            hysteresis: 3,
+            min_exchangers: 1,
+            max_exchangers: parallelism * 2,
+            parallelism,
+            seed: None,
+        }
+    }
+}
+
+/// Number of threads the process can run in parallel, as reported by
+/// [`std::thread::available_parallelism`], thus respecting e.g. CPU quotas
+/// and affinity masks. `1` in case it can not be determined.
+pub fn available_parallelism() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Decision {
+    Keep,
+    Resize(usize),
+}
+
+#[derive(Default)]
+struct Streaks {
+    grow: u32,
+    shrink: u32,
+}
+
+pub(crate) struct ResizeController {
+    config: ResizeConfig,
+    // Only ever `try_lock`ed. Concurrent evaluations are skipped instead of
+    // blocking.
+    streaks: Mutex<Streaks>,
+}
+
+impl ResizeController {
+    pub(crate) fn new(config: ResizeConfig) -> Self {
+        assert!(config.window > 0, "window must not be zero");
+        assert!(
+            0 < config.min_exchangers && config.min_exchangers <= config.max_exchangers,
+            "expected 0 < min_exchangers <= max_exchangers",
+        );
+
+        ResizeController {
+            config,
+            streaks: Mutex::new(Streaks::default()),
+        }
+    }
+
+    pub(crate) fn config(&self) -> &ResizeConfig {
+        &self.config
+    }
+
+    /// Number of exchangers to start with.
+    pub(crate) fn initial_len(&self) -> usize {
+        self.config
+            .parallelism
+            .clamp(self.config.min_exchangers, self.config.max_exchangers)
+    }
+
+    /// Whether an exchanger having seen `attempts` attempts completed a
+    /// window.
+    pub(crate) fn window_elapsed(&self, attempts: usize) -> bool {
+        attempts >= self.config.window
+    }
+
+    /// Start evaluating the counters of the exchangers, see
+    /// [`Evaluation::decide`].
+    ///
+    /// Returns `None` in case a concurrent evaluation is in progress. The
+    /// caller should skip evaluating instead of waiting.
+    pub(crate) fn try_evaluate(&self) -> Option<Evaluation<'_>> {
+        Some(Evaluation {
+            config: &self.config,
+            streaks: self.streaks.try_lock().ok()?,
+        })
+    }
+}
+
+/// Counters summed across all exchangers of the elimination array.
+#[derive(Debug, Default)]
+pub(crate) struct Window {
+    pub(crate) len: usize,
+    pub(crate) attempts: usize,
+    pub(crate) busy_misses: usize,
+    pub(crate) empty_misses: usize,
+}
+
+/// Exclusive evaluation of the resize controller. Held while collecting the
+/// counters and applying the decision, thus the decision is based on the
+/// current elimination array.
+pub(crate) struct Evaluation<'a> {
+    config: &'a ResizeConfig,
+    streaks: MutexGuard<'a, Streaks>,
+}
+
+impl Evaluation<'_> {
+    pub(crate) fn decide(&mut self, window: Window) -> Decision {
+        let Window {
+            len,
+            attempts,
+            busy_misses,
+            empty_misses,
+        } = window;
+        let streaks = &mut *self.streaks;
+
+        if attempts == 0 {
+            return Decision::Keep;
+        }
+
+        let busy_rate = busy_misses as f64 / attempts as f64;
+        let empty_rate = empty_misses as f64 / attempts as f64;
+
+        if busy_rate > self.config.grow_threshold {
+            streaks.grow += 1;
+            streaks.shrink = 0;
+        } else if empty_rate > self.config.shrink_threshold {
+            streaks.shrink += 1;
+            streaks.grow = 0;
+        } else {
+            *streaks = Streaks::default();
+        }
+
+        let new_len = if streaks.grow >= self.config.hysteresis {
+            (len * 2).min(self.config.max_exchangers)
+        } else if streaks.shrink >= self.config.hysteresis {
+            (len / 2).max(self.config.min_exchangers)
+        } else {
+            return Decision::Keep;
+        };
+
+        *streaks = Streaks::default();
+
+        if new_len == len {
+            Decision::Keep
+        } else {
+            Decision::Resize(new_len)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller() -> ResizeController {
+        ResizeController::new(ResizeConfig {
+            window: 100,
+            grow_threshold: 0.5,
+            shrink_threshold: 0.9,
+            hysteresis: 2,
+            min_exchangers: 1,
+            max_exchangers: 8,
+            parallelism: 2,
+            seed: None,
+        })
+    }
+
+    fn evaluate(
+        controller: &ResizeController,
+        len: usize,
+        busy_misses: usize,
+        empty_misses: usize,
+    ) -> Decision {
+        controller
+            .try_evaluate()
+            .expect("no concurrent evaluation")
+            .decide(Window {
+                len,
+                attempts: 100,
+                busy_misses,
+                empty_misses,
+            })
+    }
+
+    #[test]
+    fn grows_after_hysteresis() {
+        let controller = controller();
+
+        assert_eq!(evaluate(&controller, 2, 60, 0), Decision::Keep);
+        assert_eq!(evaluate(&controller, 2, 60, 0), Decision::Resize(4));
+        // Streak starts anew after resizing.
+        assert_eq!(evaluate(&controller, 4, 60, 0), Decision::Keep);
+        assert_eq!(evaluate(&controller, 4, 60, 0), Decision::Resize(8));
+        // Bounded by `max_exchangers`.
+        assert_eq!(evaluate(&controller, 8, 60, 0), Decision::Keep);
+        assert_eq!(evaluate(&controller, 8, 60, 0), Decision::Keep);
+    }
+
+    #[test]
+    fn shrinks_after_hysteresis() {
+        let controller = controller();
+
+        assert_eq!(evaluate(&controller, 2, 0, 95), Decision::Keep);
+        assert_eq!(evaluate(&controller, 2, 0, 95), Decision::Resize(1));
+        assert_eq!(evaluate(&controller, 1, 0, 95), Decision::Keep);
+        assert_eq!(evaluate(&controller, 1, 0, 95), Decision::Keep);
+    }
+
+    #[test]
+    fn interrupted_streak_keeps_size() {
+        let controller = controller();
+
+        assert_eq!(evaluate(&controller, 2, 60, 0), Decision::Keep);
+        assert_eq!(evaluate(&controller, 2, 10, 10), Decision::Keep);
+        assert_eq!(evaluate(&controller, 2, 60, 0), Decision::Keep);
+        assert_eq!(evaluate(&controller, 2, 0, 95), Decision::Keep);
+        assert_eq!(evaluate(&controller, 2, 60, 0), Decision::Keep);
+        assert_eq!(evaluate(&controller, 2, 60, 0), Decision::Resize(4));
+    }
+
+    #[test]
+    fn initial_len_follows_parallelism() {
+        assert_eq!(controller().initial_len(), 2);
+
+        let config = ResizeConfig::with_parallelism(3);
+        assert_eq!(config.max_exchangers, 6);
+        assert_eq!(ResizeController::new(config).initial_len(), 3);
+
+        let config = ResizeConfig {
+            parallelism: 64,
+            ..ResizeConfig::fixed(4)
+        };
+        assert_eq!(ResizeController::new(config).initial_len(), 4);
+    }
+
+    #[test]
+    fn skips_concurrent_evaluation() {
+        let controller = controller();
+
+        let _evaluation = controller.try_evaluate().unwrap();
+        assert!(controller.try_evaluate().is_none());
+    }
+}
@@ -0,0 +1,75 @@
+//! Serialization of the contents of a [`Stack`] as a sequence with the `serde`
+//! feature, e.g. to checkpoint remaining work on shutdown and restore it on
+//! restart.
+//!
+//! The sequence lists the items bottom first, thus deserializing it via
+//! [`Stack::with_items`] restores the order, the last item on top.
+
+use crate::{PopStrategy, PushStrategy, Stack};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serializes a snapshot of the items on the stack, see
+/// [`Stack::clone_contents`], bottom first. Weakly consistent, thus meant for a
+/// quiescent stack, e.g. on shutdown. Requires `T: Copy` like any traversal
+/// leaving the items in place; drain a stack of other items via
+/// [`Stack::take_all`] and serialize the result instead.
+///
+/// ```rust
+/// # use elimination_backoff_stack::Stack;
+/// let stack = Stack::<u32>::with_items(vec![1, 2, 3]);
+/// let checkpoint = serde_json::to_string(&stack).unwrap();
+/// assert_eq!(checkpoint, "[1,2,3]");
+///
+/// let restored: Stack<u32> = serde_json::from_str(&checkpoint).unwrap();
+/// assert_eq!(restored.pop(), Some(3));
+/// ```
+impl<T, PushS, PopS> Serialize for Stack<T, PushS, PopS>
+where
+    T: Copy + Serialize,
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.clone_contents().iter().rev())
+    }
+}
+
+/// Deserializes a sequence of items into a new stack, the last item on top.
+impl<'de, T, PushS, PopS> Deserialize<'de> for Stack<T, PushS, PopS>
+where
+    T: Deserialize<'de>,
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(Stack::with_items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::strategy::NoEliminationStrategy;
+    use crate::Stack;
+
+    #[test]
+    fn round_trip_preserves_order() {
+        let stack = Stack::<u64>::new();
+        for i in 0..100 {
+            stack.push(i);
+        }
+
+        let json = serde_json::to_string(&stack).unwrap();
+        assert_eq!(stack.len(), 100, "serializing leaves the items in place");
+
+        let restored: Stack<u64, NoEliminationStrategy, NoEliminationStrategy> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.take_all(), (0..100).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn empty_stack_round_trips() {
+        let json = serde_json::to_string(&Stack::<u8>::new()).unwrap();
+        assert_eq!(json, "[]");
+        assert!(serde_json::from_str::<Stack<u8>>(&json).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,467 @@
+//! Experimental elimination back-off stack within a shared memory segment,
+//! e.g. for distributing work between processes.
+//!
+//! Unlike [`crate::Stack`], a [`ShmStack`] neither allocates nor relies on
+//! epoch based garbage collection, neither of which spans processes. Instead
+//! all of its state, nodes included, lives in a segment provided by the
+//! caller, e.g. mapped via `shm_open` and `mmap`. Nodes link to each other by
+//! their index within the segment, thus each process may map the segment at
+//! a different address. Nodes not on the stack are kept on a free list, thus
+//! the capacity is fixed when initializing the segment. Each link carries a
+//! tag incremented on every change, guarding against ABA.
+//!
+//! Items are copied into and out of the segment bit by bit, thus are
+//! restricted to plain old data, i.e. `Copy` types without pointers or
+//! references, which are meaningless in another process, see [`Pod`].
+//!
+//! ```rust
+//! # use elimination_backoff_stack::shm::ShmStack;
+//! let (capacity, exchangers) = (16, 2);
+//!
+//! // Stand-in for a shared memory segment, aligned to 8 bytes.
+//! let size = ShmStack::<u64>::required_size(capacity, exchangers).unwrap();
+//! let mut segment = vec![0u64; size.div_ceil(8)];
+//! let ptr = segment.as_mut_ptr() as *mut u8;
+//!
+//! // Process A initializes the segment ...
+//! let a = unsafe { ShmStack::<u64>::init(ptr, size, capacity, exchangers) }.unwrap();
+//! a.push(42).unwrap();
+//!
+//! // ... process B attaches to it.
+//! let b = unsafe { ShmStack::<u64>::attach(ptr, size) }.unwrap();
+//! assert_eq!(b.pop(), Some(42));
+//! ```
+//!
+//! Requires the `shm` feature. Not covered by semver.
+
+use crate::indexed::{pack, Lists, Node, NIL};
+use crate::strategy::ExpRetryStrategy;
+use crate::{PopStrategy, PushStrategy};
+use std::alloc::Layout;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::slice;
+use std::sync::atomic::{
+    AtomicU64,
+    Ordering::{Acquire, Release},
+};
+
+/// Identifies an initialized segment, including the version of its layout.
+const MAGIC: u64 = u64::from_le_bytes(*b"EBSTACK1");
+
+/// Start of a segment.
+#[repr(C)]
+struct Header {
+    /// Stored last when initializing, thus marks the segment ready to attach.
+    magic: AtomicU64,
+    item_size: u64,
+    item_align: u64,
+    capacity: u32,
+    exchangers: u32,
+    /// Tagged index of the top node of the stack.
+    head: AtomicU64,
+    /// Tagged index of the top node of the free list.
+    free: AtomicU64,
+}
+
+/// Error of [`ShmStack::init`] and [`ShmStack::attach`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShmError {
+    /// The segment is smaller than the [`ShmStack::required_size`].
+    TooSmall { required: usize, actual: usize },
+    /// The segment is not aligned as required by the header or the items.
+    Misaligned,
+    /// The capacity is [`ShmStack::MAX_CAPACITY`], no exchangers were
+    /// requested, or the layout overflows.
+    InvalidLayout,
+    /// The segment was not initialized via [`ShmStack::init`], not yet, or
+    /// by an incompatible version of this crate.
+    NotInitialized,
+    /// The segment was initialized for items of a different size or
+    /// alignment.
+    ItemMismatch,
+}
+
+impl fmt::Display for ShmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShmError::TooSmall { required, actual } => write!(
+                f,
+                "segment of {} bytes too small, requiring {} bytes",
+                actual, required
+            ),
+            ShmError::Misaligned => f.write_str("segment misaligned"),
+            ShmError::InvalidLayout => f.write_str("invalid capacity or number of exchangers"),
+            ShmError::NotInitialized => f.write_str("segment not initialized"),
+            ShmError::ItemMismatch => f.write_str("segment initialized for different items"),
+        }
+    }
+}
+
+impl std::error::Error for ShmError {}
+
+/// Plain old data, i.e. items meaningful in any process mapping the segment.
+///
+/// # Safety
+///
+/// The type must contain no pointers nor references, e.g. no `&'static str`,
+/// `fn` pointer, raw pointer or `Box`, neither directly nor within a field.
+/// Its layout must be the same in every process attaching to the segment,
+/// e.g. `#[repr(C)]`, built by the same compiler for the same target.
+///
+/// ```rust
+/// # use elimination_backoff_stack::shm::Pod;
+/// #[derive(Clone, Copy)]
+/// #[repr(C)]
+/// struct Job {
+///     id: u64,
+///     priority: u8,
+/// }
+///
+/// unsafe impl Pod for Job {}
+/// ```
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => {
+        $(unsafe impl Pod for $t {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// Offsets of the parts of a segment.
+struct SegmentLayout {
+    slots: usize,
+    nodes: usize,
+    layout: Layout,
+}
+
+/// Elimination back-off stack of plain old data within a shared memory
+/// segment, see [`crate::shm`].
+///
+/// Bounded by the capacity of the segment. Strategies are instantiated per
+/// operation via [`crate::strategy::Strategy::new`], like for a
+/// [`crate::Stack`], and may differ between processes.
+pub struct ShmStack<'a, T, PushS = ExpRetryStrategy, PopS = ExpRetryStrategy> {
+    header: &'a Header,
+    slots: &'a [AtomicU64],
+    nodes: &'a [Node<T>],
+    // See `Stack::phantom`.
+    phantom: PhantomData<fn() -> (PushS, PopS)>,
+}
+
+// Items are only ever accessed by the single operation owning their node.
+unsafe impl<T: Pod + Send, PushS, PopS> Send for ShmStack<'_, T, PushS, PopS> {}
+unsafe impl<T: Pod + Send, PushS, PopS> Sync for ShmStack<'_, T, PushS, PopS> {}
+
+impl<'a, T, PushS, PopS> ShmStack<'a, T, PushS, PopS>
+where
+    T: Pod,
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    /// Exclusive upper bound of the capacity, given that nodes are linked by
+    /// 32 bit indices.
+    pub const MAX_CAPACITY: u32 = NIL;
+
+    /// Size in bytes of a segment holding up to `capacity` items with
+    /// `exchangers` exchangers for elimination.
+    pub fn required_size(capacity: u32, exchangers: u32) -> Result<usize, ShmError> {
+        Self::layout(capacity, exchangers).map(|l| l.layout.size())
+    }
+
+    fn layout(capacity: u32, exchangers: u32) -> Result<SegmentLayout, ShmError> {
+        if capacity == Self::MAX_CAPACITY || exchangers == 0 {
+            return Err(ShmError::InvalidLayout);
+        }
+
+        let extend = |layout: Layout, array: Result<Layout, _>| {
+            array
+                .and_then(|array| layout.extend(array))
+                .map_err(|_| ShmError::InvalidLayout)
+        };
+        let (layout, slots) = extend(
+            Layout::new::<Header>(),
+            Layout::array::<AtomicU64>(exchangers as usize),
+        )?;
+        let (layout, nodes) = extend(layout, Layout::array::<Node<T>>(capacity as usize))?;
+
+        Ok(SegmentLayout {
+            slots,
+            nodes,
+            layout,
+        })
+    }
+
+    /// Initialize the segment of `len` bytes at `segment` as an empty stack,
+    /// overwriting whatever it held before.
+    ///
+    /// # Safety
+    ///
+    /// The segment must be valid for reads and writes for `'a` and only be
+    /// accessed via [`ShmStack`]s of the same item type from then on. `T`
+    /// must contain no pointers nor references, see [`Pod`].
+    /// Initializing must complete before any process attaches, e.g. signaled
+    /// via the channel sharing the segment.
+    pub unsafe fn init(
+        segment: *mut u8,
+        len: usize,
+        capacity: u32,
+        exchangers: u32,
+    ) -> Result<Self, ShmError> {
+        let layout = Self::check(segment, len, capacity, exchangers)?;
+
+        let header = segment as *mut Header;
+        header.write(Header {
+            magic: AtomicU64::new(0),
+            item_size: mem::size_of::<T>() as u64,
+            item_align: mem::align_of::<T>() as u64,
+            capacity,
+            exchangers,
+            head: AtomicU64::new(pack(0, NIL)),
+            free: AtomicU64::new(pack(0, if capacity == 0 { NIL } else { 0 })),
+        });
+
+        let slots = segment.add(layout.slots) as *mut AtomicU64;
+        for i in 0..exchangers as usize {
+            slots.add(i).write(AtomicU64::new(pack(0, NIL)));
+        }
+
+        // All nodes on the free list, in order.
+        let nodes = segment.add(layout.nodes) as *mut Node<T>;
+        for i in 0..capacity {
+            let next = if i + 1 == capacity { NIL } else { i + 1 };
+            nodes.add(i as usize).write(Node::new(next));
+        }
+
+        (*header).magic.store(MAGIC, Release);
+
+        Ok(Self::from_raw(segment, &layout, capacity, exchangers))
+    }
+
+    /// Attach to the segment of `len` bytes at `segment`, initialized via
+    /// [`ShmStack::init`], possibly by another process.
+    ///
+    /// # Safety
+    ///
+    /// The segment must be valid for reads and writes for `'a` and only be
+    /// accessed via [`ShmStack`]s of the same item type. `T` must contain no
+    /// pointers nor references, see [`Pod`].
+    pub unsafe fn attach(segment: *mut u8, len: usize) -> Result<Self, ShmError> {
+        if len < mem::size_of::<Header>() {
+            return Err(ShmError::TooSmall {
+                required: mem::size_of::<Header>(),
+                actual: len,
+            });
+        }
+        if segment.align_offset(mem::align_of::<Header>()) != 0 {
+            return Err(ShmError::Misaligned);
+        }
+
+        let header = &*(segment as *const Header);
+        if header.magic.load(Acquire) != MAGIC {
+            return Err(ShmError::NotInitialized);
+        }
+        if header.item_size != mem::size_of::<T>() as u64
+            || header.item_align != mem::align_of::<T>() as u64
+        {
+            return Err(ShmError::ItemMismatch);
+        }
+
+        let (capacity, exchangers) = (header.capacity, header.exchangers);
+        let layout = Self::check(segment, len, capacity, exchangers)?;
+        Ok(Self::from_raw(segment, &layout, capacity, exchangers))
+    }
+
+    fn check(
+        segment: *mut u8,
+        len: usize,
+        capacity: u32,
+        exchangers: u32,
+    ) -> Result<SegmentLayout, ShmError> {
+        let layout = Self::layout(capacity, exchangers)?;
+        if len < layout.layout.size() {
+            return Err(ShmError::TooSmall {
+                required: layout.layout.size(),
+                actual: len,
+            });
+        }
+        if segment.align_offset(layout.layout.align()) != 0 {
+            return Err(ShmError::Misaligned);
+        }
+        Ok(layout)
+    }
+
+    /// # Safety
+    ///
+    /// See [`ShmStack::attach`], the segment being initialized.
+    unsafe fn from_raw(
+        segment: *mut u8,
+        layout: &SegmentLayout,
+        capacity: u32,
+        exchangers: u32,
+    ) -> Self {
+        ShmStack {
+            header: &*(segment as *const Header),
+            slots: slice::from_raw_parts(
+                segment.add(layout.slots) as *const AtomicU64,
+                exchangers as usize,
+            ),
+            nodes: slice::from_raw_parts(
+                segment.add(layout.nodes) as *const Node<T>,
+                capacity as usize,
+            ),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Maximum number of items on the stack.
+    pub fn capacity(&self) -> u32 {
+        self.nodes.len() as u32
+    }
+
+    /// Push an item, handing it back if the stack is at its capacity.
+    pub fn push(&self, item: T) -> Result<(), T> {
+        let lists = self.lists();
+        let node = match lists.alloc() {
+            Some(node) => node,
+            None => return Err(item),
+        };
+        // Safe given that the node is owned by this operation until linked.
+        unsafe { lists.push(node, item, &mut PushS::new()) };
+        Ok(())
+    }
+
+    /// Pop an item, `None` if the stack was found empty.
+    pub fn pop(&self) -> Option<T> {
+        self.lists().pop(&mut PopS::new())
+    }
+
+    fn lists(&self) -> Lists<'a, T, [Node<T>]> {
+        Lists::new(&self.header.head, &self.header.free, self.slots, self.nodes)
+    }
+}
+
+impl<T, PushS, PopS> fmt::Debug for ShmStack<'_, T, PushS, PopS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShmStack")
+            .field("capacity", &self.nodes.len())
+            .field("exchangers", &self.slots.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::thread;
+
+    /// Zeroed stand-in for a shared memory segment.
+    fn segment(capacity: u32, exchangers: u32) -> (Vec<u64>, usize) {
+        let size = ShmStack::<u64>::required_size(capacity, exchangers).unwrap();
+        (vec![0; size.div_ceil(8)], size)
+    }
+
+    #[test]
+    fn lifo_bounded_by_capacity() {
+        let (mut memory, size) = segment(3, 1);
+        let stack = unsafe { ShmStack::<u64>::init(memory.as_mut_ptr() as _, size, 3, 1) }.unwrap();
+
+        assert_eq!(stack.pop(), None);
+        for i in 0..3 {
+            stack.push(i).unwrap();
+        }
+        assert_eq!(stack.push(3), Err(3));
+
+        assert_eq!(stack.pop(), Some(2));
+        stack.push(4).unwrap();
+        assert_eq!(stack.pop(), Some(4));
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), Some(0));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn attach_validates_segment() {
+        let (mut memory, size) = segment(4, 2);
+        let ptr = memory.as_mut_ptr() as *mut u8;
+
+        assert_eq!(
+            unsafe { ShmStack::<u64>::attach(ptr, size) }.unwrap_err(),
+            ShmError::NotInitialized
+        );
+        assert!(matches!(
+            unsafe { ShmStack::<u64>::init(ptr, size - 1, 4, 2) },
+            Err(ShmError::TooSmall { .. })
+        ));
+        assert_eq!(
+            unsafe { ShmStack::<u64>::init(ptr.wrapping_add(1), size - 1, 4, 0) }.unwrap_err(),
+            ShmError::InvalidLayout
+        );
+
+        unsafe { ShmStack::<u64>::init(ptr, size, 4, 2) }.unwrap();
+        assert_eq!(
+            unsafe { ShmStack::<u32>::attach(ptr, size) }.unwrap_err(),
+            ShmError::ItemMismatch
+        );
+        let stack = unsafe { ShmStack::<u64>::attach(ptr, size) }.unwrap();
+        assert_eq!(stack.capacity(), 4);
+    }
+
+    /// Links are indices, thus the segment works at any address, like one
+    /// mapped at different addresses by different processes.
+    #[test]
+    fn position_independent() {
+        let (mut memory, size) = segment(4, 1);
+        let stack = unsafe { ShmStack::<u64>::init(memory.as_mut_ptr() as _, size, 4, 1) }.unwrap();
+        stack.push(1).unwrap();
+        stack.push(2).unwrap();
+
+        let mut moved = memory.clone();
+        drop(memory);
+        let stack = unsafe { ShmStack::<u64>::attach(moved.as_mut_ptr() as _, size) }.unwrap();
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.pop(), Some(1));
+    }
+
+    #[test]
+    fn concurrent_no_duplicates_no_losses() {
+        let (threads, items_per_thread) = (4, 10_000);
+        let (mut memory, size) = segment(64, 4);
+        let ptr = memory.as_mut_ptr() as usize;
+        unsafe { ShmStack::<u64>::init(ptr as _, size, 64, 4) }.unwrap();
+
+        let popped = Mutex::new(vec![]);
+        thread::scope(|scope| {
+            for thread in 0..threads {
+                let popped = &popped;
+                scope.spawn(move || {
+                    // Each thread attached separately, like a process.
+                    let stack = unsafe { ShmStack::<u64>::attach(ptr as _, size) }.unwrap();
+                    let mut own = vec![];
+                    for i in 0..items_per_thread {
+                        let mut item = thread * items_per_thread + i;
+                        while let Err(i) = stack.push(item) {
+                            // Full, make room.
+                            own.extend(stack.pop());
+                            item = i;
+                        }
+                        own.extend(stack.pop());
+                    }
+                    popped.lock().unwrap().extend(own);
+                });
+            }
+        });
+
+        let stack = unsafe { ShmStack::<u64>::attach(ptr as _, size) }.unwrap();
+        let mut popped = popped.into_inner().unwrap();
+        popped.extend(std::iter::from_fn(|| stack.pop()));
+        popped.sort_unstable();
+        assert_eq!(popped, (0..threads * items_per_thread).collect::<Vec<_>>());
+    }
+}
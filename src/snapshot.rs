@@ -0,0 +1,152 @@
+//! Saving and restoring a [`Stack`], e.g. across a rolling restart, see
+//! [`Stack::checkpoint`] and [`Stack::restore`].
+
+use crate::conservation::Ledger;
+use crate::{Config, PopStrategy, PushStrategy, Stack};
+
+/// Items and configuration of a [`Stack`] at the time of
+/// [`Stack::checkpoint`], from which [`Stack::restore`] reconstructs an
+/// equivalent stack.
+///
+/// Serializable with the `serde` feature, e.g. to hand the remaining work of
+/// a service instance shutting down to the instance replacing it.
+///
+/// Neither the strategies, fixed by the type parameters, nor any
+/// [`crate::ItemHooks`] are part of the snapshot.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StackSnapshot<T> {
+    /// The items, bottom first, thus the last item is the top of the stack,
+    /// like [`Stack::with_items`] expects.
+    pub items: Vec<T>,
+    /// The configuration in effect. [`Config::min_parallelism`] is `1`, with
+    /// [`Config::elimination`] telling whether the stack considered the
+    /// elimination array, thus a restored stack behaves the same regardless
+    /// of the parallelism of the machine it is restored on. With the
+    /// `no-elimination` feature [`Config::resize`] is the default, given that
+    /// there is no elimination array to configure.
+    pub config: Config,
+}
+
+impl<T, PushS, PopS> Stack<T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    /// Capture the items and the configuration of the stack, leaving the
+    /// stack untouched.
+    ///
+    /// Consistent, given that exclusive access rules out any operation in
+    /// flight, e.g. once the other handles of an [`Arc`](std::sync::Arc) are
+    /// gone after stopping producers and consumers. Without stopping them,
+    /// see [`Stack::clone_contents`] for a weakly consistent copy of the
+    /// items instead.
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let mut stack = Stack::<String>::builder().exchangers(2).build();
+    /// stack.push("older".to_string());
+    /// stack.push("newer".to_string());
+    ///
+    /// let snapshot = stack.checkpoint();
+    /// let restored = Stack::<String>::restore(snapshot);
+    /// assert_eq!(restored.pop().as_deref(), Some("newer"));
+    /// assert_eq!(restored.pop().as_deref(), Some("older"));
+    /// ```
+    pub fn checkpoint(&mut self) -> StackSnapshot<T>
+    where
+        T: Clone,
+    {
+        let mut items = Vec::with_capacity(self.stack.len());
+        self.stack
+            .for_each_exclusive(|item| items.push(Ledger::peek(item).clone()));
+        items.reverse();
+
+        StackSnapshot {
+            items,
+            config: Config {
+                resize: self.elimination_array.resize_config(),
+                tuning: self.tuning,
+                elimination: self.elimination,
+                min_parallelism: 1,
+            },
+        }
+    }
+
+    /// Reconstruct a stack from a [`Stack::checkpoint`], holding the same
+    /// items in the same order and configured the same.
+    ///
+    /// # Panics
+    ///
+    /// Panics on an invalid configuration, see [`Stack::with_config`], e.g.
+    /// of a snapshot deserialized from a tampered file.
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn restore(snapshot: StackSnapshot<T>) -> Self {
+        let mut stack = Stack::with_config(snapshot.config);
+        for item in snapshot.items {
+            stack.push_mut(item);
+        }
+        stack
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::Tuning;
+    use crate::ResizeConfig;
+
+    #[test]
+    fn restores_items_and_config() {
+        let config = Config {
+            resize: ResizeConfig::fixed(3),
+            tuning: Tuning {
+                retry_limit: 7,
+                ..Tuning::default()
+            },
+            elimination: false,
+            ..Config::default()
+        };
+        let mut stack = Stack::<Vec<u8>>::with_config(config.clone());
+        for i in 0..10 {
+            stack.push(vec![i; 2]);
+        }
+
+        let snapshot = stack.checkpoint();
+        assert_eq!(stack.len(), 10, "checkpoint leaves the items in place");
+        assert_eq!(
+            snapshot.config,
+            Config {
+                resize: if cfg!(feature = "no-elimination") {
+                    ResizeConfig::default()
+                } else {
+                    config.resize.clone()
+                },
+                min_parallelism: 1,
+                ..config
+            }
+        );
+
+        let mut restored = Stack::<Vec<u8>>::restore(snapshot.clone());
+        assert_eq!(restored.checkpoint(), snapshot);
+        assert!(!restored.elimination);
+        assert_eq!(restored.take_all(), stack.take_all());
+    }
+
+    #[test]
+    fn zero_sized_items() {
+        let mut stack = Stack::<()>::with_items(vec![(); 3]);
+        let restored = Stack::<()>::restore(stack.checkpoint());
+        assert_eq!(restored.len(), 3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn round_trips_through_serde() {
+        let mut stack = Stack::<String>::with_items(vec!["a".to_string(), "b".to_string()]);
+        let json = serde_json::to_string(&stack.checkpoint()).unwrap();
+
+        let restored = Stack::<String>::restore(serde_json::from_str(&json).unwrap());
+        assert_eq!(restored.take_all(), vec!["b", "a"]);
+    }
+}
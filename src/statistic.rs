@@ -1,20 +1,402 @@
-use crate::event::{Event, print_padded};
+//! Contention report summarizing the events recorded by instrumented push and
+//! pop operations, see [`crate::Stack::instrumented_push`].
+//!
+//! Available with the `serde` feature, thus a CI job can store the
+//! [`Report::to_json`] of a benchmark run and diff it against the one of the
+//! previous commit, see `benches/lib.rs`.
 
-pub(crate) fn print_report(events: Vec<Event>) {
-    let operations = split_by_operation(events);
-    println!("# operations: {:?}\n", operations.len());
+use crate::event::{write_padded, Event, OperationId};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::time::Instant;
 
-    let (push_ops, pop_ops) = seperate_push_and_pop(operations);
-    println!("# push ops: {:?}", push_ops.len());
-    println!("# pop ops: {:?}\n", pop_ops.len());
+#[cfg(test)]
+pub(crate) fn print_report(events: Vec<Event>, timed: &[TimedOperation]) {
+    println!("{}", Report::with_timed_operations(events, timed));
+}
+
+/// Summary of a recorded event trace.
+///
+/// Field names are considered stable, given that they end up in the output of
+/// [`Report::to_json`], which is meant to be stored and diffed across commits.
+///
+/// ```rust
+/// # use elimination_backoff_stack::statistic::Report;
+/// # use elimination_backoff_stack::Stack;
+/// let stack = Stack::<u8>::new();
+/// let mut events = vec![];
+/// stack.instrumented_push(1, &mut events);
+/// stack.instrumented_pop(&mut events);
+///
+/// let report = Report::new(events);
+/// assert_eq!(report.push_operations, 1);
+/// # #[cfg(feature = "serde")]
+/// assert!(report.to_json().starts_with('{'));
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Report {
+    pub operations: usize,
+    pub push_operations: usize,
+    pub pop_operations: usize,
+    /// Number of events of the longest push operation, not counting strategy
+    /// decisions.
+    pub longest_push_operation: usize,
+    /// Number of events of the longest pop operation, not counting strategy
+    /// decisions.
+    pub longest_pop_operation: usize,
+    /// Number of strategy decisions of the longest push operation, only
+    /// recorded for traced strategies, see [`Event::Decision`].
+    pub longest_push_decisions: usize,
+    /// Number of strategy decisions of the longest pop operation.
+    pub longest_pop_decisions: usize,
+    /// Pop operations by the index of the thread that started them.
+    pub pops_per_thread: BTreeMap<usize, ThreadPops>,
+    /// Gini coefficient of the successful pop operations across all threads
+    /// popping. 0 if each thread popped the same number of items, approaching
+    /// 1 if a single thread monopolized the stack.
+    pub pop_gini: f64,
+    /// Deviation of the popped order from LIFO, see [`OrderingInversions`].
+    /// Only known given timed operations, see
+    /// [`Report::with_timed_operations`].
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub ordering: Option<OrderingInversions>,
+    /// Heap allocations per operation, see [`Allocations`]. Only known given
+    /// timed operations.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub allocations: Option<Allocations>,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    longest_push_trace: Vec<Event>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    longest_pop_trace: Vec<Event>,
+}
+
+/// Pop operations of a single thread.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ThreadPops {
+    pub operations: usize,
+    /// Number of operations returning an item.
+    pub successes: usize,
+}
+
+impl Report {
+    pub fn new(events: Vec<Event>) -> Self {
+        let operations = split_by_operation(events);
+        let num_operations = operations.len();
+
+        let (mut push_ops, mut pop_ops) = seperate_push_and_pop(operations);
+
+        let pops_per_thread = pops_per_thread(&pop_ops);
+        let pop_gini = gini(pops_per_thread.values().map(|pops| pops.successes));
+
+        let longest_push_trace = take_longest_operation(&mut push_ops);
+        let longest_pop_trace = take_longest_operation(&mut pop_ops);
 
-    println!("longest push op: {:?}", longest_operation(&push_ops));
-    push_ops[index_longest_operation(&push_ops)].iter().for_each(print_padded);
-    println!("");
+        let (push_decisions, pop_decisions) = (
+            decisions(&longest_push_trace),
+            decisions(&longest_pop_trace),
+        );
+
+        Report {
+            operations: num_operations,
+            push_operations: push_ops.len(),
+            pop_operations: pop_ops.len(),
+            longest_push_operation: longest_push_trace.len() - push_decisions,
+            longest_pop_operation: longest_pop_trace.len() - pop_decisions,
+            longest_push_decisions: push_decisions,
+            longest_pop_decisions: pop_decisions,
+            pops_per_thread,
+            pop_gini,
+            ordering: None,
+            allocations: None,
+            longest_push_trace,
+            longest_pop_trace,
+        }
+    }
+
+    /// Like [`Report::new`], though including the [`OrderingInversions`] and
+    /// the [`Allocations`] of the `timed` operations, whose events are part of
+    /// `events`.
+    pub fn with_timed_operations(events: Vec<Event>, timed: &[TimedOperation]) -> Self {
+        let ordering = OrderingInversions::new(&events, timed);
+
+        Report {
+            ordering: Some(ordering),
+            allocations: Allocations::new(timed),
+            ..Report::new(events)
+        }
+    }
+
+    /// Serialize the report, leaving out the traces of the longest
+    /// operations.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("report to be serializable")
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# operations: {:?}\n", self.operations)?;
+
+        writeln!(f, "# push ops: {:?}", self.push_operations)?;
+        writeln!(f, "# pop ops: {:?}\n", self.pop_operations)?;
+
+        // Traces include the strategy decisions interleaved, explaining why
+        // the operation went where it went.
+        writeln!(
+            f,
+            "longest push op: {:?} ({} decisions)",
+            self.longest_push_operation, self.longest_push_decisions,
+        )?;
+        for e in &self.longest_push_trace {
+            write_padded(f, e)?;
+        }
+        writeln!(f)?;
+
+        writeln!(
+            f,
+            "longest pop op: {:?} ({} decisions)\n",
+            self.longest_pop_operation, self.longest_pop_decisions,
+        )?;
+        for e in &self.longest_pop_trace {
+            write_padded(f, e)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "pops per thread (successful / total):")?;
+        for (thread, pops) in &self.pops_per_thread {
+            writeln!(f, "\t{}: {} / {}", thread, pops.successes, pops.operations)?;
+        }
+        writeln!(f, "pop fairness (gini): {:.3}", self.pop_gini)?;
+
+        if let Some(ordering) = &self.ordering {
+            writeln!(f, "\nordering inversions:")?;
+            writeln!(f, "\teliminated: {}", ordering.eliminated)?;
+            writeln!(f, "\tstack: {}", ordering.stack)?;
+        }
+
+        if let Some(allocations) = &self.allocations {
+            writeln!(f, "\nallocations per op:")?;
+            writeln!(f, "\tpush: {:.3}", allocations.per_push)?;
+            writeln!(f, "\tpop: {:.3}", allocations.per_pop)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Push or pop operation timed by the caller, the item identified by a number
+/// unique across all pushed items.
+#[derive(Clone, Debug)]
+pub struct TimedOperation {
+    /// As recorded in the `StartPush` or `StartPop` event of the operation.
+    pub id: OperationId,
+    pub start: Instant,
+    pub end: Instant,
+    pub kind: TimedKind,
+    /// Heap allocations of the calling thread between `start` and `end`, see
+    /// [`crate::event::Operation::allocations`]. `None` unless counted.
+    pub allocations: Option<u64>,
+}
 
-    println!("longest pop op: {:?}\n", longest_operation(&pop_ops));
-    pop_ops[index_longest_operation(&pop_ops)].iter().for_each(print_padded);
-    println!("");
+#[derive(Clone, Debug)]
+pub enum TimedKind {
+    Push(usize),
+    Pop(Option<usize>),
+}
+
+/// Mean heap allocations of push and pop operations, i.e. of nodes, of
+/// exchanger states and of epoch garbage, not counting the ones of the event
+/// recorder.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Allocations {
+    pub per_push: f64,
+    pub per_pop: f64,
+}
+
+impl Allocations {
+    /// `None` unless the allocations of every operation were counted.
+    fn new(timed: &[TimedOperation]) -> Option<Self> {
+        let per_op = |is_push: bool| {
+            let (operations, allocations) = timed
+                .iter()
+                .filter(|o| matches!(o.kind, TimedKind::Push(_)) == is_push)
+                .try_fold((0u64, 0u64), |(n, a), o| Some((n + 1, a + o.allocations?)))?;
+            Some(allocations as f64 / operations.max(1) as f64)
+        };
+
+        Some(Allocations {
+            per_push: per_op(true)?,
+            per_pop: per_op(false)?,
+        })
+    }
+}
+
+/// How far the popped order deviates from LIFO, separately for pop operations
+/// exchanging their item on the elimination array and pop operations taking
+/// their item from the Treiber stack, thus quantifying the semantic cost of
+/// elimination for a workload.
+///
+/// A pop operation is inverted if, when it started, the stack held items
+/// younger than the one it popped, i.e. items whose push operation completed
+/// after the one of the popped item and whose pop operation had not started.
+/// Its distance is the number of such younger items. Operations overlapping
+/// in time have no order, thus concurrent operations never count as
+/// inversions.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OrderingInversions {
+    pub eliminated: Inversions,
+    pub stack: Inversions,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Inversions {
+    /// Pop operations returning an item.
+    pub pops: usize,
+    /// Pop operations returning an item while younger ones were on the stack.
+    pub inverted: usize,
+    /// Largest distance of a single pop operation.
+    pub max_distance: usize,
+    /// Distances summed across all pop operations.
+    pub total_distance: usize,
+}
+
+impl Inversions {
+    fn record(&mut self, distance: usize) {
+        self.pops += 1;
+        if distance > 0 {
+            self.inverted += 1;
+        }
+        self.max_distance = self.max_distance.max(distance);
+        self.total_distance += distance;
+    }
+}
+
+impl fmt::Display for Inversions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} / {} pops inverted, distance mean {:.3} max {}",
+            self.inverted,
+            self.pops,
+            self.total_distance as f64 / self.pops.max(1) as f64,
+            self.max_distance,
+        )
+    }
+}
+
+impl OrderingInversions {
+    fn new(events: &[Event], timed: &[TimedOperation]) -> Self {
+        // Pop operations that exchanged their item on the elimination array.
+        let mut eliminated = HashSet::new();
+        let mut current = None;
+        for event in events {
+            match event {
+                Event::StartPush(_) => current = None,
+                Event::StartPop(id) => current = Some(*id),
+                Event::ExchangedWith(_) => eliminated.extend(current),
+                _ => {}
+            }
+        }
+
+        // Items by the completion order of their push operation.
+        let mut pushes = timed
+            .iter()
+            .filter_map(|o| match o.kind {
+                TimedKind::Push(item) => Some((o.end, item)),
+                TimedKind::Pop(_) => None,
+            })
+            .collect::<Vec<_>>();
+        pushes.sort();
+        let ranks = pushes
+            .iter()
+            .enumerate()
+            .map(|(rank, (_, item))| (*item, rank))
+            .collect::<HashMap<_, _>>();
+
+        // Completed push operations and started pop operations in time order,
+        // the former first on ties.
+        let mut steps = timed
+            .iter()
+            .filter_map(|o| match o.kind {
+                TimedKind::Push(item) => Some((o.end, 0, item, o.id)),
+                TimedKind::Pop(Some(item)) => Some((o.start, 1, item, o.id)),
+                TimedKind::Pop(None) => None,
+            })
+            .collect::<Vec<_>>();
+        steps.sort_by_key(|(at, order, _, _)| (*at, *order));
+
+        let len = ranks.len();
+        let mut on_stack = Fenwick::new(len);
+        let mut popped = HashSet::new();
+        let mut ordering = OrderingInversions::default();
+
+        for (_, order, item, id) in steps {
+            let rank = match ranks.get(&item) {
+                Some(rank) => *rank,
+                None => continue,
+            };
+
+            if order == 0 {
+                if !popped.contains(&item) {
+                    on_stack.add(rank, 1);
+                }
+                continue;
+            }
+
+            let distance = (on_stack.prefix(len) - on_stack.prefix(rank + 1)) as usize;
+            if popped.insert(item) && on_stack.prefix(rank + 1) > on_stack.prefix(rank) {
+                on_stack.add(rank, -1);
+            }
+
+            if eliminated.contains(&id) {
+                ordering.eliminated.record(distance);
+            } else {
+                ordering.stack.record(distance);
+            }
+        }
+
+        ordering
+    }
+}
+
+/// Fenwick tree, counting items per rank with logarithmic updates and prefix
+/// sums.
+struct Fenwick {
+    tree: Vec<i64>,
+}
+
+impl Fenwick {
+    fn new(len: usize) -> Self {
+        Fenwick {
+            tree: vec![0; len + 1],
+        }
+    }
+
+    fn add(&mut self, index: usize, delta: i64) {
+        let mut i = index + 1;
+        while i < self.tree.len() {
+            self.tree[i] += delta;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// Sum of the counts of `0..end`.
+    fn prefix(&self, end: usize) -> i64 {
+        let mut sum = 0;
+        let mut i = end;
+        while i > 0 {
+            sum += self.tree[i];
+            i -= i & i.wrapping_neg();
+        }
+        sum
+    }
 }
 
 enum Operation {
@@ -31,47 +413,393 @@ impl Operation {
     }
 }
 
+/// Split `events` at each `StartPush` and `StartPop` event. Events before
+/// the first one, e.g. of an operation in flight when recording started, are
+/// dropped, given that their operation is unknown.
 fn split_by_operation(events: Vec<Event>) -> Vec<Operation> {
     events.into_iter().fold(vec![], |mut acc, event| {
-        let len = acc.len();
         match event {
-            e @ Event::StartPush => acc.push(Operation::Push(vec![e])),
-            e @ Event::StartPop => acc.push(Operation::Pop(vec![e])),
-            e @ _ => acc[len - 1].push(e),
+            e @ Event::StartPush(_) => acc.push(Operation::Push(vec![e])),
+            e @ Event::StartPop(_) => acc.push(Operation::Pop(vec![e])),
+            e => {
+                if let Some(operation) = acc.last_mut() {
+                    operation.push(e);
+                }
+            }
         };
         acc
     })
 }
 
 fn seperate_push_and_pop(operations: Vec<Operation>) -> (Vec<Vec<Event>>, Vec<Vec<Event>>) {
-    operations.into_iter().fold((vec![], vec![]), |mut acc, operation| {
-        match operation {
-            Operation::Push(events) => acc.0.push(events),
-            Operation::Pop(events) => acc.1.push(events),
-        };
+    operations
+        .into_iter()
+        .fold((vec![], vec![]), |mut acc, operation| {
+            match operation {
+                Operation::Push(events) => acc.0.push(events),
+                Operation::Pop(events) => acc.1.push(events),
+            };
 
-        acc
-    })
+            acc
+        })
 }
 
-fn longest_operation(operations: &Vec<Vec<Event>>) -> usize {
-    operations.iter().fold(0, |acc, o| {
-        if o.len() > acc {
-            o.len()
-        } else {
-            acc
+fn pops_per_thread(pop_ops: &[Vec<Event>]) -> BTreeMap<usize, ThreadPops> {
+    let mut pops_per_thread = BTreeMap::<_, ThreadPops>::new();
+
+    for events in pop_ops {
+        let thread = match events.first() {
+            Some(Event::StartPop(id)) => id.thread_id(),
+            _ => unreachable!("pop operation to start with `StartPop`"),
+        };
+        let pops = pops_per_thread.entry(thread).or_default();
+
+        pops.operations += 1;
+        if let Some(Event::FinishPop(true)) = events.last() {
+            pops.successes += 1;
         }
-    })
+    }
+
+    pops_per_thread
 }
 
-fn index_longest_operation(operations: &Vec<Vec<Event>>) -> usize {
-    let (index, _) = operations.iter().enumerate().fold((0, 0), |(acc_index, acc_len), (o_index, o)| {
-        if o.len() > acc_len {
-            (o_index, o.len())
-        } else {
-            (acc_index, acc_len)
-        }
-    });
+/// Gini coefficient of `values`, i.e. the mean absolute difference of all
+/// pairs relative to twice the mean. 0 for no or all zero values.
+fn gini(values: impl Iterator<Item = usize>) -> f64 {
+    let mut values: Vec<f64> = values.map(|v| v as f64).collect();
+    let n = values.len() as f64;
+    let sum: f64 = values.iter().sum();
+    if sum == 0.0 {
+        return 0.0;
+    }
+
+    // With sorted values the sum over all pairs reduces to a weighted sum.
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let weighted: f64 = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (2.0 * (i as f64 + 1.0) - n - 1.0) * v)
+        .sum();
+
+    weighted / (n * sum)
+}
+
+/// Number of strategy decisions among `events`.
+fn decisions(events: &[Event]) -> usize {
+    events
+        .iter()
+        .filter(|e| matches!(e, Event::Decision(..)))
+        .count()
+}
+
+/// Take the events of the longest operation, leaving an empty trace in its
+/// place. Returns an empty trace if there are no operations.
+///
+/// Length is measured without strategy decisions, thus the operation
+/// bouncing between the Treiber stack and the elimination array the longest
+/// is picked whether its strategy is traced or not.
+fn take_longest_operation(operations: &mut [Vec<Event>]) -> Vec<Event> {
+    let (index, _) =
+        operations
+            .iter()
+            .enumerate()
+            .fold((0, 0), |(acc_index, acc_len), (o_index, o)| {
+                let len = o.len() - decisions(o);
+                if len > acc_len {
+                    (o_index, len)
+                } else {
+                    (acc_index, acc_len)
+                }
+            });
+
+    operations
+        .get_mut(index)
+        .map(std::mem::take)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{Decision, OperationId};
+
+    fn trace() -> Vec<Event> {
+        vec![
+            Event::StartPush(OperationId::next()),
+            Event::TryStack,
+            Event::FinishPush,
+            Event::StartPop(OperationId::next()),
+            Event::TryStack,
+            Event::TryEliminationArray,
+            Event::StartEliminationArrayPop,
+            Event::FinishPop(true),
+            Event::StartPop(OperationId::next()),
+            Event::TryStack,
+            Event::FinishPop(false),
+        ]
+    }
+
+    #[test]
+    fn report_summarizes_trace() {
+        let report = Report::new(trace());
+
+        assert_eq!(report.operations, 3);
+        assert_eq!(report.push_operations, 1);
+        assert_eq!(report.pop_operations, 2);
+        assert_eq!(report.longest_push_operation, 3);
+        assert_eq!(report.longest_pop_operation, 5);
+
+        assert!(report
+            .to_string()
+            .starts_with("# operations: 3\n\n# push ops: 1\n# pop ops: 2\n"));
+    }
+
+    #[test]
+    fn longest_operation_includes_decisions() {
+        let decision = |d, permitted| Event::Decision(d, permitted);
+        let events = vec![
+            Event::StartPop(OperationId::next()),
+            decision(Decision::TryStack, true),
+            decision(Decision::TryStack, false),
+            Event::TryStack,
+            decision(Decision::UseEliminationArray, true),
+            Event::TryEliminationArray,
+            Event::FinishPop(false),
+            // Longer only counting decisions.
+            Event::StartPop(OperationId::next()),
+            decision(Decision::TryStack, true),
+            decision(Decision::TryStack, true),
+            decision(Decision::TryStack, true),
+            decision(Decision::TryStack, true),
+            Event::TryStack,
+            Event::FinishPop(true),
+        ];
 
-    index
+        let report = Report::new(events);
+
+        assert_eq!(report.longest_pop_operation, 4);
+        assert_eq!(report.longest_pop_decisions, 3);
+        let report = report.to_string();
+        assert!(
+            report.contains("longest pop op: 4 (3 decisions)\n"),
+            "{}",
+            report
+        );
+        assert!(
+            report.contains("\t\tDecision(TryStack, false)\n\tTryStack\n"),
+            "{}",
+            report
+        );
+    }
+
+    #[test]
+    fn report_pops_per_thread() {
+        let report = Report::new(trace());
+        let thread = OperationId::next().thread_id();
+
+        assert_eq!(
+            report.pops_per_thread.get(&thread),
+            Some(&ThreadPops {
+                operations: 2,
+                successes: 1,
+            })
+        );
+        assert_eq!(report.pop_gini, 0.0);
+    }
+
+    #[test]
+    fn gini_index() {
+        assert_eq!(gini(vec![].into_iter()), 0.0);
+        assert_eq!(gini(vec![0, 0].into_iter()), 0.0);
+        assert_eq!(gini(vec![5, 5, 5, 5].into_iter()), 0.0);
+        assert_eq!(gini(vec![0, 0, 0, 8].into_iter()), 0.75);
+        assert!((gini(vec![1, 2, 3].into_iter()) - 2.0 / 9.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ordering_inversions() {
+        let base = Instant::now();
+        let at = |ms| base + std::time::Duration::from_millis(ms);
+        let timed = |start, end, kind| TimedOperation {
+            id: OperationId::next(),
+            start: at(start),
+            end: at(end),
+            kind,
+            allocations: Some(0),
+        };
+
+        let operations = vec![
+            timed(0, 1, TimedKind::Push(1)),
+            timed(2, 3, TimedKind::Push(2)),
+            timed(4, 5, TimedKind::Push(3)),
+            // Pops 1 while 2 and 3 are younger, thus distance 2.
+            timed(6, 7, TimedKind::Pop(Some(1))),
+            // Exchanged with a concurrent push, thus no inversion.
+            timed(8, 10, TimedKind::Push(4)),
+            timed(9, 10, TimedKind::Pop(Some(4))),
+            // 3 is the youngest.
+            timed(11, 12, TimedKind::Pop(Some(3))),
+            timed(13, 14, TimedKind::Pop(None)),
+        ];
+        let eliminated = operations[5].id;
+        let events = vec![
+            Event::StartPop(eliminated),
+            Event::ExchangedWith(operations[4].id),
+            Event::FinishPop(true),
+        ];
+
+        let report = Report::with_timed_operations(events, &operations);
+
+        assert_eq!(
+            report.ordering,
+            Some(OrderingInversions {
+                eliminated: Inversions {
+                    pops: 1,
+                    inverted: 0,
+                    max_distance: 0,
+                    total_distance: 0,
+                },
+                stack: Inversions {
+                    pops: 2,
+                    inverted: 1,
+                    max_distance: 2,
+                    total_distance: 2,
+                },
+            })
+        );
+        assert!(report
+            .to_string()
+            .contains("stack: 1 / 2 pops inverted, distance mean 1.000 max 2"));
+    }
+
+    #[test]
+    fn allocations_per_operation() {
+        let now = Instant::now();
+        let timed = |kind, allocations| TimedOperation {
+            id: OperationId::next(),
+            start: now,
+            end: now,
+            kind,
+            allocations: Some(allocations),
+        };
+
+        let operations = vec![
+            timed(TimedKind::Push(1), 1),
+            timed(TimedKind::Push(2), 2),
+            timed(TimedKind::Pop(Some(2)), 0),
+        ];
+        let report = Report::with_timed_operations(vec![], &operations);
+
+        assert_eq!(
+            report.allocations,
+            Some(Allocations {
+                per_push: 1.5,
+                per_pop: 0.0,
+            })
+        );
+        assert!(report.to_string().contains("push: 1.500"));
+        assert_eq!(Report::new(vec![]).allocations, None);
+
+        let uncounted = vec![
+            timed(TimedKind::Push(1), 1),
+            TimedOperation {
+                allocations: None,
+                ..timed(TimedKind::Pop(Some(1)), 0)
+            },
+        ];
+        assert_eq!(
+            Report::with_timed_operations(vec![], &uncounted).allocations,
+            None
+        );
+    }
+
+    #[test]
+    fn report_golden_output() {
+        let events = trace();
+        let id = |i: usize| match &events[i] {
+            Event::StartPush(id) | Event::StartPop(id) => *id,
+            _ => unreachable!(),
+        };
+        let (push, pop) = (id(0), id(3));
+
+        assert_eq!(
+            Report::new(events.clone()).to_string(),
+            format!(
+                "# operations: 3\n\n\
+                 # push ops: 1\n\
+                 # pop ops: 2\n\n\
+                 longest push op: 3 (0 decisions)\n\
+                 {:?}\n\
+                 \tTryStack\n\
+                 FinishPush\n\
+                 \n\
+                 longest pop op: 5 (0 decisions)\n\n\
+                 {:?}\n\
+                 \tTryStack\n\
+                 \tTryEliminationArray\n\
+                 \t\tStartEliminationArrayPop\n\
+                 FinishPop(true)\n\
+                 \n\
+                 pops per thread (successful / total):\n\
+                 \t{}: 1 / 2\n\
+                 pop fairness (gini): 0.000\n",
+                Event::StartPush(push),
+                Event::StartPop(pop),
+                pop.thread_id(),
+            )
+        );
+    }
+
+    #[test]
+    fn split_by_operation_golden() {
+        let operations = split_by_operation(trace())
+            .into_iter()
+            .map(|operation| match operation {
+                Operation::Push(events) => ("push", events.len()),
+                Operation::Pop(events) => ("pop", events.len()),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(operations, vec![("push", 3), ("pop", 5), ("pop", 3)]);
+    }
+
+    #[test]
+    fn events_before_first_start_are_dropped() {
+        let mut events = vec![Event::TryStack, Event::FinishPop(true)];
+        events.extend(trace());
+
+        assert_eq!(split_by_operation(events.clone()).len(), 3);
+        let report = Report::new(events);
+        assert_eq!(report.operations, 3);
+        assert_eq!(report.longest_pop_operation, 5);
+
+        assert_eq!(Report::new(vec![Event::FinishPush]).operations, 0);
+    }
+
+    #[test]
+    fn report_of_empty_trace() {
+        let report = Report::new(vec![]);
+
+        assert_eq!(report.operations, 0);
+        assert_eq!(report.longest_push_operation, 0);
+        assert_eq!(report.longest_pop_operation, 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn report_to_json() {
+        let thread = OperationId::next().thread_id();
+
+        assert_eq!(
+            Report::new(trace()).to_json(),
+            format!(
+                "{{\"operations\":3,\"push_operations\":1,\"pop_operations\":2,\
+                 \"longest_push_operation\":3,\"longest_pop_operation\":5,\
+                 \"longest_push_decisions\":0,\"longest_pop_decisions\":0,\
+                 \"pops_per_thread\":{{\"{}\":{{\"operations\":2,\"successes\":1}}}},\
+                 \"pop_gini\":0.0}}",
+                thread
+            )
+        );
+    }
 }
@@ -15,25 +15,346 @@
 //! >::new();
 //! ```
 //!
+//! Where the strategy is only known at run time, e.g. from a command line flag,
+//! the [`DynStrategy`] dispatches to the one selected via [`Tuning::strategy`].
+//!
+//! Custom strategies implement the [`Strategy`] trait. The traits of the
+//! individual building blocks (Treiber stack, elimination array, exchanger) are
+//! internal and implemented for every [`Strategy`], thus they can evolve
+//! without breaking custom strategies.
+//!
+//! ```rust
+//! # use elimination_backoff_stack::Stack;
+//! # use elimination_backoff_stack::strategy::Strategy;
+//! /// Retry the Treiber stack forever, never eliminating.
+//! struct Spin;
+//!
+//! impl Strategy for Spin {
+//!     fn new() -> Self { Spin }
+//!     fn use_elimination_array(&mut self) -> bool { false }
+//!     fn try_stack(&mut self) -> bool { true }
+//!     fn try_elimination_array(&mut self) -> bool { false }
+//!     fn try_start_exchange(&mut self) -> bool { false }
+//!     fn retry_check_exchanged(&mut self) -> bool { false }
+//!     fn try_exchange(&mut self) -> bool { false }
+//! }
+//!
+//! let stack = Stack::<String, Spin, Spin>::new();
+//! stack.push("item".to_string());
+//! assert_eq!(stack.pop(), Some("item".to_string()));
+//! ```
+//!
+//! Most applications do not need a custom strategy, but merely one of the
+//! [`Preset`]s, each a curated combination of the shipped strategies for a
+//! common scenario.
+//!
+//! ```rust
+//! # use elimination_backoff_stack::Stack;
+//! # use elimination_backoff_stack::strategy::Oversubscribed;
+//! let stack = Stack::<String>::builder().preset(Oversubscribed).build();
+//! ```
+//!
 //! Why at compile time?
 //!
 //! To reduce the overhead introduced through isolated behavior management by
 //! enabling the compiler to do all kinds of things, e.g. constant folding.
+//!
+//! The constants the shipped strategies are tuned with, e.g. how long to wait
+//! on an exchanger, are the exception: a [`Tuning`] is set at run time, e.g.
+//! from a configuration file.
+
+use crate::event::{record_decision, Decision};
+#[cfg(not(feature = "no-elimination"))]
+use crate::exchanger;
+use crate::{elimination_array, treiber_stack, ResizeConfig};
+use std::cell::Cell;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Reason a compare-and-swap of a push or pop operation failed, see
+/// [`Strategy::on_cas_failure`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CasFailure {
+    /// Lost against a concurrent operation, e.g. one changing the head of the
+    /// Treiber stack or another pop operation taking the item on offer.
+    LostRace,
+    /// The exchanger was occupied, either by the item of another push
+    /// operation or by an exchange in progress.
+    SlotBusy,
+    /// The item on offer was withdrawn by its push operation before the pop
+    /// operation could take it.
+    SlotEmptied,
+}
+
+/// Tuning constants of the shipped strategies, configured at run time via
+/// [`crate::Builder::tuning`], e.g. read from a configuration file, instead of
+/// at compile time via the strategy types.
+///
+/// Custom strategies ignore the tuning unless they implement
+/// [`Strategy::with_tuning`].
+///
+/// ```rust
+/// # use elimination_backoff_stack::Stack;
+/// # use elimination_backoff_stack::strategy::Tuning;
+/// let stack = Stack::<u8>::builder()
+///     .exchangers(4)
+///     .tuning(Tuning {
+///         retry_limit: 64,
+///         ..Tuning::default()
+///     })
+///     .build();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Tuning {
+    /// Upper bound on the retry exponent of the [`ExpRetryStrategy`], which
+    /// grows on congestion, doubling the number of exchangers considered and
+    /// the attempts on the elimination array each step. At most
+    /// [`Tuning::MAX_RETRY_EXPONENT`].
+    pub max_retry_exponent: u8,
+    /// Number of checks per retry exponent a push operation of the
+    /// [`ExpRetryStrategy`] waits for a pop operation to take its offered
+    /// item.
+    pub exchange_wait_checks: usize,
+    /// Number of decisions after which [`crate::Stack::try_push`] and
+    /// [`crate::Stack::try_pop`] give up.
+    pub retry_limit: usize,
+    /// Strategy a [`DynStrategy`] dispatches to, for both push and pop
+    /// operations. Ignored by all other strategies.
+    pub strategy: StrategyKind,
+    /// How the number of exchangers considered by the [`ExpRetryStrategy`]
+    /// grows with its retry exponent.
+    pub curve: Curve,
+}
 
-use crate::{
-    elimination_array, exchanger, treiber_stack, PopStrategy as StackPopStrategy,
-    PushStrategy as StackPushStrategy,
-};
+impl Tuning {
+    /// Largest valid [`Tuning::max_retry_exponent`], beyond which the
+    /// attempts on the elimination array overflow on 32-bit targets.
+    pub const MAX_RETRY_EXPONENT: u8 = 16;
+
+    /// # Panics
+    ///
+    /// Panics if `max_retry_exponent` exceeds [`Tuning::MAX_RETRY_EXPONENT`].
+    pub(crate) fn validate(&self) {
+        assert!(
+            self.max_retry_exponent <= Tuning::MAX_RETRY_EXPONENT,
+            "max_retry_exponent must not exceed {}",
+            Tuning::MAX_RETRY_EXPONENT,
+        );
+    }
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning {
+            max_retry_exponent: MAX_RETRY_EXPONENT,
+            exchange_wait_checks: 10,
+            retry_limit: BOUNDED_DECISIONS,
+            strategy: StrategyKind::ExpRetry,
+            curve: Curve::default(),
+        }
+    }
+}
+
+/// Decisions taken by a single push or pop operation on a [`super::Stack`].
+///
+/// A new instance is created for each operation. Given that an instance is
+/// used by either a push or a pop operation, never both, state can be shared
+/// across the push and the pop specific decisions.
+pub trait Strategy {
+    fn new() -> Self;
+
+    /// Create an instance tuned as configured at run time via
+    /// [`crate::Builder::tuning`]. Defaults to [`Strategy::new`], thus
+    /// ignoring the [`Tuning`].
+    fn with_tuning(_tuning: &Tuning) -> Self
+    where
+        Self: Sized,
+    {
+        Self::new()
+    }
+
+    /// Decide whether the stack should try eliminating the operation on the
+    /// elimination array next. Is called each time such elimination is
+    /// possible.
+    fn use_elimination_array(&mut self) -> bool;
+
+    /// Decide whether to (re-)try the compare-and-swap on the Treiber stack.
+    fn try_stack(&mut self) -> bool;
+
+    /// Decide whether to (re-)try a random exchanger of the elimination array.
+    fn try_elimination_array(&mut self) -> bool;
+
+    /// Decide how many of the `total` exchangers should be considered. On low
+    /// contention one could only use the first x exchangers to increase the
+    /// exchange-success rate.
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        total
+    }
+
+    /// Push only: Decide whether to (re-)try offering the item on an exchanger.
+    fn try_start_exchange(&mut self) -> bool;
+
+    /// Push only: Decide whether to keep waiting for a pop operation to take
+    /// the item offered on an exchanger.
+    fn retry_check_exchanged(&mut self) -> bool;
+
+    /// Pop only: Decide whether to (re-)try taking an item from an exchanger.
+    fn try_exchange(&mut self) -> bool;
+
+    /// Pop only: Called when an exchanger is in use by other operations.
+    fn on_contention(&mut self) {}
+
+    /// Pop only: Called when an exchanger has no item on offer.
+    fn on_no_contention(&mut self) {}
+
+    /// Decide whether to overlap retrying the Treiber stack with elimination.
+    ///
+    /// If so, a pop operation, before retrying the Treiber stack, announces its
+    /// interest in an exchanger and tries that exchanger right after a failed
+    /// retry. Push operations in turn prefer exchangers with announced
+    /// interest. Thus a concurrent push operation can rendezvous with a pop
+    /// operation while the latter retries the Treiber stack, instead of
+    /// strictly alternating between the two.
+    fn use_pop_interest(&mut self) -> bool {
+        false
+    }
+
+    /// Called after each failed compare-and-swap, before the next decision.
+    ///
+    /// Enables a strategy to tell losing against an operation of the same
+    /// kind from finding an exchanger busy or emptied, and to adjust where to
+    /// go next accordingly.
+    fn on_cas_failure(&mut self, _reason: CasFailure) {}
+
+    /// Called after each successful compare-and-swap, be it on the Treiber
+    /// stack or on an exchanger.
+    fn on_cas_success(&mut self) {}
+
+    /// Pause after a failed compare-and-swap on the Treiber stack, before the
+    /// next attempt, e.g. spinning or yielding. Retrying right away thrashes
+    /// the cache line of the head of the stack.
+    ///
+    /// Called after [`Strategy::on_cas_failure`].
+    fn after_cas_failure(&mut self) {}
+}
+
+impl<S: Strategy> treiber_stack::PushStrategy for S {
+    #[inline]
+    fn try_push(&mut self) -> bool {
+        self.try_stack()
+    }
+
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        Strategy::on_cas_failure(self, reason)
+    }
+
+    fn after_cas_failure(&mut self) {
+        Strategy::after_cas_failure(self)
+    }
+
+    fn on_cas_success(&mut self) {
+        Strategy::on_cas_success(self)
+    }
+}
+
+impl<S: Strategy> treiber_stack::PopStrategy for S {
+    #[inline]
+    fn try_pop(&mut self) -> bool {
+        self.try_stack()
+    }
+
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        Strategy::on_cas_failure(self, reason)
+    }
+
+    fn after_cas_failure(&mut self) {
+        Strategy::after_cas_failure(self)
+    }
+
+    fn on_cas_success(&mut self) {
+        Strategy::on_cas_success(self)
+    }
+}
+
+impl<S: Strategy> elimination_array::PushStrategy for S {
+    fn try_push(&mut self) -> bool {
+        self.try_elimination_array()
+    }
+
+    fn use_pop_interest(&mut self) -> bool {
+        Strategy::use_pop_interest(self)
+    }
+
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        Strategy::num_exchangers(self, total)
+    }
+}
+
+impl<S: Strategy> elimination_array::PopStrategy for S {
+    fn try_pop(&mut self) -> bool {
+        self.try_elimination_array()
+    }
+
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        Strategy::num_exchangers(self, total)
+    }
+}
+
+#[cfg(not(feature = "no-elimination"))]
+impl<S: Strategy> exchanger::PushStrategy for S {
+    fn try_start_exchange(&mut self) -> bool {
+        Strategy::try_start_exchange(self)
+    }
+
+    fn retry_check_exchanged(&mut self) -> bool {
+        Strategy::retry_check_exchanged(self)
+    }
+
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        Strategy::on_cas_failure(self, reason)
+    }
+
+    fn on_cas_success(&mut self) {
+        Strategy::on_cas_success(self)
+    }
+}
+
+#[cfg(not(feature = "no-elimination"))]
+impl<S: Strategy> exchanger::PopStrategy for S {
+    fn try_exchange(&mut self) -> bool {
+        Strategy::try_exchange(self)
+    }
+
+    fn on_contention(&mut self) {
+        Strategy::on_contention(self)
+    }
+
+    fn on_no_contention(&mut self) {
+        Strategy::on_no_contention(self)
+    }
+
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        Strategy::on_cas_failure(self, reason)
+    }
+
+    fn on_cas_success(&mut self) {
+        Strategy::on_cas_success(self)
+    }
+}
 
 /// Represents the default strategy aiming for good average performance.
 #[derive(Default)]
 pub struct BackAndForthStrategy {
     // TODO: usize is a bit big on 64bit machines, no?
-    treiber_stack_push_cnt: usize,
-    treiber_stack_pop_cnt: usize,
+    treiber_stack_cnt: usize,
 
-    elimination_array_push_cnt: usize,
-    elimination_array_pop_cnt: usize,
+    elimination_array_cnt: usize,
 
     exchanger_start_push_cnt: usize,
     exchanger_retry_check_success_cnt: usize,
@@ -46,191 +367,665 @@ impl BackAndForthStrategy {
     }
 }
 
-impl StackPushStrategy for BackAndForthStrategy {
+impl Strategy for BackAndForthStrategy {
     fn new() -> Self {
         BackAndForthStrategy::new()
     }
 
+    #[inline]
     fn use_elimination_array(&mut self) -> bool {
         true
     }
-}
 
-impl StackPopStrategy for BackAndForthStrategy {
-    fn new() -> Self {
-        BackAndForthStrategy::new()
+    #[inline]
+    fn try_stack(&mut self) -> bool {
+        if self.treiber_stack_cnt == 1 {
+            self.treiber_stack_cnt = 0;
+            return false;
+        }
+
+        self.treiber_stack_cnt += 1;
+        true
     }
 
-    fn use_elimination_array(&mut self) -> bool {
+    fn try_elimination_array(&mut self) -> bool {
+        if self.elimination_array_cnt == 1 {
+            self.elimination_array_cnt = 0;
+            return false;
+        }
+
+        self.elimination_array_cnt += 1;
         true
     }
-}
 
-impl treiber_stack::PushStrategy for BackAndForthStrategy {
-    fn try_push(&mut self) -> bool {
-        if self.treiber_stack_push_cnt == 1 {
-            self.treiber_stack_push_cnt = 0;
+    fn try_start_exchange(&mut self) -> bool {
+        if self.exchanger_start_push_cnt > 10 {
+            self.exchanger_start_push_cnt = 0;
             return false;
         }
 
-        self.treiber_stack_push_cnt += 1;
+        self.exchanger_start_push_cnt += 1;
         true
     }
-}
 
-impl treiber_stack::PopStrategy for BackAndForthStrategy {
-    fn try_pop(&mut self) -> bool {
-        if self.treiber_stack_pop_cnt == 1 {
-            self.treiber_stack_pop_cnt = 0;
+    fn retry_check_exchanged(&mut self) -> bool {
+        if self.exchanger_retry_check_success_cnt > 10 {
+            self.exchanger_retry_check_success_cnt = 0;
             return false;
         }
 
-        self.treiber_stack_pop_cnt += 1;
+        self.exchanger_retry_check_success_cnt += 1;
         true
     }
-}
 
-impl elimination_array::PushStrategy for BackAndForthStrategy {
-    fn try_push(&mut self) -> bool {
-        if self.elimination_array_push_cnt == 1 {
-            self.elimination_array_push_cnt = 0;
+    fn try_exchange(&mut self) -> bool {
+        if self.exchanger_try_pop_cnt > 10 {
+            self.exchanger_try_pop_cnt = 0;
             return false;
         }
 
-        self.elimination_array_push_cnt += 1;
+        self.exchanger_try_pop_cnt += 1;
         true
     }
+
+    fn after_cas_failure(&mut self) {
+        spin(BACK_AND_FORTH_SPINS);
+    }
 }
 
-impl elimination_array::PopStrategy for BackAndForthStrategy {
-    fn try_pop(&mut self) -> bool {
-        if self.elimination_array_pop_cnt == 1 {
-            self.elimination_array_pop_cnt = 0;
+/// Spin loop iterations of [`BackAndForthStrategy`] after a failed
+/// compare-and-swap on the Treiber stack.
+const BACK_AND_FORTH_SPINS: u32 = 4;
+
+fn spin(iterations: u32) {
+    for _ in 0..iterations {
+        std::hint::spin_loop();
+    }
+}
+
+/// Strategy to have Stack use the Treiber stack only and not elude to the
+/// elimination array on contention.
+#[derive(Default)]
+pub struct NoEliminationStrategy {
+    treiber_stack_cnt: usize,
+    /// Failed compare-and-swaps of the operation so far.
+    cas_failures: u8,
+}
+
+/// Number of spin loop iterations [`NoEliminationStrategy`] pauses after the
+/// given number of failed compare-and-swaps, doubling up to a bound.
+fn no_elimination_spins(cas_failures: u8) -> u32 {
+    1 << cas_failures.min(6)
+}
+
+impl NoEliminationStrategy {
+    fn new() -> Self {
+        NoEliminationStrategy::default()
+    }
+}
+
+impl Strategy for NoEliminationStrategy {
+    fn new() -> Self {
+        NoEliminationStrategy::new()
+    }
+
+    #[inline]
+    fn use_elimination_array(&mut self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn try_stack(&mut self) -> bool {
+        if self.treiber_stack_cnt == 1 {
+            self.treiber_stack_cnt = 0;
             return false;
         }
 
-        self.elimination_array_pop_cnt += 1;
+        self.treiber_stack_cnt += 1;
+        true
+    }
+
+    fn try_elimination_array(&mut self) -> bool {
+        false
+    }
+
+    fn try_start_exchange(&mut self) -> bool {
+        false
+    }
+
+    fn retry_check_exchanged(&mut self) -> bool {
+        false
+    }
+
+    fn try_exchange(&mut self) -> bool {
+        false
+    }
+
+    fn after_cas_failure(&mut self) {
+        spin(no_elimination_spins(self.cas_failures));
+        self.cas_failures = self.cas_failures.saturating_add(1);
+    }
+}
+
+/// Wraps a [`Strategy`], enabling [`Strategy::use_pop_interest`].
+///
+/// ```rust
+/// # use elimination_backoff_stack::Stack;
+/// # use elimination_backoff_stack::strategy::{ExpRetryStrategy, WithPopInterest};
+/// Stack::<
+///   String,
+///   WithPopInterest<ExpRetryStrategy>,
+///   WithPopInterest<ExpRetryStrategy>,
+/// >::new();
+/// ```
+pub struct WithPopInterest<S>(S);
+
+impl<S: Strategy> Strategy for WithPopInterest<S> {
+    fn new() -> Self {
+        WithPopInterest(S::new())
+    }
+
+    fn with_tuning(tuning: &Tuning) -> Self {
+        WithPopInterest(S::with_tuning(tuning))
+    }
+
+    #[inline]
+    fn use_elimination_array(&mut self) -> bool {
+        self.0.use_elimination_array()
+    }
+
+    #[inline]
+    fn try_stack(&mut self) -> bool {
+        self.0.try_stack()
+    }
+
+    fn try_elimination_array(&mut self) -> bool {
+        self.0.try_elimination_array()
+    }
+
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        Strategy::num_exchangers(&mut self.0, total)
+    }
+
+    fn try_start_exchange(&mut self) -> bool {
+        Strategy::try_start_exchange(&mut self.0)
+    }
+
+    fn retry_check_exchanged(&mut self) -> bool {
+        Strategy::retry_check_exchanged(&mut self.0)
+    }
+
+    fn try_exchange(&mut self) -> bool {
+        Strategy::try_exchange(&mut self.0)
+    }
+
+    fn on_contention(&mut self) {
+        Strategy::on_contention(&mut self.0)
+    }
+
+    fn on_no_contention(&mut self) {
+        Strategy::on_no_contention(&mut self.0)
+    }
+
+    fn use_pop_interest(&mut self) -> bool {
         true
     }
+
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        Strategy::on_cas_failure(&mut self.0, reason)
+    }
+
+    fn on_cas_success(&mut self) {
+        Strategy::on_cas_success(&mut self.0)
+    }
+
+    fn after_cas_failure(&mut self) {
+        Strategy::after_cas_failure(&mut self.0)
+    }
+}
+
+/// Wraps a [`Strategy`], skipping its decisions while the calling thread
+/// observes no contention.
+///
+/// Once the last `N` operations of the calling thread each succeeded without a
+/// failed compare-and-swap, an operation retries the Treiber stack without
+/// consulting the wrapped strategy and never uses the elimination array. The
+/// first failed compare-and-swap hands the operation to the wrapped strategy
+/// again, as well as the operations following it until there were `N`
+/// uncontended operations in a row again.
+///
+/// Meant for applications that are only occasionally concurrent, thus should
+/// not pay for the elimination machinery in the steady state.
+///
+/// ```rust
+/// # use elimination_backoff_stack::Stack;
+/// # use elimination_backoff_stack::strategy::{ExpRetryStrategy, SingleThreadFast};
+/// Stack::<
+///   String,
+///   SingleThreadFast<ExpRetryStrategy>,
+///   SingleThreadFast<ExpRetryStrategy>,
+/// >::new();
+/// ```
+pub struct SingleThreadFast<S, const N: usize = 1024> {
+    inner: S,
+    fast: bool,
+}
+
+thread_local! {
+    /// Operations of this thread in a row without a failed compare-and-swap,
+    /// see [`SingleThreadFast`]. Shared across all stacks.
+    static UNCONTENDED_OPERATIONS: Cell<usize> = const { Cell::new(0) };
 }
 
-impl exchanger::PushStrategy for BackAndForthStrategy {
+impl<S: Strategy, const N: usize> Strategy for SingleThreadFast<S, N> {
+    fn new() -> Self {
+        SingleThreadFast::with_tuning(&Tuning::default())
+    }
+
+    fn with_tuning(tuning: &Tuning) -> Self {
+        SingleThreadFast {
+            inner: S::with_tuning(tuning),
+            fast: UNCONTENDED_OPERATIONS.with(Cell::get) >= N,
+        }
+    }
+
+    #[inline]
+    fn use_elimination_array(&mut self) -> bool {
+        !self.fast && self.inner.use_elimination_array()
+    }
+
+    #[inline]
+    fn try_stack(&mut self) -> bool {
+        self.fast || self.inner.try_stack()
+    }
+
+    fn try_elimination_array(&mut self) -> bool {
+        !self.fast && self.inner.try_elimination_array()
+    }
+
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        Strategy::num_exchangers(&mut self.inner, total)
+    }
+
     fn try_start_exchange(&mut self) -> bool {
-        if self.exchanger_start_push_cnt > 10 {
-            self.exchanger_start_push_cnt = 0;
+        !self.fast && Strategy::try_start_exchange(&mut self.inner)
+    }
+
+    fn retry_check_exchanged(&mut self) -> bool {
+        !self.fast && Strategy::retry_check_exchanged(&mut self.inner)
+    }
+
+    fn try_exchange(&mut self) -> bool {
+        !self.fast && Strategy::try_exchange(&mut self.inner)
+    }
+
+    fn on_contention(&mut self) {
+        Strategy::on_contention(&mut self.inner)
+    }
+
+    fn on_no_contention(&mut self) {
+        Strategy::on_no_contention(&mut self.inner)
+    }
+
+    fn use_pop_interest(&mut self) -> bool {
+        !self.fast && Strategy::use_pop_interest(&mut self.inner)
+    }
+
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        self.fast = false;
+        UNCONTENDED_OPERATIONS.with(|c| c.set(0));
+        Strategy::on_cas_failure(&mut self.inner, reason)
+    }
+
+    #[inline]
+    fn on_cas_success(&mut self) {
+        UNCONTENDED_OPERATIONS.with(|c| c.set(c.get().saturating_add(1)));
+        if !self.fast {
+            Strategy::on_cas_success(&mut self.inner)
+        }
+    }
+
+    fn after_cas_failure(&mut self) {
+        Strategy::after_cas_failure(&mut self.inner)
+    }
+}
+
+/// Treiber stack only strategy used by [`super::Stack::push_urgent`] and
+/// [`super::Stack::pop_urgent`], retrying the Treiber stack up to
+/// [`URGENT_ATTEMPTS`] times.
+#[derive(Default)]
+pub(crate) struct UrgentStrategy {
+    attempts: usize,
+}
+
+/// Number of attempts on the Treiber stack by [`UrgentStrategy`].
+pub(crate) const URGENT_ATTEMPTS: usize = 64;
+
+impl UrgentStrategy {
+    pub(crate) fn new() -> Self {
+        UrgentStrategy::default()
+    }
+
+    fn try_again(&mut self) -> bool {
+        if self.attempts == URGENT_ATTEMPTS {
             return false;
         }
 
-        self.exchanger_start_push_cnt += 1;
+        self.attempts += 1;
         true
     }
+}
+
+impl treiber_stack::PushStrategy for UrgentStrategy {
+    #[inline]
+    fn try_push(&mut self) -> bool {
+        self.try_again()
+    }
+}
+
+impl treiber_stack::PopStrategy for UrgentStrategy {
+    #[inline]
+    fn try_pop(&mut self) -> bool {
+        self.try_again()
+    }
+}
+
+/// Wraps a [`Strategy`], denying any further attempt once
+/// [`Tuning::retry_limit`] decisions were taken, used by
+/// [`super::Stack::try_push`] and [`super::Stack::try_pop`].
+///
+/// Counts every decision, not only the ones permitting an attempt, thus an
+/// operation terminates even if the wrapped strategy keeps denying.
+pub(crate) struct Bounded<S> {
+    inner: S,
+    remaining: usize,
+}
+
+/// Total number of decisions taken by [`Bounded`] before denying, unless
+/// tuned otherwise.
+pub(crate) const BOUNDED_DECISIONS: usize = 256;
+
+/// Total number of decisions taken by [`Bounded`] when briefly offering a
+/// single item of [`super::Stack::push_iter_balanced`] on the elimination
+/// array.
+pub(crate) const BRIEF_OFFER_DECISIONS: usize = 8;
+
+impl<S> Bounded<S> {
+    pub(crate) fn exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn decide(&mut self, decision: impl FnOnce(&mut S) -> bool) -> bool {
+        if self.remaining == 0 {
+            return false;
+        }
+
+        self.remaining -= 1;
+        decision(&mut self.inner)
+    }
+}
+
+impl<S: Strategy> Bounded<S> {
+    /// Deny any further attempt after `decisions` instead of
+    /// [`Tuning::retry_limit`] decisions.
+    pub(crate) fn with_decisions(decisions: usize, tuning: &Tuning) -> Self {
+        Bounded {
+            inner: S::with_tuning(tuning),
+            remaining: decisions,
+        }
+    }
+}
+
+impl<S: Strategy> Strategy for Bounded<S> {
+    fn new() -> Self {
+        Bounded::with_tuning(&Tuning::default())
+    }
+
+    fn with_tuning(tuning: &Tuning) -> Self {
+        Bounded::with_decisions(tuning.retry_limit, tuning)
+    }
+
+    fn use_elimination_array(&mut self) -> bool {
+        self.decide(S::use_elimination_array)
+    }
+
+    fn try_stack(&mut self) -> bool {
+        self.decide(S::try_stack)
+    }
+
+    fn try_elimination_array(&mut self) -> bool {
+        self.decide(S::try_elimination_array)
+    }
+
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        self.inner.num_exchangers(total)
+    }
+
+    fn try_start_exchange(&mut self) -> bool {
+        self.decide(S::try_start_exchange)
+    }
 
     fn retry_check_exchanged(&mut self) -> bool {
-        if self.exchanger_retry_check_success_cnt > 10 {
-            self.exchanger_retry_check_success_cnt = 0;
+        self.decide(S::retry_check_exchanged)
+    }
+
+    fn try_exchange(&mut self) -> bool {
+        self.decide(S::try_exchange)
+    }
+
+    fn on_contention(&mut self) {
+        self.inner.on_contention()
+    }
+
+    fn on_no_contention(&mut self) {
+        self.inner.on_no_contention()
+    }
+
+    fn use_pop_interest(&mut self) -> bool {
+        self.inner.use_pop_interest()
+    }
+
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        self.inner.on_cas_failure(reason)
+    }
+
+    fn on_cas_success(&mut self) {
+        self.inner.on_cas_success()
+    }
+
+    fn after_cas_failure(&mut self) {
+        self.inner.after_cas_failure()
+    }
+}
+
+/// Wraps a [`Strategy`], denying any further attempt once a wall-clock deadline
+/// passed, used by [`super::Stack::pop_timeout`].
+///
+/// Like [`Bounded`], checks the deadline on every decision, not only the ones
+/// permitting an attempt.
+pub(crate) struct Deadline<S> {
+    inner: S,
+    deadline: Instant,
+}
+
+impl<S: Strategy> Deadline<S> {
+    pub(crate) fn after(timeout: Duration, tuning: &Tuning) -> Self {
+        let now = Instant::now();
+        Deadline {
+            inner: S::with_tuning(tuning),
+            // A timeout too large to represent is as good as none.
+            deadline: now.checked_add(timeout).unwrap_or(now + FAR_FUTURE),
+        }
+    }
+}
+
+/// Stand-in for a timeout overflowing [`Instant`].
+const FAR_FUTURE: Duration = Duration::from_secs(60 * 60 * 24 * 365 * 30);
+
+impl<S> Deadline<S> {
+    pub(crate) fn exhausted(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    fn decide(&mut self, decision: impl FnOnce(&mut S) -> bool) -> bool {
+        if self.exhausted() {
             return false;
         }
 
-        self.exchanger_retry_check_success_cnt += 1;
-        true
+        decision(&mut self.inner)
+    }
+}
+
+impl<S: Strategy> Strategy for Deadline<S> {
+    /// Without a timeout, the deadline passed already. Use [`Deadline::after`]
+    /// instead.
+    fn new() -> Self {
+        Deadline::after(Duration::ZERO, &Tuning::default())
+    }
+
+    fn use_elimination_array(&mut self) -> bool {
+        self.decide(S::use_elimination_array)
+    }
+
+    fn try_stack(&mut self) -> bool {
+        self.decide(S::try_stack)
+    }
+
+    fn try_elimination_array(&mut self) -> bool {
+        self.decide(S::try_elimination_array)
+    }
+
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        self.inner.num_exchangers(total)
+    }
+
+    fn try_start_exchange(&mut self) -> bool {
+        self.decide(S::try_start_exchange)
+    }
+
+    fn retry_check_exchanged(&mut self) -> bool {
+        self.decide(S::retry_check_exchanged)
+    }
+
+    fn try_exchange(&mut self) -> bool {
+        self.decide(S::try_exchange)
+    }
+
+    fn on_contention(&mut self) {
+        self.inner.on_contention()
+    }
+
+    fn on_no_contention(&mut self) {
+        self.inner.on_no_contention()
+    }
+
+    fn use_pop_interest(&mut self) -> bool {
+        self.inner.use_pop_interest()
+    }
+
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        self.inner.on_cas_failure(reason)
     }
-}
 
-impl exchanger::PopStrategy for BackAndForthStrategy {
-    fn try_exchange(&mut self) -> bool {
-        if self.exchanger_try_pop_cnt > 10 {
-            self.exchanger_try_pop_cnt = 0;
-            return false;
-        }
+    fn on_cas_success(&mut self) {
+        self.inner.on_cas_success()
+    }
 
-        self.exchanger_try_pop_cnt += 1;
-        true
+    fn after_cas_failure(&mut self) {
+        self.inner.after_cas_failure()
     }
 }
 
-/// Strategy to have Stack use the Treiber stack only and not elude to the
-/// elimination array on contention.
-#[derive(Default)]
-pub struct NoEliminationStrategy {
-    treiber_stack_push_cnt: usize,
-    treiber_stack_pop_cnt: usize,
+/// Wraps a [`Strategy`], recording each of its decisions as an
+/// [`Event::Decision`](crate::event::Event::Decision), thus explaining the
+/// path of an operation in its trace, e.g. why it bounced between the Treiber
+/// stack and the elimination array.
+///
+/// Decisions are only recorded by instrumented operations, e.g.
+/// [`Stack::instrumented_push`](crate::Stack::instrumented_push), in the order
+/// taken, right before the final event of the operation. Otherwise merely a
+/// thread local lookup per decision.
+///
+/// ```rust
+/// # use elimination_backoff_stack::Stack;
+/// # use elimination_backoff_stack::event::{Decision, Event};
+/// # use elimination_backoff_stack::strategy::{ExpRetryStrategy, Traced};
+/// type S = Traced<ExpRetryStrategy>;
+/// let stack = Stack::<u8, S, S>::new();
+///
+/// let mut trace = vec![];
+/// stack.instrumented_push(1, &mut trace);
+/// assert!(trace.contains(&Event::Decision(Decision::TryStack, true)));
+/// ```
+pub struct Traced<S>(S);
+
+impl<S> Traced<S> {
+    fn trace(&mut self, decision: Decision, decide: impl FnOnce(&mut S) -> bool) -> bool {
+        let permitted = decide(&mut self.0);
+        record_decision(decision, permitted);
+        permitted
+    }
 }
 
-impl NoEliminationStrategy {
+impl<S: Strategy> Strategy for Traced<S> {
     fn new() -> Self {
-        NoEliminationStrategy::default()
+        Traced(S::new())
     }
-}
 
-impl StackPushStrategy for NoEliminationStrategy {
-    fn new() -> Self {
-        NoEliminationStrategy::new()
+    fn with_tuning(tuning: &Tuning) -> Self {
+        Traced(S::with_tuning(tuning))
     }
 
     fn use_elimination_array(&mut self) -> bool {
-        false
+        self.trace(Decision::UseEliminationArray, S::use_elimination_array)
     }
-}
 
-impl StackPopStrategy for NoEliminationStrategy {
-    fn new() -> Self {
-        NoEliminationStrategy::new()
+    fn try_stack(&mut self) -> bool {
+        self.trace(Decision::TryStack, S::try_stack)
     }
 
-    fn use_elimination_array(&mut self) -> bool {
-        false
+    fn try_elimination_array(&mut self) -> bool {
+        self.trace(Decision::TryEliminationArray, S::try_elimination_array)
     }
-}
 
-impl treiber_stack::PushStrategy for NoEliminationStrategy {
-    fn try_push(&mut self) -> bool {
-        if self.treiber_stack_push_cnt == 1 {
-            self.treiber_stack_push_cnt = 0;
-            return false;
-        }
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        self.0.num_exchangers(total)
+    }
 
-        self.treiber_stack_push_cnt += 1;
-        true
+    fn try_start_exchange(&mut self) -> bool {
+        self.trace(Decision::TryStartExchange, S::try_start_exchange)
     }
-}
 
-impl treiber_stack::PopStrategy for NoEliminationStrategy {
-    fn try_pop(&mut self) -> bool {
-        if self.treiber_stack_pop_cnt == 1 {
-            self.treiber_stack_pop_cnt = 0;
-            return false;
-        }
+    fn retry_check_exchanged(&mut self) -> bool {
+        self.trace(Decision::RetryCheckExchanged, S::retry_check_exchanged)
+    }
 
-        self.treiber_stack_pop_cnt += 1;
-        true
+    fn try_exchange(&mut self) -> bool {
+        self.trace(Decision::TryExchange, S::try_exchange)
     }
-}
 
-impl elimination_array::PushStrategy for NoEliminationStrategy {
-    fn try_push(&mut self) -> bool {
-        false
+    fn on_contention(&mut self) {
+        self.0.on_contention()
     }
-}
 
-impl elimination_array::PopStrategy for NoEliminationStrategy {
-    fn try_pop(&mut self) -> bool {
-        false
+    fn on_no_contention(&mut self) {
+        self.0.on_no_contention()
     }
-}
 
-impl exchanger::PushStrategy for NoEliminationStrategy {
-    fn try_start_exchange(&mut self) -> bool {
-        false
+    fn use_pop_interest(&mut self) -> bool {
+        self.trace(Decision::UsePopInterest, S::use_pop_interest)
     }
 
-    fn retry_check_exchanged(&mut self) -> bool {
-        false
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        self.0.on_cas_failure(reason)
     }
-}
 
-impl exchanger::PopStrategy for NoEliminationStrategy {
-    fn try_exchange(&mut self) -> bool {
-        false
+    fn on_cas_success(&mut self) {
+        self.0.on_cas_success()
+    }
+
+    fn after_cas_failure(&mut self) {
+        self.0.after_cas_failure()
     }
 }
 
@@ -242,137 +1037,127 @@ impl exchanger::PopStrategy for NoEliminationStrategy {
 ///
 /// Back-off in time: Retry elimination array on congestion and Treiber stack on
 /// disappearing of congestion.
-#[derive(Default)]
 pub struct ExpRetryStrategy {
     retry_exponent: u8,
+    /// See [`Tuning::max_retry_exponent`].
+    max_retry_exponent: u8,
+    /// See [`Tuning::exchange_wait_checks`].
+    exchange_wait_checks: usize,
 
     // TODO: usize is a bit big on 64bit machines, no?
-    treiber_stack_push_cnt: usize,
-    treiber_stack_pop_cnt: usize,
+    treiber_stack_cnt: usize,
 
-    elimination_array_push_cnt: usize,
-    elimination_array_pop_cnt: usize,
+    elimination_array_cnt: usize,
 
     exchanger_try_start_exchange_cnt: usize,
     exchanger_retry_check_exchanged_cnt: usize,
     exchanger_try_pop_exchange_cnt: usize,
+
+    /// Whether a compare-and-swap failed since the last successful one.
+    cas_failed: bool,
+    /// See [`Tuning::curve`].
+    curve: Curve,
 }
 
+/// Default of [`Tuning::max_retry_exponent`].
 const MAX_RETRY_EXPONENT: u8 = 5;
 
+/// Number of spin loop iterations to wait in between two checks of an
+/// exchanger given the current retry exponent.
+///
+/// Kept as a pure function to pin the curve in tests, thus making tuning
+/// changes deliberate.
+//
+// TODO: Should this grow exponentially with contention? 1 on 8 threads and 100
+// for 128 threads worked well in the past.
+fn spins_for(exponent: u8) -> u32 {
+    u32::from(exponent)
+}
+
 impl ExpRetryStrategy {
     pub fn new() -> Self {
         ExpRetryStrategy::default()
     }
 }
 
-impl StackPushStrategy for ExpRetryStrategy {
-    fn new() -> Self {
-        ExpRetryStrategy::new()
-    }
-
-    fn use_elimination_array(&mut self) -> bool {
-        true
+impl Default for ExpRetryStrategy {
+    fn default() -> Self {
+        ExpRetryStrategy::with_tuning(&Tuning::default())
     }
 }
 
-impl StackPopStrategy for ExpRetryStrategy {
+impl Strategy for ExpRetryStrategy {
     fn new() -> Self {
         ExpRetryStrategy::new()
     }
 
-    fn use_elimination_array(&mut self) -> bool {
-        true
-    }
-}
-
-impl treiber_stack::PushStrategy for ExpRetryStrategy {
-    // Try push to Treiber stack at most once. Failing on Treiber stack implies
-    // congestion which is best resolved via elimination array.
-    //
-    // TODO: Maybe retry once. Should improve the case of light congestion.
-    fn try_push(&mut self) -> bool {
-        if self.treiber_stack_push_cnt == 1 {
-            // Increase retry exponent due to congestion.
-            self.retry_exponent = (self.retry_exponent + 1).min(MAX_RETRY_EXPONENT);
-
-            self.treiber_stack_push_cnt = 0;
-
-            return false;
+    fn with_tuning(tuning: &Tuning) -> Self {
+        ExpRetryStrategy {
+            retry_exponent: 0,
+            max_retry_exponent: tuning.max_retry_exponent,
+            exchange_wait_checks: tuning.exchange_wait_checks,
+            treiber_stack_cnt: 0,
+            elimination_array_cnt: 0,
+            exchanger_try_start_exchange_cnt: 0,
+            exchanger_retry_check_exchanged_cnt: 0,
+            exchanger_try_pop_exchange_cnt: 0,
+            cas_failed: false,
+            curve: tuning.curve,
         }
+    }
 
-        self.treiber_stack_push_cnt += 1;
+    #[inline]
+    fn use_elimination_array(&mut self) -> bool {
         true
     }
-}
 
-impl treiber_stack::PopStrategy for ExpRetryStrategy {
-    // Try pop from Treiber stack at most once. Failing on Treiber stack implies
-    // congestion which is best resolved via elimination array.
+    // Try push to or pop from Treiber stack at most once. Failing on Treiber
+    // stack implies congestion which is best resolved via elimination array.
     //
     // TODO: Maybe retry once. Should improve the case of light congestion.
-    fn try_pop(&mut self) -> bool {
-        if self.treiber_stack_pop_cnt == 1 {
+    #[inline]
+    fn try_stack(&mut self) -> bool {
+        if self.treiber_stack_cnt == 1 {
             // Increase retry exponent due to congestion.
-            self.retry_exponent = (self.retry_exponent + 1).min(MAX_RETRY_EXPONENT);
-
-            self.treiber_stack_pop_cnt = 0;
+            self.retry_exponent = (self.retry_exponent + 1).min(self.max_retry_exponent);
 
-            return false;
-        }
-
-        self.treiber_stack_pop_cnt += 1;
-        true
-    }
-}
+            self.treiber_stack_cnt = 0;
 
-impl elimination_array::PushStrategy for ExpRetryStrategy {
-    // Try at least 2 times multiplied by 2 each time congestion occurs.
-    fn try_push(&mut self) -> bool {
-        if self.elimination_array_push_cnt >= (2 << self.retry_exponent) {
-            self.elimination_array_push_cnt = 0;
             return false;
         }
 
-        self.elimination_array_push_cnt += 1;
+        self.treiber_stack_cnt += 1;
         true
     }
 
-    fn num_exchangers(&mut self, total: usize) -> usize {
-        (1 << self.retry_exponent).min(total)
-    }
-}
-
-impl elimination_array::PopStrategy for ExpRetryStrategy {
     // Try at least 2 times multiplied by 2 each time congestion occurs.
     //
     // See page 260 for more research: Moir, Mark, et al. "Using elimination to
     // implement scalable and lock-free fifo queues." Proceedings of the
     // seventeenth annual ACM symposium on Parallelism in algorithms and
     // architectures. 2005.
-    fn try_pop(&mut self) -> bool {
-        if self.elimination_array_pop_cnt >= (2 << self.retry_exponent) {
-            self.elimination_array_pop_cnt = 0;
+    fn try_elimination_array(&mut self) -> bool {
+        if self.elimination_array_cnt >= (2 << self.retry_exponent) {
+            self.elimination_array_cnt = 0;
             return false;
         }
 
-        self.elimination_array_pop_cnt += 1;
+        self.elimination_array_cnt += 1;
         true
     }
 
     fn num_exchangers(&mut self, total: usize) -> usize {
-        elimination_array::PushStrategy::num_exchangers(self, total)
+        self.curve
+            .num_exchangers(self.retry_exponent, self.max_retry_exponent, total)
     }
-}
 
-impl exchanger::PushStrategy for ExpRetryStrategy {
     // Try to exchange a put on an exchanger at most once. Failure implies usage
     // by a different push operation. Thus never retry the same exchanger but
     // try a different one.
     fn try_start_exchange(&mut self) -> bool {
         if self.exchanger_try_start_exchange_cnt == 1 {
             // Given that there was congestion, increase the retry exponent.
-            self.retry_exponent = (self.retry_exponent + 1).min(MAX_RETRY_EXPONENT);
+            self.retry_exponent = (self.retry_exponent + 1).min(self.max_retry_exponent);
 
             self.exchanger_try_start_exchange_cnt = 0;
 
@@ -383,17 +1168,19 @@ impl exchanger::PushStrategy for ExpRetryStrategy {
         true
     }
 
-    // Wait for a pop operation for up to 50 atomic loads.
+    // Wait for a pop operation for up to `exchange_wait_checks` atomic loads
+    // per retry exponent, 50 by default.
     fn retry_check_exchanged(&mut self) -> bool {
-        // TODO: Should this grow exponentially with contention? 1 on 8 threads
-        // and 100 for 128 threads worked well in the past.
-        for _ in 0..(self.retry_exponent) {
-            std::sync::atomic::spin_loop_hint();
-        }
+        spin(spins_for(self.retry_exponent));
 
         // TODO: Should this grow exponentially with contention? 10 on 8 threads
         // and 50 on 128 threads worked well in the past.
-        if self.exchanger_retry_check_exchanged_cnt == (10 * self.retry_exponent) as usize {
+        //
+        // Wait at least one round, given that the exponent might have decayed
+        // to 0 by offering the item on the first try.
+        if self.exchanger_retry_check_exchanged_cnt
+            >= self.exchange_wait_checks * usize::from(self.retry_exponent.max(1))
+        {
             // No pop operation exchanging with this push operation signals less
             // congestion. Thus decreasing the retry exponent.
             self.retry_exponent = self.retry_exponent.saturating_sub(2);
@@ -406,9 +1193,7 @@ impl exchanger::PushStrategy for ExpRetryStrategy {
         self.exchanger_retry_check_exchanged_cnt += 1;
         true
     }
-}
 
-impl exchanger::PopStrategy for ExpRetryStrategy {
     // Failure on pop implies that either (a) there is no concurrent push
     // operation in progress on the exchanger (b) the concurrent push operation
     // was already matched with a pop operation. Thus best to try a different
@@ -424,10 +1209,540 @@ impl exchanger::PopStrategy for ExpRetryStrategy {
     }
 
     fn on_contention(&mut self) {
-        self.retry_exponent = (self.retry_exponent + 1).max(MAX_RETRY_EXPONENT);
+        self.retry_exponent = (self.retry_exponent + 1).min(self.max_retry_exponent);
     }
 
     fn on_no_contention(&mut self) {
         self.retry_exponent = self.retry_exponent.saturating_sub(2);
     }
+
+    fn on_cas_failure(&mut self, _reason: CasFailure) {
+        self.cas_failed = true;
+    }
+
+    // A compare-and-swap succeeding on the first try, e.g. offering an item
+    // on an exchanger, signals that contention disappeared. Thus contract the
+    // prefix of exchangers considered, mirroring the growth on failure.
+    // Pause as long as between two checks of an exchanger.
+    fn after_cas_failure(&mut self) {
+        spin(spins_for(self.retry_exponent));
+    }
+
+    fn on_cas_success(&mut self) {
+        if !self.cas_failed {
+            self.retry_exponent = self.retry_exponent.saturating_sub(1);
+        }
+        self.cas_failed = false;
+    }
+}
+
+/// Growth of the number of exchangers considered by the [`ExpRetryStrategy`]
+/// with its retry exponent, i.e. with the contention observed by the
+/// operation, see [`Tuning::curve`].
+///
+/// The literature disagrees on the best shape, thus `benches/lib.rs` compares
+/// them across thread counts.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Curve {
+    /// One more exchanger per retry exponent, starting at one.
+    Linear,
+    /// Twice the exchangers per retry exponent, starting at one.
+    #[default]
+    Exponential,
+    /// The fraction of all exchangers given by the square root of the retry
+    /// exponent relative to its maximum, i.e. growing fast on little
+    /// contention and leveling off on high contention. At least one.
+    Sqrt,
+    /// All exchangers, whatever the contention.
+    Full,
+}
+
+impl Curve {
+    /// Every curve, e.g. to benchmark them against each other.
+    pub const ALL: [Curve; 4] = [Curve::Linear, Curve::Exponential, Curve::Sqrt, Curve::Full];
+
+    /// Number of exchangers out of `total` to consider at `exponent`, out of
+    /// `max_exponent`.
+    pub fn num_exchangers(self, exponent: u8, max_exponent: u8, total: usize) -> usize {
+        let n = match self {
+            Curve::Linear => usize::from(exponent) + 1,
+            Curve::Exponential => 1 << exponent,
+            Curve::Sqrt if max_exponent == 0 => 1,
+            Curve::Sqrt => {
+                let fraction = (f64::from(exponent) / f64::from(max_exponent)).sqrt();
+                ((total as f64 * fraction).ceil() as usize).max(1)
+            }
+            Curve::Full => total,
+        };
+        n.min(total)
+    }
+}
+
+/// Strategy chosen at run time, e.g. from a command line flag, via
+/// [`Tuning::strategy`], at the cost of a branch per decision.
+///
+/// Use the strategy types directly instead where the choice is known at
+/// compile time.
+///
+/// ```rust
+/// # use elimination_backoff_stack::Stack;
+/// # use elimination_backoff_stack::strategy::{DynStrategy, StrategyKind};
+/// let kind: StrategyKind = "no-elimination".parse().unwrap();
+/// let stack = Stack::<u8, DynStrategy, DynStrategy>::builder()
+///     .strategy(kind)
+///     .build();
+///
+/// stack.push(1);
+/// assert_eq!(stack.pop(), Some(1));
+/// ```
+pub enum DynStrategy {
+    ExpRetry(ExpRetryStrategy),
+    BackAndForth(BackAndForthStrategy),
+    NoElimination(NoEliminationStrategy),
+}
+
+/// Strategies a [`DynStrategy`] can dispatch to.
+///
+/// Parsed from and displayed as the kebab case name of the strategy, e.g.
+/// `exp-retry` for [`StrategyKind::ExpRetry`].
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum StrategyKind {
+    /// See [`ExpRetryStrategy`].
+    ExpRetry,
+    /// See [`BackAndForthStrategy`].
+    BackAndForth,
+    /// See [`NoEliminationStrategy`].
+    NoElimination,
+}
+
+impl StrategyKind {
+    /// Every kind, e.g. to list the valid values of a command line flag.
+    pub const ALL: [StrategyKind; 3] = [
+        StrategyKind::ExpRetry,
+        StrategyKind::BackAndForth,
+        StrategyKind::NoElimination,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            StrategyKind::ExpRetry => "exp-retry",
+            StrategyKind::BackAndForth => "back-and-forth",
+            StrategyKind::NoElimination => "no-elimination",
+        }
+    }
+}
+
+impl fmt::Display for StrategyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for StrategyKind {
+    type Err = ParseStrategyKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StrategyKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == s)
+            .ok_or(ParseStrategyKindError)
+    }
+}
+
+/// Error of parsing a [`StrategyKind`] from an unknown name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseStrategyKindError;
+
+impl fmt::Display for ParseStrategyKindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown strategy, expected one of")?;
+        for kind in StrategyKind::ALL.iter() {
+            write!(f, " {}", kind)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseStrategyKindError {}
+
+/// Calls `$method` on the strategy the [`DynStrategy`] dispatches to.
+macro_rules! dispatch {
+    ($self:ident.$method:ident($($arg:expr),*)) => {
+        match $self {
+            DynStrategy::ExpRetry(s) => s.$method($($arg),*),
+            DynStrategy::BackAndForth(s) => s.$method($($arg),*),
+            DynStrategy::NoElimination(s) => s.$method($($arg),*),
+        }
+    };
+}
+
+impl Strategy for DynStrategy {
+    fn new() -> Self {
+        DynStrategy::with_tuning(&Tuning::default())
+    }
+
+    fn with_tuning(tuning: &Tuning) -> Self {
+        match tuning.strategy {
+            StrategyKind::ExpRetry => DynStrategy::ExpRetry(ExpRetryStrategy::with_tuning(tuning)),
+            StrategyKind::BackAndForth => {
+                DynStrategy::BackAndForth(BackAndForthStrategy::with_tuning(tuning))
+            }
+            StrategyKind::NoElimination => {
+                DynStrategy::NoElimination(NoEliminationStrategy::with_tuning(tuning))
+            }
+        }
+    }
+
+    #[inline]
+    fn use_elimination_array(&mut self) -> bool {
+        dispatch!(self.use_elimination_array())
+    }
+
+    #[inline]
+    fn try_stack(&mut self) -> bool {
+        dispatch!(self.try_stack())
+    }
+
+    fn try_elimination_array(&mut self) -> bool {
+        dispatch!(self.try_elimination_array())
+    }
+
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        dispatch!(self.num_exchangers(total))
+    }
+
+    fn try_start_exchange(&mut self) -> bool {
+        dispatch!(self.try_start_exchange())
+    }
+
+    fn retry_check_exchanged(&mut self) -> bool {
+        dispatch!(self.retry_check_exchanged())
+    }
+
+    fn try_exchange(&mut self) -> bool {
+        dispatch!(self.try_exchange())
+    }
+
+    fn on_contention(&mut self) {
+        dispatch!(self.on_contention())
+    }
+
+    fn on_no_contention(&mut self) {
+        dispatch!(self.on_no_contention())
+    }
+
+    fn use_pop_interest(&mut self) -> bool {
+        dispatch!(self.use_pop_interest())
+    }
+
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        dispatch!(self.on_cas_failure(reason))
+    }
+
+    fn on_cas_success(&mut self) {
+        dispatch!(self.on_cas_success())
+    }
+
+    fn after_cas_failure(&mut self) {
+        dispatch!(self.after_cas_failure())
+    }
+}
+
+/// Curated combination of strategies and elimination array configuration for a
+/// common scenario, selected via [`crate::Builder::preset`].
+///
+/// Presets are types, not values, given that strategies are chosen at compile
+/// time.
+pub trait Preset {
+    /// Strategy of push operations.
+    type Push: Strategy;
+    /// Strategy of pop operations.
+    type Pop: Strategy;
+
+    fn resize_config() -> ResizeConfig {
+        ResizeConfig::default()
+    }
+}
+
+/// Keeps single operations short: alternates between the Treiber stack and
+/// the elimination array after each attempt and waits on an exchanger for a
+/// bounded number of checks only.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LowLatency;
+
+impl Preset for LowLatency {
+    type Push = BackAndForthStrategy;
+    type Pop = BackAndForthStrategy;
+}
+
+/// Maximizes eliminated operations under sustained contention of push and pop
+/// operations, pop operations announcing their interest in an exchanger while
+/// retrying the Treiber stack.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HighThroughput;
+
+impl Preset for HighThroughput {
+    type Push = WithPopInterest<ExpRetryStrategy>;
+    type Pop = WithPopInterest<ExpRetryStrategy>;
+}
+
+/// For more threads than the machine runs in parallel. An item offered on an
+/// exchanger likely waits for a partner that is not scheduled, thus only uses
+/// the Treiber stack, backing off exponentially, and keeps a single exchanger.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Oversubscribed;
+
+impl Preset for Oversubscribed {
+    type Push = NoEliminationStrategy;
+    type Pop = NoEliminationStrategy;
+
+    fn resize_config() -> ResizeConfig {
+        ResizeConfig::fixed(1)
+    }
+}
+
+/// For a single thread pushing and many threads popping. The producer skips
+/// the elimination machinery while uncontended. At most one item is offered
+/// at a time, thus a single exchanger, consumers announcing their interest in
+/// it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SingleProducer;
+
+impl Preset for SingleProducer {
+    type Push = SingleThreadFast<ExpRetryStrategy>;
+    type Pop = WithPopInterest<ExpRetryStrategy>;
+
+    fn resize_config() -> ResizeConfig {
+        ResizeConfig::fixed(1)
+    }
+}
+
+/// Instantiate code for every strategy shipped with this crate, thus a new
+/// strategy is covered by the same tests and benchmarks as the existing ones
+/// by adding it here.
+///
+/// `for_each_strategy!(callback)` invokes `callback!(name, Strategy)` for each
+/// strategy, `name` being a snake case identifier.
+///
+/// `for_each_strategy!(test f)` expects a function `f::<PushS, PopS>()` and
+/// generates a module `f` with a test per strategy, calling `f` with the
+/// strategy for both push and pop operations.
+///
+/// Meant for the tests and benchmarks of this crate. Not covered by semver.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! for_each_strategy {
+    (test $test:ident) => {
+        mod $test {
+            macro_rules! instantiate {
+                ($name:ident, $strategy:ty) => {
+                    #[test]
+                    fn $name() {
+                        super::$test::<$strategy, $strategy>();
+                    }
+                };
+            }
+
+            $crate::for_each_strategy!(instantiate);
+        }
+    };
+    ($callback:ident) => {
+        $callback!(exp_retry, $crate::strategy::ExpRetryStrategy);
+        $callback!(back_and_forth, $crate::strategy::BackAndForthStrategy);
+        $callback!(no_elimination, $crate::strategy::NoEliminationStrategy);
+        $callback!(dyn_strategy, $crate::strategy::DynStrategy);
+        $callback!(
+            pop_interest,
+            $crate::strategy::WithPopInterest<$crate::strategy::ExpRetryStrategy>
+        );
+        $callback!(
+            single_thread_fast,
+            $crate::strategy::SingleThreadFast<$crate::strategy::ExpRetryStrategy>
+        );
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pop_contention_clamps_retry_exponent() {
+        let mut strategy = ExpRetryStrategy::new();
+
+        // Previously overflowed the exponent after 256 contended exchanges.
+        for _ in 0..=u8::MAX {
+            Strategy::on_contention(&mut strategy);
+        }
+
+        assert_eq!(strategy.retry_exponent, MAX_RETRY_EXPONENT);
+    }
+
+    #[test]
+    fn spins_for_curve() {
+        let curve: Vec<u32> = (0..=MAX_RETRY_EXPONENT).map(spins_for).collect();
+        assert_eq!(curve, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn exp_retry_decays_on_first_try_cas_success() {
+        let mut strategy = ExpRetryStrategy {
+            retry_exponent: 3,
+            ..Default::default()
+        };
+
+        strategy.on_cas_success();
+        assert_eq!(strategy.num_exchangers(usize::MAX), 1 << 2);
+
+        // A success after a failure is no sign of disappearing contention.
+        Strategy::on_cas_failure(&mut strategy, CasFailure::LostRace);
+        strategy.on_cas_success();
+        assert_eq!(strategy.num_exchangers(usize::MAX), 1 << 2);
+
+        strategy.on_cas_success();
+        strategy.on_cas_success();
+        strategy.on_cas_success();
+        assert_eq!(strategy.num_exchangers(usize::MAX), 1);
+    }
+
+    #[test]
+    fn num_exchangers_curves() {
+        let curve = |curve: Curve| -> Vec<usize> {
+            (0..=4).map(|e| curve.num_exchangers(e, 4, 8)).collect()
+        };
+        assert_eq!(curve(Curve::Linear), vec![1, 2, 3, 4, 5]);
+        assert_eq!(curve(Curve::Exponential), vec![1, 2, 4, 8, 8]);
+        assert_eq!(curve(Curve::Sqrt), vec![1, 4, 6, 7, 8]);
+        assert_eq!(curve(Curve::Full), vec![8, 8, 8, 8, 8]);
+
+        for curve in Curve::ALL.iter() {
+            assert_eq!(curve.num_exchangers(3, 4, 0), 0, "{:?}", curve);
+        }
+
+        let mut strategy = ExpRetryStrategy::with_tuning(&Tuning {
+            curve: Curve::Linear,
+            ..Tuning::default()
+        });
+        strategy.on_contention();
+        strategy.on_contention();
+        assert_eq!(strategy.num_exchangers(usize::MAX), 3);
+    }
+
+    #[test]
+    fn dyn_strategy_dispatches_to_tuned_kind() {
+        for kind in StrategyKind::ALL.iter().copied() {
+            assert_eq!(kind.to_string().parse(), Ok(kind));
+
+            let mut strategy = DynStrategy::with_tuning(&Tuning {
+                strategy: kind,
+                ..Tuning::default()
+            });
+            assert_eq!(
+                strategy.use_elimination_array(),
+                kind != StrategyKind::NoElimination,
+                "{}",
+                kind
+            );
+        }
+
+        assert_eq!(
+            "treiber".parse::<StrategyKind>(),
+            Err(ParseStrategyKindError)
+        );
+        assert!(matches!(DynStrategy::new(), DynStrategy::ExpRetry(_)));
+    }
+
+    #[test]
+    fn bounded_denies_once_exhausted() {
+        let mut strategy = Bounded::<ExpRetryStrategy>::new();
+
+        let permitted = (0..2 * BOUNDED_DECISIONS)
+            .filter(|_| strategy.try_stack())
+            .count();
+
+        assert!(strategy.exhausted());
+        assert!(permitted <= BOUNDED_DECISIONS);
+        assert!(!strategy.try_elimination_array());
+    }
+
+    #[test]
+    fn tuning_limits_exp_retry() {
+        let mut strategy = ExpRetryStrategy::with_tuning(&Tuning {
+            max_retry_exponent: 1,
+            exchange_wait_checks: 3,
+            ..Tuning::default()
+        });
+
+        for _ in 0..4 {
+            strategy.on_contention();
+        }
+        assert_eq!(strategy.num_exchangers(usize::MAX), 1 << 1);
+
+        let waits = (0..10)
+            .take_while(|_| strategy.retry_check_exchanged())
+            .count();
+        assert_eq!(waits, 3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tuning_from_json_defaults_missing_fields() {
+        let tuning: Tuning =
+            serde_json::from_str(r#"{"retry_limit": 8, "strategy": "back-and-forth"}"#).unwrap();
+        assert_eq!(
+            tuning,
+            Tuning {
+                retry_limit: 8,
+                strategy: StrategyKind::BackAndForth,
+                ..Tuning::default()
+            }
+        );
+    }
+
+    #[test]
+    fn single_thread_fast_until_contended() {
+        type Fast = SingleThreadFast<ExpRetryStrategy, 2>;
+        UNCONTENDED_OPERATIONS.with(|c| c.set(0));
+
+        for _ in 0..2 {
+            let mut strategy = Fast::new();
+            assert!(!strategy.fast);
+            strategy.on_cas_success();
+        }
+
+        let mut strategy = Fast::new();
+        assert!(strategy.fast);
+        assert!(!strategy.use_elimination_array());
+        Strategy::on_cas_failure(&mut strategy, CasFailure::LostRace);
+        assert!(!strategy.fast);
+        strategy.on_cas_success();
+
+        assert!(!Fast::new().fast);
+    }
+
+    #[test]
+    fn no_elimination_spins_curve() {
+        let curve: Vec<u32> = (0..8).map(no_elimination_spins).collect();
+        assert_eq!(curve, vec![1, 2, 4, 8, 16, 32, 64, 64]);
+    }
+
+    #[test]
+    fn spins_for_is_monotonic() {
+        for exponent in 0..MAX_RETRY_EXPONENT {
+            assert!(spins_for(exponent) <= spins_for(exponent + 1));
+        }
+    }
 }
@@ -0,0 +1,171 @@
+//! Epoch protected, atomically swappable, immutable slice.
+//!
+//! Readers pin an epoch and load the current slice, which stays valid for as
+//! long as the epoch is pinned, even when a writer swaps in a new slice in the
+//! meantime. Each swap bumps a generation counter, thus readers can tell
+//! whether the slice they operate on is outdated.
+
+use crossbeam::epoch::{Atomic, Guard, Owned};
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed};
+
+struct Generation<T> {
+    number: u64,
+    items: Box<[T]>,
+}
+
+/// Slice loaded from a [`SwappableSlice`].
+pub(crate) struct View<'g, T> {
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub(crate) generation: u64,
+    pub(crate) items: &'g [T],
+}
+
+pub(crate) struct SwappableSlice<T> {
+    current: Atomic<Generation<T>>,
+}
+
+impl<T> SwappableSlice<T> {
+    pub(crate) fn new(items: Vec<T>) -> Self {
+        SwappableSlice {
+            current: Atomic::new(Generation {
+                number: 0,
+                items: items.into_boxed_slice(),
+            }),
+        }
+    }
+
+    /// Load the current slice, valid for as long as `guard` is pinned.
+    pub(crate) fn load<'g>(&self, guard: &'g Guard) -> View<'g, T> {
+        // Assume using `Acquire` is correct, given that the slice is written
+        // before being published by `swap` via `Release`.
+        let current = self.current.load(Acquire, guard);
+        // Never null. Set in `new` and only ever replaced by `swap`.
+        let current = unsafe { current.deref() };
+
+        View {
+            generation: current.number,
+            items: &current.items,
+        }
+    }
+
+    /// Replace the current slice with `items`, returning the new generation.
+    ///
+    /// The previous slice is destroyed once all readers which might still hold
+    /// a reference to it have unpinned their epoch.
+    pub(crate) fn swap(&self, items: Vec<T>, guard: &Guard) -> u64 {
+        let mut new = Owned::new(Generation {
+            number: 0,
+            items: items.into_boxed_slice(),
+        });
+
+        loop {
+            let current = self.current.load(Acquire, guard);
+            let number = unsafe { current.deref() }.number + 1;
+            new.number = number;
+
+            match self
+                .current
+                .compare_exchange(current, new, AcqRel, Relaxed, guard)
+            {
+                Ok(_) => {
+                    unsafe { guard.defer_destroy(current) };
+                    return number;
+                }
+                Err(e) => new = e.new,
+            }
+        }
+    }
+}
+
+impl<T> Drop for SwappableSlice<T> {
+    fn drop(&mut self) {
+        // By now the slice lives only in our thread and we are sure we don't
+        // hold any Shared or & to it ourselves.
+        unsafe {
+            drop(std::mem::replace(&mut self.current, Atomic::null()).into_owned());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam::epoch;
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn swap_bumps_generation() {
+        let slice = SwappableSlice::new(vec![1, 2]);
+
+        let guard = epoch::pin();
+        let view = slice.load(&guard);
+        assert_eq!(view.generation, 0);
+        assert_eq!(view.items, &[1, 2]);
+
+        assert_eq!(slice.swap(vec![3, 4, 5], &guard), 1);
+
+        // Previously loaded view stays valid.
+        assert_eq!(view.items, &[1, 2]);
+
+        let view = slice.load(&guard);
+        assert_eq!(view.generation, 1);
+        assert_eq!(view.items, &[3, 4, 5]);
+    }
+
+    #[test]
+    fn drops_every_generation() {
+        struct Counted(Arc<AtomicUsize>);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, SeqCst);
+            }
+        }
+
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        {
+            let slice = SwappableSlice::new(vec![Counted(drops.clone())]);
+            for _ in 0..10 {
+                slice.swap(vec![Counted(drops.clone())], &epoch::pin());
+            }
+        }
+
+        // Deferred destruction is only guaranteed to run eventually.
+        while drops.load(SeqCst) != 11 {
+            epoch::pin().flush();
+        }
+    }
+
+    #[test]
+    fn concurrent_load_and_swap() {
+        let slice = Arc::new(SwappableSlice::new(vec![0usize; 1]));
+
+        let mut handlers = vec![];
+        for _ in 0..2 {
+            let slice = slice.clone();
+            handlers.push(thread::spawn(move || {
+                let mut last_generation = 0;
+                for _ in 0..1_000 {
+                    let guard = epoch::pin();
+                    let view = slice.load(&guard);
+
+                    // A generation `n` slice has length `n + 1`.
+                    assert_eq!(view.items.len() as u64, view.generation + 1);
+                    assert!(view.generation >= last_generation);
+                    last_generation = view.generation;
+                }
+            }));
+        }
+
+        for len in 2..100 {
+            slice.swap(vec![0; len], &epoch::pin());
+        }
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+    }
+}
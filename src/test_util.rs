@@ -0,0 +1,332 @@
+//! Deterministic execution of concurrent operations, for tests of this crate
+//! and of crates building on it.
+//!
+//! A [`Pool`] runs a fixed number of logical threads one at a time, switching
+//! between them at interleaving points only, i.e. at each decision of a
+//! [`Scheduled`] strategy and at each [`yield_now`]. Which logical thread
+//! runs next is drawn from a seeded pseudo random number generator, thus a
+//! seed reproduces the exact interleaving, e.g. the one of a failing test,
+//! independent of the machine and its load.
+//!
+//! ```rust
+//! # use elimination_backoff_stack::Stack;
+//! # use elimination_backoff_stack::strategy::ExpRetryStrategy;
+//! # use elimination_backoff_stack::test_util::{Pool, Scheduled};
+//! type S = Scheduled<ExpRetryStrategy>;
+//!
+//! for seed in 0..10 {
+//!     let stack = Stack::<usize, S, S>::new();
+//!
+//!     let schedule = Pool::new(2, seed).run(|thread| {
+//!         stack.push(thread);
+//!         stack.pop();
+//!     });
+//!
+//!     // Same seed, same interleaving.
+//!     let stack = Stack::<usize, S, S>::new();
+//!     assert_eq!(
+//!         Pool::new(2, seed).run(|thread| {
+//!             stack.push(thread);
+//!             stack.pop();
+//!         }),
+//!         schedule,
+//!     );
+//! }
+//! ```
+//!
+//! Operations blocking the calling thread, e.g. [`crate::Stack::pop_blocking`],
+//! never reach the next interleaving point, thus must not be used within a
+//! [`Pool`].
+//!
+//! Requires the `test-util` feature. Not covered by semver.
+
+use crate::strategy::{CasFailure, Strategy, Tuning};
+use std::cell::RefCell;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+/// Runs logical threads one at a time in a seeded, thus reproducible,
+/// interleaving.
+pub struct Pool {
+    threads: usize,
+    seed: u64,
+}
+
+impl Pool {
+    /// # Panics
+    ///
+    /// Panics if `threads` is zero.
+    pub fn new(threads: usize, seed: u64) -> Self {
+        assert!(threads > 0, "pool needs at least one thread");
+        Pool { threads, seed }
+    }
+
+    /// Run `f` on each logical thread, passing the index of the thread,
+    /// returning once all of them finished.
+    ///
+    /// Returns the schedule, i.e. the index of the logical thread run after
+    /// each interleaving point, the first one included. Equal for equal seeds,
+    /// given that `f` is deterministic.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` panicked on any logical thread, after the remaining ones
+    /// finished.
+    pub fn run<F>(&self, f: F) -> Vec<usize>
+    where
+        F: Fn(usize) + Sync,
+    {
+        let scheduler = Arc::new(Scheduler {
+            state: Mutex::new(State {
+                current: 0,
+                finished: vec![false; self.threads],
+                rng: self.seed,
+                schedule: vec![],
+            }),
+            turn: Condvar::new(),
+        });
+        scheduler.pass_turn(&mut scheduler.lock());
+
+        thread::scope(|scope| {
+            for thread in 0..self.threads {
+                let (scheduler, f) = (scheduler.clone(), &f);
+                scope.spawn(move || {
+                    // Passes the turn on, even if `f` panics.
+                    let _finish = Finish(&scheduler, thread);
+                    drop(scheduler.wait_for_turn(scheduler.lock(), thread));
+
+                    CURRENT.with(|c| *c.borrow_mut() = Some((scheduler.clone(), thread)));
+                    f(thread);
+                    CURRENT.with(|c| *c.borrow_mut() = None);
+                });
+            }
+        });
+
+        let schedule = std::mem::take(&mut scheduler.lock().schedule);
+        schedule
+    }
+}
+
+thread_local! {
+    /// Scheduler and index of the logical thread run by this thread, if any.
+    static CURRENT: RefCell<Option<(Arc<Scheduler>, usize)>> = const { RefCell::new(None) };
+}
+
+/// Interleaving point, passing the turn to the logical thread drawn next.
+///
+/// A no-op outside of a [`Pool`].
+pub fn yield_now() {
+    let current = CURRENT.with(|c| c.borrow().clone());
+    if let Some((scheduler, thread)) = current {
+        let mut state = scheduler.lock();
+        scheduler.pass_turn(&mut state);
+        drop(scheduler.wait_for_turn(state, thread));
+    }
+}
+
+struct Scheduler {
+    state: Mutex<State>,
+    turn: Condvar,
+}
+
+struct State {
+    /// Logical thread whose turn it is.
+    current: usize,
+    finished: Vec<bool>,
+    /// State of the xorshift generator, independent of the version of `rand`
+    /// for the sake of stable schedules.
+    rng: u64,
+    schedule: Vec<usize>,
+}
+
+impl Scheduler {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // A panicking logical thread never holds the lock while running `f`.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Draw the logical thread to run next among the unfinished ones, if any.
+    fn pass_turn(&self, state: &mut State) {
+        let unfinished = state.finished.iter().filter(|f| !**f).count();
+        if unfinished == 0 {
+            return;
+        }
+
+        // xorshift64*, seeded with a non-zero state.
+        let mut x = state.rng.wrapping_add(0x9E37_79B9_7F4A_7C15) | 1;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.rng = x;
+        let draw = (x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 32) as usize % unfinished;
+
+        state.current = state
+            .finished
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| !**f)
+            .nth(draw)
+            .map(|(thread, _)| thread)
+            .expect("draw to be in bounds");
+        state.schedule.push(state.current);
+        self.turn.notify_all();
+    }
+
+    fn wait_for_turn<'a>(
+        &self,
+        state: MutexGuard<'a, State>,
+        thread: usize,
+    ) -> MutexGuard<'a, State> {
+        self.turn
+            .wait_while(state, |state| state.current != thread)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Marks the logical thread finished on drop, passing the turn on.
+struct Finish<'a>(&'a Scheduler, usize);
+
+impl Drop for Finish<'_> {
+    fn drop(&mut self) {
+        CURRENT.with(|c| *c.borrow_mut() = None);
+        let mut state = self.0.lock();
+        state.finished[self.1] = true;
+        if state.current == self.1 {
+            self.0.pass_turn(&mut state);
+        }
+    }
+}
+
+/// Wraps a [`Strategy`], placing an interleaving point, see [`yield_now`],
+/// before each of its decisions. Behaves like the wrapped strategy outside of
+/// a [`Pool`].
+pub struct Scheduled<S>(S);
+
+impl<S> Scheduled<S> {
+    fn decide<R>(&mut self, decide: impl FnOnce(&mut S) -> R) -> R {
+        yield_now();
+        decide(&mut self.0)
+    }
+}
+
+impl<S: Strategy> Strategy for Scheduled<S> {
+    fn new() -> Self {
+        Scheduled(S::new())
+    }
+
+    fn with_tuning(tuning: &Tuning) -> Self {
+        Scheduled(S::with_tuning(tuning))
+    }
+
+    fn use_elimination_array(&mut self) -> bool {
+        self.decide(S::use_elimination_array)
+    }
+
+    fn try_stack(&mut self) -> bool {
+        self.decide(S::try_stack)
+    }
+
+    fn try_elimination_array(&mut self) -> bool {
+        self.decide(S::try_elimination_array)
+    }
+
+    fn num_exchangers(&mut self, total: usize) -> usize {
+        self.0.num_exchangers(total)
+    }
+
+    fn try_start_exchange(&mut self) -> bool {
+        self.decide(S::try_start_exchange)
+    }
+
+    fn retry_check_exchanged(&mut self) -> bool {
+        self.decide(S::retry_check_exchanged)
+    }
+
+    fn try_exchange(&mut self) -> bool {
+        self.decide(S::try_exchange)
+    }
+
+    fn on_contention(&mut self) {
+        self.0.on_contention()
+    }
+
+    fn on_no_contention(&mut self) {
+        self.0.on_no_contention()
+    }
+
+    fn use_pop_interest(&mut self) -> bool {
+        self.decide(S::use_pop_interest)
+    }
+
+    fn on_cas_failure(&mut self, reason: CasFailure) {
+        self.0.on_cas_failure(reason)
+    }
+
+    fn on_cas_success(&mut self) {
+        self.0.on_cas_success()
+    }
+
+    fn after_cas_failure(&mut self) {
+        self.0.after_cas_failure()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::ExpRetryStrategy;
+    use crate::Stack;
+
+    type S = Scheduled<ExpRetryStrategy>;
+
+    /// Pushes and pops concurrently, returning the schedule and the items
+    /// popped by each logical thread.
+    fn push_and_pop(seed: u64) -> (Vec<usize>, Vec<Vec<Option<usize>>>) {
+        let stack = Stack::<usize, S, S>::new();
+        let popped = Mutex::new(vec![vec![]; 3]);
+
+        let schedule = Pool::new(3, seed).run(|thread| {
+            for i in 0..10 {
+                stack.push(thread * 10 + i);
+                let item = stack.pop();
+                popped.lock().unwrap()[thread].push(item);
+            }
+        });
+
+        (schedule, popped.into_inner().unwrap())
+    }
+
+    #[test]
+    fn same_seed_same_interleaving() {
+        let (schedule, popped) = push_and_pop(42);
+        assert_eq!(push_and_pop(42), (schedule.clone(), popped));
+
+        // Logical threads actually interleave.
+        assert!(schedule.windows(2).any(|w| w[0] != w[1]), "{:?}", schedule);
+        assert!(
+            (0..100).any(|seed| push_and_pop(seed).0 != schedule),
+            "seed to matter"
+        );
+    }
+
+    #[test]
+    fn panic_propagates_after_remaining_threads_finish() {
+        let finished = Mutex::new(vec![]);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Pool::new(3, 0).run(|thread| {
+                yield_now();
+                if thread == 1 {
+                    panic!("logical thread 1");
+                }
+                yield_now();
+                finished.lock().unwrap().push(thread);
+            })
+        }));
+
+        assert!(result.is_err());
+        let mut finished = finished.into_inner().unwrap();
+        finished.sort_unstable();
+        assert_eq!(finished, vec![0, 2]);
+    }
+}
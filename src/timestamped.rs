@@ -0,0 +1,149 @@
+//! Stack recording when each item was pushed, for monitoring the health of
+//! work queues.
+//!
+//! A stack whose items keep getting older signals stalled consumers, even
+//! while its length looks unsuspicious. Timestamping costs a read of the
+//! clock per push and the space of an [`Instant`] per item, thus is opt-in
+//! via [`TimestampedStack`] instead of built into [`Stack`].
+
+use crate::conservation::Ledger;
+use crate::strategy::ExpRetryStrategy;
+use crate::{PopStrategy, PushStrategy, Stack};
+use std::ops::ControlFlow;
+use std::ptr;
+use std::time::{Duration, Instant};
+
+struct Timestamped<T> {
+    pushed_at: Instant,
+    item: T,
+}
+
+/// [`Stack`] recording when each item was pushed, exposing the age of the
+/// items currently on the stack.
+///
+/// ```rust
+/// # use elimination_backoff_stack::TimestampedStack;
+/// # use std::time::Duration;
+/// let stack = TimestampedStack::<String>::new();
+/// stack.push("job".to_string());
+///
+/// // E.g. alert once consumers fall behind.
+/// let stale = stack
+///     .oldest_age_estimate()
+///     .map_or(false, |age| age > Duration::from_secs(60));
+/// assert!(!stale);
+/// ```
+pub struct TimestampedStack<T, PushS = ExpRetryStrategy, PopS = ExpRetryStrategy> {
+    stack: Stack<Timestamped<T>, PushS, PopS>,
+}
+
+impl<T, PushS, PopS> TimestampedStack<T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    pub fn new() -> Self {
+        TimestampedStack {
+            stack: Stack::new(),
+        }
+    }
+
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn push(&self, item: T) {
+        self.stack.push(Timestamped {
+            pushed_at: Instant::now(),
+            item,
+        });
+    }
+
+    #[cfg_attr(feature = "debug-conservation", track_caller)]
+    pub fn pop(&self) -> Option<T> {
+        self.stack.pop().map(|timestamped| timestamped.item)
+    }
+
+    /// See [`Stack::len`].
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// See [`Stack::is_empty`].
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Time since the item currently on top was pushed, `None` if the stack
+    /// was found empty.
+    ///
+    /// In a busy stack the top is the most recently pushed item, thus this
+    /// merely tells how long ago the last push happened.
+    pub fn age_of_top(&self) -> Option<Duration> {
+        match self.for_each_pushed_at(ControlFlow::Break) {
+            ControlFlow::Break(pushed_at) => Some(pushed_at.elapsed()),
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
+    /// Time since the item at the bottom, usually the oldest one, was pushed,
+    /// `None` if the stack was found empty.
+    ///
+    /// Walks the whole stack, thus takes time linear in its length. Like
+    /// [`Stack::clone_contents`] the walk is weakly consistent, thus an
+    /// estimate in the presence of concurrent operations.
+    pub fn oldest_age_estimate(&self) -> Option<Duration> {
+        let mut oldest = None;
+        let _ = self.for_each_pushed_at(|pushed_at| {
+            oldest = Some(oldest.map_or(pushed_at, |o: Instant| o.min(pushed_at)));
+            ControlFlow::<()>::Continue(())
+        });
+        oldest.map(|pushed_at| pushed_at.elapsed())
+    }
+
+    /// Calls `f` with the timestamp of each item currently on the stack, top
+    /// first.
+    fn for_each_pushed_at<B>(&self, f: impl FnMut(Instant) -> ControlFlow<B>) -> ControlFlow<B> {
+        // Safe, given that the timestamp is stored inline and points nowhere.
+        unsafe {
+            self.stack.stack.for_each_field(
+                |stored| ptr::addr_of!((*Ledger::peek_raw(stored)).pushed_at),
+                f,
+            )
+        }
+    }
+}
+
+impl<T, PushS, PopS> Default for TimestampedStack<T, PushS, PopS>
+where
+    PushS: PushStrategy,
+    PopS: PopStrategy,
+{
+    fn default() -> Self {
+        TimestampedStack::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn ages_of_top_and_bottom() {
+        let stack = TimestampedStack::<String>::new();
+        assert_eq!(stack.age_of_top(), None);
+        assert_eq!(stack.oldest_age_estimate(), None);
+
+        stack.push("oldest".to_string());
+        thread::sleep(Duration::from_millis(20));
+        stack.push("newest".to_string());
+
+        let (top, oldest) = (
+            stack.age_of_top().unwrap(),
+            stack.oldest_age_estimate().unwrap(),
+        );
+        assert!(oldest >= Duration::from_millis(20), "{:?}", oldest);
+        assert!(top < oldest, "{:?} {:?}", top, oldest);
+
+        assert_eq!(stack.pop().as_deref(), Some("newest"));
+        assert!(stack.age_of_top().unwrap() >= Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,160 @@
+//! Plain lock-free stack, i.e. the Treiber stack underlying [`crate::Stack`]
+//! without the elimination array and without strategies.
+//!
+//! Meant for use cases not contended enough to benefit from elimination,
+//! which would otherwise pull in another crate for a lock-free stack.
+//! Contended operations back off by spinning for exponentially longer after
+//! each failed compare-and-swap, like the [`NoEliminationStrategy`].
+//!
+//! [`NoEliminationStrategy`]: crate::strategy::NoEliminationStrategy
+
+use crate::treiber_stack::{self, PopResult};
+use std::fmt;
+use std::iter::FromIterator;
+
+/// Treiber's lock-free stack, usable with any number of producers and
+/// consumers.
+///
+/// ```rust
+/// # use elimination_backoff_stack::treiber::TreiberStack;
+/// # use std::sync::Arc;
+/// # use std::thread;
+/// let stack = Arc::new(TreiberStack::new());
+///
+/// let producer = {
+///     let stack = stack.clone();
+///     thread::spawn(move || stack.push(1))
+/// };
+/// producer.join().unwrap();
+///
+/// assert_eq!(stack.pop(), Some(1));
+/// assert_eq!(stack.pop(), None);
+/// ```
+pub struct TreiberStack<T>(treiber_stack::TreiberStack<T>);
+
+impl<T> TreiberStack<T> {
+    pub fn new() -> Self {
+        TreiberStack(treiber_stack::TreiberStack::new())
+    }
+
+    pub fn push(&self, item: T) {
+        if self.0.push(item, &mut Backoff::default()).is_err() {
+            unreachable!("never gives up");
+        }
+    }
+
+    /// Pop the most recently pushed item, `None` if the stack was found empty.
+    pub fn pop(&self) -> Option<T> {
+        match self.0.pop(&mut Backoff::default()) {
+            PopResult::Popped(item) => Some(item),
+            PopResult::Empty => None,
+            PopResult::Contended => unreachable!("never gives up"),
+        }
+    }
+
+    /// Approximate number of items on the stack, see [`crate::Stack::len`].
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the stack appeared empty, see [`crate::Stack::is_empty`].
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T> Default for TreiberStack<T> {
+    fn default() -> Self {
+        TreiberStack::new()
+    }
+}
+
+/// Collects the items into a new stack, the last item on top.
+impl<T> FromIterator<T> for TreiberStack<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(items: I) -> Self {
+        TreiberStack(treiber_stack::TreiberStack::from_items(items))
+    }
+}
+
+impl<T> fmt::Debug for TreiberStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TreiberStack")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Retries until the operation completes, spinning after each failed
+/// compare-and-swap, doubling the spin loop iterations up to a bound.
+#[derive(Default)]
+struct Backoff {
+    cas_failures: u8,
+}
+
+impl Backoff {
+    fn after_cas_failure(&mut self) {
+        for _ in 0..1u32 << self.cas_failures.min(6) {
+            std::hint::spin_loop();
+        }
+        self.cas_failures = self.cas_failures.saturating_add(1);
+    }
+}
+
+impl treiber_stack::PushStrategy for Backoff {
+    fn try_push(&mut self) -> bool {
+        true
+    }
+
+    fn after_cas_failure(&mut self) {
+        Backoff::after_cas_failure(self)
+    }
+}
+
+impl treiber_stack::PopStrategy for Backoff {
+    fn try_pop(&mut self) -> bool {
+        true
+    }
+
+    fn after_cas_failure(&mut self) {
+        Backoff::after_cas_failure(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::thread;
+
+    #[test]
+    fn concurrent_no_duplicates_no_losses() {
+        let (threads, items_per_thread) = (4, 10_000);
+        let stack = (0..10)
+            .map(|i| threads * items_per_thread + i)
+            .collect::<TreiberStack<_>>();
+        let popped = Mutex::new(vec![]);
+
+        thread::scope(|scope| {
+            for thread in 0..threads {
+                let (stack, popped) = (&stack, &popped);
+                scope.spawn(move || {
+                    let mut own = vec![];
+                    for i in 0..items_per_thread {
+                        stack.push(thread * items_per_thread + i);
+                        own.extend(stack.pop());
+                    }
+                    popped.lock().unwrap().extend(own);
+                });
+            }
+        });
+
+        let mut popped = popped.into_inner().unwrap();
+        popped.extend(std::iter::from_fn(|| stack.pop()));
+        popped.sort_unstable();
+        assert_eq!(
+            popped,
+            (0..threads * items_per_thread + 10).collect::<Vec<_>>()
+        );
+        assert!(stack.is_empty());
+    }
+}
@@ -7,20 +7,46 @@
 //! [2]:
 //! https://github.com/crossbeam-rs/crossbeam/blob/master/crossbeam-epoch/examples/treiber_stack.rs
 
+use crate::counter::Counter;
+use crate::strategy::CasFailure;
 use crossbeam::epoch;
 
-use std::mem::ManuallyDrop;
+use crate::ordering::{AcqRel, Acquire, Relaxed, Release};
+use std::mem::{self, ManuallyDrop};
+use std::ops::ControlFlow;
 use std::ptr;
-use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::atomic::AtomicUsize;
 
 use epoch::{Atomic, Owned};
 
 /// Treiber's lock-free stack.
 ///
 /// Usable with any number of producers and consumers.
+///
+/// Zero-sized items carry no data, thus instead of allocating a node per item
+/// they are only counted. Pushing them never fails.
 #[derive(Debug, Default)]
 pub struct TreiberStack<T> {
     head: Atomic<Node<T>>,
+    /// Number of zero-sized items on the stack. Unused otherwise.
+    zst_len: AtomicUsize,
+    /// Approximate number of items on the stack, see [`TreiberStack::len`].
+    /// Unused for zero-sized items.
+    len: Counter,
+}
+
+/// Outcome of [`TreiberStack::pop`].
+///
+/// A named enum instead of `Result<Option<T>, ()>`, thus callers can not
+/// accidentally treat a contended stack as an empty one or vice versa.
+#[must_use]
+#[derive(Debug, PartialEq)]
+pub(crate) enum PopResult<T> {
+    Popped(T),
+    /// The stack was empty at the time of the attempt.
+    Empty,
+    /// The strategy gave up after failing compare-and-swaps.
+    Contended,
 }
 
 #[derive(Debug)]
@@ -29,16 +55,95 @@ struct Node<T> {
     next: Atomic<Node<T>>,
 }
 
+/// Items linked into nodes locally, not shared with any other thread, to be
+/// pushed at once via [`TreiberStack::push_chain`].
+#[derive(Debug)]
+pub struct Chain<T> {
+    top: Option<Owned<Node<T>>>,
+    /// First item, thus the node linked to the current head on push. Valid as
+    /// long as `top` is `Some`.
+    bottom: *const Node<T>,
+    len: usize,
+}
+
+impl<T> Chain<T> {
+    pub fn new(items: impl IntoIterator<Item = T>) -> Chain<T> {
+        let mut chain = Chain {
+            top: None,
+            bottom: ptr::null(),
+            len: 0,
+        };
+
+        for item in items {
+            chain.len += 1;
+
+            if mem::size_of::<T>() == 0 {
+                mem::forget(item);
+                continue;
+            }
+
+            let n = Owned::new(Node {
+                data: ManuallyDrop::new(item),
+                next: Atomic::null(),
+            });
+            match chain.top.take() {
+                Some(top) => n.next.store(top, Relaxed),
+                // Moving an `Owned` does not move the node on the heap.
+                None => chain.bottom = &*n,
+            }
+            chain.top = Some(n);
+        }
+
+        chain
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Drops the items of a chain never pushed, e.g. when an item iterator panics.
+impl<T> Drop for Chain<T> {
+    fn drop(&mut self) {
+        if mem::size_of::<T>() == 0 {
+            for _ in 0..self.len {
+                // See `TreiberStack::pop_zst`.
+                drop(unsafe { ptr::NonNull::<T>::dangling().as_ptr().read() });
+            }
+            return;
+        }
+
+        // Safe given that the chain is not shared.
+        let guard = unsafe { epoch::unprotected() };
+        let mut next = self.top.take();
+        while let Some(node) = next {
+            let mut node = node.into_box();
+            next = unsafe { node.next.load(Relaxed, guard).try_into_owned() };
+            unsafe { ManuallyDrop::drop(&mut node.data) };
+        }
+    }
+}
+
 impl<T> TreiberStack<T> {
     /// Creates a new, empty stack.
     pub fn new() -> TreiberStack<T> {
         TreiberStack {
             head: Atomic::null(),
+            zst_len: AtomicUsize::new(0),
+            len: Counter::new(),
         }
     }
 
     /// Pushes a value on top of the stack.
+    #[inline]
     pub fn push<S: PushStrategy>(&self, t: T, strategy: &mut S) -> Result<(), T> {
+        if mem::size_of::<T>() == 0 {
+            mem::forget(t);
+            // Acquire to synchronize with `confirm_empty`.
+            self.zst_len.fetch_add(1, AcqRel);
+            return Ok(());
+        }
+
         let mut n = Owned::new(Node {
             data: ManuallyDrop::new(t),
             next: Atomic::null(),
@@ -50,10 +155,17 @@ impl<T> TreiberStack<T> {
             let head = self.head.load(Relaxed, &guard);
             n.next.store(head, Relaxed);
 
-            match self.head.compare_and_set(head, n, Release, &guard) {
-                Ok(_) => return Ok(()),
+            // Acquire to synchronize with `confirm_empty`.
+            match self.head.compare_exchange(head, n, AcqRel, Relaxed, &guard) {
+                Ok(_) => {
+                    strategy.on_cas_success();
+                    self.len.add(1);
+                    return Ok(());
+                }
                 Err(e) => {
                     n = e.new;
+                    strategy.on_cas_failure(CasFailure::LostRace);
+                    strategy.after_cas_failure();
                 }
             }
         }
@@ -65,60 +177,878 @@ impl<T> TreiberStack<T> {
         //
         // See:
         // https://stackoverflow.com/questions/42264041/how-do-i-get-an-owned-value-out-of-a-box
-        Err(ManuallyDrop::into_inner((*n.into_box()).data))
+        Err(ManuallyDrop::into_inner(n.into_box().data))
+    }
+
+    /// Pushes all items of `chain` at once, the last item on top, with a
+    /// single compare-and-swap on the head.
+    pub fn push_chain<S: PushStrategy>(
+        &self,
+        mut chain: Chain<T>,
+        strategy: &mut S,
+    ) -> Result<(), Chain<T>> {
+        if mem::size_of::<T>() == 0 {
+            // Acquire to synchronize with `confirm_empty`.
+            self.zst_len.fetch_add(mem::take(&mut chain.len), AcqRel);
+            return Ok(());
+        }
+
+        let mut top = match chain.top.take() {
+            Some(top) => top,
+            None => return Ok(()),
+        };
+
+        let guard = epoch::pin();
+
+        while strategy.try_push() {
+            let head = self.head.load(Relaxed, &guard);
+            // The chain is not shared yet, thus its bottom node neither.
+            unsafe { &*chain.bottom }.next.store(head, Relaxed);
+
+            // Acquire to synchronize with `confirm_empty`.
+            match self
+                .head
+                .compare_exchange(head, top, AcqRel, Relaxed, &guard)
+            {
+                Ok(_) => {
+                    strategy.on_cas_success();
+                    self.len.add(chain.len);
+                    return Ok(());
+                }
+                Err(e) => {
+                    top = e.new;
+                    strategy.on_cas_failure(CasFailure::LostRace);
+                    strategy.after_cas_failure();
+                }
+            }
+        }
+
+        // Unlink from the stack again, thus dropping the chain stops at its
+        // bottom node.
+        unsafe { &*chain.bottom }
+            .next
+            .store(epoch::Shared::null(), Relaxed);
+        chain.top = Some(top);
+        Err(chain)
     }
 
     /// Attempts to pop the top element from the stack.
-    pub fn pop<S: PopStrategy>(&self, strategy: &mut S) -> Result<Option<T>, ()> {
+    #[inline]
+    pub fn pop<S: PopStrategy>(&self, strategy: &mut S) -> PopResult<T> {
+        self.pop_with(strategy, |data| unsafe { ptr::read(data) })
+    }
+
+    /// [`TreiberStack::pop`] moving the element out via `take`, given a
+    /// pointer to it within its node, e.g. copying it straight into storage
+    /// of the caller instead of returning it by value.
+    ///
+    /// `take` runs before the node is handed to the garbage collector, which
+    /// frees the node without dropping the element, thus `take` has to take
+    /// ownership of the element.
+    #[inline]
+    pub fn pop_with<S: PopStrategy, O>(
+        &self,
+        strategy: &mut S,
+        take: impl FnOnce(*const T) -> O,
+    ) -> PopResult<O> {
+        if mem::size_of::<T>() == 0 {
+            return match self.pop_zst(strategy) {
+                PopResult::Popped(item) => {
+                    let item = ManuallyDrop::new(item);
+                    PopResult::Popped(take(&*item))
+                }
+                PopResult::Empty => PopResult::Empty,
+                PopResult::Contended => PopResult::Contended,
+            };
+        }
+
         let guard = epoch::pin();
-        let head = self.head.load(Acquire, &guard);
 
         while strategy.try_pop() {
+            let head = self.head.load(Acquire, &guard);
+
+            match unsafe { head.as_ref() } {
+                Some(h) => {
+                    let next = h.next.load(Relaxed, &guard);
+
+                    if self
+                        .head
+                        .compare_exchange(head, next, Release, Relaxed, &guard)
+                        .is_ok()
+                    {
+                        strategy.on_cas_success();
+                        self.len.sub(1);
+                        let taken = take(&*h.data);
+                        unsafe { guard.defer_destroy(head) };
+                        return PopResult::Popped(taken);
+                    }
+
+                    strategy.on_cas_failure(CasFailure::LostRace);
+                    strategy.after_cas_failure();
+                }
+                None => return PopResult::Empty,
+            }
+        }
+
+        PopResult::Contended
+    }
+
+    /// Pops the top element only if `accept` holds for it, evaluating `accept`
+    /// on the element in place before the compare-and-swap. Returns
+    /// [`PopResult::Empty`] if the stack was empty or `accept` rejected the
+    /// top element.
+    ///
+    /// Requires `T: Copy` for the same reason as
+    /// [`TreiberStack::for_each_ref`]. A successful compare-and-swap implies
+    /// the popped element is the one accepted, given that a node is not reused
+    /// while the epoch is pinned.
+    pub fn pop_if<S: PopStrategy>(
+        &self,
+        strategy: &mut S,
+        mut accept: impl FnMut(&T) -> bool,
+    ) -> PopResult<T>
+    where
+        T: Copy,
+    {
+        if mem::size_of::<T>() == 0 {
+            if self.zst_len.load(Acquire) == 0 {
+                return PopResult::Empty;
+            }
+            // A zero-sized `Copy` value is as good as any other.
+            if !accept(&unsafe { ptr::NonNull::<T>::dangling().as_ptr().read() }) {
+                return PopResult::Empty;
+            }
+            return self.pop_zst(strategy);
+        }
+
+        let guard = epoch::pin();
+
+        while strategy.try_pop() {
+            let head = self.head.load(Acquire, &guard);
+
             match unsafe { head.as_ref() } {
                 Some(h) => {
+                    if !accept(&h.data) {
+                        return PopResult::Empty;
+                    }
+
                     let next = h.next.load(Relaxed, &guard);
 
-                    match self.head.compare_and_set(head, next, Release, &guard) {
-                        Ok(_) => unsafe {
+                    if self
+                        .head
+                        .compare_exchange(head, next, Release, Relaxed, &guard)
+                        .is_ok()
+                    {
+                        strategy.on_cas_success();
+                        self.len.sub(1);
+                        unsafe {
                             guard.defer_destroy(head);
-                            return Ok(Some(ManuallyDrop::into_inner(ptr::read(&(*h).data))));
-                        },
-                        Err(_) => {}
+                            return PopResult::Popped(ManuallyDrop::into_inner(ptr::read(&h.data)));
+                        }
+                    }
+
+                    strategy.on_cas_failure(CasFailure::LostRace);
+                    strategy.after_cas_failure();
+                }
+                None => return PopResult::Empty,
+            }
+        }
+
+        PopResult::Contended
+    }
+
+    /// Attempts to pop up to `max` elements from the top of the stack at once,
+    /// with a single compare-and-swap on the head, calling `f` on each, top
+    /// first.
+    ///
+    /// `max` has to be non-zero.
+    pub fn pop_n<S: PopStrategy>(
+        &self,
+        max: usize,
+        strategy: &mut S,
+        mut f: impl FnMut(T),
+    ) -> PopResult<usize> {
+        self.pop_n_with(max, strategy, |data| f(unsafe { ptr::read(data) }))
+    }
+
+    /// [`TreiberStack::pop_n`] moving each element out via `take`, see
+    /// [`TreiberStack::pop_with`].
+    pub fn pop_n_with<S: PopStrategy>(
+        &self,
+        max: usize,
+        strategy: &mut S,
+        mut take: impl FnMut(*const T),
+    ) -> PopResult<usize> {
+        debug_assert!(max > 0);
+
+        if mem::size_of::<T>() == 0 {
+            return self.pop_n_zst(max, strategy, take);
+        }
+
+        let guard = epoch::pin();
+
+        while strategy.try_pop() {
+            let head = self.head.load(Acquire, &guard);
+            if head.is_null() {
+                return PopResult::Empty;
+            }
+
+            // Find the first node to remain on the stack. Nodes below the head
+            // only change once the head changes, thus are stable as long as the
+            // compare-and-swap below succeeds.
+            let mut rest = head;
+            let mut n = 0;
+            while n < max {
+                match unsafe { rest.as_ref() } {
+                    Some(node) => rest = node.next.load(Acquire, &guard),
+                    None => break,
+                }
+                n += 1;
+            }
+
+            if self
+                .head
+                .compare_exchange(head, rest, Release, Relaxed, &guard)
+                .is_ok()
+            {
+                strategy.on_cas_success();
+                self.len.sub(n);
+
+                let mut current = head;
+                for _ in 0..n {
+                    unsafe {
+                        let node = current.deref();
+                        let next = node.next.load(Relaxed, &guard);
+                        take(&*node.data);
+                        guard.defer_destroy(current);
+                        current = next;
                     }
                 }
-                None => return Ok(None),
+
+                return PopResult::Popped(n);
+            }
+
+            strategy.on_cas_failure(CasFailure::LostRace);
+            strategy.after_cas_failure();
+        }
+
+        PopResult::Contended
+    }
+
+    fn pop_n_zst<S: PopStrategy>(
+        &self,
+        max: usize,
+        strategy: &mut S,
+        mut take: impl FnMut(*const T),
+    ) -> PopResult<usize> {
+        while strategy.try_pop() {
+            let len = self.zst_len.load(Acquire);
+            if len == 0 {
+                return PopResult::Empty;
+            }
+
+            let n = len.min(max);
+            if self
+                .zst_len
+                .compare_exchange(len, len - n, Acquire, Relaxed)
+                .is_ok()
+            {
+                strategy.on_cas_success();
+                for _ in 0..n {
+                    // See `pop_zst`.
+                    take(ptr::NonNull::<T>::dangling().as_ptr());
+                }
+                return PopResult::Popped(n);
+            }
+
+            strategy.on_cas_failure(CasFailure::LostRace);
+            strategy.after_cas_failure();
+        }
+
+        PopResult::Contended
+    }
+
+    /// Detaches all items at once by swapping the head with null, calling `f`
+    /// on each, top first. Returns the number of items.
+    ///
+    /// Items are moved out right away, while destroying the nodes is deferred
+    /// like for any popped node, as concurrent operations might still be
+    /// reading them. In case `f` panics, the items below are still dropped
+    /// before the panic is propagated.
+    pub fn take_all(&self, mut f: impl FnMut(T)) -> usize {
+        if mem::size_of::<T>() == 0 {
+            let n = self.zst_len.swap(0, Acquire);
+            for _ in 0..n {
+                // See `pop_zst`.
+                f(unsafe { ptr::NonNull::<T>::dangling().as_ptr().read() });
+            }
+            return n;
+        }
+
+        let guard = epoch::pin();
+
+        let mut detached = Detached {
+            current: self.head.swap(epoch::Shared::null(), AcqRel, &guard),
+            guard: &guard,
+            len: &self.len,
+            n: 0,
+        };
+        for item in &mut detached {
+            f(item);
+        }
+        detached.n
+    }
+
+    /// Single attempt to pop the top element, allowed to fail spuriously.
+    ///
+    /// Uses a weak compare-and-swap, which may fail even without contention,
+    /// and never retries.
+    #[inline]
+    pub fn pop_weak(&self) -> PopResult<T> {
+        if mem::size_of::<T>() == 0 {
+            let len = self.zst_len.load(Acquire);
+            if len == 0 {
+                return PopResult::Empty;
             }
+
+            return match self
+                .zst_len
+                .compare_exchange_weak(len, len - 1, Acquire, Relaxed)
+            {
+                // See `pop_zst`.
+                Ok(_) => {
+                    PopResult::Popped(unsafe { ptr::NonNull::<T>::dangling().as_ptr().read() })
+                }
+                Err(_) => PopResult::Contended,
+            };
         }
 
-        Err(())
+        let guard = epoch::pin();
+
+        // `Acquire` instead of `Relaxed`, given that the node is dereferenced
+        // below.
+        let head = self.head.load(Acquire, &guard);
+        let h = match unsafe { head.as_ref() } {
+            Some(h) => h,
+            None => return PopResult::Empty,
+        };
+
+        let next = h.next.load(Relaxed, &guard);
+        match self
+            .head
+            .compare_exchange_weak(head, next, Release, Relaxed, &guard)
+        {
+            Ok(_) => unsafe {
+                self.len.sub(1);
+                guard.defer_destroy(head);
+                PopResult::Popped(ManuallyDrop::into_inner(ptr::read(&h.data)))
+            },
+            Err(_) => PopResult::Contended,
+        }
     }
+
+    #[inline]
+    fn pop_zst<S: PopStrategy>(&self, strategy: &mut S) -> PopResult<T> {
+        while strategy.try_pop() {
+            let len = self.zst_len.load(Acquire);
+            if len == 0 {
+                return PopResult::Empty;
+            }
+
+            if self
+                .zst_len
+                .compare_exchange(len, len - 1, Acquire, Relaxed)
+                .is_ok()
+            {
+                strategy.on_cas_success();
+                // Reading a zero-sized value reads no memory. The counter
+                // witnesses that such a value was pushed and forgotten before,
+                // thus this hands out the very same value.
+                return PopResult::Popped(unsafe { ptr::NonNull::<T>::dangling().as_ptr().read() });
+            }
+
+            strategy.on_cas_failure(CasFailure::LostRace);
+            strategy.after_cas_failure();
+        }
+
+        PopResult::Contended
+    }
+
+    /// Approximate number of items on the stack, exact in the absence of
+    /// concurrent operations.
+    pub fn len(&self) -> usize {
+        if mem::size_of::<T>() == 0 {
+            return self.zst_len.load(Relaxed);
+        }
+
+        self.len.get()
+    }
+
+    /// Whether the stack appeared empty at the time of a single load of the
+    /// head.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        if mem::size_of::<T>() == 0 {
+            return self.zst_len.load(Acquire) == 0;
+        }
+
+        let guard = epoch::pin();
+        self.head.load(Acquire, &guard).is_null()
+    }
+
+    /// Whether the stack is empty, confirmed via a read-modify-write. Thus a
+    /// concurrent push operation, whose compare-and-swap acquires the head,
+    /// either comes first or observes everything written before this call.
+    pub(crate) fn confirm_empty(&self) -> bool {
+        if mem::size_of::<T>() == 0 {
+            return self.zst_len.compare_exchange(0, 0, AcqRel, Acquire).is_ok();
+        }
+
+        let guard = epoch::pin();
+        let null = epoch::Shared::null();
+        self.head
+            .compare_exchange(null, null, AcqRel, Acquire, &guard)
+            .is_ok()
+    }
+
+    /// Copies all items currently on the stack, top first, without removing
+    /// them.
+    pub fn clone_contents(&self) -> Vec<T>
+    where
+        T: Copy,
+    {
+        let mut items = vec![];
+        let _ = self.for_each_ref(|item| {
+            items.push(*item);
+            ControlFlow::<()>::Continue(())
+        });
+        items
+    }
+
+    /// Calls `f` on each item currently on the stack, top first, without
+    /// removing them, until `f` returns [`ControlFlow::Break`].
+    ///
+    /// Requires `T: Copy`, given that a concurrent pop operation might move an
+    /// item out of its node while `f` holds a reference to it. The node itself
+    /// stays allocated as long as the epoch is pinned, but for any other `T`
+    /// the new owner might already have freed what the item points to.
+    pub fn for_each_ref<B, F>(&self, mut f: F) -> ControlFlow<B>
+    where
+        T: Copy,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        if mem::size_of::<T>() == 0 {
+            let len = self.zst_len.load(Acquire);
+            // A zero-sized `Copy` value is as good as any other.
+            let item = unsafe { ptr::NonNull::<T>::dangling().as_ptr().read() };
+            for _ in 0..len {
+                f(&item)?;
+            }
+            return ControlFlow::Continue(());
+        }
+
+        let guard = epoch::pin();
+
+        let mut current = self.head.load(Acquire, &guard);
+        while let Some(node) = unsafe { current.as_ref() } {
+            f(&node.data)?;
+            current = node.next.load(Acquire, &guard);
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Iterator over the items currently on the stack, top first, without
+    /// removing them. Like [`TreiberStack::for_each_ref`], though the items
+    /// are referenced as long as `guard` keeps the epoch pinned.
+    pub fn iter<'g>(&'g self, guard: &'g epoch::Guard) -> Iter<'g, T>
+    where
+        T: Copy,
+    {
+        Iter {
+            current: self.head.load(Acquire, guard),
+            zsts: if mem::size_of::<T>() == 0 {
+                self.zst_len.load(Acquire)
+            } else {
+                0
+            },
+            guard,
+        }
+    }
+
+    /// Like [`TreiberStack::for_each_ref`], though copying a field out of each
+    /// item instead of referencing the item, thus not requiring `T: Copy`.
+    ///
+    /// # Safety
+    ///
+    /// `field` has to project a pointer to an item to a pointer to a field
+    /// stored inline in the item, e.g. via `ptr::addr_of!`, without
+    /// dereferencing the item. The field itself must not point anywhere, e.g.
+    /// be a timestamp or a number. A concurrent pop operation might move the
+    /// item out of its node in the meantime, though the bytes of the node stay
+    /// untouched as long as the epoch is pinned.
+    pub unsafe fn for_each_field<U, B, P, F>(&self, field: P, mut f: F) -> ControlFlow<B>
+    where
+        U: Copy,
+        P: Fn(*const T) -> *const U,
+        F: FnMut(U) -> ControlFlow<B>,
+    {
+        if mem::size_of::<T>() == 0 {
+            let len = self.zst_len.load(Acquire);
+            let item = ptr::NonNull::<T>::dangling().as_ptr();
+            for _ in 0..len {
+                f(field(item).read())?;
+            }
+            return ControlFlow::Continue(());
+        }
+
+        let guard = epoch::pin();
+
+        let mut current = self.head.load(Acquire, &guard);
+        while let Some(node) = current.as_ref() {
+            f(field(ptr::addr_of!(node.data).cast::<T>()).read())?;
+            current = node.next.load(Acquire, &guard);
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+impl<T> TreiberStack<T> {
+    /// Creates a stack holding `items`, the last item on top, as if pushed one
+    /// by one. Links the nodes directly, given that the stack is not shared
+    /// yet, thus neither compare-and-swaps nor pinning.
+    pub fn from_items(items: impl IntoIterator<Item = T>) -> TreiberStack<T> {
+        let mut stack = TreiberStack::new();
+        for item in items {
+            stack.push_exclusive(item);
+        }
+        stack
+    }
+
+    /// Calls `f` on each item, top first, given exclusive access, thus unlike
+    /// [`TreiberStack::for_each_ref`] not requiring `T: Copy`: no concurrent
+    /// pop operation can move an item out in the meantime.
+    pub(crate) fn for_each_exclusive(&mut self, mut f: impl FnMut(&T)) {
+        if mem::size_of::<T>() == 0 {
+            // A zero-sized item has no bytes to read.
+            let item = unsafe { &*ptr::NonNull::<T>::dangling().as_ptr() };
+            for _ in 0..*self.zst_len.get_mut() {
+                f(item);
+            }
+            return;
+        }
+
+        // Not shared, thus nothing to protect against.
+        let guard = unsafe { epoch::unprotected() };
+        let mut current = self.head.load(Relaxed, guard);
+        while let Some(node) = unsafe { current.as_ref() } {
+            f(&node.data);
+            current = node.next.load(Relaxed, guard);
+        }
+    }
+
+    /// Pushes a value given exclusive access, thus without contention.
+    pub(crate) fn push_exclusive(&mut self, t: T) {
+        if mem::size_of::<T>() == 0 {
+            mem::forget(t);
+            *self.zst_len.get_mut() += 1;
+            return;
+        }
+
+        // Safe given that `&mut self` excludes any concurrent access.
+        let guard = unsafe { epoch::unprotected() };
+        let head = self.head.load(Relaxed, guard);
+        let n = Owned::new(Node {
+            data: ManuallyDrop::new(t),
+            next: Atomic::null(),
+        });
+        n.next.store(head, Relaxed);
+        self.head.store(n, Relaxed);
+        self.len.add(1);
+    }
+
+    /// Pops the top element given exclusive access, thus without contention.
+    /// Frees the node right away instead of deferring it, given that no other
+    /// thread can hold a reference to it.
+    pub(crate) fn pop_exclusive(&mut self) -> Option<T> {
+        if mem::size_of::<T>() == 0 {
+            let len = self.zst_len.get_mut();
+            if *len == 0 {
+                return None;
+            }
+            *len -= 1;
+            // See `pop_zst`.
+            return Some(unsafe { ptr::NonNull::<T>::dangling().as_ptr().read() });
+        }
+
+        // Safe given that `&mut self` excludes any concurrent access.
+        let guard = unsafe { epoch::unprotected() };
+        let head = self.head.load(Relaxed, guard);
+        if head.is_null() {
+            return None;
+        }
+
+        let node = unsafe { head.into_owned() }.into_box();
+        self.head.store(node.next.load(Relaxed, guard), Relaxed);
+        self.len.sub(1);
+        Some(ManuallyDrop::into_inner(node.data))
+    }
+}
+
+/// Iterator of [`TreiberStack::iter`].
+pub(crate) struct Iter<'g, T> {
+    current: epoch::Shared<'g, Node<T>>,
+    /// Number of zero-sized items left to yield. Unused otherwise.
+    zsts: usize,
+    guard: &'g epoch::Guard,
 }
 
+impl<'g, T: Copy> Iterator for Iter<'g, T> {
+    type Item = &'g T;
+
+    fn next(&mut self) -> Option<&'g T> {
+        if mem::size_of::<T>() == 0 {
+            if self.zsts == 0 {
+                return None;
+            }
+            self.zsts -= 1;
+            // A zero-sized `Copy` value is as good as any other, and a
+            // dangling pointer a valid reference to it.
+            return Some(unsafe { &*ptr::NonNull::<T>::dangling().as_ptr() });
+        }
+
+        // Safe given that the node is not freed while the epoch is pinned.
+        let node = unsafe { self.current.as_ref() }?;
+        self.current = node.next.load(Acquire, self.guard);
+        Some(&node.data)
+    }
+}
+
+/// Nodes detached from a stack by [`TreiberStack::take_all`], not reachable
+/// by any operation starting later.
+struct Detached<'g, T> {
+    current: epoch::Shared<'g, Node<T>>,
+    guard: &'g epoch::Guard,
+    len: &'g Counter,
+    /// Number of items moved out so far.
+    n: usize,
+}
+
+impl<T> Iterator for Detached<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = unsafe { self.current.as_ref() }?;
+        unsafe {
+            let item = ManuallyDrop::into_inner(ptr::read(&node.data));
+            let next = node.next.load(Relaxed, self.guard);
+            self.guard.defer_destroy(self.current);
+            self.current = next;
+            self.n += 1;
+            Some(item)
+        }
+    }
+}
+
+/// Drops the items not moved out yet, e.g. when a caller panics, and accounts
+/// for all detached items in the length of the stack.
+impl<T> Drop for Detached<'_, T> {
+    fn drop(&mut self) {
+        for item in &mut *self {
+            drop(item);
+        }
+        self.len.sub(self.n);
+    }
+}
+
+/// Drops the remaining items top to bottom.
+///
+/// In case dropping an item panics, the items below are still dropped before
+/// the panic is propagated. A second panic while doing so aborts.
 impl<T> Drop for TreiberStack<T> {
     fn drop(&mut self) {
-        /// Used to enable `<TreiberStack<T> as Drop>::drop` to call
-        /// `TreiberStack::pop`.
-        //
-        // TODO: A bit of a hack. Can we do better?
-        struct DropStrategy {}
+        struct DropGuard<'a, T>(&'a mut TreiberStack<T>);
 
-        impl PopStrategy for DropStrategy {
-            fn try_pop(&mut self) -> bool {
-                true
+        impl<T> Drop for DropGuard<'_, T> {
+            fn drop(&mut self) {
+                // Only runs when dropping an item panicked. Continue dropping
+                // the remaining items.
+                while self.0.pop_exclusive().is_some() {}
             }
         }
 
-        let mut strategy = DropStrategy {};
-
-        // TODO: Document unwrap.
-        while self.pop(&mut strategy).unwrap().is_some() {}
+        while let Some(item) = self.pop_exclusive() {
+            let guard = DropGuard(self);
+            drop(item);
+            std::mem::forget(guard);
+        }
     }
 }
 
 pub trait PushStrategy {
     fn try_push(&mut self) -> bool;
+
+    fn on_cas_failure(&mut self, _reason: CasFailure) {}
+
+    fn on_cas_success(&mut self) {}
+
+    fn after_cas_failure(&mut self) {}
 }
 
 pub trait PopStrategy {
     fn try_pop(&mut self) -> bool;
+
+    fn on_cas_failure(&mut self, _reason: CasFailure) {}
+
+    fn on_cas_success(&mut self) {}
+
+    fn after_cas_failure(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::UrgentStrategy;
+
+    /// Retries until the stack is found empty.
+    struct Persist {}
+
+    impl PopStrategy for Persist {
+        fn try_pop(&mut self) -> bool {
+            true
+        }
+    }
+
+    struct GiveUp {}
+
+    impl PopStrategy for GiveUp {
+        fn try_pop(&mut self) -> bool {
+            false
+        }
+    }
+
+    impl PushStrategy for GiveUp {
+        fn try_push(&mut self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn pop_distinguishes_empty_from_contended() {
+        let mut stack = TreiberStack::new();
+
+        assert_eq!(stack.pop(&mut Persist {}), PopResult::Empty);
+        assert_eq!(stack.pop(&mut GiveUp {}), PopResult::Contended);
+        assert!(stack.is_empty());
+
+        stack.push(1, &mut UrgentStrategy::new()).unwrap();
+        assert!(!stack.is_empty());
+        assert_eq!(stack.pop(&mut GiveUp {}), PopResult::Contended);
+        assert_eq!(stack.pop_exclusive(), Some(1));
+    }
+
+    #[test]
+    fn from_items_keeps_push_order() {
+        let mut stack = TreiberStack::from_items(0..3);
+        stack.push(3, &mut UrgentStrategy::new()).unwrap();
+
+        let popped = std::iter::from_fn(|| stack.pop_exclusive()).collect::<Vec<_>>();
+        assert_eq!(popped, vec![3, 2, 1, 0]);
+
+        let mut zsts = TreiberStack::from_items(vec![(); 3]);
+        assert_eq!(std::iter::from_fn(|| zsts.pop_exclusive()).count(), 3);
+    }
+
+    #[test]
+    fn push_chain_keeps_push_order() {
+        let mut stack = TreiberStack::from_items(0..2);
+
+        let chain = stack
+            .push_chain(Chain::new(2..5), &mut GiveUp {})
+            .unwrap_err();
+        assert_eq!(chain.len(), 3);
+        assert_eq!(stack.clone_contents(), vec![1, 0]);
+
+        stack.push_chain(chain, &mut UrgentStrategy::new()).unwrap();
+        stack
+            .push_chain(Chain::new(vec![]), &mut GiveUp {})
+            .unwrap();
+
+        assert_eq!(stack.len(), 5);
+        let popped = std::iter::from_fn(|| stack.pop_exclusive()).collect::<Vec<_>>();
+        assert_eq!(popped, vec![4, 3, 2, 1, 0]);
+        assert_eq!(stack.len(), 0);
+
+        let mut zsts = TreiberStack::new();
+        zsts.push_chain(Chain::new(vec![(); 3]), &mut UrgentStrategy::new())
+            .unwrap();
+        assert_eq!(std::iter::from_fn(|| zsts.pop_exclusive()).count(), 3);
+    }
+
+    #[test]
+    fn pop_n_detaches_up_to_max() {
+        let stack = TreiberStack::from_items(0..5);
+        let mut popped = vec![];
+
+        assert_eq!(
+            stack.pop_n(2, &mut GiveUp {}, |i| popped.push(i)),
+            PopResult::Contended
+        );
+        assert_eq!(
+            stack.pop_n(2, &mut Persist {}, |i| popped.push(i)),
+            PopResult::Popped(2)
+        );
+        assert_eq!(
+            stack.pop_n(8, &mut Persist {}, |i| popped.push(i)),
+            PopResult::Popped(3)
+        );
+        assert_eq!(
+            stack.pop_n(8, &mut Persist {}, |i| popped.push(i)),
+            PopResult::Empty
+        );
+        assert_eq!(popped, vec![4, 3, 2, 1, 0]);
+
+        let zsts = TreiberStack::from_items(vec![(); 3]);
+        assert_eq!(zsts.pop_n(2, &mut Persist {}, drop), PopResult::Popped(2));
+        assert_eq!(zsts.pop_n(2, &mut Persist {}, drop), PopResult::Popped(1));
+    }
+
+    #[test]
+    fn dropping_chain_drops_items() {
+        let item = std::rc::Rc::new(());
+        let chain = Chain::new(vec![item.clone(); 3]);
+        assert_eq!(std::rc::Rc::strong_count(&item), 4);
+
+        let chain = TreiberStack::new()
+            .push_chain(chain, &mut GiveUp {})
+            .unwrap_err();
+        drop(chain);
+        assert_eq!(std::rc::Rc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn take_all_detaches_every_item() {
+        let stack = TreiberStack::from_items(0..5);
+        let mut taken = vec![];
+
+        assert_eq!(stack.take_all(|i| taken.push(i)), 5);
+        assert_eq!(taken, vec![4, 3, 2, 1, 0]);
+        assert_eq!(stack.len(), 0);
+        assert_eq!(stack.pop(&mut Persist {}), PopResult::Empty);
+
+        // Items below a panicking caller are still dropped.
+        let item = std::rc::Rc::new(());
+        let stack = TreiberStack::from_items(vec![item.clone(); 3]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            stack.take_all(|_| panic!("taking item"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(std::rc::Rc::strong_count(&item), 1);
+        assert_eq!(stack.len(), 0);
+
+        let zsts = TreiberStack::from_items(vec![(); 3]);
+        assert_eq!(zsts.take_all(drop), 3);
+        assert!(zsts.is_empty());
+    }
 }
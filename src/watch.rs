@@ -0,0 +1,196 @@
+//! Notification on items becoming available, without popping them, see
+//! [`Stack::watch`].
+//!
+//! Shares the parking of [`Stack::pop_blocking`], see `src/park.rs`, i.e. a
+//! sequence counter bumped by each wake up and the threads waiting on it.
+//! While a thread is watching, a push operation wakes every waiting thread
+//! instead of a single one, thus a watching thread, not taking the item,
+//! never swallows the wake up of a popping thread. Tasks awaiting
+//! [`Watch::changed`] register their waker like [`Stack::pop_async`] and are
+//! woken by every wake up anyway.
+//!
+//! [`Stack`] can not be closed, thus there is no notification of a stack
+//! being closed, only of it holding items.
+
+use crate::park::TaskRegistration;
+use crate::Stack;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Handle to wait for a [`Stack`] to hold items, e.g. for a scheduler to
+/// sleep until there is work, without popping it. Created via
+/// [`Stack::watch`].
+///
+/// ```rust
+/// # use elimination_backoff_stack::Stack;
+/// # use std::sync::Arc;
+/// # use std::thread;
+/// let stack = Arc::new(Stack::<u8>::new());
+///
+/// let scheduler = {
+///     let stack = stack.clone();
+///     thread::spawn(move || {
+///         stack.watch().wait();
+///         // Hand the work to a worker.
+///         stack.pop()
+///     })
+/// };
+///
+/// stack.push(1);
+/// assert_eq!(scheduler.join().unwrap(), Some(1));
+/// ```
+pub struct Watch<'a, T, PushS, PopS> {
+    stack: &'a Stack<T, PushS, PopS>,
+}
+
+impl<'a, T, PushS, PopS> Watch<'a, T, PushS, PopS> {
+    pub(crate) fn new(stack: &'a Stack<T, PushS, PopS>) -> Self {
+        Watch { stack }
+    }
+
+    /// Block the calling thread until the stack transitions from empty to
+    /// non-empty, returning right away if it is not empty.
+    ///
+    /// The stack might be empty again on return, e.g. in case a concurrent
+    /// pop operation took the item first. Items exchanged on the elimination
+    /// array never land on the stack, thus do not end the wait.
+    pub fn wait(&self) {
+        while self.stack.stack.is_empty() {
+            let registration = self.stack.park.register_watcher();
+            // See `Stack::pop_blocking`.
+            if self.stack.stack.confirm_empty() && registration.wait() {
+                return;
+            }
+        }
+    }
+
+    /// Like [`Watch::wait`], though awaiting the transition via the waker of
+    /// the calling task instead of blocking the thread, see
+    /// [`Stack::pop_async`].
+    ///
+    /// ```rust
+    /// # use elimination_backoff_stack::Stack;
+    /// let stack = Stack::<u8>::new();
+    ///
+    /// futures::executor::block_on(async {
+    ///     futures::join!(stack.watch().changed(), async { stack.push(1) });
+    /// });
+    /// assert_eq!(stack.pop(), Some(1));
+    /// ```
+    pub fn changed(&self) -> Changed<'a, T, PushS, PopS> {
+        Changed {
+            stack: self.stack,
+            registration: None,
+        }
+    }
+}
+
+/// Future resolving once a [`Stack`] transitions from empty to non-empty.
+/// Created via [`Watch::changed`].
+#[must_use = "futures do nothing unless polled"]
+pub struct Changed<'a, T, PushS, PopS> {
+    stack: &'a Stack<T, PushS, PopS>,
+    registration: Option<TaskRegistration<'a>>,
+}
+
+impl<T, PushS, PopS> Future for Changed<'_, T, PushS, PopS> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let stack = self.stack;
+        loop {
+            let woken = self.registration.as_ref().is_some_and(|r| r.woken());
+            if woken || !stack.stack.is_empty() {
+                self.registration = None;
+                return Poll::Ready(());
+            }
+
+            match &self.registration {
+                Some(registration) => registration.update(cx.waker()),
+                None => self.registration = Some(stack.park.register_task(cx.waker())),
+            }
+            // See `Stack::pop_blocking`.
+            if stack.stack.confirm_empty() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl<T, PushS, PopS> fmt::Debug for Changed<'_, T, PushS, PopS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Changed")
+            .field("registered", &self.registration.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, PushS, PopS> fmt::Debug for Watch<'_, T, PushS, PopS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watch").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Stack;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn watch_does_not_swallow_wake_up_of_popping_thread() {
+        let stack = Arc::new(Stack::<u8>::new());
+
+        let watchers = (0..2)
+            .map(|_| {
+                let stack = stack.clone();
+                thread::spawn(move || stack.watch().wait())
+            })
+            .collect::<Vec<_>>();
+        let consumer = {
+            let stack = stack.clone();
+            thread::spawn(move || stack.pop_blocking())
+        };
+        while stack.park.sleepers() < 3 {
+            thread::yield_now();
+        }
+
+        // Whichever thread the push operation wakes, all of them return.
+        stack.push(1);
+        for watcher in watchers {
+            watcher.join().unwrap();
+        }
+        assert_eq!(consumer.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn changed_resolves_once_pushed() {
+        let stack = Arc::new(Stack::<u8>::new());
+
+        let watcher = {
+            let stack = stack.clone();
+            thread::spawn(move || futures::executor::block_on(stack.watch().changed()))
+        };
+        let consumer = {
+            let stack = stack.clone();
+            thread::spawn(move || stack.pop_blocking())
+        };
+        while stack.park.sleepers() < 2 {
+            thread::yield_now();
+        }
+
+        stack.push(1);
+        watcher.join().unwrap();
+        assert_eq!(consumer.join().unwrap(), 1);
+        assert_eq!(stack.park.sleepers(), 0);
+    }
+
+    #[test]
+    fn wait_returns_right_away_given_items() {
+        let stack = Stack::<u8>::with_items(vec![1]);
+        stack.watch().wait();
+        assert_eq!(stack.pop(), Some(1));
+    }
+}
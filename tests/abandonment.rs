@@ -0,0 +1,157 @@
+//! Stress test of the riskiest corner of the exchange protocol: a push
+//! operation withdrawing its offer while a pop operation claims it.
+//!
+//! The [`Abandon`] strategy sends every operation to the elimination array
+//! first and has push operations withdraw their offer right after placing it,
+//! thus nearly every exchange races an abandonment. Items are tagged uniquely
+//! and count their drops, asserting that each item is handed out exactly once
+//! and dropped exactly once.
+//!
+//! Scale up via the `ABANDONMENT_ITEMS` environment variable, e.g.:
+//!
+//! ```sh
+//! ABANDONMENT_ITEMS=10000000 cargo test --release --test abandonment
+//! ```
+
+use elimination_backoff_stack::strategy::Strategy;
+use elimination_backoff_stack::Stack;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
+use std::thread;
+
+const DEFAULT_ITEMS: usize = 200_000;
+const PRODUCERS: usize = 2;
+const CONSUMERS: usize = 2;
+
+/// Alternates between denying and permitting each decision, starting with
+/// denying the Treiber stack, thus trying the elimination array first. Never
+/// waits for a pop operation to take an offered item.
+#[derive(Default)]
+struct Abandon {
+    stack: bool,
+    elimination_array: bool,
+    start_exchange: bool,
+    exchange: bool,
+}
+
+/// Permit every other decision, starting with denying.
+fn every_other(permit: &mut bool) -> bool {
+    let permitted = *permit;
+    *permit = !*permit;
+    permitted
+}
+
+impl Strategy for Abandon {
+    fn new() -> Self {
+        Abandon {
+            elimination_array: true,
+            start_exchange: true,
+            exchange: true,
+            ..Abandon::default()
+        }
+    }
+
+    fn use_elimination_array(&mut self) -> bool {
+        true
+    }
+
+    fn try_stack(&mut self) -> bool {
+        every_other(&mut self.stack)
+    }
+
+    fn try_elimination_array(&mut self) -> bool {
+        every_other(&mut self.elimination_array)
+    }
+
+    fn try_start_exchange(&mut self) -> bool {
+        every_other(&mut self.start_exchange)
+    }
+
+    fn retry_check_exchanged(&mut self) -> bool {
+        false
+    }
+
+    fn try_exchange(&mut self) -> bool {
+        every_other(&mut self.exchange)
+    }
+}
+
+/// Item unique by producer and sequence number, counting its drops.
+struct Tagged {
+    producer: usize,
+    sequence_number: usize,
+    drops: Arc<AtomicUsize>,
+}
+
+impl Drop for Tagged {
+    fn drop(&mut self) {
+        self.drops.fetch_add(1, SeqCst);
+    }
+}
+
+#[test]
+fn abandoned_offers_are_handed_out_exactly_once() {
+    let items = std::env::var("ABANDONMENT_ITEMS")
+        .map(|items| items.parse().expect("ABANDONMENT_ITEMS to be a number"))
+        .unwrap_or(DEFAULT_ITEMS);
+    let per_producer = items / PRODUCERS;
+
+    let stack = Arc::new(Stack::<Tagged, Abandon, Abandon>::new());
+    let drops = Arc::new(AtomicUsize::new(0));
+    let producers_done = Arc::new(AtomicBool::new(false));
+
+    let consumers = (0..CONSUMERS)
+        .map(|_| {
+            let stack = stack.clone();
+            let producers_done = producers_done.clone();
+            thread::spawn(move || {
+                let mut tags = vec![];
+                loop {
+                    // Read before popping, thus an empty stack after all
+                    // producers finished is final.
+                    let done = producers_done.load(SeqCst);
+                    match stack.pop() {
+                        Some(item) => tags.push((item.producer, item.sequence_number)),
+                        None if done => return tags,
+                        None => {}
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let producers = (0..PRODUCERS)
+        .map(|producer| {
+            let stack = stack.clone();
+            let drops = drops.clone();
+            thread::spawn(move || {
+                for sequence_number in 0..per_producer {
+                    stack.push(Tagged {
+                        producer,
+                        sequence_number,
+                        drops: drops.clone(),
+                    });
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for producer in producers {
+        producer.join().unwrap();
+    }
+    producers_done.store(true, SeqCst);
+
+    let mut tags = consumers
+        .into_iter()
+        .flat_map(|consumer| consumer.join().unwrap())
+        .collect::<Vec<_>>();
+    tags.sort_unstable();
+
+    let expected = (0..PRODUCERS)
+        .flat_map(|producer| (0..per_producer).map(move |s| (producer, s)))
+        .collect::<Vec<_>>();
+    assert_eq!(tags.len(), expected.len(), "items lost or duplicated");
+    assert!(tags == expected, "items lost or duplicated");
+    assert_eq!(drops.load(SeqCst), PRODUCERS * per_producer);
+    assert!(stack.is_empty());
+}
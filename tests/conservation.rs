@@ -0,0 +1,266 @@
+//! Smoke test running every public operation concurrently in random
+//! combinations, asserting that no item is lost or duplicated.
+//!
+//! Extend [`Operation`] whenever the public API grows.
+//!
+//! On failure, run with `--features debug-conservation` to have the stack
+//! panic on the very operation duplicating or losing an item.
+
+use elimination_backoff_stack::{PopStrategy, PushStrategy, Stack};
+use rand::{thread_rng, Rng};
+use std::mem::MaybeUninit;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
+use std::thread;
+
+const THREADS: usize = 4;
+const OPERATIONS_PER_THREAD: usize = 2_000;
+
+#[derive(Clone, Copy, Debug)]
+enum Operation {
+    Push,
+    PushUrgent,
+    PushIter,
+    PushIterBalanced,
+    TryPush,
+    Pop,
+    PopUrgent,
+    TryPop,
+    TryPopWeak,
+    PopN,
+    PopInto,
+    PopIntoSlice,
+    Drain,
+    TryPopEachUntilEmpty,
+    Clear,
+    CloneContents,
+    ForEachRef,
+    Peek,
+    Len,
+}
+
+const OPERATIONS: &[Operation] = &[
+    Operation::Push,
+    Operation::PushUrgent,
+    Operation::PushIter,
+    Operation::PushIterBalanced,
+    Operation::TryPush,
+    Operation::Pop,
+    Operation::PopUrgent,
+    Operation::TryPop,
+    Operation::TryPopWeak,
+    Operation::PopN,
+    Operation::PopInto,
+    Operation::PopIntoSlice,
+    Operation::Drain,
+    Operation::TryPopEachUntilEmpty,
+    Operation::Clear,
+    Operation::CloneContents,
+    Operation::ForEachRef,
+    Operation::Peek,
+    Operation::Len,
+];
+
+#[derive(Default)]
+struct Counters {
+    created: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+/// Payload counting its own creation and destruction.
+struct Payload(Arc<Counters>);
+
+impl Payload {
+    fn new(counters: &Arc<Counters>) -> Self {
+        counters.created.fetch_add(1, SeqCst);
+        Payload(counters.clone())
+    }
+}
+
+impl Drop for Payload {
+    fn drop(&mut self) {
+        self.0.dropped.fetch_add(1, SeqCst);
+    }
+}
+
+fn conserves_items<PushS: PushStrategy + 'static, PopS: PopStrategy + 'static>() {
+    let counters = Arc::new(Counters::default());
+    let stack = Arc::new(Stack::<Payload, PushS, PopS>::new());
+    // Read-only operations need a `Copy` item, thus run them on a companion
+    // stack of plain numbers.
+    let numbers = Arc::new(Stack::<usize, PushS, PopS>::new());
+
+    let handlers = (0..THREADS)
+        .map(|_| {
+            let counters = counters.clone();
+            let stack = stack.clone();
+            let numbers = numbers.clone();
+
+            thread::spawn(move || {
+                let mut rng = thread_rng();
+                let (mut pushed, mut popped) = (0, 0);
+
+                for _ in 0..OPERATIONS_PER_THREAD {
+                    match OPERATIONS[rng.gen_range(0, OPERATIONS.len())] {
+                        Operation::Push => {
+                            stack.push(Payload::new(&counters));
+                            numbers.push(1);
+                            pushed += 1;
+                        }
+                        Operation::PushUrgent => {
+                            stack.push_urgent(Payload::new(&counters));
+                            numbers.push_urgent(1);
+                            pushed += 1;
+                        }
+                        Operation::PushIter => {
+                            let n = rng.gen_range(0, 4);
+                            stack.push_iter((0..n).map(|_| Payload::new(&counters)));
+                            numbers.push_iter(vec![1; n]);
+                            pushed += n;
+                        }
+                        Operation::PushIterBalanced => {
+                            let n = rng.gen_range(0, 4);
+                            stack.push_iter_balanced((0..n).map(|_| Payload::new(&counters)));
+                            numbers.push_iter_balanced(vec![1; n]);
+                            pushed += n;
+                        }
+                        Operation::TryPush => {
+                            // An item handed back is dropped right away, as
+                            // if pushed and popped.
+                            pushed += 1;
+                            if stack.try_push(Payload::new(&counters)).is_err() {
+                                popped += 1;
+                            }
+                            let _ = numbers.try_push(1);
+                        }
+                        Operation::Pop => {
+                            if stack.pop().is_some() {
+                                popped += 1;
+                            }
+                            numbers.pop();
+                        }
+                        Operation::PopUrgent => {
+                            if stack.pop_urgent().is_some() {
+                                popped += 1;
+                            }
+                            numbers.pop_urgent();
+                        }
+                        Operation::TryPop => {
+                            if let Ok(Some(_)) = stack.try_pop() {
+                                popped += 1;
+                            }
+                            let _ = numbers.try_pop();
+                        }
+                        Operation::TryPopWeak => {
+                            if stack.try_pop_weak().is_some() {
+                                popped += 1;
+                            }
+                            numbers.try_pop_weak();
+                        }
+                        Operation::PopN => {
+                            let max = rng.gen_range(0, 4);
+                            popped += stack.pop_n(&mut vec![], max);
+                            numbers.pop_n(&mut vec![], max);
+                        }
+                        Operation::PopInto => {
+                            let mut slot = MaybeUninit::uninit();
+                            if stack.pop_into(&mut slot) {
+                                drop(unsafe { slot.assume_init() });
+                                popped += 1;
+                            }
+                            numbers.pop_into(&mut MaybeUninit::uninit());
+                        }
+                        Operation::PopIntoSlice => {
+                            let mut slots = [(); 3].map(|_| MaybeUninit::uninit());
+                            let max = rng.gen_range(0, 4);
+                            let n = stack.pop_into_slice(&mut slots[..max]);
+                            for slot in &mut slots[..n] {
+                                drop(unsafe { slot.assume_init_read() });
+                            }
+                            popped += n;
+                            numbers.pop_into_slice(&mut [MaybeUninit::uninit(); 3][..max]);
+                        }
+                        Operation::Drain => {
+                            popped += stack.drain().count();
+                            numbers.drain().for_each(drop);
+                        }
+                        Operation::TryPopEachUntilEmpty => {
+                            let batch = rng.gen_range(1, 4);
+                            popped +=
+                                stack.try_pop_each_until_empty(batch, drop, || rng.gen_bool(0.5));
+                            numbers.try_pop_each_until_empty(batch, drop, || false);
+                        }
+                        Operation::Clear => {
+                            popped += stack.clear();
+                            numbers.clear();
+                        }
+                        Operation::CloneContents => {
+                            assert!(numbers.clone_contents().iter().all(|n| *n == 1));
+                        }
+                        Operation::ForEachRef => {
+                            let _ = numbers.for_each_ref(|n| {
+                                assert_eq!(*n, 1);
+                                ControlFlow::<()>::Continue(())
+                            });
+                        }
+                        Operation::Peek => {
+                            assert!(matches!(numbers.peek(), None | Some(1)));
+                        }
+                        Operation::Len => {
+                            // At most 3 items pushed per operation.
+                            assert!(stack.len() <= 3 * THREADS * OPERATIONS_PER_THREAD);
+                            let _ = stack.is_empty();
+                        }
+                    }
+                }
+
+                (pushed, popped)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let (mut pushed, mut popped) = (0, 0);
+    for handler in handlers {
+        let (p, q) = handler.join().unwrap();
+        pushed += p;
+        popped += q;
+    }
+
+    // Popped items are dropped right away, thus whatever was not dropped yet
+    // remains on the stack.
+    assert_eq!(counters.created.load(SeqCst), pushed);
+    assert_eq!(counters.dropped.load(SeqCst), popped);
+
+    // Pop half of the remaining items and have dropping the stack drop the
+    // rest.
+    for _ in 0..(pushed - popped) / 2 {
+        stack.pop().expect("item to remain on the stack");
+    }
+    drop(stack);
+
+    assert_eq!(counters.dropped.load(SeqCst), pushed);
+}
+
+elimination_backoff_stack::for_each_strategy!(test conserves_items);
+
+mod presets {
+    use super::conserves_items;
+    use elimination_backoff_stack::strategy::{
+        HighThroughput, LowLatency, Oversubscribed, Preset, SingleProducer,
+    };
+
+    macro_rules! conserves_items_with {
+        ($name:ident, $preset:ty) => {
+            #[test]
+            fn $name() {
+                conserves_items::<<$preset as Preset>::Push, <$preset as Preset>::Pop>();
+            }
+        };
+    }
+
+    conserves_items_with!(low_latency, LowLatency);
+    conserves_items_with!(high_throughput, HighThroughput);
+    conserves_items_with!(oversubscribed, Oversubscribed);
+    conserves_items_with!(single_producer, SingleProducer);
+}
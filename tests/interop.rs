@@ -0,0 +1,173 @@
+//! Runs the stack properties against every [`ConcurrentStack`]
+//! implementation, differential testing [`Stack`] as well as the stack of the
+//! `lockfree` crate against a lock based reference implementation.
+
+use elimination_backoff_stack::{
+    concurrent_stack::ConcurrentStack,
+    strategy::{BackAndForthStrategy, NoEliminationStrategy, WithPopInterest},
+    Stack,
+};
+use quickcheck::{quickcheck, Arbitrary, Gen, TestResult};
+use rand::Rng;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Lock based reference implementation.
+struct Reference<T>(Mutex<Vec<T>>);
+
+impl<T> Default for Reference<T> {
+    fn default() -> Self {
+        Reference(Mutex::new(vec![]))
+    }
+}
+
+impl<T> ConcurrentStack<T> for Reference<T> {
+    fn push(&self, item: T) {
+        self.0.lock().unwrap().push(item);
+    }
+
+    fn pop(&self) -> Option<T> {
+        self.0.lock().unwrap().pop()
+    }
+}
+
+/// Treiber stack of the `lockfree` crate, reclaiming nodes via its own
+/// incinerator instead of epochs.
+struct LockfreeStack<T>(lockfree::stack::Stack<T>);
+
+impl<T> Default for LockfreeStack<T> {
+    fn default() -> Self {
+        LockfreeStack(lockfree::stack::Stack::new())
+    }
+}
+
+impl<T> ConcurrentStack<T> for LockfreeStack<T> {
+    fn push(&self, item: T) {
+        self.0.push(item);
+    }
+
+    fn pop(&self) -> Option<T> {
+        self.0.pop()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Operation {
+    Push(u32),
+    Pop,
+}
+
+impl Arbitrary for Operation {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        if g.gen::<bool>() {
+            Operation::Push(Arbitrary::arbitrary(g))
+        } else {
+            Operation::Pop
+        }
+    }
+}
+
+/// Apply `operations` on a fresh `S`, returning the result of each pop.
+fn run_single_threaded<S: ConcurrentStack<u32> + Default>(
+    operations: &[Operation],
+) -> Vec<Option<u32>> {
+    let stack = S::default();
+
+    operations
+        .iter()
+        .filter_map(|operation| match operation {
+            Operation::Push(item) => {
+                stack.push(*item);
+                None
+            }
+            Operation::Pop => Some(stack.pop()),
+        })
+        .collect()
+}
+
+/// Push and pop from `num_threads` threads concurrently, then drain the stack.
+/// Every pushed item has to come out exactly once.
+fn conserves_items<S>(num_threads: usize, mut operations: Vec<Vec<Operation>>) -> TestResult
+where
+    S: ConcurrentStack<(usize, usize)> + Default + Send + Sync + 'static,
+{
+    if num_threads > num_cpus::get() * 2 || operations.len() < num_threads {
+        return TestResult::discard();
+    }
+
+    let stack = Arc::new(S::default());
+    let mut handlers = vec![];
+    let mut pushed = HashSet::new();
+
+    for thread_id in 0..num_threads {
+        let operations = operations.pop().unwrap();
+        for (nonce, operation) in operations.iter().enumerate() {
+            if let Operation::Push(_) = operation {
+                pushed.insert((thread_id, nonce));
+            }
+        }
+
+        let stack = stack.clone();
+        handlers.push(thread::spawn(move || {
+            let mut popped = vec![];
+            for (nonce, operation) in operations.into_iter().enumerate() {
+                match operation {
+                    Operation::Push(_) => stack.push((thread_id, nonce)),
+                    Operation::Pop => popped.extend(stack.pop()),
+                }
+            }
+            popped
+        }));
+    }
+
+    let mut popped = vec![];
+    for handler in handlers {
+        popped.extend(handler.join().unwrap());
+    }
+    while let Some(item) = stack.pop() {
+        popped.push(item);
+    }
+
+    let num_popped = popped.len();
+    let popped: HashSet<_> = popped.into_iter().collect();
+    TestResult::from_bool(num_popped == popped.len() && popped == pushed)
+}
+
+macro_rules! interop_tests {
+    ($($name:ident: $stack:ty,)*) => {
+        $(
+            mod $name {
+                use super::*;
+
+                #[test]
+                fn single_threaded_compare_to_reference() {
+                    fn prop(operations: Vec<Operation>) -> bool {
+                        run_single_threaded::<$stack>(&operations)
+                            == run_single_threaded::<Reference<u32>>(&operations)
+                    }
+
+                    quickcheck(prop as fn(_) -> bool);
+                }
+
+                #[test]
+                fn multithreaded_conserves_items() {
+                    fn prop(num_threads: usize, operations: Vec<Vec<Operation>>) -> TestResult {
+                        conserves_items::<$stack>(num_threads, operations)
+                    }
+
+                    quickcheck(prop as fn(_, _) -> TestResult);
+                }
+            }
+        )*
+    };
+}
+
+interop_tests! {
+    reference: Reference<_>,
+    lockfree_stack: LockfreeStack<_>,
+    exp_retry: Stack<_>,
+    back_and_forth: Stack<_, BackAndForthStrategy, BackAndForthStrategy>,
+    treiber_stack: Stack<_, NoEliminationStrategy, NoEliminationStrategy>,
+    pop_interest: Stack<_, WithPopInterest<BackAndForthStrategy>, WithPopInterest<BackAndForthStrategy>>,
+}
@@ -0,0 +1,137 @@
+//! Exhaustive model check of the exchanger protocol via [loom].
+//!
+//! The exchanger builds on crossbeam's epoch based garbage collection, which
+//! loom can not model. Instead this models the protocol itself on a plain
+//! atomic pointer, leaking replaced items instead of reclaiming them:
+//!
+//! - Push operations only replace `Empty` with their `Waiting` item.
+//! - Pop operations only replace `Waiting` with `Busy`.
+//! - The push operation whose item was taken resets `Busy` to `Empty` with a
+//!   plain store, given that no other operation writes to a `Busy` exchanger.
+//!
+//! ```sh
+//! RUSTFLAGS="--cfg loom" cargo test --release --test loom
+//! ```
+//!
+//! [loom]: https://docs.rs/loom
+#![cfg(loom)]
+
+use loom::sync::atomic::AtomicPtr;
+use loom::sync::Arc;
+use loom::thread;
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release};
+
+#[derive(Debug, PartialEq)]
+enum Item {
+    Empty,
+    Waiting(usize),
+    Busy,
+}
+
+fn leak(item: Item) -> *mut Item {
+    Box::into_raw(Box::new(item))
+}
+
+struct Exchanger {
+    item: AtomicPtr<Item>,
+}
+
+impl Exchanger {
+    fn new() -> Self {
+        Exchanger {
+            item: AtomicPtr::new(leak(Item::Empty)),
+        }
+    }
+
+    /// Offer `value` once and wait for a single check before withdrawing.
+    fn exchange_push(&self, value: usize) -> Result<(), usize> {
+        let current = self.item.load(Relaxed);
+        if unsafe { &*current } != &Item::Empty {
+            return Err(value);
+        }
+
+        let waiting = leak(Item::Waiting(value));
+        if self
+            .item
+            .compare_exchange(current, waiting, Release, Relaxed)
+            .is_err()
+        {
+            return Err(value);
+        }
+
+        if unsafe { &*self.item.load(Acquire) } == &Item::Busy {
+            self.release();
+            return Ok(());
+        }
+
+        match self
+            .item
+            .compare_exchange(waiting, leak(Item::Empty), Release, Acquire)
+        {
+            Ok(_) => Err(value),
+            Err(current) => {
+                assert_eq!(unsafe { &*current }, &Item::Busy);
+                self.release();
+                Ok(())
+            }
+        }
+    }
+
+    fn release(&self) {
+        self.item.store(leak(Item::Empty), Release);
+    }
+
+    fn exchange_pop(&self) -> Option<usize> {
+        let current = self.item.load(Relaxed);
+        let value = match unsafe { &*current } {
+            Item::Waiting(value) => *value,
+            Item::Empty | Item::Busy => return None,
+        };
+
+        self.item
+            .compare_exchange(current, leak(Item::Busy), AcqRel, Relaxed)
+            .ok()
+            .map(|_| value)
+    }
+}
+
+/// Every offered item ends up either with exactly one pop operation or back
+/// with its push operation.
+fn check(num_pushers: usize, num_poppers: usize) {
+    loom::model(move || {
+        let exchanger = Arc::new(Exchanger::new());
+
+        let pushers = (0..num_pushers)
+            .map(|value| {
+                let exchanger = exchanger.clone();
+                thread::spawn(move || exchanger.exchange_push(value).err())
+            })
+            .collect::<Vec<_>>();
+        let poppers = (0..num_poppers)
+            .map(|_| {
+                let exchanger = exchanger.clone();
+                thread::spawn(move || exchanger.exchange_pop())
+            })
+            .collect::<Vec<_>>();
+
+        let mut values = pushers
+            .into_iter()
+            .chain(poppers)
+            .filter_map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>();
+        values.sort_unstable();
+
+        assert_eq!(values, (0..num_pushers).collect::<Vec<_>>());
+        assert_eq!(unsafe { &*exchanger.item.load(Acquire) }, &Item::Empty);
+    });
+}
+
+#[test]
+fn one_pusher_two_poppers() {
+    check(1, 2);
+}
+
+#[test]
+fn two_pushers_one_popper() {
+    check(2, 1);
+}